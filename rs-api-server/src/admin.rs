@@ -0,0 +1,131 @@
+use crate::{
+    alerts,
+    pagination::{Page, PageQuery},
+    to_rfc3339, usage,
+};
+use axum::{
+    extract::{Path, Query, Request},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::Response,
+    routing::{delete, get},
+    Json, Router,
+};
+use bson::{oid::ObjectId, DateTime};
+use rs_subscan_parser::OperationType;
+use rs_telegram_feed_bot::{mongodb_client_mute_rules::MongoDbClientMuteRules, MuteRule};
+use serde::{Deserialize, Serialize};
+use std::env;
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/mute-rules", get(get_mute_rules).post(add_mute_rule))
+        .route("/mute-rules/:id", delete(delete_mute_rule))
+        .route("/alerts", get(alerts::get_alerts))
+        .route("/usage", get(usage::get_all_usage))
+        .route_layer(middleware::from_fn(require_admin_token))
+}
+
+// admin endpoints stay closed unless API_ADMIN_TOKEN is configured
+pub async fn require_admin_token(request: Request, next: Next) -> Result<Response, StatusCode> {
+    let token = env::var("API_ADMIN_TOKEN").map_err(|_| StatusCode::FORBIDDEN)?;
+    let is_authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v == format!("Bearer {token}"));
+    if !is_authorized {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    Ok(next.run(request).await)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct NewMuteRule {
+    pub wallet: Option<String>,
+    pub label: Option<String>,
+    pub operation_type: Option<OperationType>,
+    pub starts_at: Option<String>,
+    pub ends_at: Option<String>,
+    pub comment: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ApiMuteRule {
+    pub id: String,
+    pub wallet: Option<String>,
+    pub label: Option<String>,
+    pub operation_type: Option<OperationType>,
+    pub starts_at: Option<String>,
+    pub ends_at: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl From<MuteRule> for ApiMuteRule {
+    fn from(r: MuteRule) -> Self {
+        Self {
+            id: r.id,
+            wallet: r.wallet,
+            label: r.label,
+            operation_type: r.operation_type,
+            starts_at: r.starts_at.map(to_rfc3339),
+            ends_at: r.ends_at.map(to_rfc3339),
+            comment: r.comment,
+        }
+    }
+}
+
+fn parse_timestamp(timestamp: Option<String>) -> Result<Option<DateTime>, StatusCode> {
+    timestamp
+        .map(|t| DateTime::parse_rfc3339_str(t).map_err(|_| StatusCode::UNPROCESSABLE_ENTITY))
+        .transpose()
+}
+
+pub async fn get_mute_rules(
+    Query(page): Query<PageQuery>,
+) -> Result<Json<Page<ApiMuteRule>>, StatusCode> {
+    let mut mongodb_client_mute_rules = MongoDbClientMuteRules::new().await;
+    let mute_rules = mongodb_client_mute_rules
+        .get_mute_rules(page.get_after()?, page.get_fetch_limit())
+        .await;
+
+    Ok(Json(
+        Page::new(mute_rules, &page, MongoDbClientMuteRules::get_page_key).map(ApiMuteRule::from),
+    ))
+}
+
+pub async fn add_mute_rule(
+    Json(mute_rule): Json<NewMuteRule>,
+) -> Result<(StatusCode, Json<ApiMuteRule>), StatusCode> {
+    let mute_rule = MuteRule {
+        id: ObjectId::new().to_hex(),
+        wallet: mute_rule.wallet,
+        label: mute_rule.label,
+        operation_type: mute_rule.operation_type,
+        starts_at: parse_timestamp(mute_rule.starts_at)?,
+        ends_at: parse_timestamp(mute_rule.ends_at)?,
+        comment: mute_rule.comment,
+    };
+
+    // a rule without criteria would mute the whole feed
+    if !mute_rule.has_criteria() {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let mut mongodb_client_mute_rules = MongoDbClientMuteRules::new().await;
+    mongodb_client_mute_rules
+        .import_mute_rule(mute_rule.clone())
+        .await;
+
+    Ok((StatusCode::CREATED, Json(ApiMuteRule::from(mute_rule))))
+}
+
+pub async fn delete_mute_rule(Path(id): Path<String>) -> StatusCode {
+    let mut mongodb_client_mute_rules = MongoDbClientMuteRules::new().await;
+    if mongodb_client_mute_rules.delete_mute_rule(&id).await {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
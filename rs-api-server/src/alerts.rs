@@ -0,0 +1,105 @@
+use crate::{
+    pagination::{Page, PageQuery},
+    to_rfc3339,
+};
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    Json,
+};
+use bson::DateTime;
+use rs_telegram_feed_bot::{
+    mongodb_client_alerts::MongoDbClientAlerts, Alert, AlertRule, AlertStatus,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct AckQuery {
+    pub token: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct AlertsQuery {
+    pub status: Option<AlertStatus>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ApiAlert {
+    pub id: String,
+    pub rule: AlertRule,
+    pub wallet: String,
+    pub operation_hash: String,
+    pub message: String,
+    pub status: AlertStatus,
+    pub fired_at: String,
+    pub acked_at: Option<String>,
+    pub resolved_at: Option<String>,
+    pub escalated_at: Option<String>,
+}
+
+impl From<Alert> for ApiAlert {
+    fn from(a: Alert) -> Self {
+        Self {
+            id: a.id,
+            rule: a.rule,
+            wallet: a.wallet,
+            operation_hash: a.operation_hash,
+            message: a.message,
+            status: a.status,
+            fired_at: to_rfc3339(a.fired_at),
+            acked_at: a.acked_at.map(to_rfc3339),
+            resolved_at: a.resolved_at.map(to_rfc3339),
+            escalated_at: a.escalated_at.map(to_rfc3339),
+        }
+    }
+}
+
+pub async fn get_alerts(
+    Query(query): Query<AlertsQuery>,
+    Query(page): Query<PageQuery>,
+) -> Result<Json<Page<ApiAlert>>, StatusCode> {
+    let mut mongodb_client_alerts = MongoDbClientAlerts::new().await;
+    let alerts = mongodb_client_alerts
+        .get_alerts(query.status, page.get_after()?, page.get_fetch_limit())
+        .await;
+
+    Ok(Json(
+        Page::new(alerts, &page, MongoDbClientAlerts::get_page_key).map(ApiAlert::from),
+    ))
+}
+
+pub async fn ack_alert(
+    Path(id): Path<String>,
+    Query(query): Query<AckQuery>,
+) -> Result<Json<ApiAlert>, StatusCode> {
+    set_alert_status(&id, &query.token, AlertStatus::Acked).await
+}
+
+pub async fn resolve_alert(
+    Path(id): Path<String>,
+    Query(query): Query<AckQuery>,
+) -> Result<Json<ApiAlert>, StatusCode> {
+    set_alert_status(&id, &query.token, AlertStatus::Resolved).await
+}
+
+// links are posted publicly to the channel, so the alert's own token is the only authorization
+async fn set_alert_status(
+    id: &str,
+    token: &str,
+    status: AlertStatus,
+) -> Result<Json<ApiAlert>, StatusCode> {
+    let mut mongodb_client_alerts = MongoDbClientAlerts::new().await;
+    let mut alert = mongodb_client_alerts
+        .get_alert(id)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if alert.ack_token != token {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if alert.set_status(status, DateTime::now()) {
+        mongodb_client_alerts.update_status(&alert).await;
+    }
+
+    Ok(Json(ApiAlert::from(alert)))
+}
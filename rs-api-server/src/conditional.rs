@@ -0,0 +1,187 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+static MAX_TRACKED_QUERIES: usize = 10_000;
+static HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+// etag and the second it was first served, by path and query
+static LAST_MODIFIED: OnceLock<Mutex<HashMap<String, (String, i64)>>> = OnceLock::new();
+
+// the last modification is when a query first returned its current body, so a restart
+// only costs one full response per polling client
+fn get_last_modified(key: String, etag: &str) -> i64 {
+    let mut last_modified = LAST_MODIFIED
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some((known_etag, timestamp)) = last_modified.get(&key) {
+        if known_etag == etag {
+            return *timestamp;
+        }
+    }
+
+    if last_modified.len() >= MAX_TRACKED_QUERIES {
+        last_modified.clear();
+    }
+    let timestamp = Utc::now().timestamp();
+    last_modified.insert(key, (etag.to_string(), timestamp));
+
+    timestamp
+}
+
+fn is_etag_matching(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(|e| e.trim().trim_start_matches("W/"))
+        .any(|e| e == "*" || e == etag)
+}
+
+fn is_not_modified(headers: &HeaderMap, etag: &str, last_modified: i64) -> bool {
+    // If-Modified-Since is ignored when If-None-Match is sent
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
+        return if_none_match
+            .to_str()
+            .is_ok_and(|v| is_etag_matching(v, etag));
+    }
+
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| DateTime::parse_from_rfc2822(v).ok())
+        .is_some_and(|since| last_modified <= since.timestamp())
+}
+
+// only complete json and csv bodies are hashed, event streams never end
+fn is_conditional(response: &Response) -> bool {
+    response.status() == StatusCode::OK
+        && response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .is_some_and(|v| {
+                v.as_bytes().starts_with(b"application/json")
+                    || v.as_bytes().starts_with(b"text/csv")
+            })
+}
+
+// dashboards polling the same query get an empty 304 while its result is unchanged
+pub async fn with_conditional_requests(request: Request, next: Next) -> Response {
+    if request.method() != Method::GET {
+        return next.run(request).await;
+    }
+
+    let key = request.uri().to_string();
+    let headers = request.headers().clone();
+    let response = next.run(request).await;
+    if !is_conditional(&response) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(body) = to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let etag = format!("\"{}\"", sha256::digest(&body[..]));
+    let last_modified = get_last_modified(key, &etag);
+    let last_modified_date = DateTime::<Utc>::from_timestamp(last_modified, 0)
+        .unwrap_or_default()
+        .format(HTTP_DATE_FORMAT)
+        .to_string();
+
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        parts.headers.insert(header::ETAG, etag);
+    }
+    if let Ok(last_modified_date) = HeaderValue::from_str(&last_modified_date) {
+        parts
+            .headers
+            .insert(header::LAST_MODIFIED, last_modified_date);
+    }
+
+    if is_not_modified(&headers, &etag, last_modified) {
+        parts.status = StatusCode::NOT_MODIFIED;
+        parts.headers.remove(header::CONTENT_LENGTH);
+        return Response::from_parts(parts, Body::empty());
+    }
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conditional::with_conditional_requests;
+    use axum::{
+        body::{to_bytes, Body},
+        http::{header, Request, StatusCode},
+        middleware,
+        response::Response,
+        routing::get,
+        Json, Router,
+    };
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    async fn send(uri: &str, headers: &[(header::HeaderName, &str)]) -> Response {
+        let router = Router::new()
+            .route("/stats", get(|| async { Json(json!({"count": 1})) }))
+            .layer(middleware::from_fn(with_conditional_requests));
+        let mut request = Request::get(uri);
+        for (name, value) in headers {
+            request = request.header(name, *value);
+        }
+
+        router
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn unchanged_results_are_not_modified() {
+        let response = send("/stats?etag", &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let response = send("/stats?etag", &[(header::IF_NONE_MATCH, &etag)]).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(body.is_empty());
+
+        let response = send("/stats?etag", &[(header::IF_NONE_MATCH, "\"other\"")]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn if_modified_since_uses_first_serve_time() {
+        let response = send("/stats?since", &[]).await;
+        let last_modified = response.headers()[header::LAST_MODIFIED]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let response = send(
+            "/stats?since",
+            &[(header::IF_MODIFIED_SINCE, &last_modified)],
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let response = send(
+            "/stats?since",
+            &[(header::IF_MODIFIED_SINCE, "Mon, 01 Jan 2001 00:00:00 GMT")],
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
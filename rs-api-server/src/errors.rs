@@ -0,0 +1,105 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use rs_subscan_parser::{
+    pipeline_error::{ErrorCode, PipelineError},
+    subscan_parser::Network,
+};
+
+static MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
+// error responses use the pipeline error payload so consumers can match on stable codes
+pub async fn with_error_codes(request: Request, next: Next) -> Response {
+    let endpoint = request.uri().path().to_string();
+    let response = next.run(request).await;
+    let status = response.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let is_json = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"application/json"));
+    let body = to_bytes(body, MAX_ERROR_BODY_BYTES)
+        .await
+        .unwrap_or_default();
+    if is_json {
+        return Response::from_parts(parts, Body::from(body));
+    }
+
+    let message = String::from_utf8_lossy(&body).trim().to_string();
+    let message = if message.is_empty() {
+        status.canonical_reason().unwrap_or_default().to_string()
+    } else {
+        message
+    };
+    let pipeline_error = PipelineError::new(
+        ErrorCode::from_http_status(status.as_u16()),
+        &message,
+        &Network::from_env(),
+    )
+    .with_endpoint(&endpoint);
+
+    // headers like Retry-After or WWW-Authenticate are kept
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    let body = Json(pipeline_error).into_response().into_body();
+
+    Response::from_parts(parts, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::with_error_codes;
+    use axum::{
+        body::{to_bytes, Body},
+        http::{Request, StatusCode},
+        middleware,
+        routing::get,
+        Router,
+    };
+    use rs_subscan_parser::pipeline_error::{ErrorCode, PipelineError};
+    use tower::ServiceExt;
+
+    async fn get_error(uri: &str) -> (StatusCode, PipelineError) {
+        let router = Router::new()
+            .route("/missing", get(|| async { StatusCode::NOT_FOUND }))
+            .route(
+                "/invalid",
+                get(|| async { (StatusCode::BAD_REQUEST, "invalid cursor") }),
+            )
+            .layer(middleware::from_fn(with_error_codes));
+        let response = router
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn error_responses_carry_codes() {
+        let (status, pipeline_error) = get_error("/missing").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(pipeline_error.code, ErrorCode::NotFound);
+        assert_eq!(pipeline_error.message, "Not Found");
+        assert_eq!(pipeline_error.endpoint.as_deref(), Some("/missing"));
+
+        let (status, pipeline_error) = get_error("/invalid").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(pipeline_error.code, ErrorCode::InvalidRequest);
+        assert_eq!(pipeline_error.message, "invalid cursor");
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{
+    fields::{FieldsPage, FieldsQuery},
+    pagination::PageQuery,
+};
+use axum::{
+    extract::Query,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use rs_subscan_parser::{
+    address::AddressFormat,
+    exports::{
+        compression::ExportCompression,
+        csv::{write_csv, CsvColumn, CsvPreset},
+        get_export_annotations,
+        precision::{ExportPrecision, Rounding},
+        ExportOperation,
+    },
+    mongodb_client_subscan::MongoDbClientSubscan,
+    subscan_parser::Network,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ExportQuery {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub decimals: Option<u32>,
+    pub rounding: Option<Rounding>,
+}
+
+impl ExportQuery {
+    pub fn precision(&self) -> ExportPrecision {
+        ExportPrecision {
+            decimals: self.decimals,
+            rounding: self.rounding.unwrap_or_default(),
+        }
+    }
+}
+
+// explicit options override the ones of the preset
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CsvExportQuery {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub preset: Option<CsvPreset>,
+    pub delimiter: Option<char>,
+    pub decimal_separator: Option<char>,
+    pub timestamp_format: Option<String>,
+    pub decimals: Option<u32>,
+    pub rounding: Option<Rounding>,
+    pub address_format: Option<AddressFormat>,
+
+    // comma separated, every column in the default order if not set
+    pub columns: Option<String>,
+
+    // gzip or zstd, answered as application/gzip or application/zstd
+    pub compression: Option<ExportCompression>,
+}
+
+// last 24h with full precision by default, paged in block order
+pub async fn get_operations_export(
+    Query(query): Query<ExportQuery>,
+    Query(page): Query<PageQuery>,
+    Query(fields): Query<FieldsQuery>,
+) -> Result<Json<FieldsPage>, StatusCode> {
+    let precision = query.precision();
+    let from = query.from.unwrap_or(Utc::now().timestamp() - 24 * 60 * 60);
+
+    let operations = page
+        .get_operations_page(MongoDbClientSubscan::get_time_range_query(from, query.to))
+        .await?;
+    let annotations = get_export_annotations(&operations.items).await;
+    let network = Network::from_env();
+    let operations = operations.map(|o| {
+        ExportOperation::new(fields.encode(o, &network), &precision).with_annotations(&annotations)
+    });
+    let (fields, items) = fields.select_all(&operations.items)?;
+
+    Ok(Json(FieldsPage {
+        fields,
+        items,
+        next_cursor: operations.next_cursor,
+    }))
+}
+
+pub async fn get_operations_csv_export(Query(query): Query<CsvExportQuery>) -> impl IntoResponse {
+    let mut options = query.preset.unwrap_or_default().options();
+    if let Some(delimiter) = query.delimiter {
+        options.delimiter = delimiter;
+    }
+    if let Some(decimal_separator) = query.decimal_separator {
+        options.decimal_separator = decimal_separator;
+    }
+    if let Some(timestamp_format) = query.timestamp_format {
+        options.timestamp_format = timestamp_format;
+        if let Err(e) = options.check_timestamp_format() {
+            return (
+                StatusCode::BAD_REQUEST,
+                [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+                e.into_bytes(),
+            );
+        }
+    }
+    if query.decimals.is_some() {
+        options.precision.decimals = query.decimals;
+    }
+    if let Some(rounding) = query.rounding {
+        options.precision.rounding = rounding;
+    }
+    if let Some(columns) = &query.columns {
+        match CsvColumn::parse_columns(columns) {
+            Ok(columns) => options.columns = columns,
+            Err(e) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+                    e.into_bytes(),
+                );
+            }
+        }
+    }
+    let from = query.from.unwrap_or(Utc::now().timestamp() - 24 * 60 * 60);
+
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let mut operations = mongodb_client_subscan
+        .get_filtered_operations(from, query.to)
+        .await;
+    if let Some(address_format) = query.address_format {
+        let network = Network::from_env();
+        operations = operations
+            .into_iter()
+            .map(|o| o.with_address_format(&network, address_format))
+            .collect();
+    }
+    let annotations = get_export_annotations(&operations).await;
+
+    let compression = query.compression.unwrap_or_default();
+    let mut buffer = Vec::new();
+    let csv = write_csv(&mut buffer, operations, &annotations, &options)
+        .and_then(|_| compression.compress(buffer));
+    match csv {
+        Ok(csv) => (
+            StatusCode::OK,
+            [(
+                header::CONTENT_TYPE,
+                compression
+                    .get_content_type()
+                    .unwrap_or("text/csv; charset=utf-8"),
+            )],
+            csv,
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            e.to_string().into_bytes(),
+        ),
+    }
+}
@@ -0,0 +1,217 @@
+use crate::ApiOperation;
+use axum::http::StatusCode;
+use rs_subscan_parser::{
+    address::AddressFormat, exports::ExportOperation, subscan_parser::Network, SubscanOperation,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+// served items with named fields, listed as (short name, serialized key) in the order
+// compact items use when no fields are requested
+pub trait Fields: Serialize {
+    const FIELDS: &'static [(&'static str, &'static str)];
+}
+
+impl Fields for ApiOperation {
+    const FIELDS: &'static [(&'static str, &'static str)] = &[
+        ("hash", "hash"),
+        ("block", "block_number"),
+        ("extrinsic", "extrinsic_index"),
+        ("timestamp", "operation_timestamp"),
+        ("type", "operation_type"),
+        ("from", "from_wallet"),
+        ("controller", "controller_wallet"),
+        ("to", "to_wallet"),
+        ("quantity", "operation_quantity"),
+        ("usd", "operation_usd"),
+        ("fee", "operation_fee"),
+        ("era", "operation_era"),
+        ("status", "status"),
+        ("explorer", "explorer_url"),
+        ("id", "operation_id"),
+    ];
+}
+
+impl Fields for ExportOperation {
+    const FIELDS: &'static [(&'static str, &'static str)] = &[
+        ("hash", "hash"),
+        ("block", "block_number"),
+        ("extrinsic", "extrinsic_index"),
+        ("timestamp", "operation_timestamp"),
+        ("type", "operation_type"),
+        ("from", "from_wallet"),
+        ("controller", "controller_wallet"),
+        ("to", "to_wallet"),
+        ("quantity", "amount"),
+        ("planck", "amount_planck"),
+        ("usd", "amount_usd"),
+        ("annotations", "annotations"),
+        ("prices", "price_annotation"),
+        ("id", "operation_id"),
+    ];
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct FieldsQuery {
+    // comma separated short names or keys, all fields by default
+    pub fields: Option<String>,
+    // items are arrays of values in the order of fields instead of objects
+    #[serde(default)]
+    pub compact: bool,
+    // encoding of the served wallets, the stored one by default
+    pub address_format: Option<AddressFormat>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct FieldsPage {
+    pub fields: Vec<String>,
+    pub items: Vec<Value>,
+    pub next_cursor: Option<String>,
+}
+
+impl FieldsQuery {
+    // unknown fields are rejected so typos don't silently return empty items
+    pub fn get_keys<T: Fields>(&self) -> Result<Vec<&'static str>, StatusCode> {
+        let Some(fields) = &self.fields else {
+            return Ok(T::FIELDS.iter().map(|(_, key)| *key).collect());
+        };
+
+        let mut keys = Vec::new();
+        for field in fields.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let key = T::FIELDS
+                .iter()
+                .find(|(name, key)| *name == field || *key == field)
+                .map(|(_, key)| *key)
+                .ok_or(StatusCode::BAD_REQUEST)?;
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+
+        Ok(keys)
+    }
+
+    pub fn encode(&self, operation: SubscanOperation, network: &Network) -> SubscanOperation {
+        match self.address_format {
+            Some(address_format) => operation.with_address_format(network, address_format),
+            None => operation,
+        }
+    }
+
+    pub fn select<T: Fields>(&self, item: &T, keys: &[&str]) -> Value {
+        let mut value = serde_json::to_value(item).unwrap_or_default();
+        let Value::Object(map) = &mut value else {
+            return value;
+        };
+
+        if self.compact {
+            Value::Array(
+                keys.iter()
+                    .map(|k| map.remove(*k).unwrap_or_default())
+                    .collect(),
+            )
+        } else {
+            Value::Object(
+                keys.iter()
+                    .filter_map(|k| map.remove(*k).map(|v| (k.to_string(), v)))
+                    .collect::<Map<String, Value>>(),
+            )
+        }
+    }
+
+    pub fn select_all<T: Fields>(
+        &self,
+        items: &[T],
+    ) -> Result<(Vec<String>, Vec<Value>), StatusCode> {
+        let keys = self.get_keys::<T>()?;
+        let items = items.iter().map(|i| self.select(i, &keys)).collect();
+
+        Ok((keys.into_iter().map(str::to_string).collect(), items))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{fields::FieldsQuery, ApiOperation};
+    use axum::http::StatusCode;
+    use rs_subscan_parser::{OperationStatus, OperationType};
+    use serde_json::json;
+
+    fn operation() -> ApiOperation {
+        ApiOperation {
+            hash: "hash".to_string(),
+            block_number: 1,
+            extrinsic_index: "1-1".to_string(),
+            operation_timestamp: "2023-11-14T22:13:20Z".to_string(),
+            operation_quantity: 1.5,
+            operation_usd: 3.0,
+            operation_fee: None,
+            operation_era: None,
+            operation_type: OperationType::Stake,
+            from_wallet: "from".to_string(),
+            controller_wallet: "controller".to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Finalized,
+            explorer_url: Some("https://alephzero.subscan.io/extrinsic/1-1".to_string()),
+            operation_id: None,
+        }
+    }
+
+    #[test]
+    fn fields_accept_short_names_and_keys() {
+        let query = FieldsQuery {
+            fields: Some("type, operation_quantity,timestamp,type".to_string()),
+            compact: false,
+            address_format: None,
+        };
+        assert_eq!(
+            query.get_keys::<ApiOperation>(),
+            Ok(vec![
+                "operation_type",
+                "operation_quantity",
+                "operation_timestamp"
+            ])
+        );
+
+        let query = FieldsQuery {
+            fields: Some("type,amount".to_string()),
+            compact: false,
+            address_format: None,
+        };
+        assert_eq!(
+            query.get_keys::<ApiOperation>(),
+            Err(StatusCode::BAD_REQUEST)
+        );
+    }
+
+    #[test]
+    fn selected_fields_as_objects_or_arrays() {
+        let mut query = FieldsQuery {
+            fields: Some("type,quantity".to_string()),
+            compact: false,
+            address_format: None,
+        };
+
+        let (fields, items) = query.select_all(&[operation()]).unwrap();
+        assert_eq!(fields, vec!["operation_type", "operation_quantity"]);
+        assert_eq!(
+            items,
+            vec![json!({"operation_type": "Stake", "operation_quantity": 1.5})]
+        );
+
+        query.compact = true;
+        let (_, items) = query.select_all(&[operation()]).unwrap();
+        assert_eq!(items, vec![json!(["Stake", 1.5])]);
+
+        let (fields, items) = FieldsQuery::default().select_all(&[operation()]).unwrap();
+        assert_eq!(fields.len(), 15);
+        assert_eq!(items[0]["to_wallet"], "to");
+
+        query.fields = Some("extrinsic,explorer".to_string());
+        let (_, items) = query.select_all(&[operation()]).unwrap();
+        assert_eq!(
+            items,
+            vec![json!(["1-1", "https://alephzero.subscan.io/extrinsic/1-1"])]
+        );
+    }
+}
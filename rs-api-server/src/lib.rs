@@ -0,0 +1,262 @@
+use axum::{middleware, routing::get, Router};
+use bson::DateTime;
+use rs_subscan_parser::{
+    explorer, subscan_parser::Network, OperationStatus, OperationSummary, OperationTotals,
+    OperationType, StakingFlowCandle, SubscanOperation, SummaryDirection, Validator, VolumeAnomaly,
+    VolumeAnomalyKind,
+};
+use serde::{Deserialize, Serialize};
+
+pub mod admin;
+pub mod alerts;
+pub mod conditional;
+pub mod errors;
+pub mod exports;
+pub mod fields;
+pub mod limits;
+pub mod mongodb_client_api_usage;
+pub mod operations;
+pub mod pagination;
+pub mod stats;
+pub mod stream;
+pub mod usage;
+pub mod wallets;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ApiOperation {
+    pub hash: String,
+    pub block_number: u64,
+    pub extrinsic_index: String,
+    pub operation_timestamp: String,
+    pub operation_quantity: f64,
+    pub operation_usd: f64,
+
+    // only known for transfers
+    pub operation_fee: Option<f64>,
+
+    // only known for rewards and slashes
+    pub operation_era: Option<u32>,
+    pub operation_type: OperationType,
+    pub from_wallet: String,
+    pub controller_wallet: String,
+    pub to_wallet: String,
+
+    // pending operations are provisional until their block is finalized
+    pub status: OperationStatus,
+
+    // subscan page of the extrinsic, none on networks without a web explorer
+    pub explorer_url: Option<String>,
+
+    // time sortable id next to the hash, none unless the parser sets OPERATION_ID_SCHEME
+    pub operation_id: Option<String>,
+}
+
+impl ApiOperation {
+    pub fn new(s: SubscanOperation, network: &Network) -> Self {
+        let explorer_url = explorer::get_operation_url(network, &s);
+        Self {
+            hash: s.hash,
+            block_number: s.block_number,
+            extrinsic_index: s.extrinsic_index,
+            operation_timestamp: to_rfc3339(s.operation_timestamp),
+            operation_quantity: s.operation_quantity,
+            operation_usd: s.operation_usd,
+            operation_fee: s.operation_fee,
+            operation_era: s.operation_era,
+            operation_type: s.operation_type,
+            from_wallet: s.from_wallet,
+            controller_wallet: s.controller_wallet,
+            to_wallet: s.to_wallet,
+            status: s.status,
+            explorer_url,
+            operation_id: s.operation_id,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ApiNomination {
+    pub validators: Vec<String>,
+    pub valid_from: Option<String>,
+    pub valid_to: Option<String>,
+}
+
+impl From<Validator> for ApiNomination {
+    fn from(v: Validator) -> Self {
+        // mappings stored before versioning have no known start
+        let valid_from = if v.valid_from == DateTime::MIN {
+            None
+        } else {
+            Some(to_rfc3339(v.valid_from))
+        };
+
+        Self {
+            validators: v.validators,
+            valid_from,
+            valid_to: v.valid_to.map(to_rfc3339),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ApiStakingFlowCandle {
+    pub day: String,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub inflow: f64,
+    pub outflow: f64,
+}
+
+impl From<StakingFlowCandle> for ApiStakingFlowCandle {
+    fn from(c: StakingFlowCandle) -> Self {
+        Self {
+            day: to_rfc3339(c.day),
+            open: c.open,
+            high: c.high,
+            low: c.low,
+            close: c.close,
+            inflow: c.inflow,
+            outflow: c.outflow,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ApiOperationSummary {
+    pub hour: String,
+    pub operation_type: OperationType,
+    pub direction: SummaryDirection,
+    pub count: u64,
+    pub quantity: f64,
+    pub usd: f64,
+}
+
+impl From<OperationSummary> for ApiOperationSummary {
+    fn from(s: OperationSummary) -> Self {
+        Self {
+            hour: to_rfc3339(s.hour),
+            operation_type: s.operation_type,
+            direction: s.direction,
+            count: s.count,
+            quantity: s.quantity,
+            usd: s.usd,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ApiOperationTotals {
+    pub operation_type: OperationType,
+    pub count: u64,
+    pub quantity: f64,
+    pub usd: f64,
+}
+
+impl From<OperationTotals> for ApiOperationTotals {
+    fn from(t: OperationTotals) -> Self {
+        Self {
+            operation_type: t.operation_type,
+            count: t.count,
+            quantity: t.quantity,
+            usd: t.usd,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ApiVolumeAnomaly {
+    pub hour: String,
+    pub operation_type: OperationType,
+    pub kind: VolumeAnomalyKind,
+    pub count: u64,
+    pub baseline_mean: f64,
+    pub baseline_stddev: f64,
+    pub z_score: f64,
+    pub detected_at: String,
+}
+
+impl From<VolumeAnomaly> for ApiVolumeAnomaly {
+    fn from(a: VolumeAnomaly) -> Self {
+        Self {
+            hour: to_rfc3339(a.hour),
+            operation_type: a.operation_type,
+            kind: a.kind,
+            count: a.count,
+            baseline_mean: a.baseline_mean,
+            baseline_stddev: a.baseline_stddev,
+            z_score: a.z_score,
+            detected_at: to_rfc3339(a.detected_at),
+        }
+    }
+}
+
+pub fn to_rfc3339(timestamp: DateTime) -> String {
+    timestamp.try_to_rfc3339_string().unwrap_or_default()
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/wallets/:address/nominations/history",
+            get(wallets::get_nominations_history),
+        )
+        .route(
+            "/wallets/:address/summaries",
+            get(wallets::get_wallet_summaries),
+        )
+        .route("/wallets/:address/totals", get(wallets::get_wallet_totals))
+        .route(
+            "/wallets/:address/timeline",
+            get(wallets::get_wallet_timeline),
+        )
+        .route(
+            "/stats/validators/:address/totals",
+            get(stats::get_validator_totals),
+        )
+        .route(
+            "/stats/validators/:address/staking-flow",
+            get(stats::get_staking_flow),
+        )
+        .route("/stats/volume-anomalies", get(stats::get_volume_anomalies))
+        .route("/stats/latency", get(stats::get_latency_report))
+        .route(
+            "/stats/validator-concentration",
+            get(stats::get_validator_concentration),
+        )
+        .route(
+            "/stats/wallet-comparison",
+            get(stats::get_wallet_comparison),
+        )
+        .route("/operations/stream", get(stream::stream_operations))
+        .route(
+            "/operations/:hash/annotations",
+            get(operations::get_annotations).post(operations::add_annotation),
+        )
+        .route(
+            "/operations/:hash/prices",
+            get(operations::get_price_annotation),
+        )
+        .route("/operations/:hash/latency", get(operations::get_latency))
+        .route("/exports/operations", get(exports::get_operations_export))
+        .route(
+            "/alerts/:id/ack",
+            get(alerts::ack_alert).post(alerts::ack_alert),
+        )
+        .route(
+            "/alerts/:id/resolve",
+            get(alerts::resolve_alert).post(alerts::resolve_alert),
+        )
+        .route(
+            "/exports/operations.csv",
+            get(exports::get_operations_csv_export),
+        )
+        .route("/usage", get(usage::get_usage))
+        // metering counts the bytes actually sent, i.e. after 304 answers
+        .route_layer(middleware::from_fn(conditional::with_conditional_requests))
+        .route_layer(middleware::from_fn(usage::with_usage_metering))
+        .route_layer(middleware::from_fn(limits::with_tenant_limits))
+        .nest("/admin", admin::router())
+        .layer(middleware::from_fn(errors::with_error_codes))
+}
@@ -0,0 +1,234 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures::StreamExt;
+use std::{
+    collections::HashMap,
+    env,
+    str::FromStr,
+    sync::{Mutex, MutexGuard, OnceLock},
+    time::Instant,
+};
+
+static DEFAULT_REQUESTS_PER_MINUTE: u32 = 120;
+static DEFAULT_MAX_CONNECTIONS: usize = 10;
+
+static TENANT_STATES: OnceLock<Mutex<HashMap<String, TenantState>>> = OnceLock::new();
+
+// api consumer identified by its key, limits are shared by all keys of a tenant
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tenant {
+    pub name: String,
+    pub requests_per_minute: u32,
+    pub max_connections: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct TenantState {
+    tokens: f64,
+    refilled_at: Instant,
+    connections: usize,
+}
+
+// frees the connection slot once the response body is dropped, streams hold it until closed
+struct ConnectionGuard {
+    tenant: String,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let mut states = get_tenant_states();
+        if let Some(state) = states.get_mut(&self.tenant) {
+            state.connections = state.connections.saturating_sub(1);
+        }
+    }
+}
+
+fn get_tenant_states() -> MutexGuard<'static, HashMap<String, TenantState>> {
+    TENANT_STATES
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+// the api stays open while API_KEYS is not configured
+pub fn has_api_keys() -> bool {
+    !env::var("API_KEYS").unwrap_or_default().trim().is_empty()
+}
+
+// API_KEYS=partner:key,... as api key -> tenant name
+pub fn parse_api_keys(api_keys: &str) -> HashMap<String, String> {
+    api_keys
+        .split(',')
+        .filter_map(|k| k.trim().split_once(':'))
+        .map(|(tenant, key)| (key.trim().to_string(), tenant.trim().to_string()))
+        .filter(|(key, tenant)| !key.is_empty() && !tenant.is_empty())
+        .collect()
+}
+
+impl Tenant {
+    // e.g. API_REQUESTS_PER_MINUTE_PARTNER, API_REQUESTS_PER_MINUTE for all other tenants
+    fn get_limit<T: FromStr>(name: &str, tenant: &str, default: T) -> T {
+        let tenant = tenant.to_uppercase().replace('-', "_");
+        env::var(format!("{name}_{tenant}"))
+            .or_else(|_| env::var(name))
+            .ok()
+            .and_then(|v| v.parse::<T>().ok())
+            .unwrap_or(default)
+    }
+
+    pub fn from_api_key(api_key: &str) -> Option<Tenant> {
+        let api_keys = parse_api_keys(&env::var("API_KEYS").unwrap_or_default());
+        let name = api_keys.get(api_key)?.clone();
+
+        Some(Tenant {
+            requests_per_minute: Tenant::get_limit(
+                "API_REQUESTS_PER_MINUTE",
+                &name,
+                DEFAULT_REQUESTS_PER_MINUTE,
+            ),
+            max_connections: Tenant::get_limit(
+                "API_MAX_CONNECTIONS",
+                &name,
+                DEFAULT_MAX_CONNECTIONS,
+            ),
+            name,
+        })
+    }
+
+    // seconds to wait when out of requests, none when all connections are in use
+    fn acquire(&self) -> Result<ConnectionGuard, Option<u64>> {
+        let mut states = get_tenant_states();
+        let state = states.entry(self.name.clone()).or_insert(TenantState {
+            tokens: self.requests_per_minute as f64,
+            refilled_at: Instant::now(),
+            connections: 0,
+        });
+        if state.connections >= self.max_connections {
+            return Err(None);
+        }
+        take_token(state, self.requests_per_minute, Instant::now()).map_err(Some)?;
+        state.connections += 1;
+
+        Ok(ConnectionGuard {
+            tenant: self.name.clone(),
+        })
+    }
+}
+
+// token bucket refilled continuously up to one minute of requests
+fn take_token(state: &mut TenantState, requests_per_minute: u32, now: Instant) -> Result<(), u64> {
+    let per_second = requests_per_minute as f64 / 60.0;
+    let elapsed = now
+        .saturating_duration_since(state.refilled_at)
+        .as_secs_f64();
+    state.tokens = (state.tokens + elapsed * per_second).min(requests_per_minute as f64);
+    state.refilled_at = now;
+
+    if state.tokens < 1.0 {
+        if per_second <= 0.0 {
+            return Err(60);
+        }
+        return Err(((1.0 - state.tokens) / per_second).ceil() as u64);
+    }
+    state.tokens -= 1.0;
+
+    Ok(())
+}
+
+// the header is preferred, the query parameter is there for EventSource clients
+fn get_api_key(request: &Request) -> Option<String> {
+    if let Some(api_key) = request
+        .headers()
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok())
+    {
+        return Some(api_key.to_string());
+    }
+
+    request
+        .uri()
+        .query()?
+        .split('&')
+        .find_map(|p| p.strip_prefix("api_key="))
+        .map(str::to_string)
+}
+
+pub async fn with_tenant_limits(mut request: Request, next: Next) -> Response {
+    if !has_api_keys() {
+        return next.run(request).await;
+    }
+
+    let Some(tenant) = get_api_key(&request).and_then(|k| Tenant::from_api_key(&k)) else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let guard = match tenant.acquire() {
+        Ok(guard) => guard,
+        Err(Some(retry_after)) => {
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                "request rate limit exceeded",
+            )
+                .into_response();
+        }
+        Err(None) => {
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                "concurrent connection limit exceeded",
+            )
+                .into_response();
+        }
+    };
+
+    request.extensions_mut().insert(tenant);
+    let (parts, body) = next.run(request).await.into_parts();
+    let body = body.into_data_stream().map(move |chunk| {
+        let _connection = &guard;
+        chunk
+    });
+
+    Response::from_parts(parts, Body::from_stream(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::limits::{parse_api_keys, take_token, TenantState};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn api_keys_map_to_tenants() {
+        let api_keys = parse_api_keys("partner:key1, partner:key2,broken,other:key3");
+        assert_eq!(api_keys.len(), 3);
+        assert_eq!(api_keys["key2"], "partner");
+        assert_eq!(api_keys["key3"], "other");
+    }
+
+    #[test]
+    fn tokens_refill_over_time() {
+        let now = Instant::now();
+        let mut state = TenantState {
+            tokens: 2.0,
+            refilled_at: now,
+            connections: 0,
+        };
+
+        assert_eq!(take_token(&mut state, 60, now), Ok(()));
+        assert_eq!(take_token(&mut state, 60, now), Ok(()));
+        assert_eq!(take_token(&mut state, 60, now), Err(1));
+        assert_eq!(take_token(&mut state, 6, now), Err(10));
+
+        let later = now + Duration::from_secs(5);
+        assert_eq!(take_token(&mut state, 60, later), Ok(()));
+        assert!((state.tokens - 4.0).abs() < 1e-9);
+
+        // refills never exceed one minute of requests
+        let much_later = later + Duration::from_secs(3_600);
+        assert_eq!(take_token(&mut state, 60, much_later), Ok(()));
+        assert!((state.tokens - 59.0).abs() < 1e-9);
+    }
+}
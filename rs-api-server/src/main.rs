@@ -0,0 +1,32 @@
+use log::info;
+use rs_api_server::{limits::has_api_keys, router, usage::flush_usage_periodically};
+use rs_subscan_parser::mongodb_client_operation_annotations::MongoDbClientOperationAnnotations;
+use rs_utils::utils::logger::initialize_logger;
+use std::env;
+use tokio::net::TcpListener;
+
+static DEFAULT_API_SERVER_ADDRESS: &str = "0.0.0.0:3000";
+
+#[tokio::main(worker_threads = 10)]
+async fn main() {
+    initialize_logger().expect("failed to initialize logging.");
+
+    let mut mongodb_client_operation_annotations = MongoDbClientOperationAnnotations::new().await;
+    mongodb_client_operation_annotations.create_index().await;
+
+    // only requests of api key tenants are metered
+    if has_api_keys() {
+        tokio::spawn(flush_usage_periodically());
+    }
+
+    let address = env::var("API_SERVER_ADDRESS").unwrap_or(DEFAULT_API_SERVER_ADDRESS.to_string());
+    let listener = TcpListener::bind(&address)
+        .await
+        .expect("failed to bind api server address.");
+
+    info!(target: "api_server", "Started api server on {address}.");
+
+    axum::serve(listener, router())
+        .await
+        .expect("api server stopped.");
+}
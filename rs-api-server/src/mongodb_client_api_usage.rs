@@ -0,0 +1,89 @@
+use crate::usage::ApiUsage;
+use bson::{doc, Bson, DateTime};
+use mongodb::{
+    options::{IndexOptions, UpdateOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientApiUsage {
+    pub client_api_usage: MongoDbClient<ApiUsage>,
+}
+
+impl MongoDbClientApiUsage {
+    pub async fn new() -> MongoDbClientApiUsage {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_API_USAGE").unwrap();
+        let client_name = "mongodb_api_usage";
+        let client_api_usage = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self { client_api_usage }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"tenant": 1u32, "day": 1u32})
+            .options(options)
+            .build();
+        self.client_api_usage.create_index(model, None).await;
+    }
+
+    pub async fn increment_usage(&mut self, usage: Vec<ApiUsage>) {
+        for u in usage {
+            let options = Some(UpdateOptions::builder().upsert(true).build());
+            self.client_api_usage
+                .update_one(
+                    doc! {
+                        "tenant": u.tenant,
+                        "day": u.day,
+                    },
+                    doc! { "$inc": {
+                        "requests": u.requests as i64,
+                        "bytes": u.bytes as i64,
+                    }},
+                    options,
+                )
+                .await;
+        }
+    }
+
+    // all tenants if none is given
+    pub async fn get_usage_page(
+        &mut self,
+        tenant: Option<&str>,
+        from: i64,
+        to: i64,
+        after: Option<Vec<Bson>>,
+        limit: i64,
+    ) -> Vec<ApiUsage> {
+        let mut query = doc! {
+            "day": {
+                "$gte": DateTime::from_millis(from * 1000),
+                "$lte": DateTime::from_millis(to * 1000),
+            },
+        };
+        if let Some(tenant) = tenant {
+            query.insert("tenant", tenant);
+        }
+
+        self.client_api_usage
+            .find_page(
+                query,
+                doc! {"day": 1i32, "tenant": 1i32},
+                after,
+                limit,
+                None,
+            )
+            .await
+    }
+
+    pub fn get_page_key(usage: &ApiUsage) -> Vec<Bson> {
+        vec![
+            Bson::DateTime(usage.day),
+            Bson::String(usage.tenant.clone()),
+        ]
+    }
+}
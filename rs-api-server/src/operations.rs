@@ -0,0 +1,138 @@
+use crate::{
+    pagination::{Page, PageQuery},
+    to_rfc3339,
+};
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    Json,
+};
+use bson::DateTime;
+use rs_subscan_parser::{
+    exports::ExportPriceAnnotation,
+    mongodb_client_operation_annotations::MongoDbClientOperationAnnotations,
+    mongodb_client_operation_latencies::MongoDbClientOperationLatencies,
+    mongodb_client_price_annotations::MongoDbClientPriceAnnotations, OperationAnnotation,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct NewAnnotation {
+    pub text: String,
+    #[serde(default)]
+    pub suppress_alerts: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ApiAnnotation {
+    pub text: String,
+    pub suppress_alerts: bool,
+    pub created_at: String,
+}
+
+impl From<OperationAnnotation> for ApiAnnotation {
+    fn from(a: OperationAnnotation) -> Self {
+        Self {
+            text: a.text,
+            suppress_alerts: a.suppress_alerts,
+            created_at: to_rfc3339(a.created_at),
+        }
+    }
+}
+
+pub async fn get_annotations(
+    Path(hash): Path<String>,
+    Query(page): Query<PageQuery>,
+) -> Result<Json<Page<ApiAnnotation>>, StatusCode> {
+    let mut mongodb_client_operation_annotations = MongoDbClientOperationAnnotations::new().await;
+    let annotations = mongodb_client_operation_annotations
+        .get_annotations_page(&hash, page.get_after()?, page.get_fetch_limit())
+        .await;
+
+    Ok(Json(
+        Page::new(
+            annotations,
+            &page,
+            MongoDbClientOperationAnnotations::get_page_key,
+        )
+        .map(ApiAnnotation::from),
+    ))
+}
+
+// only large operations are annotated with prices
+pub async fn get_price_annotation(
+    Path(hash): Path<String>,
+) -> Result<Json<ExportPriceAnnotation>, StatusCode> {
+    let mut mongodb_client_price_annotations = MongoDbClientPriceAnnotations::new().await;
+    let annotation = mongodb_client_price_annotations
+        .get_annotation(&hash)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(ExportPriceAnnotation::from(annotation)))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ApiDelivery {
+    pub sink: String,
+    pub delivered_at: String,
+    pub latency_ms: i64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ApiOperationLatency {
+    pub hash: String,
+    pub operation_timestamp: String,
+    pub deliveries: Vec<ApiDelivery>,
+}
+
+// sinks the operation has not reached yet are missing from the deliveries
+pub async fn get_latency(
+    Path(hash): Path<String>,
+) -> Result<Json<ApiOperationLatency>, StatusCode> {
+    let mut mongodb_client_operation_latencies = MongoDbClientOperationLatencies::new().await;
+    let latency = mongodb_client_operation_latencies
+        .get_latency(&hash)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let timestamp_ms = latency.operation_timestamp.timestamp_millis();
+    let mut deliveries = latency
+        .delivered_at
+        .into_iter()
+        .map(|(sink, delivered_at)| ApiDelivery {
+            sink,
+            delivered_at: to_rfc3339(delivered_at),
+            latency_ms: delivered_at.timestamp_millis() - timestamp_ms,
+        })
+        .collect::<Vec<_>>();
+    deliveries.sort_by_key(|d| d.latency_ms);
+
+    Ok(Json(ApiOperationLatency {
+        hash: latency.hash,
+        operation_timestamp: to_rfc3339(latency.operation_timestamp),
+        deliveries,
+    }))
+}
+
+pub async fn add_annotation(
+    Path(hash): Path<String>,
+    Json(annotation): Json<NewAnnotation>,
+) -> Result<(StatusCode, Json<ApiAnnotation>), StatusCode> {
+    if annotation.text.trim().is_empty() {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let annotation = OperationAnnotation {
+        hash,
+        text: annotation.text,
+        suppress_alerts: annotation.suppress_alerts,
+        created_at: DateTime::now(),
+    };
+    let mut mongodb_client_operation_annotations = MongoDbClientOperationAnnotations::new().await;
+    mongodb_client_operation_annotations
+        .import_annotation(annotation.clone())
+        .await;
+
+    Ok((StatusCode::CREATED, Json(ApiAnnotation::from(annotation))))
+}
@@ -0,0 +1,127 @@
+use axum::http::StatusCode;
+use bson::{doc, Bson, Document};
+use rs_subscan_parser::{mongodb_client_subscan::MongoDbClientSubscan, SubscanOperation};
+use serde::{Deserialize, Serialize};
+
+static DEFAULT_PAGE_LIMIT: i64 = 100;
+static MAX_PAGE_LIMIT: i64 = 1_000;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct PageQuery {
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    // none on the last page
+    pub next_cursor: Option<String>,
+}
+
+// cursors are opaque to clients, they only pass back what they got in next_cursor
+pub fn encode_cursor(after: Vec<Bson>) -> String {
+    let mut bytes = Vec::new();
+    let _ = doc! {"after": after}.to_writer(&mut bytes);
+    hex::encode(bytes)
+}
+
+pub fn decode_cursor(cursor: &str) -> Option<Vec<Bson>> {
+    let bytes = hex::decode(cursor).ok()?;
+    let document = Document::from_reader(bytes.as_slice()).ok()?;
+    document.get_array("after").ok().cloned()
+}
+
+impl PageQuery {
+    pub fn get_limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_LIMIT)
+            .clamp(1, MAX_PAGE_LIMIT)
+    }
+
+    // one extra item is fetched to know whether another page exists
+    pub fn get_fetch_limit(&self) -> i64 {
+        self.get_limit() + 1
+    }
+
+    pub fn get_after(&self) -> Result<Option<Vec<Bson>>, StatusCode> {
+        match &self.cursor {
+            None => Ok(None),
+            Some(cursor) => decode_cursor(cursor)
+                .map(Some)
+                .ok_or(StatusCode::BAD_REQUEST),
+        }
+    }
+
+    pub async fn get_operations_page(
+        &self,
+        query: Document,
+    ) -> Result<Page<SubscanOperation>, StatusCode> {
+        let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+        let items = mongodb_client_subscan
+            .get_operations_page(query, self.get_after()?, self.get_fetch_limit())
+            .await;
+
+        Ok(Page::new(items, self, MongoDbClientSubscan::get_page_key))
+    }
+}
+
+impl<T> Page<T> {
+    // items are expected to be fetched with get_fetch_limit
+    pub fn new(mut items: Vec<T>, query: &PageQuery, get_key: impl Fn(&T) -> Vec<Bson>) -> Self {
+        let limit = query.get_limit() as usize;
+        let mut next_cursor = None;
+        if items.len() > limit {
+            items.truncate(limit);
+            next_cursor = items.last().map(|i| encode_cursor(get_key(i)));
+        }
+
+        Self { items, next_cursor }
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pagination::{decode_cursor, encode_cursor, Page, PageQuery};
+    use bson::{Bson, DateTime};
+
+    #[test]
+    fn cursors_roundtrip() {
+        let after = vec![
+            Bson::Int64(42),
+            Bson::String("42-3".to_string()),
+            Bson::DateTime(DateTime::from_millis(1_700_000_000_000)),
+        ];
+
+        let encoded = encode_cursor(after.clone());
+        assert!(encoded.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(decode_cursor(&encoded), Some(after));
+        assert_eq!(decode_cursor("not a cursor"), None);
+    }
+
+    #[test]
+    fn next_cursor_only_when_more_items() {
+        let query = PageQuery {
+            cursor: None,
+            limit: Some(2),
+        };
+        let get_key = |i: &i64| vec![Bson::Int64(*i)];
+
+        let page = Page::new(vec![1, 2, 3], &query, get_key);
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!(
+            page.next_cursor.as_deref().and_then(decode_cursor),
+            Some(vec![Bson::Int64(2)])
+        );
+
+        let page = Page::new(vec![1, 2], &query, get_key);
+        assert_eq!(page.next_cursor, None);
+    }
+}
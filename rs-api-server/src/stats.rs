@@ -0,0 +1,227 @@
+use crate::{
+    pagination::{Page, PageQuery},
+    wallets::get_stored_address,
+    ApiOperationTotals, ApiStakingFlowCandle, ApiVolumeAnomaly,
+};
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    Json,
+};
+use bson::DateTime;
+use rs_subscan_parser::{
+    latency::{build_report, get_latency_slo_ms, LatencyReport},
+    mongodb_client_latency_histograms::MongoDbClientLatencyHistograms,
+    mongodb_client_operation_totals::MongoDbClientOperationTotals,
+    mongodb_client_staking_flow::MongoDbClientStakingFlow,
+    mongodb_client_volume_anomalies::MongoDbClientVolumeAnomalies,
+    sinks::Sink,
+    validator_concentration::{
+        get_concentration_report, get_max_share, get_top_nominators, ConcentrationReport,
+    },
+    wallet_comparison::{
+        compare_wallets, get_comparison_window_seconds, WalletComparison, MAX_COMPARED_WALLETS,
+    },
+    TotalsView,
+};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+static DEFAULT_LATENCY_REPORT_HOURS: i64 = 24;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct TimeRange {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct Totals {
+    pub address: String,
+    pub totals: Vec<ApiOperationTotals>,
+    pub next_cursor: Option<String>,
+}
+
+impl Totals {
+    pub async fn get(
+        view: TotalsView,
+        address: String,
+        page: &PageQuery,
+    ) -> Result<Totals, StatusCode> {
+        let mut mongodb_client_operation_totals = MongoDbClientOperationTotals::new().await;
+        let totals = mongodb_client_operation_totals
+            .get_totals_page(&view, &address, page.get_after()?, page.get_fetch_limit())
+            .await;
+        let totals = Page::new(totals, page, MongoDbClientOperationTotals::get_page_key)
+            .map(ApiOperationTotals::from);
+
+        Ok(Totals {
+            address,
+            totals: totals.items,
+            next_cursor: totals.next_cursor,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct StakingFlow {
+    pub validator: String,
+    pub candles: Vec<ApiStakingFlowCandle>,
+    pub next_cursor: Option<String>,
+}
+
+// from/to are unix timestamps in seconds, whole history by default
+pub async fn get_staking_flow(
+    Path(address): Path<String>,
+    Query(range): Query<TimeRange>,
+    Query(page): Query<PageQuery>,
+) -> Result<Json<StakingFlow>, StatusCode> {
+    let address = get_stored_address(address);
+    let mut mongodb_client_staking_flow = MongoDbClientStakingFlow::new().await;
+    let candles = mongodb_client_staking_flow
+        .get_candles(
+            &address,
+            range.from.unwrap_or(0),
+            range.to.unwrap_or(i64::MAX / 1000),
+            page.get_after()?,
+            page.get_fetch_limit(),
+        )
+        .await;
+    let candles = Page::new(candles, &page, MongoDbClientStakingFlow::get_page_key)
+        .map(ApiStakingFlowCandle::from);
+
+    Ok(Json(StakingFlow {
+        validator: address,
+        candles: candles.items,
+        next_cursor: candles.next_cursor,
+    }))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct WalletComparisonQuery {
+    // comma separated wallets in any encoding
+    pub wallets: String,
+    // seconds between coinciding operations, WALLET_COMPARISON_WINDOW_SECONDS by default
+    pub window: Option<i64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ValidatorConcentrationQuery {
+    // nominators summed up per validator, VALIDATOR_CONCENTRATION_TOP_NOMINATORS by default
+    pub top: Option<usize>,
+    // share of the top nominators flagged above, VALIDATOR_CONCENTRATION_MAX_SHARE by default
+    pub max_share: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct LatencyQuery {
+    // snake case sink name, mongodb by default
+    pub sink: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct VolumeAnomalies {
+    pub anomalies: Vec<ApiVolumeAnomaly>,
+    pub next_cursor: Option<String>,
+}
+
+// from/to are unix timestamps in seconds of the anomalous hours
+pub async fn get_volume_anomalies(
+    Query(range): Query<TimeRange>,
+    Query(page): Query<PageQuery>,
+) -> Result<Json<VolumeAnomalies>, StatusCode> {
+    let mut mongodb_client_volume_anomalies = MongoDbClientVolumeAnomalies::new().await;
+    let anomalies = mongodb_client_volume_anomalies
+        .get_anomalies_page(
+            range.from.unwrap_or(0),
+            range.to.unwrap_or(i64::MAX / 1000),
+            page.get_after()?,
+            page.get_fetch_limit(),
+        )
+        .await;
+    let anomalies = Page::new(anomalies, &page, MongoDbClientVolumeAnomalies::get_page_key)
+        .map(ApiVolumeAnomaly::from);
+
+    Ok(Json(VolumeAnomalies {
+        anomalies: anomalies.items,
+        next_cursor: anomalies.next_cursor,
+    }))
+}
+
+pub async fn get_validator_totals(
+    Path(address): Path<String>,
+    Query(page): Query<PageQuery>,
+) -> Result<Json<Totals>, StatusCode> {
+    Totals::get(TotalsView::Validator, get_stored_address(address), &page)
+        .await
+        .map(Json)
+}
+
+pub async fn get_wallet_comparison(
+    Query(query): Query<WalletComparisonQuery>,
+) -> Result<Json<WalletComparison>, StatusCode> {
+    let wallets = query
+        .wallets
+        .split(',')
+        .map(str::trim)
+        .filter(|w| !w.is_empty())
+        .map(|w| get_stored_address(w.to_string()))
+        .collect::<Vec<_>>();
+    if wallets.len() < 2 || wallets.len() > MAX_COMPARED_WALLETS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let window_seconds = match query.window {
+        Some(window) if window <= 0 => return Err(StatusCode::BAD_REQUEST),
+        Some(window) => window,
+        None => get_comparison_window_seconds(),
+    };
+
+    Ok(Json(compare_wallets(wallets, window_seconds).await))
+}
+
+pub async fn get_validator_concentration(
+    Query(query): Query<ValidatorConcentrationQuery>,
+) -> Result<Json<ConcentrationReport>, StatusCode> {
+    let top_nominators = match query.top {
+        Some(0) => return Err(StatusCode::BAD_REQUEST),
+        Some(top) => top,
+        None => get_top_nominators(),
+    };
+    let max_share = match query.max_share {
+        Some(share) if share <= 0.0 || share > 1.0 => return Err(StatusCode::BAD_REQUEST),
+        Some(share) => share,
+        None => get_max_share(),
+    };
+
+    Ok(Json(
+        get_concentration_report(top_nominators, max_share).await,
+    ))
+}
+
+// from/to are unix timestamps in seconds of the delivery hours, the last day by default
+pub async fn get_latency_report(
+    Query(range): Query<TimeRange>,
+    Query(query): Query<LatencyQuery>,
+) -> Result<Json<LatencyReport>, StatusCode> {
+    let sink = match query.sink {
+        Some(sink) => Sink::from_str(&sink).map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => Sink::default(),
+    };
+
+    let now = DateTime::now().timestamp_millis() / 1000;
+    let from = range
+        .from
+        .unwrap_or(now - DEFAULT_LATENCY_REPORT_HOURS * 60 * 60);
+    let to = range.to.unwrap_or(now);
+    let mut mongodb_client_latency_histograms = MongoDbClientLatencyHistograms::new().await;
+    let histograms = mongodb_client_latency_histograms
+        .get_histograms(
+            &sink,
+            DateTime::from_millis(from * 1000),
+            DateTime::from_millis(to * 1000),
+        )
+        .await;
+
+    Ok(Json(build_report(&sink, &histograms, get_latency_slo_ms())))
+}
@@ -0,0 +1,78 @@
+use crate::{fields::FieldsQuery, ApiOperation};
+use axum::{
+    extract::Query,
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use bson::oid::ObjectId;
+use chrono::Utc;
+use futures::{stream, Stream};
+use rs_subscan_parser::{
+    mongodb_client_subscan::MongoDbClientSubscan, subscan_parser::Network, StoredOperation,
+};
+use std::{collections::VecDeque, convert::Infallible, time::Duration};
+use tokio::time::sleep;
+
+static POLL_INTERVAL_MS: u64 = 1_000;
+static BATCH_SIZE: i64 = 100;
+
+// cursor pointing right before now, so a new stream starts with the next stored operation
+fn get_now_cursor() -> ObjectId {
+    let mut bytes = [0u8; 12];
+    bytes[..4].copy_from_slice(&(Utc::now().timestamp() as u32).to_be_bytes());
+    ObjectId::from_bytes(bytes)
+}
+
+// event ids are mongodb ids, reconnecting clients resume after the one sent as Last-Event-ID
+pub async fn stream_operations(
+    headers: HeaderMap,
+    Query(fields): Query<FieldsQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let keys = fields.get_keys::<ApiOperation>()?;
+    let cursor = headers
+        .get("last-event-id")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| ObjectId::parse_str(h).ok())
+        .unwrap_or_else(get_now_cursor);
+
+    let state = (
+        MongoDbClientSubscan::new().await,
+        cursor,
+        VecDeque::<StoredOperation>::new(),
+        (fields, keys, Network::from_env()),
+    );
+    let events = stream::unfold(
+        state,
+        |(mut mongodb_client_subscan, mut cursor, mut buffer, selection)| async move {
+            while buffer.is_empty() {
+                buffer.extend(
+                    mongodb_client_subscan
+                        .get_operations_after(cursor, BATCH_SIZE)
+                        .await,
+                );
+                if buffer.is_empty() {
+                    sleep(Duration::from_millis(POLL_INTERVAL_MS)).await;
+                }
+            }
+
+            let stored = buffer.pop_front()?;
+            cursor = stored.id;
+            let (fields, keys, network) = &selection;
+            let event = Event::default()
+                .id(stored.id.to_hex())
+                .event("operation")
+                .json_data(fields.select(
+                    &ApiOperation::new(fields.encode(stored.operation, network), network),
+                    keys,
+                ))
+                .unwrap_or_default();
+
+            Some((
+                Ok(event),
+                (mongodb_client_subscan, cursor, buffer, selection),
+            ))
+        },
+    );
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
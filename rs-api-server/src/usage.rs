@@ -0,0 +1,266 @@
+use crate::{
+    limits::Tenant,
+    mongodb_client_api_usage::MongoDbClientApiUsage,
+    pagination::{Page, PageQuery},
+    stats::TimeRange,
+    to_rfc3339,
+};
+use axum::{
+    body::Body,
+    extract::{Query, Request},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+    Extension, Json,
+};
+use bson::DateTime;
+use futures::StreamExt;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    mem,
+    sync::{Mutex, MutexGuard, OnceLock},
+    time::Duration,
+};
+use tokio::time::sleep;
+
+static MILLIS_IN_DAY: i64 = 24 * 60 * 60 * 1_000;
+static FLUSH_INTERVAL_SECONDS: u64 = 60;
+
+// usage counted since the last flush, by tenant and day
+static PENDING_USAGE: OnceLock<Mutex<HashMap<(String, DateTime), ApiUsage>>> = OnceLock::new();
+
+// requests and response bytes served to a tenant on one utc day
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ApiUsage {
+    pub tenant: String,
+    pub day: DateTime,
+    pub requests: u64,
+    pub bytes: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ApiUsageDay {
+    pub tenant: String,
+    pub day: String,
+    pub requests: u64,
+    pub bytes: u64,
+}
+
+impl From<ApiUsage> for ApiUsageDay {
+    fn from(u: ApiUsage) -> Self {
+        Self {
+            tenant: u.tenant,
+            day: to_rfc3339(u.day),
+            requests: u.requests,
+            bytes: u.bytes,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct UsageQuery {
+    pub tenant: Option<String>,
+}
+
+// a request is recorded once its response body is done, streams count when they close
+struct UsageRecorder {
+    tenant: String,
+    day: DateTime,
+    bytes: u64,
+}
+
+impl Drop for UsageRecorder {
+    fn drop(&mut self) {
+        let mut pending_usage = get_pending_usage();
+        let usage = pending_usage
+            .entry((self.tenant.clone(), self.day))
+            .or_insert(ApiUsage {
+                tenant: self.tenant.clone(),
+                day: self.day,
+                requests: 0,
+                bytes: 0,
+            });
+        usage.requests += 1;
+        usage.bytes += self.bytes;
+    }
+}
+
+impl UsageRecorder {
+    fn add_bytes(&mut self, bytes: usize) {
+        self.bytes += bytes as u64;
+    }
+}
+
+fn get_pending_usage() -> MutexGuard<'static, HashMap<(String, DateTime), ApiUsage>> {
+    PENDING_USAGE
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+fn get_day(timestamp: DateTime) -> DateTime {
+    let millis = timestamp.timestamp_millis();
+    DateTime::from_millis(millis - millis.rem_euclid(MILLIS_IN_DAY))
+}
+
+// only requests of known tenants are metered, the tenant is set by with_tenant_limits
+pub async fn with_usage_metering(request: Request, next: Next) -> Response {
+    let Some(tenant) = request.extensions().get::<Tenant>().cloned() else {
+        return next.run(request).await;
+    };
+
+    let mut recorder = UsageRecorder {
+        tenant: tenant.name,
+        day: get_day(DateTime::now()),
+        bytes: 0,
+    };
+    let (parts, body) = next.run(request).await.into_parts();
+    // the method call moves the whole recorder into the stream, a field access would only copy
+    // the counter and drop the recorder before the body is sent
+    let body = body.into_data_stream().map(move |chunk| {
+        if let Ok(chunk) = &chunk {
+            recorder.add_bytes(chunk.len());
+        }
+        chunk
+    });
+
+    Response::from_parts(parts, Body::from_stream(body))
+}
+
+// counters are kept in memory and added to the daily documents every minute
+pub async fn flush_usage_periodically() {
+    let mut mongodb_client_api_usage = MongoDbClientApiUsage::new().await;
+    mongodb_client_api_usage.create_index().await;
+
+    loop {
+        sleep(Duration::from_secs(FLUSH_INTERVAL_SECONDS)).await;
+
+        let usage = mem::take(&mut *get_pending_usage())
+            .into_values()
+            .collect::<Vec<_>>();
+        if usage.is_empty() {
+            continue;
+        }
+
+        info!(target: "api_usage", "Flushing usage of {} tenant days.", usage.len());
+        mongodb_client_api_usage.increment_usage(usage).await;
+    }
+}
+
+async fn get_usage_page(
+    tenant: Option<&str>,
+    range: &TimeRange,
+    page: &PageQuery,
+) -> Result<Page<ApiUsageDay>, StatusCode> {
+    let mut mongodb_client_api_usage = MongoDbClientApiUsage::new().await;
+    let usage = mongodb_client_api_usage
+        .get_usage_page(
+            tenant,
+            range.from.unwrap_or(0),
+            range.to.unwrap_or(i64::MAX / 1000),
+            page.get_after()?,
+            page.get_fetch_limit(),
+        )
+        .await;
+
+    Ok(Page::new(usage, page, MongoDbClientApiUsage::get_page_key).map(ApiUsageDay::from))
+}
+
+// daily usage of the calling tenant, usage of the last minute may not be flushed yet
+pub async fn get_usage(
+    tenant: Option<Extension<Tenant>>,
+    Query(range): Query<TimeRange>,
+    Query(page): Query<PageQuery>,
+) -> Result<Json<Page<ApiUsageDay>>, StatusCode> {
+    let Some(Extension(tenant)) = tenant else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+
+    get_usage_page(Some(&tenant.name), &range, &page)
+        .await
+        .map(Json)
+}
+
+pub async fn get_all_usage(
+    Query(query): Query<UsageQuery>,
+    Query(range): Query<TimeRange>,
+    Query(page): Query<PageQuery>,
+) -> Result<Json<Page<ApiUsageDay>>, StatusCode> {
+    get_usage_page(query.tenant.as_deref(), &range, &page)
+        .await
+        .map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        limits::Tenant,
+        usage::{get_day, get_pending_usage, with_usage_metering, UsageRecorder},
+    };
+    use axum::{
+        body::{to_bytes, Body},
+        http::Request,
+        middleware,
+        routing::get,
+        Extension, Router,
+    };
+    use bson::DateTime;
+    use futures::stream;
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    #[test]
+    fn recorders_add_to_the_tenant_day() {
+        let day = get_day(DateTime::from_millis(1_700_000_000_000));
+        assert_eq!(day, DateTime::from_millis(1_699_920_000_000));
+
+        for bytes in [100, 250] {
+            drop(UsageRecorder {
+                tenant: "metered".to_string(),
+                day,
+                bytes,
+            });
+        }
+
+        let usage = get_pending_usage()
+            .remove(&("metered".to_string(), day))
+            .unwrap();
+        assert_eq!(usage.requests, 2);
+        assert_eq!(usage.bytes, 350);
+    }
+
+    #[tokio::test]
+    async fn streamed_bodies_are_metered_when_they_close() {
+        let tenant = Tenant {
+            name: "streamed".to_string(),
+            requests_per_minute: 60,
+            max_connections: 1,
+        };
+        let router = Router::new()
+            .route(
+                "/stream",
+                get(|| async {
+                    let chunks = ["first chunk", "second"].map(Ok::<_, Infallible>);
+                    Body::from_stream(stream::iter(chunks))
+                }),
+            )
+            .layer(middleware::from_fn(with_usage_metering))
+            .layer(Extension(tenant));
+
+        let response = router
+            .oneshot(Request::get("/stream").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let key = ("streamed".to_string(), get_day(DateTime::now()));
+        assert!(!get_pending_usage().contains_key(&key));
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body.len(), 17);
+
+        let usage = get_pending_usage().remove(&key).unwrap();
+        assert_eq!(usage.requests, 1);
+        assert_eq!(usage.bytes, 17);
+    }
+}
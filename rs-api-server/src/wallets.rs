@@ -0,0 +1,146 @@
+use crate::{
+    fields::FieldsQuery,
+    pagination::{Page, PageQuery},
+    stats::{TimeRange, Totals},
+    ApiNomination, ApiOperation, ApiOperationSummary,
+};
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    Json,
+};
+use rs_subscan_parser::{
+    mongodb_client_operation_summaries::MongoDbClientOperationSummaries,
+    mongodb_client_subscan::MongoDbClientSubscan, mongodb_client_validator::MongoDbClientValidator,
+    subscan_parser::Network, wallet_formats::get_canonical_address, TotalsView,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct NominationsHistory {
+    pub address: String,
+    pub nominations: Vec<ApiNomination>,
+    // keys of the selected operation fields, in the order of compact operations
+    pub operation_fields: Vec<String>,
+    // the operations which started one of the nominations
+    pub nominate_operations: Vec<Value>,
+    // pages nominate_operations, nominations are always complete
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct WalletTimeline {
+    pub address: String,
+    // keys of the selected operation fields, in the order of compact operations
+    pub operation_fields: Vec<String>,
+    pub operations: Vec<Value>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct WalletSummaries {
+    pub address: String,
+    pub summaries: Vec<ApiOperationSummary>,
+    pub next_cursor: Option<String>,
+}
+
+// wallets are stored in their canonical encoding, any other encoding of them is accepted
+pub fn get_stored_address(address: String) -> String {
+    get_canonical_address(&address).unwrap_or(address)
+}
+
+pub async fn get_nominations_history(
+    Path(address): Path<String>,
+    Query(page): Query<PageQuery>,
+    Query(fields): Query<FieldsQuery>,
+) -> Result<Json<NominationsHistory>, StatusCode> {
+    let address = get_stored_address(address);
+    let network = Network::from_env();
+    let mut mongodb_client_validator = MongoDbClientValidator::new().await;
+    let history = mongodb_client_validator
+        .get_nominations_histories(vec![address.clone()])
+        .await
+        .remove(&address)
+        .unwrap_or_default();
+
+    let nominate_operations = page
+        .get_operations_page(MongoDbClientSubscan::get_nominations_query(&history))
+        .await?
+        .map(|o| ApiOperation::new(fields.encode(o, &network), &network));
+    let (operation_fields, items) = fields.select_all(&nominate_operations.items)?;
+
+    Ok(Json(NominationsHistory {
+        address,
+        nominations: history.into_iter().map(ApiNomination::from).collect(),
+        operation_fields,
+        nominate_operations: items,
+        next_cursor: nominate_operations.next_cursor,
+    }))
+}
+
+// every stored operation the wallet sent, received or controlled, newest first: stakes,
+// transfers, exchange flows and the rewards and slashes of the staking pass, governance
+// actions aren't parsed so there are none
+pub async fn get_wallet_timeline(
+    Path(address): Path<String>,
+    Query(page): Query<PageQuery>,
+    Query(fields): Query<FieldsQuery>,
+) -> Result<Json<WalletTimeline>, StatusCode> {
+    let address = get_stored_address(address);
+    let network = Network::from_env();
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let operations = mongodb_client_subscan
+        .get_timeline_page(&address, page.get_after()?, page.get_fetch_limit())
+        .await;
+    let operations = Page::new(operations, &page, MongoDbClientSubscan::get_timeline_key)
+        .map(|o| ApiOperation::new(fields.encode(o, &network), &network));
+    let (operation_fields, items) = fields.select_all(&operations.items)?;
+
+    Ok(Json(WalletTimeline {
+        address,
+        operation_fields,
+        operations: items,
+        next_cursor: operations.next_cursor,
+    }))
+}
+
+// hourly totals of operations that were compacted out of the raw collection
+pub async fn get_wallet_summaries(
+    Path(address): Path<String>,
+    Query(range): Query<TimeRange>,
+    Query(page): Query<PageQuery>,
+) -> Result<Json<WalletSummaries>, StatusCode> {
+    let address = get_stored_address(address);
+    let mut mongodb_client_operation_summaries = MongoDbClientOperationSummaries::new().await;
+    let summaries = mongodb_client_operation_summaries
+        .get_wallet_summaries_page(
+            &address,
+            range.from.unwrap_or(0),
+            range.to.unwrap_or(i64::MAX / 1000),
+            page.get_after()?,
+            page.get_fetch_limit(),
+        )
+        .await;
+    let summaries = Page::new(
+        summaries,
+        &page,
+        MongoDbClientOperationSummaries::get_page_key,
+    )
+    .map(ApiOperationSummary::from);
+
+    Ok(Json(WalletSummaries {
+        address,
+        summaries: summaries.items,
+        next_cursor: summaries.next_cursor,
+    }))
+}
+
+pub async fn get_wallet_totals(
+    Path(address): Path<String>,
+    Query(page): Query<PageQuery>,
+) -> Result<Json<Totals>, StatusCode> {
+    Totals::get(TotalsView::Wallet, get_stored_address(address), &page)
+        .await
+        .map(Json)
+}
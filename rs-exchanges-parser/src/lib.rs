@@ -68,13 +68,16 @@ pub enum ExchangesWallets {
 }
 
 impl ExchangesWallets {
-    pub fn get_beautiful_name(&self) -> String {
+    pub fn get_exchange(&self) -> Exchanges {
         match self {
             ExchangesWallets::Mexc => Exchanges::Mexc,
             ExchangesWallets::Kucoin => Exchanges::Kucoin,
             ExchangesWallets::Gate => Exchanges::Gate,
         }
-        .get_beautiful_name()
+    }
+
+    pub fn get_beautiful_name(&self) -> String {
+        self.get_exchange().get_beautiful_name()
     }
 }
 
@@ -2,6 +2,7 @@ use crate::{ExchangeTrade, PrimaryToken, SecondaryToken};
 use bson::{doc, DateTime};
 use chrono::Utc;
 use mongodb::{
+    change_stream::{event::ChangeStreamEvent, ChangeStream},
     options::{FindOneOptions, FindOptions, IndexOptions},
     IndexModel,
 };
@@ -59,6 +60,14 @@ impl MongoDbClientExchanges {
         }
     }
 
+    pub async fn watch_trades(&mut self) -> Option<ChangeStream<ChangeStreamEvent<ExchangeTrade>>> {
+        let pipeline = vec![doc! {
+            "$match": { "operationType": "insert" }
+        }];
+
+        self.client_exchanges.watch(pipeline, None).await
+    }
+
     pub async fn get_filtered_trades(
         &mut self,
         primary_token: PrimaryToken,
@@ -101,4 +110,31 @@ impl MongoDbClientExchanges {
         let item = self.client_exchanges.find_one(query, options).await?;
         Some(item.trade_price)
     }
+
+    // latest trade price at or before timestamp (in seconds), trades older than max_age_seconds
+    // don't count as the price at that time
+    pub async fn get_usd_price_at(
+        &mut self,
+        primary_token: PrimaryToken,
+        secondary_token: SecondaryToken,
+        timestamp: i64,
+        max_age_seconds: i64,
+    ) -> Option<f64> {
+        let options = Some(
+            FindOneOptions::builder()
+                .sort(doc! {"trade_timestamp": -1i32})
+                .build(),
+        );
+        let query = doc! {
+            "primary_token": primary_token.to_string(),
+            "secondary_token": secondary_token.to_string(),
+            "trade_timestamp": {
+                "$gte": DateTime::from_millis((timestamp - max_age_seconds) * 1000),
+                "$lte": DateTime::from_millis(timestamp * 1000),
+            }
+        };
+
+        let item = self.client_exchanges.find_one(query, options).await?;
+        Some(item.trade_price)
+    }
 }
@@ -0,0 +1,154 @@
+use crate::{subscan_parser::Network, wallet_formats::get_network_address};
+use serde::{Deserialize, Serialize};
+use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+use std::{env, str::FromStr};
+use strum_macros::EnumString;
+
+// prefix of substrate chains without one of their own
+pub static GENERIC_SS58_PREFIX: u16 = 42;
+
+// encoding wallets are stored and served in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum AddressFormat {
+    // ss58 with the prefix of the network, hex on networks with ethereum style accounts
+    #[default]
+    Native,
+
+    // ss58 with the generic substrate prefix, hex on networks with ethereum style accounts
+    Generic,
+
+    // lowercase hex of the public key
+    Hex,
+}
+
+impl AddressFormat {
+    // SUBSCAN_ADDRESS_FORMAT, native if it is not set
+    pub fn from_env() -> AddressFormat {
+        env::var("SUBSCAN_ADDRESS_FORMAT")
+            .ok()
+            .and_then(|f| AddressFormat::from_str(&f).ok())
+            .unwrap_or_default()
+    }
+
+    // none if the input is no account of the network
+    pub fn format(&self, network: &Network, input: &str) -> Option<String> {
+        let native = get_network_address(network, input)?;
+        match self {
+            AddressFormat::Native => Some(native),
+            _ if network.has_ethereum_accounts() => Some(native),
+            AddressFormat::Generic => Some(
+                to_account_id(input)?
+                    .to_ss58check_with_version(Ss58AddressFormat::custom(GENERIC_SS58_PREFIX)),
+            ),
+            AddressFormat::Hex => to_hex(input),
+        }
+    }
+}
+
+// 32 byte public key from its hex or an ss58 of any prefix
+pub fn to_account_id(input: &str) -> Option<AccountId32> {
+    match input.strip_prefix("0x") {
+        Some(public_key) => {
+            let public_key: [u8; 32] = hex::decode(public_key).ok()?.try_into().ok()?;
+            Some(AccountId32::from(public_key))
+        }
+        None => Some(AccountId32::from_ss58check_with_version(input).ok()?.0),
+    }
+}
+
+pub fn from_account_id(network: &Network, account: &AccountId32) -> String {
+    account.to_ss58check_with_version(Ss58AddressFormat::custom(network.get_ss58_prefix()))
+}
+
+// ss58 with the prefix of the network, none if the input is no account
+pub fn to_ss58(network: &Network, input: &str) -> Option<String> {
+    Some(from_account_id(network, &to_account_id(input)?))
+}
+
+// lowercase hex with 0x prefix, 20 byte ethereum style accounts are kept as they are
+pub fn to_hex(input: &str) -> Option<String> {
+    let account = match input.strip_prefix("0x") {
+        Some(account) => hex::decode(account).ok()?,
+        None => <[u8; 32]>::from(to_account_id(input)?).to_vec(),
+    };
+    if account.len() != 20 && account.len() != 32 {
+        return None;
+    }
+
+    Some(format!("0x{}", hex::encode(account)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        address::{to_hex, to_ss58, AddressFormat},
+        subscan_parser::Network,
+    };
+    use std::str::FromStr;
+
+    static ALEPHZERO: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+    static POLKADOT: &str = "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5";
+    static HEX: &str = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+    static UPPERCASE_HEX: &str =
+        "0xD43593C715FDD31C61141ABD04A99FD6822C8558854CCDE39A5684E7A56DA27D";
+
+    #[test]
+    fn every_encoding_converts_to_every_other() {
+        for input in [ALEPHZERO, POLKADOT, HEX, UPPERCASE_HEX] {
+            assert_eq!(
+                to_ss58(&Network::Alephzero, input).as_deref(),
+                Some(ALEPHZERO)
+            );
+            assert_eq!(
+                to_ss58(&Network::Polkadot, input).as_deref(),
+                Some(POLKADOT)
+            );
+            assert_eq!(to_hex(input).as_deref(), Some(HEX));
+        }
+    }
+
+    #[test]
+    fn addresses_are_written_in_the_chosen_format() {
+        // aleph zero uses the generic prefix
+        for input in [POLKADOT, HEX] {
+            let format = |f: AddressFormat| f.format(&Network::Polkadot, input);
+            assert_eq!(format(AddressFormat::Native).as_deref(), Some(POLKADOT));
+            assert_eq!(format(AddressFormat::Generic).as_deref(), Some(ALEPHZERO));
+            assert_eq!(format(AddressFormat::Hex).as_deref(), Some(HEX));
+        }
+
+        // ethereum style accounts have no other encoding
+        let input = "0xf24ff3a9cf04c71dbc94d0b566f7a27b94566cac";
+        assert_eq!(
+            AddressFormat::Generic
+                .format(&Network::Moonbeam, input)
+                .as_deref(),
+            Some(input)
+        );
+        assert_eq!(
+            AddressFormat::Hex.format(&Network::Alephzero, "0x1234"),
+            None
+        );
+        assert_eq!(AddressFormat::from_str("hex"), Ok(AddressFormat::Hex));
+    }
+
+    #[test]
+    fn ethereum_style_accounts_have_no_ss58() {
+        let input = "0xF24FF3a9CF04c71Dbc94D0b566f7A27B94566cac";
+        assert_eq!(
+            to_hex(input).as_deref(),
+            Some("0xf24ff3a9cf04c71dbc94d0b566f7a27b94566cac")
+        );
+        assert_eq!(to_ss58(&Network::Moonbeam, input), None);
+    }
+
+    #[test]
+    fn malformed_inputs_are_no_accounts() {
+        for input in ["", "0x", "0x1234", "0xzz", "5Grwva"] {
+            assert_eq!(to_ss58(&Network::Alephzero, input), None);
+            assert_eq!(to_hex(input), None);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AmountError {
+    // not an unsigned integer, e.g. empty, signed, fractional or in exponent notation
+    Malformed(String),
+
+    // more planck than a decimal holds with the decimals of the network
+    Overflow(String),
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AmountError::Malformed(value) => write!(f, "{value} is not a planck amount"),
+            AmountError::Overflow(value) => write!(f, "{value} planck overflows a decimal"),
+        }
+    }
+}
+
+// exact amount in planck, serialized as its digits since neither bson nor json numbers hold a u128
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Balance(pub u128);
+
+impl Balance {
+    // planck amount as subscan returns it, e.g. "1500000000000"
+    pub fn from_planck_str(value: &str) -> Result<Balance, AmountError> {
+        if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(AmountError::Malformed(value.to_string()));
+        }
+
+        value
+            .parse::<u128>()
+            .map(Balance)
+            .map_err(|_| AmountError::Overflow(value.to_string()))
+    }
+
+    // tokens without going through floats
+    pub fn to_decimal(&self, decimals: u32) -> Result<Decimal, AmountError> {
+        let overflow = || AmountError::Overflow(self.0.to_string());
+        let planck = i128::try_from(self.0).map_err(|_| overflow())?;
+
+        Decimal::try_from_i128_with_scale(planck, decimals).map_err(|_| overflow())
+    }
+
+    // only for display and usd amounts
+    pub fn to_f64(&self, decimals: u32) -> Option<f64> {
+        self.to_decimal(decimals).ok()?.to_f64()
+    }
+
+    // tokens without trailing zeros, e.g. "1.5" for 1500000000000 planck and 12 decimals,
+    // exact even for amounts a decimal doesn't hold
+    pub fn format(&self, decimals: u32) -> String {
+        let Some(unit) = 10u128.checked_pow(decimals) else {
+            return format!("0.{:0>width$}", self.0, width = decimals as usize)
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string();
+        };
+
+        let fraction = format!("{:0>width$}", self.0 % unit, width = decimals as usize);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            (self.0 / unit).to_string()
+        } else {
+            format!("{}.{fraction}", self.0 / unit)
+        }
+    }
+
+    pub fn checked_add(self, other: Balance) -> Option<Balance> {
+        self.0.checked_add(other.0).map(Balance)
+    }
+}
+
+// planck, use format for tokens
+impl fmt::Display for Balance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Serialize for Balance {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Balance {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Balance::from_planck_str(&value).map_err(de::Error::custom)
+    }
+}
+
+// planck amount as subscan returns it, e.g. "1500000000000", to tokens without going through floats
+pub fn from_planck_str(value: &str, decimals: u32) -> Result<Decimal, AmountError> {
+    Balance::from_planck_str(value)?.to_decimal(decimals)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::amount::{from_planck_str, AmountError, Balance};
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    fn decimal(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn planck_converts_exactly() {
+        assert_eq!(from_planck_str("0", 12), Ok(Decimal::ZERO));
+        assert_eq!(from_planck_str("1", 12), Ok(decimal("0.000000000001")));
+        assert_eq!(from_planck_str("1500000000000", 12), Ok(decimal("1.5")));
+        assert_eq!(from_planck_str("15000000000", 10), Ok(decimal("1.5")));
+        assert_eq!(from_planck_str("000042", 0), Ok(decimal("42")));
+
+        // largest mantissa a decimal holds
+        assert_eq!(
+            from_planck_str("79228162514264337593543950335", 18),
+            Ok(decimal("79228162514.264337593543950335"))
+        );
+    }
+
+    #[test]
+    fn too_many_planck_overflow() {
+        for value in [
+            "79228162514264337593543950336",
+            // u128::MAX and one more
+            "340282366920938463463374607431768211455",
+            "340282366920938463463374607431768211456",
+        ] {
+            assert_eq!(
+                from_planck_str(value, 12),
+                Err(AmountError::Overflow(value.to_string()))
+            );
+        }
+
+        // a decimal has at most 28 decimals
+        assert_eq!(
+            from_planck_str("1", 29),
+            Err(AmountError::Overflow("1".to_string()))
+        );
+    }
+
+    #[test]
+    fn balances_keep_every_planck() {
+        // more than a decimal or a float holds
+        let balance = Balance::from_planck_str("340282366920938463463374607431768211455").unwrap();
+        assert_eq!(balance, Balance(u128::MAX));
+        assert_eq!(
+            balance.format(18),
+            "340282366920938463463.374607431768211455"
+        );
+        assert_eq!(
+            balance.to_decimal(18),
+            Err(AmountError::Overflow(u128::MAX.to_string()))
+        );
+
+        assert_eq!(Balance(1_500_000_000_000).format(12), "1.5");
+        assert_eq!(Balance(1_500_000_000_000).format(0), "1500000000000");
+        assert_eq!(Balance(2_000_000_000_000).format(12), "2");
+        assert_eq!(Balance(1).format(12), "0.000000000001");
+        assert_eq!(
+            Balance(15).format(40),
+            "0.0000000000000000000000000000000000000015"
+        );
+        assert_eq!(Balance(0).format(12), "0");
+        assert_eq!(Balance(1_500_000_000_000).to_f64(12), Some(1.5));
+        assert_eq!(Balance(u128::MAX).checked_add(Balance(1)), None);
+    }
+
+    #[test]
+    fn balances_serialize_as_their_digits() {
+        let balance = Balance(1_500_000_000_000);
+        assert_eq!(serde_json::to_value(balance).unwrap(), "1500000000000");
+        assert_eq!(
+            serde_json::from_value::<Balance>("1500000000000".into()).unwrap(),
+            balance
+        );
+        assert!(serde_json::from_value::<Balance>("1.5".into()).is_err());
+        assert!(serde_json::from_value::<Balance>(1500.into()).is_err());
+    }
+
+    #[test]
+    fn malformed_amounts_are_rejected() {
+        for value in ["", "-1", "+1", "1.5", "1e12", " 1", "0x10", "NaN"] {
+            assert_eq!(
+                from_planck_str(value, 12),
+                Err(AmountError::Malformed(value.to_string()))
+            );
+        }
+    }
+}
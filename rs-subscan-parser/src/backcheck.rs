@@ -0,0 +1,446 @@
+use crate::{
+    amount::Balance,
+    event_matcher::EventMatcher,
+    mongodb_client_subscan::MongoDbClientSubscan,
+    subscan_parser::{Network, SubscanParser},
+    subscan_stake_parser::get_staking_amount,
+    wallet_formats::get_network_address,
+    OperationType, SubscanEvent, SubscanExtrinsic, SubscanOperation,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::env;
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+use tracing::error;
+
+static DEFAULT_BACKCHECK_SAMPLE_SIZE: u64 = 100;
+static DEFAULT_BACKCHECK_DAYS: i64 = 30;
+
+// quantities of operations stored before planck amounts went through floats
+static QUANTITY_TOLERANCE: f64 = 1e-9;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BackcheckConfig {
+    pub sample_size: u64,
+
+    // operations older than this are not sampled
+    pub days: i64,
+}
+
+impl BackcheckConfig {
+    // BACKCHECK_SAMPLE_SIZE and BACKCHECK_DAYS
+    pub fn from_env() -> BackcheckConfig {
+        let sample_size = env::var("BACKCHECK_SAMPLE_SIZE")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_BACKCHECK_SAMPLE_SIZE);
+        let days = env::var("BACKCHECK_DAYS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_BACKCHECK_DAYS);
+
+        Self { sample_size, days }
+    }
+
+    pub fn with_sample_size(mut self, sample_size: u64) -> Self {
+        self.sample_size = sample_size;
+        self
+    }
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum DiscrepancyKind {
+    // subscan doesn't know the extrinsic
+    Missing,
+    Failed,
+    BlockNumber,
+    Wallet,
+    Amount,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Discrepancy {
+    pub hash: String,
+    pub extrinsic_index: String,
+    pub operation_type: OperationType,
+    pub kind: DiscrepancyKind,
+    pub stored: String,
+
+    // none if subscan has nothing to compare with
+    pub source: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BackcheckReport {
+    pub network: String,
+    pub checked_at: String,
+    pub sampled: usize,
+
+    // operations without any discrepancy
+    pub matched: usize,
+
+    // rewards, slashes and nominations are only checked for their extrinsic, their amounts
+    // are not in its events
+    pub amounts_unchecked: usize,
+
+    // operations subscan couldn't be asked about, neither matched nor discrepant
+    pub errors: usize,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+// what the events of an extrinsic say about one of its operations
+#[derive(Clone, Debug, PartialEq)]
+struct SourceAmount {
+    from_wallet: String,
+
+    // none for staking events, which only name the stash
+    to_wallet: Option<String>,
+    planck: Balance,
+}
+
+impl SourceAmount {
+    fn describe(&self) -> String {
+        match &self.to_wallet {
+            Some(to_wallet) => format!("{} -> {to_wallet}: {}", self.from_wallet, self.planck),
+            None => format!("{}: {}", self.from_wallet, self.planck),
+        }
+    }
+}
+
+fn get_transfer_matcher() -> EventMatcher {
+    EventMatcher::new("balances", "Transfer")
+        .field("from")
+        .field("to")
+        .field("amount")
+}
+
+// none for operation types whose amount can't be read from the events of their extrinsic
+fn get_source_amounts(
+    operation_type: &OperationType,
+    events: &[SubscanEvent],
+    network: &Network,
+) -> Option<Vec<SourceAmount>> {
+    match operation_type {
+        OperationType::Stake | OperationType::RequestUnstake | OperationType::WithdrawUnstaked => {
+            let amount = get_staking_amount(events, network).map(|(stash, planck)| SourceAmount {
+                from_wallet: stash,
+                to_wallet: None,
+                planck,
+            });
+            Some(amount.into_iter().collect())
+        }
+        OperationType::Transfer
+        | OperationType::DepositToExchange
+        | OperationType::WithdrawFromExchange => {
+            let matcher = get_transfer_matcher();
+            let amounts = events
+                .iter()
+                .filter_map(|e| {
+                    let fields = matcher.get_fields(e)?;
+                    Some(SourceAmount {
+                        from_wallet: fields.get("from")?.as_account(network)?,
+                        to_wallet: Some(fields.get("to")?.as_account(network)?),
+                        planck: fields.get("amount")?.as_planck()?,
+                    })
+                })
+                .collect();
+            Some(amounts)
+        }
+        OperationType::ReStake | OperationType::Reward | OperationType::Slash => None,
+    }
+}
+
+// stored wallets may be in any address format
+fn is_same_wallet(network: &Network, stored: &str, source: &str) -> bool {
+    match (
+        get_network_address(network, stored),
+        get_network_address(network, source),
+    ) {
+        (Some(stored), Some(source)) => stored == source,
+        _ => stored == source,
+    }
+}
+
+fn is_same_amount(network: &Network, operation: &SubscanOperation, planck: Balance) -> bool {
+    if let Some(stored) = operation.operation_planck {
+        return stored == planck;
+    }
+
+    planck.to_f64(network.get_decimals()).is_some_and(|q| {
+        (q - operation.operation_quantity).abs() <= QUANTITY_TOLERANCE * q.abs().max(1.0)
+    })
+}
+
+// discrepancies of the operation with the extrinsic subscan reports now, and whether its
+// amount could be checked at all
+pub fn check_operation(
+    operation: &SubscanOperation,
+    extrinsic: Option<&SubscanExtrinsic>,
+    network: &Network,
+) -> (Vec<Discrepancy>, bool) {
+    let discrepancy = |kind, stored: String, source: Option<String>| Discrepancy {
+        hash: operation.hash.clone(),
+        extrinsic_index: operation.extrinsic_index.clone(),
+        operation_type: operation.operation_type.clone(),
+        kind,
+        stored,
+        source,
+    };
+
+    let Some(extrinsic) = extrinsic else {
+        let stored = operation.extrinsic_index.clone();
+        return (
+            vec![discrepancy(DiscrepancyKind::Missing, stored, None)],
+            false,
+        );
+    };
+    if !extrinsic.is_success {
+        let stored = operation.extrinsic_index.clone();
+        let source = Some("failed".to_string());
+        return (
+            vec![discrepancy(DiscrepancyKind::Failed, stored, source)],
+            false,
+        );
+    }
+
+    let mut discrepancies = Vec::new();
+    if extrinsic.block_number != operation.block_number {
+        discrepancies.push(discrepancy(
+            DiscrepancyKind::BlockNumber,
+            operation.block_number.to_string(),
+            Some(extrinsic.block_number.to_string()),
+        ));
+    }
+
+    let Some(amounts) = get_source_amounts(&operation.operation_type, &extrinsic.events, network)
+    else {
+        return (discrepancies, false);
+    };
+
+    let same_wallets = amounts
+        .iter()
+        .filter(|a| {
+            is_same_wallet(network, &operation.from_wallet, &a.from_wallet)
+                && a.to_wallet
+                    .as_ref()
+                    .is_none_or(|w| is_same_wallet(network, &operation.to_wallet, w))
+        })
+        .collect::<Vec<_>>();
+    if same_wallets.is_empty() {
+        let stored = match operation.operation_type {
+            OperationType::Stake
+            | OperationType::RequestUnstake
+            | OperationType::WithdrawUnstaked => operation.from_wallet.clone(),
+            _ => format!("{} -> {}", operation.from_wallet, operation.to_wallet),
+        };
+        let source = amounts.first().map(SourceAmount::describe);
+        discrepancies.push(discrepancy(DiscrepancyKind::Wallet, stored, source));
+    } else if !same_wallets
+        .iter()
+        .any(|a| is_same_amount(network, operation, a.planck))
+    {
+        let stored = operation
+            .operation_planck
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| operation.operation_quantity.to_string());
+        let source = same_wallets
+            .iter()
+            .map(|a| a.planck.to_string())
+            .collect::<Vec<_>>();
+        discrepancies.push(discrepancy(
+            DiscrepancyKind::Amount,
+            stored,
+            Some(source.join(", ")),
+        ));
+    }
+
+    (discrepancies, true)
+}
+
+// samples stored operations and compares each with its extrinsic on subscan, requests go
+// through the scheduler with enrichment priority so the live parser keeps its quota
+pub async fn run_backcheck(network: &Network, config: &BackcheckConfig) -> BackcheckReport {
+    let from_timestamp = Utc::now().timestamp() - config.days * 24 * 60 * 60;
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let operations = mongodb_client_subscan
+        .get_sample_operations(from_timestamp, config.sample_size)
+        .await;
+
+    let mut subscan_parser = SubscanParser::new(network.clone()).await;
+    let mut report = BackcheckReport {
+        network: network.get_slug().to_string(),
+        checked_at: Utc::now().to_rfc3339(),
+        sampled: operations.len(),
+        matched: 0,
+        amounts_unchecked: 0,
+        errors: 0,
+        discrepancies: Vec::new(),
+    };
+    for operation in operations {
+        let extrinsic = match subscan_parser
+            .parse_subscan_extrinsic(&operation.extrinsic_index)
+            .await
+        {
+            Ok(extrinsic) => extrinsic,
+            Err(e) => {
+                error!(target: "backcheck", "Extrinsic error of {}: {e}.", operation.extrinsic_index);
+                report.errors += 1;
+                continue;
+            }
+        };
+
+        let (mut discrepancies, is_amount_checked) =
+            check_operation(&operation, extrinsic.as_ref(), network);
+        if discrepancies.is_empty() {
+            report.matched += 1;
+        }
+        if !is_amount_checked {
+            report.amounts_unchecked += 1;
+        }
+        report.discrepancies.append(&mut discrepancies);
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        amount::Balance,
+        backcheck::{check_operation, DiscrepancyKind},
+        subscan_parser::Network,
+        OperationStatus, OperationType, SubscanEvent, SubscanEventParam, SubscanExtrinsic,
+        SubscanOperation,
+    };
+    use bson::DateTime;
+    use serde_json::json;
+
+    static ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+    static BOB: &str = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+
+    fn operation(operation_type: OperationType, planck: u128) -> SubscanOperation {
+        SubscanOperation {
+            hash: "hash".to_string(),
+            hash_version: 2,
+            block_number: 42,
+            extrinsic_index: "42-3".to_string(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+            operation_quantity: 1.0,
+            operation_planck: Some(Balance(planck)),
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 1.0,
+            operation_type,
+            from_wallet: ALICE.to_string(),
+            controller_wallet: ALICE.to_string(),
+            to_wallet: BOB.to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        }
+    }
+
+    fn param(name: &str, type_name: &str, value: &str) -> SubscanEventParam {
+        SubscanEventParam {
+            type_name: type_name.to_string(),
+            value: json!(value),
+            name: name.to_string(),
+        }
+    }
+
+    fn extrinsic(events: Vec<SubscanEvent>) -> SubscanExtrinsic {
+        SubscanExtrinsic {
+            extrinsic_index: "42-3".to_string(),
+            block_number: 42,
+            is_success: true,
+            events,
+        }
+    }
+
+    fn transfer(from: &str, to: &str, amount: &str) -> SubscanEvent {
+        SubscanEvent {
+            module_id: "balances".to_string(),
+            event_id: "Transfer".to_string(),
+            event_index: "42-4".to_string(),
+            event_params: vec![
+                param("from", "AccountId", from),
+                param("to", "AccountId", to),
+                param("amount", "Balance", amount),
+            ],
+        }
+    }
+
+    #[test]
+    fn transfers_are_checked_against_their_events() {
+        let network = Network::Alephzero;
+        let transfer_operation = operation(OperationType::Transfer, 500);
+
+        let matching = extrinsic(vec![transfer(BOB, ALICE, "7"), transfer(ALICE, BOB, "500")]);
+        assert_eq!(
+            check_operation(&transfer_operation, Some(&matching), &network),
+            (Vec::new(), true)
+        );
+
+        let (discrepancies, _) = check_operation(
+            &transfer_operation,
+            Some(&extrinsic(vec![transfer(ALICE, BOB, "499")])),
+            &network,
+        );
+        assert_eq!(discrepancies[0].kind, DiscrepancyKind::Amount);
+        assert_eq!(discrepancies[0].source.as_deref(), Some("499"));
+
+        let (discrepancies, _) = check_operation(
+            &transfer_operation,
+            Some(&extrinsic(vec![transfer(BOB, ALICE, "500")])),
+            &network,
+        );
+        assert_eq!(discrepancies[0].kind, DiscrepancyKind::Wallet);
+    }
+
+    #[test]
+    fn missing_and_moved_extrinsics_are_reported() {
+        let network = Network::Alephzero;
+        let reward = operation(OperationType::Reward, 1);
+
+        let (discrepancies, is_amount_checked) = check_operation(&reward, None, &network);
+        assert_eq!(discrepancies[0].kind, DiscrepancyKind::Missing);
+        assert!(!is_amount_checked);
+
+        // rewards are only checked for their extrinsic
+        let mut moved = extrinsic(Vec::new());
+        moved.block_number = 43;
+        let (discrepancies, is_amount_checked) = check_operation(&reward, Some(&moved), &network);
+        assert_eq!(
+            discrepancies
+                .iter()
+                .map(|d| (d.kind, d.source.clone()))
+                .collect::<Vec<_>>(),
+            vec![(DiscrepancyKind::BlockNumber, Some("43".to_string()))]
+        );
+        assert!(!is_amount_checked);
+
+        moved.is_success = false;
+        let (discrepancies, _) = check_operation(&reward, Some(&moved), &network);
+        assert_eq!(discrepancies[0].kind, DiscrepancyKind::Failed);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::{
+    confirmations::{release_confirmed_operations, remove_released_operations},
+    data_quality::validate_operations,
+    feature_flags::FeatureFlags,
+    finalization::set_operation_statuses,
+    metrics::record_parsed_operations,
+    mongodb_client_backfill_progress::MongoDbClientBackfillProgress,
+    operation_ids::set_operation_ids,
+    operations_watcher::{is_change_streams_enabled, process_stored_operations},
+    outbox::{is_outbox_enabled, write_operations_with_outbox},
+    sinks::Sink,
+    spans::get_run_span,
+    subscan_error::SubscanError,
+    subscan_parser::{Network, SubscanParser},
+    subscan_stake_parser::{parse_staking, StakingParserConfig},
+    BackfillProgress, SubscanOperation,
+};
+use bson::DateTime;
+use serde_json::json;
+use std::{env, time::Duration};
+use tokio::time::sleep;
+use tracing::{error, info, Instrument};
+
+static DEFAULT_BACKFILL_PAGE_DELAY_MS: u64 = 1_000;
+static DEFAULT_BACKFILL_RETRY_SECONDS: u64 = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BackfillConfig {
+    // pause between pages on top of the scheduler, leaves quota to the live parser
+    pub page_delay: Duration,
+
+    // wait before a failed page or count is tried again
+    pub retry_delay: Duration,
+}
+
+impl BackfillConfig {
+    // BACKFILL_PAGE_DELAY_MS and BACKFILL_RETRY_SECONDS
+    pub fn from_env() -> BackfillConfig {
+        let page_delay_ms = env::var("BACKFILL_PAGE_DELAY_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_BACKFILL_PAGE_DELAY_MS);
+        let retry_seconds = env::var("BACKFILL_RETRY_SECONDS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_BACKFILL_RETRY_SECONDS);
+
+        Self {
+            page_delay: Duration::from_millis(page_delay_ms),
+            retry_delay: Duration::from_secs(retry_seconds),
+        }
+    }
+}
+
+// every call is walked page by page at once, so the longest one decides
+pub fn get_total_pages(counts: &[u64], rows_per_page: u32) -> u32 {
+    let rows_per_page = rows_per_page.max(1) as u64;
+    counts
+        .iter()
+        .map(|c| c.div_ceil(rows_per_page))
+        .max()
+        .unwrap_or_default()
+        .try_into()
+        .unwrap_or(u32::MAX)
+}
+
+// extrinsics of every enabled staking call and of batch_all
+async fn get_counts(subscan_parser: &mut SubscanParser) -> Result<Vec<u64>, SubscanError> {
+    let mut calls = FeatureFlags::global()
+        .get_enabled_modules()
+        .into_iter()
+        .flat_map(|m| {
+            m.get_extrinsics_types()
+                .into_iter()
+                .map(move |e| (json!(m), e.to_string()))
+        })
+        .collect::<Vec<_>>();
+    calls.push((json!("utility"), "batch_all".to_string()));
+
+    let mut counts = Vec::new();
+    for (module, call) in calls {
+        counts.push(subscan_parser.get_extrinsics_count(module, &call).await?);
+    }
+
+    Ok(counts)
+}
+
+// the next run starts over from the newest page
+pub async fn reset_backfill(network: &Network) {
+    let mut mongodb_client_backfill_progress = MongoDbClientBackfillProgress::new().await;
+    mongodb_client_backfill_progress
+        .save_progress(&BackfillProgress {
+            network: network.get_slug().to_string(),
+            next_page: 0,
+            total_pages: 0,
+            is_done: false,
+            updated_at: DateTime::now(),
+        })
+        .await;
+}
+
+// walks the staking calls and batch_all of the network from the newest page back to genesis,
+// the progress is saved once a page is stored so a restart continues with the next one,
+// extrinsics coming in meanwhile only push the older ones to later pages, which are then read
+// twice instead of skipped
+pub async fn run_backfill(config: &StakingParserConfig, backfill_config: &BackfillConfig) {
+    let network = config.network.get_slug().to_string();
+    let mut mongodb_client_backfill_progress = MongoDbClientBackfillProgress::new().await;
+    let mut progress = mongodb_client_backfill_progress
+        .get_progress(&network)
+        .await
+        .unwrap_or_else(|| BackfillProgress {
+            network: network.clone(),
+            next_page: 0,
+            total_pages: 0,
+            is_done: false,
+            updated_at: DateTime::now(),
+        });
+    if progress.is_done {
+        info!(target: "backfill", "Backfill of {network} is done already.");
+        return;
+    }
+
+    info!(target: "backfill", "Backfill of {network} starts at page {}.", progress.next_page);
+    let mut subscan_parser = SubscanParser::new(config.network.clone())
+        .await
+        .with_api_keys(config.api_keys.clone());
+    loop {
+        // the history grows while it is walked, so the counts are taken again at its end
+        if progress.next_page >= progress.total_pages {
+            let counts = match get_counts(&mut subscan_parser).await {
+                Ok(counts) => counts,
+                Err(e) => {
+                    error!(target: "backfill", "Extrinsics count error: {e}.");
+                    sleep(backfill_config.retry_delay).await;
+                    continue;
+                }
+            };
+
+            progress.total_pages = get_total_pages(&counts, config.rows_per_page);
+            if progress.next_page >= progress.total_pages {
+                progress.is_done = true;
+                progress.updated_at = DateTime::now();
+                mongodb_client_backfill_progress
+                    .save_progress(&progress)
+                    .await;
+                info!(target: "backfill", "Backfill of {network} done after {} pages.", progress.total_pages);
+                return;
+            }
+        }
+
+        let page = progress.next_page;
+        let page_config = config.clone().with_backfill_page(page);
+        let run_span = get_run_span(&config.network);
+        let Some((subscan_operations, _)) = parse_staking(&page_config)
+            .instrument(run_span.clone())
+            .await
+        else {
+            error!(target: "backfill", "Backfill page {page} incomplete, retrying it.");
+            sleep(backfill_config.retry_delay).await;
+            continue;
+        };
+
+        let stored = store_operations(subscan_operations, &config.network)
+            .instrument(run_span)
+            .await;
+        progress.next_page = page + 1;
+        progress.updated_at = DateTime::now();
+        mongodb_client_backfill_progress
+            .save_progress(&progress)
+            .await;
+
+        info!(target: "backfill", "Backfilled page {} of {} with {stored} operations.", page + 1, progress.total_pages);
+        sleep(backfill_config.page_delay).await;
+    }
+}
+
+// same way as the operations of the live runs, the checkpoint is left to them
+async fn store_operations(subscan_operations: Vec<SubscanOperation>, network: &Network) -> usize {
+    record_parsed_operations(&subscan_operations);
+    let subscan_operations = validate_operations(subscan_operations).await;
+    let mut subscan_operations = release_confirmed_operations(subscan_operations, network).await;
+    if subscan_operations.is_empty() {
+        return 0;
+    }
+
+    set_operation_statuses(&mut subscan_operations, network).await;
+    set_operation_ids(&mut subscan_operations);
+
+    let released_operations = subscan_operations.clone();
+    let mut stored_operations = Vec::new();
+    if is_outbox_enabled() {
+        stored_operations = write_operations_with_outbox(subscan_operations, None).await;
+    } else {
+        for sink in Sink::from_env() {
+            stored_operations.extend(sink.write_operations(subscan_operations.clone()).await);
+        }
+    }
+
+    remove_released_operations(&released_operations).await;
+
+    if !is_change_streams_enabled() {
+        process_stored_operations(&stored_operations).await;
+    }
+
+    released_operations.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backfill::get_total_pages;
+
+    #[test]
+    fn longest_call_decides_the_pages() {
+        assert_eq!(get_total_pages(&[250, 99, 0], 100), 3);
+        assert_eq!(get_total_pages(&[200], 100), 2);
+        assert_eq!(get_total_pages(&[0, 0], 100), 0);
+        assert_eq!(get_total_pages(&[], 100), 0);
+
+        // a zero page size still walks a row per page
+        assert_eq!(get_total_pages(&[3], 0), 3);
+    }
+}
@@ -0,0 +1,36 @@
+use rs_subscan_parser::{
+    backcheck::{run_backcheck, BackcheckConfig},
+    subscan_parser::Network,
+};
+use rs_utils::utils::logger::initialize_logger;
+use std::{env, process};
+use tracing::error;
+
+// compares a sample of the stored operations with subscan and prints the discrepancy report,
+// exits with 2 if there are discrepancies so scheduled runs can alert on it
+#[tokio::main]
+async fn main() {
+    initialize_logger().expect("failed to initialize logging.");
+
+    let mut config = BackcheckConfig::from_env();
+    match env::args().skip(1).collect::<Vec<_>>().as_slice() {
+        [] => {}
+        [sample_size] => {
+            let Some(sample_size) = sample_size.parse::<u64>().ok().filter(|s| *s > 0) else {
+                error!(target: "backcheck", "Usage: backcheck [sample_size]");
+                process::exit(1);
+            };
+            config = config.with_sample_size(sample_size);
+        }
+        _ => {
+            error!(target: "backcheck", "Usage: backcheck [sample_size]");
+            process::exit(1);
+        }
+    }
+
+    let report = run_backcheck(&Network::from_env(), &config).await;
+    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    if !report.discrepancies.is_empty() {
+        process::exit(2);
+    }
+}
@@ -0,0 +1,52 @@
+use rs_subscan_parser::{
+    backfill::{reset_backfill, run_backfill, BackfillConfig},
+    metrics::{get_metrics_port, serve_metrics},
+    mongodb_client_backfill_progress::MongoDbClientBackfillProgress,
+    preflight::preflight,
+    subscan_stake_parser::StakingParserConfig,
+};
+use rs_utils::utils::logger::initialize_logger;
+use std::{env, process};
+use tracing::{error, info};
+
+// parses the whole staking history of SUBSCAN_NETWORK next to the live parser, e.g. with
+// docker compose run --entrypoint /app/backfill subscan_parser, a restart resumes it and
+// --restart starts over from the newest page
+#[tokio::main(worker_threads = 20)]
+async fn main() {
+    initialize_logger().expect("failed to initialize logging.");
+
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let is_restart = match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [] => false,
+        ["--restart"] => true,
+        _ => {
+            error!(target: "backfill", "Usage: backfill [--restart]");
+            process::exit(1);
+        }
+    };
+
+    let config = StakingParserConfig::from_env();
+    if let Err(e) = preflight(config.network.clone()).await {
+        error!(target: "backfill", "Preflight check failed: {e}");
+        process::exit(1);
+    }
+
+    let mut mongodb_client_backfill_progress = MongoDbClientBackfillProgress::new().await;
+    mongodb_client_backfill_progress.create_index().await;
+    if is_restart {
+        info!(target: "backfill", "Restarting the backfill from the newest page.");
+        reset_backfill(&config.network).await;
+    }
+
+    if let Some(port) = get_metrics_port() {
+        tokio::spawn(serve_metrics(port));
+    }
+
+    run_backfill(&config, &BackfillConfig::from_env()).await;
+}
@@ -0,0 +1,41 @@
+use rs_subscan_parser::exports::parts::{export_operations, ExportConfig};
+use rs_utils::utils::logger::initialize_logger;
+use std::{env, process};
+use tracing::{error, info};
+
+// writes the operations between two unix timestamps to EXPORT_DIRECTORY in parts of
+// EXPORT_PART_SIZE operations, e.g. EXPORT_FORMAT=jsonl EXPORT_COMPRESSION=zstd, and prints
+// the path of every part, EXPORT_FORMAT=parquet needs the parquet feature, --resume continues
+// an interrupted export of the same range
+#[tokio::main]
+async fn main() {
+    initialize_logger().expect("failed to initialize logging.");
+
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    let is_resume = args.first().is_some_and(|a| a == "--resume");
+    if is_resume {
+        args.remove(0);
+    }
+    let timestamps = args
+        .iter()
+        .map(|a| a.parse::<i64>().ok())
+        .collect::<Option<Vec<_>>>();
+    let Some([from_timestamp, to_timestamp]) = timestamps.as_deref() else {
+        error!(target: "export_operations", "Usage: export_operations [--resume] <from_timestamp> <to_timestamp>");
+        process::exit(1);
+    };
+
+    let config = ExportConfig::from_env();
+    match export_operations(*from_timestamp, *to_timestamp, &config, is_resume).await {
+        Ok(parts) => {
+            info!(target: "export_operations", "Exported {} parts.", parts.len());
+            for part in parts {
+                println!("{}", part.display());
+            }
+        }
+        Err(e) => {
+            error!(target: "export_operations", "Export failed: {e}");
+            process::exit(1);
+        }
+    }
+}
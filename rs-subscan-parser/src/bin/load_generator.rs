@@ -0,0 +1,66 @@
+use bson::DateTime;
+use itertools::Itertools;
+use rs_subscan_parser::{mock_network::get_synthetic_operation, sinks::Sink};
+use rs_utils::utils::logger::initialize_logger;
+use std::{env, process, time::Instant};
+use tracing::{error, info, warn};
+
+static DEFAULT_BATCH_SIZE: u64 = 10_000;
+
+// operations are spread one second apart, ending now
+static OPERATION_INTERVAL_MS: i64 = 1_000;
+
+#[tokio::main]
+async fn main() {
+    initialize_logger().expect("failed to initialize logging.");
+
+    let args = env::args().collect::<Vec<_>>();
+    let Some(count) = args.get(1).and_then(|c| c.parse::<u64>().ok()) else {
+        error!(target: "load_generator", "Usage: load_generator <operations count> [batch size]");
+        process::exit(1);
+    };
+    let batch_size = args
+        .get(2)
+        .and_then(|b| b.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_BATCH_SIZE)
+        .max(1);
+
+    let sinks = Sink::from_env();
+    warn!(
+        target: "load_generator",
+        "Writing {count} synthetic operations to {}, point them to scratch storage, not the one the feed reads.",
+        sinks.iter().join(", "),
+    );
+
+    let started_at = Instant::now();
+    let first_timestamp = DateTime::now().timestamp_millis() - count as i64 * OPERATION_INTERVAL_MS;
+    let mut written = 0;
+    while written < count {
+        let operations = (written..count.min(written + batch_size))
+            .map(|i| {
+                let timestamp =
+                    DateTime::from_millis(first_timestamp + i as i64 * OPERATION_INTERVAL_MS);
+                get_synthetic_operation(i, timestamp)
+            })
+            .collect::<Vec<_>>();
+        written += operations.len() as u64;
+
+        for sink in sinks.iter() {
+            let sink_started_at = Instant::now();
+            sink.write_operations(operations.clone()).await;
+            info!(
+                target: "load_generator",
+                "{sink}: {} operations in {} ms.",
+                operations.len(),
+                sink_started_at.elapsed().as_millis(),
+            );
+        }
+
+        let elapsed = started_at.elapsed().as_secs_f64();
+        info!(
+            target: "load_generator",
+            "Written {written}/{count}, {:.0} operations/s.",
+            written as f64 / elapsed,
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use rs_subscan_parser::{
+    address::AddressFormat,
+    materialized_views::recheck_key,
+    mongodb_client_identities::MongoDbClientIdentity,
+    mongodb_client_operation_summaries::MongoDbClientOperationSummaries,
+    mongodb_client_operation_totals::MongoDbClientOperationTotals,
+    mongodb_client_subscan::MongoDbClientSubscan,
+    mongodb_client_validator::MongoDbClientValidator,
+    mongodb_client_wallet_formats::MongoDbClientWalletFormats,
+    subscan_parser::Network,
+    wallet_formats::{import_wallet_formats, merge_wallet_formats, normalize_address},
+    TotalsView, WalletFormats,
+};
+use rs_utils::utils::logger::initialize_logger;
+use strum::IntoEnumIterator;
+use tracing::info;
+
+// rewrites wallets stored before normalization to their canonical encoding,
+// the replaced encodings are kept as alternates, running it again changes nothing
+#[tokio::main]
+async fn main() {
+    initialize_logger().expect("failed to initialize logging.");
+
+    let mut mongodb_client_wallet_formats = MongoDbClientWalletFormats::new().await;
+    mongodb_client_wallet_formats.create_index().await;
+
+    let mut wallet_formats = Vec::new();
+
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let mut replaced = 0;
+    for formats in get_wallet_formats(mongodb_client_subscan.get_wallets().await) {
+        for alternate in formats.alternates.iter() {
+            replaced += mongodb_client_subscan
+                .replace_wallet(alternate, &formats.canonical)
+                .await;
+        }
+        wallet_formats.push(formats);
+    }
+    info!(target: "normalize_wallets", "Replaced {replaced} wallets of operations.");
+
+    let mut mongodb_client_operation_summaries = MongoDbClientOperationSummaries::new().await;
+    let mut replaced = 0;
+    for formats in get_wallet_formats(mongodb_client_operation_summaries.get_wallets().await) {
+        for alternate in formats.alternates.iter() {
+            replaced += mongodb_client_operation_summaries
+                .replace_wallet(alternate, &formats.canonical)
+                .await;
+        }
+        wallet_formats.push(formats);
+    }
+    info!(target: "normalize_wallets", "Replaced {replaced} wallets of summaries.");
+
+    let mut mongodb_client_validator = MongoDbClientValidator::new().await;
+    let mut replaced = 0;
+    for formats in get_wallet_formats(mongodb_client_validator.get_wallets().await) {
+        for alternate in formats.alternates.iter() {
+            replaced += mongodb_client_validator
+                .replace_wallet(alternate, &formats.canonical)
+                .await;
+        }
+        wallet_formats.push(formats);
+    }
+    info!(target: "normalize_wallets", "Replaced {replaced} wallets of nominations.");
+
+    let mut mongodb_client_identity = MongoDbClientIdentity::new().await;
+    let mut replaced = 0;
+    for formats in get_wallet_formats(mongodb_client_identity.get_addresses().await) {
+        for alternate in formats.alternates.iter() {
+            mongodb_client_identity
+                .replace_address(alternate, &formats.canonical)
+                .await;
+            replaced += 1;
+        }
+        wallet_formats.push(formats);
+    }
+    info!(target: "normalize_wallets", "Replaced {replaced} addresses of identities.");
+
+    // totals are rebuilt from the rewritten operations and summaries
+    let wallet_formats = merge_wallet_formats(wallet_formats);
+    let mut mongodb_client_operation_totals = MongoDbClientOperationTotals::new().await;
+    for formats in wallet_formats.iter() {
+        for alternate in formats.alternates.iter() {
+            mongodb_client_operation_totals
+                .delete_totals(alternate)
+                .await;
+        }
+        for view in TotalsView::iter() {
+            recheck_key(&view, &formats.canonical).await;
+        }
+    }
+    info!(target: "normalize_wallets", "Rechecked totals of {} wallets.", wallet_formats.len());
+
+    import_wallet_formats(wallet_formats).await;
+}
+
+// only the wallets which are not canonical yet
+fn get_wallet_formats(wallets: Vec<String>) -> Vec<WalletFormats> {
+    let network = Network::from_env();
+    let address_format = AddressFormat::from_env();
+    wallets
+        .into_iter()
+        .filter_map(|mut w| normalize_address(&mut w, &network, address_format))
+        .collect()
+}
@@ -0,0 +1,85 @@
+use rs_subscan_parser::{
+    subscan_error::SubscanError,
+    subscan_parser::{Network, SubscanParser},
+    ExtrinsicsType, Module,
+};
+use rs_utils::utils::logger::initialize_logger;
+use serde::Serialize;
+use std::{env, process, str::FromStr};
+use tracing::error;
+
+static DEFAULT_ROWS: u32 = 10;
+static USAGE: &str = "Usage: nym-tradefeed stake <module> <call> [page] [rows] | events \
+                      <event_index>... | extrinsic <extrinsic_index> | batch-all [page] [rows]";
+
+// queries subscan of SUBSCAN_NETWORK the way the parser does and prints the parsed result as
+// json, e.g. nym-tradefeed stake staking bond 0 25, nothing is quarantined or written to the db
+#[tokio::main]
+async fn main() {
+    initialize_logger().expect("failed to initialize logging.");
+
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let mut subscan_parser = SubscanParser::new(Network::from_env()).await;
+
+    let res = match args.as_slice() {
+        ["stake", module, call, rest @ ..] => {
+            let (Ok(module), Ok(call)) = (Module::from_str(module), ExtrinsicsType::from_str(call))
+            else {
+                exit_with_usage();
+            };
+            let (page, rows) = get_page(rest);
+            let operations = subscan_parser
+                .parse_subscan_operations("", module, call, page, rows)
+                .await;
+            print_json(operations.map(|p| p.operations))
+        }
+        ["events", event_indexes @ ..] if !event_indexes.is_empty() => {
+            let event_indexes = event_indexes.iter().map(|e| e.to_string()).collect();
+            print_json(subscan_parser.parse_subscan_events(event_indexes).await)
+        }
+        ["extrinsic", extrinsic_index] => print_json(
+            subscan_parser
+                .parse_subscan_extrinsic(extrinsic_index)
+                .await,
+        ),
+        ["batch-all", rest @ ..] => {
+            let (page, rows) = get_page(rest);
+            let operations = subscan_parser.parse_subscan_batch_all("", page, rows).await;
+            print_json(operations.map(|p| p.operations))
+        }
+        _ => exit_with_usage(),
+    };
+
+    if let Err(e) = res {
+        error!(target: "nym_tradefeed", "{e}");
+        process::exit(1);
+    }
+}
+
+// [page] [rows], the first page of DEFAULT_ROWS rows unless given
+fn get_page(args: &[&str]) -> (u32, u32) {
+    let numbers = args
+        .iter()
+        .map(|a| a.parse::<u32>().ok())
+        .collect::<Option<Vec<_>>>();
+    match numbers.as_deref() {
+        Some([]) => (0, DEFAULT_ROWS),
+        Some([page]) => (*page, DEFAULT_ROWS),
+        Some([page, rows]) if *rows > 0 => (*page, *rows),
+        _ => exit_with_usage(),
+    }
+}
+
+fn print_json<T: Serialize>(res: Result<T, SubscanError>) -> Result<(), String> {
+    let value = res.map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(&value).map_err(|e| e.to_string())?;
+    println!("{json}");
+
+    Ok(())
+}
+
+fn exit_with_usage() -> ! {
+    error!(target: "nym_tradefeed", "{USAGE}");
+    process::exit(1);
+}
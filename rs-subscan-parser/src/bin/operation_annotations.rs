@@ -0,0 +1,60 @@
+use bson::DateTime;
+use rs_subscan_parser::{
+    mongodb_client_operation_annotations::MongoDbClientOperationAnnotations,
+    mongodb_client_subscan::MongoDbClientSubscan, subscan_parser::Network, OperationAnnotation,
+};
+use rs_utils::utils::logger::initialize_logger;
+use std::{env, process};
+use tracing::{error, info};
+
+static SUPPRESS_ALERTS_FLAG: &str = "--suppress-alerts";
+
+#[tokio::main]
+async fn main() {
+    initialize_logger().expect("failed to initialize logging.");
+
+    let args = env::args().collect::<Vec<_>>();
+    let (Some(command), Some(hash)) = (args.get(1), args.get(2)) else {
+        error!(target: "operation_annotations", "Usage: operation_annotations <add|list> <operation hash> [text] [{SUPPRESS_ALERTS_FLAG}]");
+        process::exit(1);
+    };
+
+    let mut mongodb_client_operation_annotations = MongoDbClientOperationAnnotations::new().await;
+    mongodb_client_operation_annotations.create_index().await;
+
+    match command.as_str() {
+        "add" => {
+            let Some(text) = args.get(3) else {
+                error!(target: "operation_annotations", "Annotation text is missing");
+                process::exit(1);
+            };
+            let annotation = OperationAnnotation {
+                hash: hash.to_string(),
+                text: text.to_string(),
+                suppress_alerts: args.iter().any(|a| a == SUPPRESS_ALERTS_FLAG),
+                created_at: DateTime::now(),
+            };
+            mongodb_client_operation_annotations
+                .import_annotation(annotation)
+                .await;
+            info!(target: "operation_annotations", "Annotated {hash}");
+        }
+        "list" => {
+            let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+            if let Some(operation) = mongodb_client_subscan.get_operation_by_hash(hash).await {
+                println!("{}", operation.summary(&Network::from_env()));
+            }
+
+            let annotations = mongodb_client_operation_annotations
+                .get_annotations(vec![hash.to_string()])
+                .await;
+            for annotation in annotations {
+                println!("{}", serde_json::to_string(&annotation).unwrap());
+            }
+        }
+        _ => {
+            error!(target: "operation_annotations", "unknown command {command}");
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use rs_subscan_parser::operations_watcher::{is_change_streams_enabled, watch_operations};
+use rs_utils::utils::logger::initialize_logger;
+use std::process;
+use tracing::{error, info};
+
+#[tokio::main(worker_threads = 10)]
+async fn main() {
+    initialize_logger().expect("failed to initialize logging.");
+
+    // stats are built by the subscan parser itself then
+    if !is_change_streams_enabled() {
+        info!(target: "operations_watcher", "CHANGE_STREAMS is not enabled, nothing to watch.");
+        return;
+    }
+
+    info!(target: "operations_watcher", "Started operations watcher.");
+
+    watch_operations().await;
+
+    error!(target: "operations_watcher", "Change streams are not supported, mongodb must run as a replica set.");
+    process::exit(1);
+}
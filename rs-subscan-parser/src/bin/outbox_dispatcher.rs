@@ -0,0 +1,18 @@
+use rs_subscan_parser::outbox::{dispatch_outbox, is_outbox_enabled};
+use rs_utils::utils::logger::initialize_logger;
+use tracing::info;
+
+#[tokio::main(worker_threads = 10)]
+async fn main() {
+    initialize_logger().expect("failed to initialize logging.");
+
+    // sinks are written by the subscan parser itself then
+    if !is_outbox_enabled() {
+        info!(target: "outbox", "OUTBOX is not enabled, nothing to dispatch.");
+        return;
+    }
+
+    info!(target: "outbox", "Started outbox dispatcher.");
+
+    dispatch_outbox().await;
+}
@@ -0,0 +1,107 @@
+use rs_subscan_parser::{
+    parser_diff::{decode_call, diff_operations, parse_call_spec, record_call, RecordedCall},
+    subscan_parser::Network,
+    SubscanOperation,
+};
+use rs_utils::utils::logger::initialize_logger;
+use serde::de::DeserializeOwned;
+use std::{env, fs, process};
+use tracing::error;
+
+// validates parser changes against recorded subscan answers: record them once, decode them
+// with the old build into a baseline, then diff the new build against it, exits with 2 on
+// diffs, replays write quarantine and wallet formats so point MONGODB_DATABASE at a scratch one
+#[tokio::main]
+async fn main() {
+    initialize_logger().expect("failed to initialize logging.");
+
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let network = Network::from_env();
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["record", path, "--from-block", block, specs @ ..] if !specs.is_empty() => {
+            let Ok(block) = block.parse::<u64>() else {
+                exit_with_usage();
+            };
+            record(&network, path, Some(block), specs).await;
+        }
+        ["record", path, specs @ ..] if !specs.is_empty() => {
+            record(&network, path, None, specs).await;
+        }
+        ["decode", path] => {
+            let operations = decode(&network, path).await;
+            println!("{}", serde_json::to_string_pretty(&operations).unwrap());
+        }
+        ["diff", path, baseline_path] => {
+            let baseline = read_json::<Vec<SubscanOperation>>(baseline_path);
+            let operations = decode(&network, path).await;
+            let report = diff_operations(&baseline, &operations);
+            println!("{}", serde_json::to_string_pretty(&report).unwrap());
+            if !report.diffs.is_empty() {
+                process::exit(2);
+            }
+        }
+        _ => exit_with_usage(),
+    }
+}
+
+async fn record(network: &Network, path: &str, min_block_number: Option<u64>, specs: &[&str]) {
+    let mut recorded_calls = Vec::new();
+    for spec in specs {
+        let Some((module, call)) = parse_call_spec(spec) else {
+            error!(target: "parser_diff", "Unknown call {spec}.");
+            exit_with_usage();
+        };
+
+        match record_call(network, module, &call, min_block_number).await {
+            Ok(recorded_call) => recorded_calls.push(recorded_call),
+            Err(e) => {
+                error!(target: "parser_diff", "Recording {spec} failed: {e}.");
+                process::exit(1);
+            }
+        }
+    }
+
+    let recorded = serde_json::to_string_pretty(&recorded_calls).unwrap();
+    if let Err(e) = fs::write(path, recorded) {
+        error!(target: "parser_diff", "Writing {path} failed: {e}.");
+        process::exit(1);
+    }
+}
+
+async fn decode(network: &Network, path: &str) -> Vec<SubscanOperation> {
+    let mut operations = Vec::new();
+    for recorded_call in read_json::<Vec<RecordedCall>>(path) {
+        match decode_call(network, &recorded_call).await {
+            Ok(mut decoded) => operations.append(&mut decoded),
+            Err(e) => {
+                error!(target: "parser_diff", "Decoding {} failed: {e}.", recorded_call.call);
+                process::exit(1);
+            }
+        }
+    }
+
+    operations
+}
+
+fn read_json<T: DeserializeOwned>(path: &str) -> T {
+    let parsed = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| serde_json::from_str(&s).map_err(|e| e.to_string()));
+    match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            error!(target: "parser_diff", "Reading {path} failed: {e}.");
+            process::exit(1);
+        }
+    }
+}
+
+fn exit_with_usage() -> ! {
+    error!(target: "parser_diff", "Usage: parser_diff record <recorded.json> [--from-block <block>] <call>... | decode <recorded.json> | diff <recorded.json> <baseline.json>, calls like unbond or nominationpools:join");
+    process::exit(1);
+}
@@ -0,0 +1,6 @@
+use rs_subscan_parser::sinks::protobuf::get_proto;
+
+// prints the .proto of protobuf sink messages, kept in proto/operation.proto
+fn main() {
+    print!("{}", get_proto());
+}
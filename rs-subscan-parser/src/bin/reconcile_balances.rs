@@ -0,0 +1,64 @@
+use rs_subscan_parser::{
+    mongodb_client_balance_snapshots::MongoDbClientBalanceSnapshots,
+    reconciliation::{
+        get_reconciliation_tolerance, get_reconciliation_wallets, reconcile_wallets,
+        take_balance_snapshots,
+    },
+};
+use rs_utils::utils::logger::initialize_logger;
+use std::{env, process};
+use tracing::{error, info};
+
+// snapshot takes the balances of RECONCILIATION_WALLETS now, report prints the reconciliation
+// of every snapshotted wallet between two unix timestamps as json and exits with 2 if any
+// delta is unexplained
+#[tokio::main]
+async fn main() {
+    initialize_logger().expect("failed to initialize logging.");
+
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    match args.as_slice() {
+        ["snapshot"] => {
+            let wallets = get_reconciliation_wallets();
+            let mut mongodb_client_balance_snapshots = MongoDbClientBalanceSnapshots::new().await;
+            mongodb_client_balance_snapshots.create_index().await;
+            let snapshots = take_balance_snapshots(&wallets).await;
+            info!(target: "reconcile_balances", "Took {} of {} balance snapshots.", snapshots.len(), wallets.len());
+        }
+        ["report", from_timestamp, to_timestamp] => {
+            let (Ok(from_timestamp), Ok(to_timestamp)) =
+                (from_timestamp.parse::<i64>(), to_timestamp.parse::<i64>())
+            else {
+                exit_with_usage();
+            };
+
+            let mut mongodb_client_balance_snapshots = MongoDbClientBalanceSnapshots::new().await;
+            let wallets = mongodb_client_balance_snapshots.get_wallets().await;
+            let report = reconcile_wallets(
+                wallets,
+                from_timestamp,
+                to_timestamp,
+                get_reconciliation_tolerance(),
+            )
+            .await;
+            match serde_json::to_string_pretty(&report) {
+                Ok(json) => println!("{json}"),
+                Err(e) => {
+                    error!(target: "reconcile_balances", "{e}");
+                    process::exit(1);
+                }
+            }
+
+            if report.wallets.iter().any(|w| w.unreconciled_intervals > 0) {
+                process::exit(2);
+            }
+        }
+        _ => exit_with_usage(),
+    }
+}
+
+fn exit_with_usage() -> ! {
+    error!(target: "reconcile_balances", "Usage: reconcile_balances snapshot | report <from_timestamp> <to_timestamp>");
+    process::exit(1);
+}
@@ -0,0 +1,94 @@
+use rs_subscan_parser::{mongodb_client_validator::MongoDbClientValidator, Validator};
+use rs_utils::utils::logger::initialize_logger;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    process,
+};
+use tracing::{error, info};
+
+// one line of the snapshot file, checksum is sha256 of the serialized validator
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SnapshotLine {
+    validator: Validator,
+    checksum: String,
+}
+
+#[tokio::main]
+async fn main() {
+    initialize_logger().expect("failed to initialize logging.");
+
+    let args = env::args().collect::<Vec<_>>();
+    let (Some(command), Some(path)) = (args.get(1), args.get(2)) else {
+        error!(target: "validators_snapshot", "Usage: validators_snapshot <export|import> <file.jsonl>");
+        process::exit(1);
+    };
+
+    let res = match command.as_str() {
+        "export" => export_validators(path).await,
+        "import" => import_validators(path).await,
+        _ => Err(format!("unknown command {command}")),
+    };
+
+    if let Err(e) = res {
+        error!(target: "validators_snapshot", "{e}");
+        process::exit(1);
+    }
+}
+
+async fn export_validators(path: &str) -> Result<(), String> {
+    let mut mongodb_client_validator = MongoDbClientValidator::new().await;
+    let validators = mongodb_client_validator.get_all_validators().await;
+
+    let file = File::create(path).map_err(|e| format!("failed to create {path}: {e}"))?;
+    let mut writer = BufWriter::new(file);
+    for validator in validators.iter() {
+        let serialized = serde_json::to_string(validator).map_err(|e| e.to_string())?;
+        let line = SnapshotLine {
+            validator: validator.clone(),
+            checksum: sha256::digest(serialized),
+        };
+        let line = serde_json::to_string(&line).map_err(|e| e.to_string())?;
+        writeln!(writer, "{line}").map_err(|e| format!("failed to write {path}: {e}"))?;
+    }
+    writer
+        .flush()
+        .map_err(|e| format!("failed to write {path}: {e}"))?;
+
+    info!(target: "validators_snapshot", "Exported {} validators to {path}", validators.len());
+    Ok(())
+}
+
+async fn import_validators(path: &str) -> Result<(), String> {
+    let file = File::open(path).map_err(|e| format!("failed to open {path}: {e}"))?;
+
+    // whole file is verified before anything is written to the db
+    let mut validators = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("failed to read {path}: {e}"))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let line: SnapshotLine = serde_json::from_str(&line)
+            .map_err(|e| format!("line {} is not a valid snapshot record: {e}", i + 1))?;
+        let serialized = serde_json::to_string(&line.validator).map_err(|e| e.to_string())?;
+        if sha256::digest(serialized) != line.checksum {
+            return Err(format!("checksum mismatch on line {}", i + 1));
+        }
+
+        validators.push(line.validator);
+    }
+
+    let validators_len = validators.len();
+    let mut mongodb_client_validator = MongoDbClientValidator::new().await;
+    mongodb_client_validator.create_index().await;
+    mongodb_client_validator
+        .import_or_update_validators(validators)
+        .await;
+
+    info!(target: "validators_snapshot", "Imported {validators_len} validators from {path}");
+    Ok(())
+}
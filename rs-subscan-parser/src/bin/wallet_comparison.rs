@@ -0,0 +1,25 @@
+use rs_subscan_parser::{
+    wallet_comparison::{compare_wallets, get_comparison_window_seconds, MAX_COMPARED_WALLETS},
+    wallet_formats::get_canonical_address,
+};
+use rs_utils::utils::logger::initialize_logger;
+use std::{env, process};
+use tracing::error;
+
+// prints the staking behavior of the wallets side by side, see wallet_comparison
+#[tokio::main]
+async fn main() {
+    initialize_logger().expect("failed to initialize logging.");
+
+    let wallets = env::args()
+        .skip(1)
+        .map(|w| get_canonical_address(&w).unwrap_or(w))
+        .collect::<Vec<_>>();
+    if wallets.len() < 2 || wallets.len() > MAX_COMPARED_WALLETS {
+        error!(target: "wallet_comparison", "Usage: wallet_comparison <wallet> <wallet> [wallet]..., at most {MAX_COMPARED_WALLETS} wallets");
+        process::exit(1);
+    }
+
+    let comparison = compare_wallets(wallets, get_comparison_window_seconds()).await;
+    println!("{}", serde_json::to_string_pretty(&comparison).unwrap());
+}
@@ -0,0 +1,281 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+use strum_macros::Display;
+use tracing::{error, info};
+
+static DEFAULT_SUBSCAN_CIRCUIT_FAILURES: u32 = 20;
+static DEFAULT_SUBSCAN_CIRCUIT_COOLDOWN_MS: u64 = 60_000;
+
+static CIRCUIT_BREAKER: OnceLock<CircuitBreaker> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Display, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+
+    // requests fail fast until the cool-down is over
+    Open,
+
+    // the cool-down is over and a single trial request decides whether subscan is back
+    HalfOpen,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CircuitStatus {
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    pub times_opened: u64,
+
+    // until requests are let through again, zero unless open
+    pub retry_in_ms: u64,
+}
+
+// how an answer counts for the breaker
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestOutcome {
+    Success,
+    Failure,
+
+    // e.g. a malformed response, it says nothing about whether subscan is up
+    Neutral,
+}
+
+struct BreakerState {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    trial_started_at: Option<Instant>,
+    times_opened: u64,
+}
+
+// shared by every parser of the process, so a subscan outage stops all of them at once
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold,
+            cooldown,
+            state: Mutex::new(BreakerState {
+                consecutive_failures: 0,
+                opened_at: None,
+                trial_started_at: None,
+                times_opened: 0,
+            }),
+        }
+    }
+
+    // SUBSCAN_CIRCUIT_FAILURES consecutive failed requests open it for SUBSCAN_CIRCUIT_COOLDOWN_MS
+    fn from_env() -> Self {
+        let failure_threshold = env::var("SUBSCAN_CIRCUIT_FAILURES")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_SUBSCAN_CIRCUIT_FAILURES);
+        let cooldown_ms = env::var("SUBSCAN_CIRCUIT_COOLDOWN_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_SUBSCAN_CIRCUIT_COOLDOWN_MS);
+
+        Self::new(failure_threshold, Duration::from_millis(cooldown_ms))
+    }
+
+    pub fn global() -> &'static CircuitBreaker {
+        CIRCUIT_BREAKER.get_or_init(CircuitBreaker::from_env)
+    }
+
+    // the time left of the cool-down if the request has to fail fast
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        self.try_acquire_at(Instant::now())
+    }
+
+    pub fn record(&self, outcome: RequestOutcome) {
+        match outcome {
+            RequestOutcome::Success => self.record_success(),
+            RequestOutcome::Failure => self.record_failure(),
+            RequestOutcome::Neutral => self.record_neutral(),
+        }
+    }
+
+    // a response with code 0
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.opened_at.is_some() {
+            info!(target: "subscan_parser", "Subscan circuit closed, the trial request succeeded.");
+        }
+
+        state.consecutive_failures = 0;
+        state.opened_at = None;
+        state.trial_started_at = None;
+    }
+
+    pub fn record_failure(&self) {
+        self.record_failure_at(Instant::now());
+    }
+
+    // keeps the count, a neutral trial only lets the next request be the trial
+    fn record_neutral(&self) {
+        self.state.lock().unwrap().trial_started_at = None;
+    }
+
+    pub fn get_status(&self) -> CircuitStatus {
+        self.get_status_at(Instant::now())
+    }
+
+    fn try_acquire_at(&self, now: Instant) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+        let Some(opened_at) = state.opened_at else {
+            return Ok(());
+        };
+
+        let elapsed = now.saturating_duration_since(opened_at);
+        if elapsed < self.cooldown {
+            return Err(self.cooldown - elapsed);
+        }
+
+        // the other requests keep failing fast while the trial one is out, a trial dropped
+        // without an answer is replaced after another cool-down
+        if let Some(trial_started_at) = state.trial_started_at {
+            if now.saturating_duration_since(trial_started_at) < self.cooldown {
+                return Err(Duration::ZERO);
+            }
+        }
+
+        info!(target: "subscan_parser", "Subscan circuit half open, sending a trial request.");
+        state.trial_started_at = Some(now);
+        Ok(())
+    }
+
+    fn record_failure_at(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+
+        // failures of requests sent before it opened don't extend the cool-down
+        let is_trial = state.trial_started_at.is_some();
+        let is_open = state.opened_at.is_some();
+        if !is_trial && (is_open || state.consecutive_failures < self.failure_threshold) {
+            return;
+        }
+
+        state.opened_at = Some(now);
+        state.trial_started_at = None;
+        state.times_opened += 1;
+        error!(target: "subscan_parser", "Subscan circuit opened after {} consecutive failures, failing fast for {} ms.", state.consecutive_failures, self.cooldown.as_millis());
+    }
+
+    fn get_status_at(&self, now: Instant) -> CircuitStatus {
+        let state = self.state.lock().unwrap();
+        let (circuit_state, retry_in) = match state.opened_at {
+            None => (CircuitState::Closed, Duration::ZERO),
+            Some(opened_at) => {
+                let elapsed = now.saturating_duration_since(opened_at);
+                if elapsed < self.cooldown {
+                    (CircuitState::Open, self.cooldown - elapsed)
+                } else {
+                    (CircuitState::HalfOpen, Duration::ZERO)
+                }
+            }
+        };
+
+        CircuitStatus {
+            state: circuit_state,
+            consecutive_failures: state.consecutive_failures,
+            times_opened: state.times_opened,
+            retry_in_ms: retry_in.as_millis() as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        circuit_breaker::{CircuitBreaker, CircuitState, RequestOutcome},
+        subscan_error::SubscanError,
+    };
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        let now = Instant::now();
+
+        breaker.record_failure_at(now);
+        breaker.record_failure_at(now);
+        breaker.record_success();
+        breaker.record_failure_at(now);
+        breaker.record_failure_at(now);
+        assert_eq!(breaker.try_acquire_at(now), Ok(()));
+
+        breaker.record_failure_at(now);
+        assert_eq!(
+            breaker.try_acquire_at(now + Duration::from_secs(15)),
+            Err(Duration::from_secs(45))
+        );
+
+        let status = breaker.get_status_at(now + Duration::from_secs(15));
+        assert_eq!(status.state, CircuitState::Open);
+        assert_eq!((status.consecutive_failures, status.times_opened), (3, 1));
+        assert_eq!(status.retry_in_ms, 45_000);
+    }
+
+    #[test]
+    fn a_single_trial_decides_after_the_cooldown() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60));
+        let now = Instant::now();
+        breaker.record_failure_at(now);
+
+        // the failed trial starts another cool-down
+        let after_cooldown = now + Duration::from_secs(60);
+        assert_eq!(
+            breaker.get_status_at(after_cooldown).state,
+            CircuitState::HalfOpen
+        );
+        assert_eq!(breaker.try_acquire_at(after_cooldown), Ok(()));
+        assert_eq!(breaker.try_acquire_at(after_cooldown), Err(Duration::ZERO));
+        breaker.record_failure_at(after_cooldown);
+        assert_eq!(
+            breaker.try_acquire_at(after_cooldown),
+            Err(Duration::from_secs(60))
+        );
+        assert_eq!(breaker.get_status_at(after_cooldown).times_opened, 2);
+
+        let after_second_cooldown = after_cooldown + Duration::from_secs(60);
+        assert_eq!(breaker.try_acquire_at(after_second_cooldown), Ok(()));
+        breaker.record_success();
+        assert_eq!(
+            breaker.get_status_at(after_second_cooldown).state,
+            CircuitState::Closed
+        );
+        assert_eq!(breaker.try_acquire_at(after_second_cooldown), Ok(()));
+    }
+
+    #[test]
+    fn repeated_api_errors_open_it() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        let now = Instant::now();
+
+        let api_error = SubscanError::from_code(10004, "Record Not Found");
+        let malformed = SubscanError::MissingField("data.extrinsics".to_string());
+        assert_eq!(api_error.get_request_outcome(), RequestOutcome::Failure);
+        assert_eq!(malformed.get_request_outcome(), RequestOutcome::Neutral);
+
+        // malformed responses neither count nor reset the count
+        breaker.record(api_error.get_request_outcome());
+        breaker.record(malformed.get_request_outcome());
+        breaker.record(api_error.get_request_outcome());
+        assert_eq!(breaker.try_acquire_at(now), Ok(()));
+
+        breaker.record(api_error.get_request_outcome());
+        let status = breaker.get_status();
+        assert_eq!(status.state, CircuitState::Open);
+        assert_eq!(status.consecutive_failures, 3);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{
+    mongodb_client_operation_summaries::MongoDbClientOperationSummaries,
+    mongodb_client_subscan::{MongoDbClientSubscan, RECORDS_TTL_SECONDS},
+    subscan_parser::SubscanParser,
+    OperationSummary, SubscanOperation, SummaryDirection,
+};
+use bson::DateTime;
+use chrono::Utc;
+use std::{collections::HashMap, env};
+use tracing::info;
+
+static SECONDS_IN_HOUR: i64 = 60 * 60;
+static DEFAULT_COMPACTION_RETENTION_DAYS: i64 = 30;
+
+// hours compacted per call, so the ingestion loop is not blocked on a large backlog
+static MAX_HOURS_PER_RUN: usize = 24;
+
+// raw operations older than the retention window are replaced with hourly per-wallet summaries,
+// the window is kept below the ttl of raw operations so nothing expires before it is compacted
+pub async fn compact_operations() {
+    let retention_seconds = env::var("COMPACTION_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_COMPACTION_RETENTION_DAYS)
+        * 24
+        * SECONDS_IN_HOUR;
+    let retention_seconds = retention_seconds.min(RECORDS_TTL_SECONDS as i64 - SECONDS_IN_HOUR);
+    let cutoff = Utc::now().timestamp() - retention_seconds;
+
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let mut mongodb_client_operation_summaries = MongoDbClientOperationSummaries::new().await;
+    for _ in 0..MAX_HOURS_PER_RUN {
+        let Some(oldest) = mongodb_client_subscan.get_oldest_operation().await else {
+            return;
+        };
+        let timestamp = oldest.operation_timestamp.timestamp_millis() / 1000;
+        let hour = timestamp - timestamp.rem_euclid(SECONDS_IN_HOUR);
+        if hour + SECONDS_IN_HOUR > cutoff {
+            return;
+        }
+
+        // summaries are recomputed from all raw operations of the hour, so a rerun after a crash is safe
+        let operations = mongodb_client_subscan
+            .get_filtered_operations(hour, Some(hour + SECONDS_IN_HOUR))
+            .await;
+        let summaries = build_summaries(&operations);
+        let summaries_len = summaries.len();
+        mongodb_client_operation_summaries
+            .import_or_update_summaries(summaries)
+            .await;
+        let deleted = mongodb_client_subscan
+            .delete_operations(hour, hour + SECONDS_IN_HOUR)
+            .await;
+
+        info!(target: "compaction", "Compacted {deleted} operations into {summaries_len} summaries for hour {hour}.");
+    }
+}
+
+pub fn build_summaries(operations: &[SubscanOperation]) -> Vec<OperationSummary> {
+    let mut summaries: HashMap<_, OperationSummary> = HashMap::new();
+
+    for operation in operations {
+        let millis = operation.operation_timestamp.timestamp_millis();
+        let hour = DateTime::from_millis(millis - millis.rem_euclid(SECONDS_IN_HOUR * 1000));
+        let sides = [
+            (&operation.from_wallet, SummaryDirection::Out),
+            (&operation.to_wallet, SummaryDirection::In),
+        ];
+
+        for (wallet, direction) in sides {
+            if SubscanParser::is_address_empty(wallet) {
+                continue;
+            }
+
+            let key = (
+                wallet.clone(),
+                operation.operation_type.clone(),
+                direction.clone(),
+            );
+            let summary = summaries.entry(key).or_insert(OperationSummary {
+                wallet: wallet.clone(),
+                hour,
+                operation_type: operation.operation_type.clone(),
+                direction,
+                count: 0,
+                quantity: 0.0,
+                usd: 0.0,
+            });
+            summary.count += 1;
+            summary.quantity += operation.operation_quantity;
+            summary.usd += operation.operation_usd;
+        }
+    }
+
+    summaries.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        compaction::build_summaries, subscan_parser::EMPTY_ADDRESS, OperationStatus, OperationType,
+        SubscanOperation, SummaryDirection,
+    };
+    use bson::DateTime;
+
+    fn operation(minute: i64, to_wallet: &str, quantity: f64) -> SubscanOperation {
+        SubscanOperation {
+            hash: String::new(),
+            hash_version: 0,
+            block_number: 0,
+            extrinsic_index: String::new(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(minute * 60 * 1_000),
+            operation_quantity: quantity,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: quantity * 2.0,
+            operation_type: OperationType::Transfer,
+            from_wallet: "whale".to_string(),
+            controller_wallet: EMPTY_ADDRESS.to_string(),
+            to_wallet: to_wallet.to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn summaries_are_per_wallet_and_direction() {
+        let operations = vec![
+            operation(1, "shrimp", 10.0),
+            operation(59, EMPTY_ADDRESS, 5.0),
+        ];
+
+        let summaries = build_summaries(&operations);
+        assert_eq!(summaries.len(), 2);
+
+        let whale = summaries.iter().find(|s| s.wallet == "whale").unwrap();
+        assert_eq!(whale.direction, SummaryDirection::Out);
+        assert_eq!(whale.count, 2);
+        assert_eq!(whale.quantity, 15.0);
+        assert_eq!(whale.usd, 30.0);
+        assert_eq!(whale.hour, DateTime::from_millis(0));
+
+        let shrimp = summaries.iter().find(|s| s.wallet == "shrimp").unwrap();
+        assert_eq!(shrimp.direction, SummaryDirection::In);
+        assert_eq!(shrimp.count, 1);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{
+    mongodb_client_pending_operations::MongoDbClientPendingOperations,
+    subscan_parser::{Network, SubscanParser},
+    SubscanOperation,
+};
+use itertools::Itertools;
+use std::env;
+use tracing::{error, info};
+
+static DEFAULT_MIN_CONFIRMATIONS: u64 = 0;
+
+// MIN_CONFIRMATIONS, blocks the head has to be past an operation before it reaches the sinks,
+// zero releases them right away
+pub fn get_min_confirmations() -> u64 {
+    env::var("MIN_CONFIRMATIONS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_MIN_CONFIRMATIONS)
+}
+
+// operations at least min_confirmations blocks below the head and the rest
+pub fn split_confirmed(
+    operations: Vec<SubscanOperation>,
+    head: u64,
+    min_confirmations: u64,
+) -> (Vec<SubscanOperation>, Vec<SubscanOperation>) {
+    operations
+        .into_iter()
+        .partition(|s| is_confirmed(s.block_number, head, min_confirmations))
+}
+
+fn is_confirmed(block_number: u64, head: u64, min_confirmations: u64) -> bool {
+    block_number.saturating_add(min_confirmations) <= head
+}
+
+// operations the head is not far enough past wait in the pending collection, the ones confirmed
+// by now are returned with the pending ones confirmed meanwhile, all wait while the head is unknown
+pub async fn release_confirmed_operations(
+    operations: Vec<SubscanOperation>,
+    network: &Network,
+) -> Vec<SubscanOperation> {
+    let min_confirmations = get_min_confirmations();
+    if min_confirmations == 0 {
+        return operations;
+    }
+
+    let mut mongodb_client_pending_operations = MongoDbClientPendingOperations::new().await;
+    let mut subscan_parser = SubscanParser::new(network.clone()).await;
+    let head = match subscan_parser.get_head_block_number().await {
+        Ok(head) => head,
+        Err(e) => {
+            error!(target: "confirmations", "Head block error, holding every operation: {e}.");
+            mongodb_client_pending_operations
+                .import_operations(&operations)
+                .await;
+            return Vec::new();
+        }
+    };
+
+    let (mut confirmed, pending) = split_confirmed(operations, head, min_confirmations);
+    mongodb_client_pending_operations
+        .import_operations(&pending)
+        .await;
+
+    if let Some(max_block_number) = head.checked_sub(min_confirmations) {
+        let mut pending_confirmed = mongodb_client_pending_operations
+            .get_operations_up_to(max_block_number)
+            .await;
+        if !pending_confirmed.is_empty() {
+            info!(target: "confirmations", "Released {} pending operations confirmed by {min_confirmations} blocks at head {head}.", pending_confirmed.len());
+        }
+        confirmed.append(&mut pending_confirmed);
+    }
+
+    // operations parsed again while pending are released once
+    confirmed
+        .into_iter()
+        .unique_by(|s| s.hash.clone())
+        .collect()
+}
+
+// called once the sinks took the released operations, so a crash in between delivers them again,
+// operations which never were pending are no-ops
+pub async fn remove_released_operations(operations: &[SubscanOperation]) {
+    if get_min_confirmations() == 0 || operations.is_empty() {
+        return;
+    }
+
+    let mut mongodb_client_pending_operations = MongoDbClientPendingOperations::new().await;
+    mongodb_client_pending_operations
+        .delete_operations(operations.iter().map(|s| s.hash.clone()).collect())
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{confirmations::split_confirmed, OperationStatus, OperationType, SubscanOperation};
+    use bson::DateTime;
+
+    fn operation(block_number: u64) -> SubscanOperation {
+        SubscanOperation {
+            hash: block_number.to_string(),
+            hash_version: 0,
+            block_number,
+            extrinsic_index: format!("{block_number}-1"),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+            operation_quantity: 1.0,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 1.0,
+            operation_type: OperationType::Transfer,
+            from_wallet: "from".to_string(),
+            controller_wallet: "from".to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn operations_wait_for_enough_blocks_past_them() {
+        let operations = vec![operation(80), operation(90), operation(91), operation(100)];
+        let (confirmed, pending) = split_confirmed(operations.clone(), 100, 10);
+        assert_eq!(
+            confirmed.iter().map(|s| s.block_number).collect::<Vec<_>>(),
+            vec![80, 90]
+        );
+        assert_eq!(
+            pending.iter().map(|s| s.block_number).collect::<Vec<_>>(),
+            vec![91, 100]
+        );
+
+        // the head block itself counts with no confirmations
+        let (confirmed, pending) = split_confirmed(operations, 100, 0);
+        assert_eq!((confirmed.len(), pending.len()), (4, 0));
+
+        let (confirmed, _) = split_confirmed(vec![operation(u64::MAX)], 100, 10);
+        assert!(confirmed.is_empty());
+    }
+}
@@ -0,0 +1,246 @@
+use crate::{
+    mongodb_client_quarantine::MongoDbClientQuarantine,
+    pipeline_error::{ErrorCode, PipelineError},
+    subscan_parser::{Network, EMPTY_ADDRESS},
+    timestamp_validation::{TimestampSkewAction, TimestampValidation},
+    wallet_formats::get_canonical_address,
+    OperationType, SubscanOperation,
+};
+use bson::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+use tracing::{error, info};
+
+#[derive(
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum QuarantineSource {
+    // raw subscan records the parsers could not read
+    Extrinsics,
+    BatchAll,
+    Transfers,
+    StakingEvents,
+    RewardSlash,
+
+    // parsed operations failing validation
+    Operations,
+}
+
+// a record kept with the reasons it failed instead of being dropped
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct QuarantinedRecord {
+    pub network: Network,
+    pub source: QuarantineSource,
+    pub record: Value,
+    pub reasons: Vec<String>,
+    pub quarantined_at: DateTime,
+}
+
+impl QuarantinedRecord {
+    pub fn new(source: QuarantineSource, record: Value, reasons: Vec<String>) -> Self {
+        Self {
+            network: Network::from_env(),
+            source,
+            record,
+            reasons,
+            quarantined_at: DateTime::now(),
+        }
+    }
+
+    pub fn malformed(source: QuarantineSource, record: &Value) -> Self {
+        let reason = format!("malformed {source} record");
+        Self::new(source, record.clone(), vec![reason])
+    }
+}
+
+pub async fn quarantine_records(records: Vec<QuarantinedRecord>) {
+    if records.is_empty() {
+        return;
+    }
+
+    for record in records.iter() {
+        error!(target: "data_quality", "Quarantined {} record: {}.", record.source, record.reasons.join(", "));
+    }
+
+    let mut mongodb_client_quarantine = MongoDbClientQuarantine::new().await;
+    mongodb_client_quarantine.import_records(records).await;
+}
+
+// exchange types are assigned by the feed bot on read, the parsers never produce them
+fn is_parsed_operation_type(operation_type: &OperationType) -> bool {
+    !matches!(
+        operation_type,
+        OperationType::DepositToExchange | OperationType::WithdrawFromExchange
+    )
+}
+
+fn is_valid_address(address: &str) -> bool {
+    get_canonical_address(address).is_some()
+}
+
+// empty if the operation can be stored
+pub fn get_operation_reasons(operation: &SubscanOperation) -> Vec<String> {
+    let mut reasons = Vec::new();
+
+    for (name, amount) in [
+        ("operation_quantity", operation.operation_quantity),
+        ("operation_usd", operation.operation_usd),
+    ] {
+        if !amount.is_finite() || amount < 0.0 {
+            reasons.push(format!("{name} {amount} is negative or not a number"));
+        }
+    }
+
+    // controller and validator are only known for some operation types
+    for (name, address, may_be_empty) in [
+        ("from_wallet", &operation.from_wallet, false),
+        ("controller_wallet", &operation.controller_wallet, true),
+        ("to_wallet", &operation.to_wallet, true),
+    ] {
+        if may_be_empty && address == EMPTY_ADDRESS {
+            continue;
+        }
+        if !is_valid_address(address) {
+            reasons.push(format!("{name} {address} is not a valid address"));
+        }
+    }
+
+    if !is_parsed_operation_type(&operation.operation_type) {
+        reasons.push(format!(
+            "operation_type {} is not a parsed operation type",
+            operation.operation_type
+        ));
+    }
+
+    reasons
+}
+
+// failing operations go to the quarantine collection with their reasons,
+// skewed timestamps only if TIMESTAMP_SKEW_ACTION=reject
+pub async fn validate_operations(operations: Vec<SubscanOperation>) -> Vec<SubscanOperation> {
+    let timestamp_validation = TimestampValidation::from_env();
+    let skew_reasons = timestamp_validation.get_skew_reasons(&operations, DateTime::now());
+
+    let mut valid = Vec::new();
+    let mut quarantined = Vec::new();
+    for (operation, skew_reason) in operations.into_iter().zip(skew_reasons) {
+        let mut reasons = get_operation_reasons(&operation);
+        if let Some(skew_reason) = skew_reason {
+            match timestamp_validation.action {
+                TimestampSkewAction::Flag => {
+                    let pipeline_error = PipelineError::new(
+                        ErrorCode::TimestampSkew,
+                        &skew_reason,
+                        &Network::from_env(),
+                    )
+                    .with_extrinsic_index(&operation.extrinsic_index);
+                    error!(target: "data_quality", "Timestamp flagged: {}.", pipeline_error.to_json());
+                }
+                TimestampSkewAction::Reject => reasons.push(skew_reason),
+            }
+        }
+
+        if reasons.is_empty() {
+            valid.push(operation);
+        } else {
+            let record = serde_json::to_value(&operation).unwrap_or_default();
+            quarantined.push(QuarantinedRecord::new(
+                QuarantineSource::Operations,
+                record,
+                reasons,
+            ));
+        }
+    }
+
+    if !quarantined.is_empty() {
+        info!(target: "data_quality", "{} valid and {} quarantined operations.", valid.len(), quarantined.len());
+    }
+    quarantine_records(quarantined).await;
+
+    valid
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        amount::Balance,
+        data_quality::{get_operation_reasons, QuarantineSource, QuarantinedRecord},
+        subscan_parser::EMPTY_ADDRESS,
+        OperationStatus, OperationType, SubscanOperation,
+    };
+    use bson::DateTime;
+    use serde_json::json;
+
+    static ADDRESS: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+    fn get_operation() -> SubscanOperation {
+        SubscanOperation {
+            hash: "hash".to_string(),
+            hash_version: 0,
+            block_number: 1,
+            extrinsic_index: "1-1".to_string(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+            operation_quantity: 1.0,
+            operation_planck: Some(Balance(1_000_000_000_000)),
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 1.0,
+            operation_type: OperationType::Stake,
+            from_wallet: ADDRESS.to_string(),
+            controller_wallet: EMPTY_ADDRESS.to_string(),
+            to_wallet: EMPTY_ADDRESS.to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn valid_operations_have_no_reasons() {
+        assert!(get_operation_reasons(&get_operation()).is_empty());
+    }
+
+    #[test]
+    fn every_failed_check_is_a_reason() {
+        let mut operation = get_operation();
+        operation.operation_quantity = -1.0;
+        operation.operation_usd = f64::NAN;
+        operation.to_wallet = "5Grwva".to_string();
+        operation.operation_type = OperationType::DepositToExchange;
+
+        assert_eq!(
+            get_operation_reasons(&operation),
+            vec![
+                "operation_quantity -1 is negative or not a number",
+                "operation_usd NaN is negative or not a number",
+                "to_wallet 5Grwva is not a valid address",
+                "operation_type DepositToExchange is not a parsed operation type",
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_records_keep_the_raw_record() {
+        let record = json!({"block_num": "x"});
+        let quarantined = QuarantinedRecord::malformed(QuarantineSource::BatchAll, &record);
+
+        assert_eq!(quarantined.record, record);
+        assert_eq!(quarantined.reasons, vec!["malformed batch_all record"]);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{subscan_parser::Network, SubscanEvent, SubscanEventParam};
+use std::collections::HashMap;
+
+// which events to pick from an extrinsic and which of their params to read, by name
+// instead of position, e.g. EventMatcher::new("staking", "Bonded").field("stash").field("amount")
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventMatcher {
+    module_id: String,
+    event_ids: Vec<String>,
+
+    // names of every field, the first one is the name it is read by
+    fields: Vec<Vec<String>>,
+}
+
+impl EventMatcher {
+    pub fn new(module_id: &str, event_id: &str) -> EventMatcher {
+        Self {
+            module_id: module_id.to_string(),
+            event_ids: vec![event_id.to_string()],
+            fields: Vec::new(),
+        }
+    }
+
+    // another event of the module with the same fields
+    pub fn event(mut self, event_id: &str) -> EventMatcher {
+        self.event_ids.push(event_id.to_string());
+        self
+    }
+
+    pub fn field(mut self, name: &str) -> EventMatcher {
+        self.fields.push(vec![name.to_string()]);
+        self
+    }
+
+    // other name of the last field, for runtimes which renamed it
+    pub fn alias(mut self, name: &str) -> EventMatcher {
+        if let Some(names) = self.fields.last_mut() {
+            names.push(name.to_string());
+        }
+        self
+    }
+
+    // none unless the event is one of the matched and has every field
+    pub fn get_fields<'a>(&'a self, event: &'a SubscanEvent) -> Option<EventFields<'a>> {
+        if event.module_id != self.module_id || !self.event_ids.contains(&event.event_id) {
+            return None;
+        }
+
+        let params = self
+            .fields
+            .iter()
+            .map(|names| {
+                let param = event
+                    .event_params
+                    .iter()
+                    .find(|p| names.contains(&p.name))?;
+                Some((names.first()?.as_str(), param))
+            })
+            .collect::<Option<HashMap<_, _>>>()?;
+
+        Some(EventFields { params })
+    }
+
+    // fields of the first matching event
+    pub fn find<'a>(&'a self, events: &'a [SubscanEvent]) -> Option<EventFields<'a>> {
+        events.iter().find_map(|e| self.get_fields(e))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventFields<'a> {
+    params: HashMap<&'a str, &'a SubscanEventParam>,
+}
+
+impl<'a> EventFields<'a> {
+    pub fn get(&self, name: &str) -> Option<&'a SubscanEventParam> {
+        self.params.get(name).copied()
+    }
+}
+
+// structs read from the events of an extrinsic
+pub trait FromEvents: Sized {
+    fn get_matcher() -> EventMatcher;
+
+    fn from_fields(fields: &EventFields, network: &Network) -> Option<Self>;
+
+    fn from_events(events: &[SubscanEvent], network: &Network) -> Option<Self> {
+        let matcher = Self::get_matcher();
+        Self::from_fields(&matcher.find(events)?, network)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{event_matcher::EventMatcher, SubscanEvent, SubscanEventParam};
+    use serde_json::Value;
+
+    fn event(module_id: &str, event_id: &str, names: &[&str]) -> SubscanEvent {
+        SubscanEvent {
+            module_id: module_id.to_string(),
+            event_id: event_id.to_string(),
+            event_index: "1-1".to_string(),
+            event_params: names
+                .iter()
+                .map(|name| SubscanEventParam {
+                    type_name: "u32".to_string(),
+                    value: Value::from(name.len()),
+                    name: name.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn fields_are_read_by_name() {
+        let matcher = EventMatcher::new("staking", "Bonded")
+            .field("stash")
+            .alias("who")
+            .field("amount");
+
+        // params in any order, under any alias
+        let events = [
+            event("balances", "Bonded", &["stash", "amount"]),
+            event("staking", "Chilled", &["stash"]),
+            event("staking", "Bonded", &["amount", "who"]),
+        ];
+        let fields = matcher.find(&events).unwrap();
+        assert_eq!(fields.get("stash").unwrap().name, "who");
+        assert_eq!(fields.get("amount").unwrap().name, "amount");
+        assert_eq!(fields.get("who"), None);
+    }
+
+    #[test]
+    fn events_missing_a_field_do_not_match() {
+        let matcher = EventMatcher::new("staking", "Bonded")
+            .event("Unbonded")
+            .field("stash")
+            .field("amount");
+
+        assert!(matcher
+            .get_fields(&event("staking", "Unbonded", &["stash", "amount"]))
+            .is_some());
+        assert!(matcher
+            .get_fields(&event("staking", "Bonded", &["stash"]))
+            .is_none());
+        assert!(matcher
+            .get_fields(&event("staking", "Withdrawn", &["stash", "amount"]))
+            .is_none());
+    }
+}
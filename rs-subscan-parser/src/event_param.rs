@@ -0,0 +1,229 @@
+use crate::{
+    address::to_hex, amount::Balance, subscan_parser::Network, wallet_formats::get_network_address,
+};
+use serde_json::Value;
+
+static BALANCE_TYPES: [&str; 3] = ["Balance", "BalanceOf", "u128"];
+static U32_TYPES: [&str; 7] = [
+    "u8",
+    "u16",
+    "u32",
+    "BlockNumber",
+    "BlockNumberFor",
+    "EraIndex",
+    "SessionIndex",
+];
+static BYTES_TYPES: [&str; 5] = ["Bytes", "Vec", "H160", "H256", "Hash"];
+
+// value of an event param decoded by its type, params subscan returns in another shape
+// than their type calls for are kept raw
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParamValue {
+    Balance(Balance),
+
+    // lowercase hex of the public key, 20 bytes for ethereum style accounts
+    AccountId(String),
+    U32(u32),
+    U64(u64),
+    Bool(bool),
+    Bytes(Vec<u8>),
+    Raw(Value),
+}
+
+// type without its path and generics, e.g. T::AccountId is AccountId and BalanceOf<T> is BalanceOf
+pub fn get_base_type(type_name: &str) -> &str {
+    let type_name = type_name.rsplit("::").next().unwrap_or_default();
+    type_name.split('<').next().unwrap_or_default().trim()
+}
+
+impl ParamValue {
+    // subscan writes numbers as strings or numbers, accounts as hex or ss58, sometimes
+    // wrapped in a multi address, and bytes as hex
+    pub fn decode(type_name: &str, value: &Value) -> ParamValue {
+        let base_type = get_base_type(type_name);
+        let decoded = if BALANCE_TYPES.contains(&base_type) {
+            decode_integer(value)
+                .and_then(|v| Balance::from_planck_str(&v).ok())
+                .map(ParamValue::Balance)
+        } else if U32_TYPES.contains(&base_type) {
+            decode_integer(value)
+                .and_then(|v| v.parse::<u32>().ok())
+                .map(ParamValue::U32)
+        } else if base_type == "u64" {
+            decode_integer(value)
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(ParamValue::U64)
+        } else if base_type == "bool" {
+            decode_bool(value).map(ParamValue::Bool)
+        } else if base_type.starts_with("AccountId") || base_type.ends_with("Address") {
+            decode_account(value).map(ParamValue::AccountId)
+        } else if BYTES_TYPES.contains(&base_type) || base_type.starts_with("[u8") {
+            decode_bytes(value).map(ParamValue::Bytes)
+        } else {
+            None
+        };
+
+        decoded.unwrap_or_else(|| ParamValue::Raw(value.clone()))
+    }
+
+    pub fn as_planck(&self) -> Option<Balance> {
+        match self {
+            ParamValue::Balance(planck) => Some(*planck),
+            _ => None,
+        }
+    }
+
+    // account in the encoding of the network, see get_network_address
+    pub fn as_account(&self, network: &Network) -> Option<String> {
+        match self {
+            ParamValue::AccountId(account) => get_network_address(network, account),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            ParamValue::U32(value) => Some(u64::from(*value)),
+            ParamValue::U64(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            ParamValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            ParamValue::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+}
+
+// digits of an unsigned integer written as a string or a number
+fn decode_integer(value: &Value) -> Option<String> {
+    match value {
+        Value::String(v) => Some(v.clone()),
+        Value::Number(v) => v.as_u64().map(|v| v.to_string()),
+        _ => None,
+    }
+}
+
+fn decode_bool(value: &Value) -> Option<bool> {
+    match value {
+        Value::Bool(v) => Some(*v),
+        Value::String(v) => v.parse::<bool>().ok(),
+        _ => None,
+    }
+}
+
+// multi addresses are objects like {"Id": "0x..."}
+fn decode_account(value: &Value) -> Option<String> {
+    match value {
+        Value::String(v) => to_hex(v),
+        Value::Object(v) => decode_account(v.get("Id")?),
+        _ => None,
+    }
+}
+
+fn decode_bytes(value: &Value) -> Option<Vec<u8>> {
+    hex::decode(value.as_str()?.strip_prefix("0x")?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        amount::Balance,
+        event_param::{get_base_type, ParamValue},
+        subscan_parser::Network,
+    };
+    use serde_json::json;
+
+    static ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+    static ALICE_HEX: &str = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+
+    #[test]
+    fn base_types_drop_paths_and_generics() {
+        assert_eq!(get_base_type("T::AccountId"), "AccountId");
+        assert_eq!(get_base_type("BalanceOf<T>"), "BalanceOf");
+        assert_eq!(get_base_type("Vec<u8>"), "Vec");
+        assert_eq!(get_base_type("EraIndex"), "EraIndex");
+    }
+
+    #[test]
+    fn params_decode_strings_and_numbers() {
+        let planck = ParamValue::Balance(Balance(1_500_000_000_000));
+        assert_eq!(
+            ParamValue::decode("BalanceOf<T>", &json!("1500000000000")),
+            planck
+        );
+        assert_eq!(
+            ParamValue::decode("Balance", &json!(1_500_000_000_000u64)),
+            planck
+        );
+        assert_eq!(
+            ParamValue::decode("EraIndex", &json!(943)),
+            ParamValue::U32(943)
+        );
+        assert_eq!(
+            ParamValue::decode("u32", &json!("943")),
+            ParamValue::U32(943)
+        );
+        assert_eq!(
+            ParamValue::decode("u64", &json!(1u64 << 40)),
+            ParamValue::U64(1 << 40)
+        );
+        assert_eq!(
+            ParamValue::decode("bool", &json!(true)),
+            ParamValue::Bool(true)
+        );
+        assert_eq!(
+            ParamValue::decode("bool", &json!("false")),
+            ParamValue::Bool(false)
+        );
+        assert_eq!(
+            ParamValue::decode("Bytes", &json!("0x0102ff")),
+            ParamValue::Bytes(vec![1, 2, 255])
+        );
+    }
+
+    #[test]
+    fn accounts_decode_from_any_encoding() {
+        let account = ParamValue::AccountId(ALICE_HEX.to_string());
+        assert_eq!(
+            ParamValue::decode("T::AccountId", &json!(ALICE_HEX)),
+            account
+        );
+        assert_eq!(ParamValue::decode("AccountId32", &json!(ALICE)), account);
+        assert_eq!(
+            ParamValue::decode("MultiAddress", &json!({"Id": ALICE_HEX})),
+            account
+        );
+        assert_eq!(
+            account.as_account(&Network::Alephzero).as_deref(),
+            Some(ALICE)
+        );
+    }
+
+    #[test]
+    fn mismatched_values_are_kept_raw() {
+        let values = [
+            ("u32", json!(-1)),
+            ("u32", json!(u64::MAX)),
+            ("Balance", json!("1.5")),
+            ("AccountId", json!("not an account")),
+            ("Bytes", json!("0102")),
+            ("RewardDestination", json!({"Staked": null})),
+        ];
+        for (type_name, value) in values {
+            assert_eq!(
+                ParamValue::decode(type_name, &value),
+                ParamValue::Raw(value)
+            );
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{subscan_parser::Network, SubscanOperation};
+
+// subscan web pages to jump to from api responses and notifications,
+// none on networks without a web explorer
+pub fn get_extrinsic_url(network: &Network, extrinsic_index: &str) -> Option<String> {
+    Some(format!(
+        "{}/extrinsic/{extrinsic_index}",
+        network.get_explorer_url()?
+    ))
+}
+
+pub fn get_account_url(network: &Network, address: &str) -> Option<String> {
+    Some(format!("{}/account/{address}", network.get_explorer_url()?))
+}
+
+pub fn get_block_url(network: &Network, block_number: u64) -> Option<String> {
+    Some(format!(
+        "{}/block/{block_number}",
+        network.get_explorer_url()?
+    ))
+}
+
+// operations are single extrinsics or calls of one
+pub fn get_operation_url(network: &Network, operation: &SubscanOperation) -> Option<String> {
+    get_extrinsic_url(network, &operation.extrinsic_index)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        explorer::{get_account_url, get_block_url, get_extrinsic_url},
+        subscan_parser::Network,
+    };
+
+    #[test]
+    fn links_point_to_the_explorer_of_the_network() {
+        assert_eq!(
+            get_extrinsic_url(&Network::Alephzero, "42-3").as_deref(),
+            Some("https://alephzero.subscan.io/extrinsic/42-3")
+        );
+        assert_eq!(
+            get_account_url(&Network::Polkadot, "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5")
+                .as_deref(),
+            Some("https://polkadot.subscan.io/account/15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5")
+        );
+
+        let network = Network::Custom {
+            slug: "devnet".to_string(),
+            base_url: "http://indexer.local".to_string(),
+            decimals: 12,
+            ss58_prefix: 42,
+            token_symbol: "DEV".to_string(),
+            explorer_url: Some("http://explorer.local".to_string()),
+        };
+        assert_eq!(
+            get_block_url(&network, 7).as_deref(),
+            Some("http://explorer.local/block/7")
+        );
+    }
+
+    #[test]
+    fn networks_without_explorer_have_no_links() {
+        assert_eq!(get_extrinsic_url(&Network::Mock, "42-3"), None);
+        assert_eq!(get_block_url(&Network::Mock, 7), None);
+    }
+}
@@ -0,0 +1,144 @@
+use flate2::{write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+
+// zstd's own default, higher levels barely shrink exports further but take much longer
+static ZSTD_LEVEL: i32 = 3;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ExportCompression {
+    #[default]
+    None,
+    Gzip,
+
+    // smaller and faster than gzip, needs zstd on the receiving side
+    Zstd,
+}
+
+impl ExportCompression {
+    // appended to the extension of the format, e.g. operations.csv.zst
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            ExportCompression::None => "",
+            ExportCompression::Gzip => ".gz",
+            ExportCompression::Zstd => ".zst",
+        }
+    }
+
+    // none when the content type of the format applies
+    pub fn get_content_type(&self) -> Option<&'static str> {
+        match self {
+            ExportCompression::None => None,
+            ExportCompression::Gzip => Some("application/gzip"),
+            ExportCompression::Zstd => Some("application/zstd"),
+        }
+    }
+
+    pub fn get_writer<W: Write>(&self, writer: W) -> io::Result<CompressedWriter<W>> {
+        Ok(match self {
+            ExportCompression::None => CompressedWriter::Plain(writer),
+            ExportCompression::Gzip => {
+                CompressedWriter::Gzip(GzEncoder::new(writer, Compression::default()))
+            }
+            ExportCompression::Zstd => {
+                CompressedWriter::Zstd(zstd::Encoder::new(writer, ZSTD_LEVEL)?)
+            }
+        })
+    }
+
+    pub fn compress(&self, bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+        if *self == ExportCompression::None {
+            return Ok(bytes);
+        }
+
+        let mut writer = self.get_writer(Vec::new())?;
+        writer.write_all(&bytes)?;
+        writer.finish()
+    }
+}
+
+// compressed streams are only complete once finished, dropping one loses its trailer
+pub enum CompressedWriter<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            CompressedWriter::Plain(mut writer) => {
+                writer.flush()?;
+                Ok(writer)
+            }
+            CompressedWriter::Gzip(writer) => writer.finish(),
+            CompressedWriter::Zstd(writer) => writer.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            CompressedWriter::Plain(writer) => writer.write(buf),
+            CompressedWriter::Gzip(writer) => writer.write(buf),
+            CompressedWriter::Zstd(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            CompressedWriter::Plain(writer) => writer.flush(),
+            CompressedWriter::Gzip(writer) => writer.flush(),
+            CompressedWriter::Zstd(writer) => writer.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::exports::compression::ExportCompression;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn compressed_exports_decompress_to_the_original() {
+        let bytes = "hash,block_number\n".repeat(1_000).into_bytes();
+
+        assert_eq!(
+            ExportCompression::None.compress(bytes.clone()).unwrap(),
+            bytes
+        );
+
+        let gzip = ExportCompression::Gzip.compress(bytes.clone()).unwrap();
+        assert!(gzip.len() < bytes.len());
+        let mut decompressed = Vec::new();
+        GzDecoder::new(gzip.as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, bytes);
+
+        let zstd = ExportCompression::Zstd.compress(bytes.clone()).unwrap();
+        assert!(zstd.len() < bytes.len());
+        assert_eq!(zstd::decode_all(zstd.as_slice()).unwrap(), bytes);
+    }
+}
@@ -0,0 +1,443 @@
+use crate::{
+    exports::{
+        get_export_annotations, precision::ExportPrecision, ExportAnnotations, ExportOperation,
+    },
+    mongodb_client_subscan::MongoDbClientSubscan,
+    SubscanOperation,
+};
+use bson::Document;
+use chrono::{
+    format::{Item, StrftimeItems},
+    TimeZone, Utc,
+};
+use csv::{Writer, WriterBuilder};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, Write},
+    str::FromStr,
+};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+
+static ANNOTATIONS_SEPARATOR: &str = " | ";
+static CSV_PAGE_SIZE: i64 = 10_000;
+
+// in the order of the default columns
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum CsvColumn {
+    Hash,
+    BlockNumber,
+    ExtrinsicIndex,
+    OperationTimestamp,
+    OperationType,
+    FromWallet,
+    ControllerWallet,
+    ToWallet,
+    Amount,
+    AmountPlanck,
+    AmountUsd,
+    Annotations,
+    #[serde(rename = "price_before_1h")]
+    #[strum(serialize = "price_before_1h")]
+    PriceBefore1h,
+    PriceAt,
+    #[serde(rename = "price_after_1h")]
+    #[strum(serialize = "price_after_1h")]
+    PriceAfter1h,
+    #[serde(rename = "price_after_24h")]
+    #[strum(serialize = "price_after_24h")]
+    PriceAfter24h,
+}
+
+impl CsvColumn {
+    pub fn get_default_columns() -> Vec<CsvColumn> {
+        CsvColumn::iter().collect()
+    }
+
+    // comma separated column names, e.g. hash,operation_timestamp,amount
+    pub fn parse_columns(value: &str) -> Result<Vec<CsvColumn>, String> {
+        let columns = value
+            .split(',')
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(|c| CsvColumn::from_str(c).map_err(|_| format!("unknown column {c}")))
+            .collect::<Result<Vec<_>, _>>()?;
+        if columns.is_empty() {
+            return Err("no columns".to_string());
+        }
+
+        Ok(columns)
+    }
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum CsvPreset {
+    #[default]
+    Default,
+
+    // spreadsheet tooling with comma decimals, as used by our accountants
+    Accounting,
+}
+
+impl CsvPreset {
+    pub fn options(&self) -> CsvOptions {
+        match self {
+            CsvPreset::Default => CsvOptions {
+                delimiter: ',',
+                decimal_separator: '.',
+                timestamp_format: "%Y-%m-%dT%H:%M:%S%.3fZ".to_string(),
+                precision: ExportPrecision::full(),
+                columns: CsvColumn::get_default_columns(),
+            },
+            CsvPreset::Accounting => CsvOptions {
+                delimiter: ';',
+                decimal_separator: ',',
+                timestamp_format: "%d.%m.%Y %H:%M:%S".to_string(),
+                precision: ExportPrecision::display(),
+                columns: CsvColumn::get_default_columns(),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    pub decimal_separator: char,
+
+    // chrono strftime format, always rendered in utc
+    pub timestamp_format: String,
+    pub precision: ExportPrecision,
+
+    // in the order they are written
+    #[serde(default = "CsvColumn::get_default_columns")]
+    pub columns: Vec<CsvColumn>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvPreset::default().options()
+    }
+}
+
+impl CsvOptions {
+    pub fn with_columns(mut self, columns: Vec<CsvColumn>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    // chrono panics while rendering an unknown specifier such as %Q
+    pub fn check_timestamp_format(&self) -> Result<(), String> {
+        if StrftimeItems::new(&self.timestamp_format).any(|i| matches!(i, Item::Error)) {
+            return Err(format!(
+                "invalid timestamp format {}",
+                self.timestamp_format
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn format_decimal(&self, value: String) -> String {
+        value.replace('.', &self.decimal_separator.to_string())
+    }
+
+    fn format_price(&self, price: Option<f64>) -> String {
+        price
+            .map(|p| self.format_decimal(p.to_string()))
+            .unwrap_or_default()
+    }
+
+    fn format_column(&self, column: CsvColumn, e: &ExportOperation, timestamp: &str) -> String {
+        let prices = e.price_annotation.as_ref();
+        match column {
+            CsvColumn::Hash => e.hash.clone(),
+            CsvColumn::BlockNumber => e.block_number.to_string(),
+            CsvColumn::ExtrinsicIndex => e.extrinsic_index.clone(),
+            CsvColumn::OperationTimestamp => timestamp.to_string(),
+            CsvColumn::OperationType => e.operation_type.to_string(),
+            CsvColumn::FromWallet => e.from_wallet.clone(),
+            CsvColumn::ControllerWallet => e.controller_wallet.clone(),
+            CsvColumn::ToWallet => e.to_wallet.clone(),
+            CsvColumn::Amount => self.format_decimal(e.amount.clone()),
+            CsvColumn::AmountPlanck => e.amount_planck.clone(),
+            CsvColumn::AmountUsd => self.format_decimal(e.amount_usd.to_string()),
+            CsvColumn::Annotations => e.annotations.join(ANNOTATIONS_SEPARATOR),
+            CsvColumn::PriceBefore1h => self.format_price(prices.and_then(|p| p.price_before_1h)),
+            CsvColumn::PriceAt => self.format_price(prices.and_then(|p| p.price_at)),
+            CsvColumn::PriceAfter1h => self.format_price(prices.and_then(|p| p.price_after_1h)),
+            CsvColumn::PriceAfter24h => self.format_price(prices.and_then(|p| p.price_after_24h)),
+        }
+    }
+
+    fn format_timestamp(&self, operation: &SubscanOperation) -> String {
+        Utc.timestamp_millis_opt(operation.operation_timestamp.timestamp_millis())
+            .single()
+            .map(|t| t.format(&self.timestamp_format).to_string())
+            .unwrap_or_default()
+    }
+}
+
+// writes the header once and the operations of any number of pages after it
+pub struct CsvExportWriter<W: Write> {
+    writer: Writer<W>,
+    options: CsvOptions,
+}
+
+impl<W: Write> CsvExportWriter<W> {
+    pub fn new(writer: W, options: &CsvOptions) -> io::Result<Self> {
+        let delimiter = u8::try_from(options.delimiter)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "delimiter must be ascii"))?;
+        options
+            .check_timestamp_format()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut writer = WriterBuilder::new()
+            .delimiter(delimiter)
+            .from_writer(writer);
+        writer.write_record(options.columns.iter().map(|c| c.to_string()))?;
+
+        Ok(Self {
+            writer,
+            options: options.clone(),
+        })
+    }
+
+    pub fn write_operations(
+        &mut self,
+        operations: Vec<SubscanOperation>,
+        annotations: &ExportAnnotations,
+    ) -> io::Result<()> {
+        let options = &self.options;
+        for operation in operations {
+            let operation_timestamp = options.format_timestamp(&operation);
+            let e =
+                ExportOperation::new(operation, &options.precision).with_annotations(annotations);
+            self.writer.write_record(
+                options
+                    .columns
+                    .iter()
+                    .map(|c| options.format_column(*c, &e, &operation_timestamp)),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> io::Result<W> {
+        self.writer.into_inner().map_err(|e| e.into_error())
+    }
+}
+
+pub fn write_csv<W: Write>(
+    writer: W,
+    operations: Vec<SubscanOperation>,
+    annotations: &ExportAnnotations,
+    options: &CsvOptions,
+) -> io::Result<()> {
+    let mut writer = CsvExportWriter::new(writer, options)?;
+    writer.write_operations(operations, annotations)?;
+    writer.into_inner()?.flush()
+}
+
+// operations matching a MongoDbClientSubscan query in block order, read page by page
+pub async fn write_query_csv<W: Write>(
+    writer: W,
+    query: Document,
+    options: &CsvOptions,
+) -> io::Result<()> {
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let mut writer = CsvExportWriter::new(writer, options)?;
+    let mut after = None;
+    loop {
+        let operations = mongodb_client_subscan
+            .get_operations_page(query.clone(), after, CSV_PAGE_SIZE)
+            .await;
+        let Some(last) = operations.last() else {
+            break;
+        };
+        after = Some(MongoDbClientSubscan::get_page_key(last));
+        let is_last_page = (operations.len() as i64) < CSV_PAGE_SIZE;
+
+        let annotations = get_export_annotations(&operations).await;
+        writer.write_operations(operations, &annotations)?;
+        if is_last_page {
+            break;
+        }
+    }
+
+    writer.into_inner()?.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        amount::Balance,
+        exports::{
+            csv::{write_csv, CsvColumn, CsvPreset},
+            ExportAnnotations, ExportPriceAnnotation,
+        },
+        OperationStatus, OperationType, SubscanOperation,
+    };
+    use bson::DateTime;
+    use std::{collections::HashMap, io};
+
+    #[test]
+    fn accounting_preset_uses_semicolons_and_comma_decimals() {
+        let operation = SubscanOperation {
+            hash: "hash".to_string(),
+            hash_version: 0,
+            block_number: 1,
+            extrinsic_index: "1-1".to_string(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+            operation_quantity: 1_234.5,
+            operation_planck: Some(Balance(1_234_500_000_000_001)),
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 2_469.25,
+            operation_type: OperationType::Stake,
+            from_wallet: "from".to_string(),
+            controller_wallet: "controller".to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        };
+
+        let annotations = ExportAnnotations {
+            texts: HashMap::from([(
+                "hash".to_string(),
+                vec!["treasury".to_string(), "rebalance".to_string()],
+            )]),
+            prices: HashMap::from([(
+                "hash".to_string(),
+                ExportPriceAnnotation {
+                    price_before_1h: Some(2.5),
+                    price_at: Some(2.0),
+                    price_after_1h: None,
+                    price_after_24h: None,
+                },
+            )]),
+        };
+
+        let mut buffer = Vec::new();
+        write_csv(
+            &mut buffer,
+            vec![operation],
+            &annotations,
+            &CsvPreset::Accounting.options(),
+        )
+        .unwrap();
+        let csv = String::from_utf8(buffer).unwrap();
+
+        let row = csv.lines().nth(1).unwrap();
+        assert_eq!(
+            row,
+            "hash;1;1-1;14.11.2023 22:13:20;Stake;from;controller;to;1234,500000;1234500000000001;2469,25;treasury | rebalance;2,5;2;;"
+        );
+    }
+
+    #[test]
+    fn columns_are_written_in_the_chosen_order() {
+        let columns =
+            CsvColumn::parse_columns("operation_timestamp, hash,price_after_24h").unwrap();
+        assert_eq!(
+            columns,
+            vec![
+                CsvColumn::OperationTimestamp,
+                CsvColumn::Hash,
+                CsvColumn::PriceAfter24h
+            ]
+        );
+        assert!(CsvColumn::parse_columns("hash,fee").is_err());
+        assert!(CsvColumn::parse_columns(" , ").is_err());
+        assert_eq!(CsvColumn::get_default_columns().len(), 16);
+
+        let operation = SubscanOperation {
+            hash: "hash".to_string(),
+            hash_version: 0,
+            block_number: 1,
+            extrinsic_index: "1-1".to_string(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+            operation_quantity: 1.0,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 2.0,
+            operation_type: OperationType::Stake,
+            from_wallet: "from".to_string(),
+            controller_wallet: "controller".to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        };
+
+        let mut buffer = Vec::new();
+        let mut options = CsvPreset::Default.options().with_columns(columns);
+        options.timestamp_format = "%Y-%m-%d".to_string();
+        write_csv(
+            &mut buffer,
+            vec![operation],
+            &ExportAnnotations::default(),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "operation_timestamp,hash,price_after_24h\n2023-11-14,hash,\n"
+        );
+    }
+
+    #[test]
+    fn invalid_timestamp_formats_are_rejected() {
+        let mut options = CsvPreset::Default.options();
+        options.timestamp_format = "%Y-%Q".to_string();
+
+        let e = write_csv(
+            Vec::new(),
+            Vec::new(),
+            &ExportAnnotations::default(),
+            &options,
+        )
+        .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(e.to_string(), "invalid timestamp format %Y-%Q");
+    }
+}
@@ -0,0 +1,21 @@
+use crate::{
+    exports::{precision::ExportPrecision, ExportAnnotations, ExportOperation},
+    SubscanOperation,
+};
+use std::io::{self, Write};
+
+// one ExportOperation per line, parts of an export can be concatenated as they are
+pub fn write_jsonl<W: Write>(
+    mut writer: W,
+    operations: Vec<SubscanOperation>,
+    annotations: &ExportAnnotations,
+    precision: &ExportPrecision,
+) -> io::Result<()> {
+    for operation in operations {
+        let e = ExportOperation::new(operation, precision).with_annotations(annotations);
+        serde_json::to_writer(&mut writer, &e)?;
+        writer.write_all(b"\n")?;
+    }
+
+    writer.flush()
+}
@@ -0,0 +1,139 @@
+use crate::{
+    mongodb_client_operation_annotations::MongoDbClientOperationAnnotations,
+    mongodb_client_price_annotations::MongoDbClientPriceAnnotations, subscan_parser::Network,
+    OperationType, PriceAnnotation, SubscanOperation,
+};
+use precision::{to_planck, ExportPrecision};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub mod compression;
+pub mod csv;
+pub mod jsonl;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod parts;
+pub mod precision;
+
+// flat operation as handed out by exports, amount_planck is never rounded and is the raw
+// subscan value unless the operation was stored before raw amounts were kept
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ExportOperation {
+    pub hash: String,
+    pub block_number: u64,
+    pub extrinsic_index: String,
+    pub operation_timestamp: String,
+    pub operation_type: OperationType,
+    pub from_wallet: String,
+    pub controller_wallet: String,
+    pub to_wallet: String,
+    pub amount: String,
+    pub amount_planck: String,
+    pub amount_usd: f64,
+    #[serde(default)]
+    pub annotations: Vec<String>,
+    #[serde(default)]
+    pub price_annotation: Option<ExportPriceAnnotation>,
+
+    // id of OPERATION_ID_SCHEME, missing on operations stored without one
+    #[serde(default)]
+    pub operation_id: Option<String>,
+}
+
+// usd prices around large operations, missing until known
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ExportPriceAnnotation {
+    pub price_before_1h: Option<f64>,
+    pub price_at: Option<f64>,
+    pub price_after_1h: Option<f64>,
+    pub price_after_24h: Option<f64>,
+}
+
+impl From<PriceAnnotation> for ExportPriceAnnotation {
+    fn from(p: PriceAnnotation) -> Self {
+        Self {
+            price_before_1h: p.price_before_1h,
+            price_at: p.price_at,
+            price_after_1h: p.price_after_1h,
+            price_after_24h: p.price_after_24h,
+        }
+    }
+}
+
+// annotation texts and price annotations grouped by operation hash
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExportAnnotations {
+    pub texts: HashMap<String, Vec<String>>,
+    pub prices: HashMap<String, ExportPriceAnnotation>,
+}
+
+impl ExportOperation {
+    pub fn new(operation: SubscanOperation, precision: &ExportPrecision) -> Self {
+        let decimals = Network::from_env().get_decimals();
+        let planck = operation
+            .operation_planck
+            .map(|p| p.0)
+            .unwrap_or_else(|| to_planck(operation.operation_quantity, decimals));
+
+        Self {
+            hash: operation.hash,
+            block_number: operation.block_number,
+            extrinsic_index: operation.extrinsic_index,
+            operation_timestamp: operation
+                .operation_timestamp
+                .try_to_rfc3339_string()
+                .unwrap_or_default(),
+            operation_type: operation.operation_type,
+            from_wallet: operation.from_wallet,
+            controller_wallet: operation.controller_wallet,
+            to_wallet: operation.to_wallet,
+            amount: precision.format_planck(planck, decimals),
+            amount_planck: planck.to_string(),
+            amount_usd: precision.round_usd(operation.operation_usd),
+            annotations: Vec::new(),
+            price_annotation: None,
+            operation_id: operation.operation_id,
+        }
+    }
+
+    pub fn with_annotations(mut self, annotations: &ExportAnnotations) -> Self {
+        self.annotations = annotations
+            .texts
+            .get(&self.hash)
+            .cloned()
+            .unwrap_or_default();
+        self.price_annotation = annotations.prices.get(&self.hash).cloned();
+        self
+    }
+}
+
+pub async fn get_export_annotations(operations: &[SubscanOperation]) -> ExportAnnotations {
+    let hashes = operations
+        .iter()
+        .map(|o| o.hash.clone())
+        .collect::<Vec<_>>();
+    let mut mongodb_client_operation_annotations = MongoDbClientOperationAnnotations::new().await;
+    let mut mongodb_client_price_annotations = MongoDbClientPriceAnnotations::new().await;
+
+    let mut annotations = ExportAnnotations::default();
+    for annotation in mongodb_client_operation_annotations
+        .get_annotations(hashes.clone())
+        .await
+    {
+        annotations
+            .texts
+            .entry(annotation.hash)
+            .or_default()
+            .push(annotation.text);
+    }
+    for annotation in mongodb_client_price_annotations
+        .get_annotations(hashes)
+        .await
+    {
+        annotations
+            .prices
+            .insert(annotation.hash.clone(), annotation.into());
+    }
+
+    annotations
+}
@@ -0,0 +1,264 @@
+use crate::{
+    exports::{
+        compression::ExportCompression, precision::ExportPrecision, ExportAnnotations,
+        ExportOperation,
+    },
+    SubscanOperation,
+};
+use arrow::{
+    array::{
+        ArrayRef, Float64Array, ListBuilder, StringArray, StringBuilder, TimestampMillisecondArray,
+        UInt64Array,
+    },
+    datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit},
+    record_batch::RecordBatch,
+};
+use parquet::{
+    arrow::ArrowWriter,
+    basic::{Compression, GzipLevel, ZstdLevel},
+    file::properties::WriterProperties,
+};
+use std::{
+    io::{self, Write},
+    sync::Arc,
+};
+
+// the columns of ExportOperation, amounts stay decimal strings so that planck values above
+// the range of integer types are never cut off
+pub fn get_parquet_schema() -> SchemaRef {
+    let timestamp = DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()));
+    let annotations = DataType::List(Arc::new(Field::new("item", DataType::Utf8, true)));
+
+    Arc::new(Schema::new(vec![
+        Field::new("hash", DataType::Utf8, false),
+        Field::new("block_number", DataType::UInt64, false),
+        Field::new("extrinsic_index", DataType::Utf8, false),
+        Field::new("operation_timestamp", timestamp, false),
+        Field::new("operation_type", DataType::Utf8, false),
+        Field::new("from_wallet", DataType::Utf8, false),
+        Field::new("controller_wallet", DataType::Utf8, false),
+        Field::new("to_wallet", DataType::Utf8, false),
+        Field::new("amount", DataType::Utf8, false),
+        Field::new("amount_planck", DataType::Utf8, false),
+        Field::new("amount_usd", DataType::Float64, false),
+        Field::new("annotations", annotations, false),
+        Field::new("price_before_1h", DataType::Float64, true),
+        Field::new("price_at", DataType::Float64, true),
+        Field::new("price_after_1h", DataType::Float64, true),
+        Field::new("price_after_24h", DataType::Float64, true),
+        Field::new("operation_id", DataType::Utf8, true),
+    ]))
+}
+
+// parquet compresses its column chunks itself, the file around them is never compressed
+pub fn get_parquet_compression(compression: ExportCompression) -> Compression {
+    match compression {
+        ExportCompression::None => Compression::UNCOMPRESSED,
+        ExportCompression::Gzip => Compression::GZIP(GzipLevel::default()),
+        ExportCompression::Zstd => Compression::ZSTD(ZstdLevel::default()),
+    }
+}
+
+// every call of write_operations is one row group, pages of the export keep them large enough
+pub struct ParquetExportWriter<W: Write + Send> {
+    writer: ArrowWriter<W>,
+    schema: SchemaRef,
+}
+
+impl<W: Write + Send> ParquetExportWriter<W> {
+    pub fn new(writer: W, compression: ExportCompression) -> io::Result<Self> {
+        let schema = get_parquet_schema();
+        let properties = WriterProperties::builder()
+            .set_compression(get_parquet_compression(compression))
+            .build();
+        let writer = ArrowWriter::try_new(writer, schema.clone(), Some(properties))
+            .map_err(io::Error::other)?;
+
+        Ok(Self { writer, schema })
+    }
+
+    pub fn write_operations(
+        &mut self,
+        operations: Vec<SubscanOperation>,
+        annotations: &ExportAnnotations,
+    ) -> io::Result<()> {
+        if operations.is_empty() {
+            return Ok(());
+        }
+
+        let batch = get_record_batch(self.schema.clone(), operations, annotations)?;
+        self.writer.write(&batch).map_err(io::Error::other)
+    }
+
+    // writes the footer, a file without it can't be read
+    pub fn into_inner(self) -> io::Result<W> {
+        self.writer.into_inner().map_err(io::Error::other)
+    }
+}
+
+fn get_record_batch(
+    schema: SchemaRef,
+    operations: Vec<SubscanOperation>,
+    annotations: &ExportAnnotations,
+) -> io::Result<RecordBatch> {
+    let timestamps = operations
+        .iter()
+        .map(|o| o.operation_timestamp.timestamp_millis())
+        .collect::<Vec<_>>();
+    let operations = operations
+        .into_iter()
+        .map(|o| ExportOperation::new(o, &ExportPrecision::full()).with_annotations(annotations))
+        .collect::<Vec<_>>();
+
+    let strings = |f: fn(&ExportOperation) -> &str| -> ArrayRef {
+        Arc::new(
+            operations
+                .iter()
+                .map(|o| Some(f(o)))
+                .collect::<StringArray>(),
+        )
+    };
+    let prices = |f: fn(&ExportOperation) -> Option<f64>| -> ArrayRef {
+        Arc::new(operations.iter().map(f).collect::<Float64Array>())
+    };
+
+    let mut annotation_texts = ListBuilder::new(StringBuilder::new());
+    for operation in &operations {
+        for text in &operation.annotations {
+            annotation_texts.values().append_value(text);
+        }
+        annotation_texts.append(true);
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        strings(|o| &o.hash),
+        Arc::new(UInt64Array::from_iter_values(
+            operations.iter().map(|o| o.block_number),
+        )),
+        strings(|o| &o.extrinsic_index),
+        Arc::new(TimestampMillisecondArray::from(timestamps).with_timezone("UTC")),
+        Arc::new(
+            operations
+                .iter()
+                .map(|o| Some(o.operation_type.to_string()))
+                .collect::<StringArray>(),
+        ),
+        strings(|o| &o.from_wallet),
+        strings(|o| &o.controller_wallet),
+        strings(|o| &o.to_wallet),
+        strings(|o| &o.amount),
+        strings(|o| &o.amount_planck),
+        Arc::new(Float64Array::from_iter_values(
+            operations.iter().map(|o| o.amount_usd),
+        )),
+        Arc::new(annotation_texts.finish()),
+        prices(|o| o.price_annotation.as_ref().and_then(|p| p.price_before_1h)),
+        prices(|o| o.price_annotation.as_ref().and_then(|p| p.price_at)),
+        prices(|o| o.price_annotation.as_ref().and_then(|p| p.price_after_1h)),
+        prices(|o| o.price_annotation.as_ref().and_then(|p| p.price_after_24h)),
+        Arc::new(
+            operations
+                .iter()
+                .map(|o| o.operation_id.as_deref())
+                .collect::<StringArray>(),
+        ),
+    ];
+
+    RecordBatch::try_new(schema, columns).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        exports::{
+            compression::ExportCompression, parquet::ParquetExportWriter, ExportAnnotations,
+            ExportPriceAnnotation,
+        },
+        OperationStatus, OperationType, SubscanOperation,
+    };
+    use arrow::array::{Array, Float64Array, ListArray, StringArray, UInt64Array};
+    use bson::DateTime;
+    use bytes::Bytes;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::collections::HashMap;
+
+    #[test]
+    fn parquet_exports_read_back_as_written() {
+        let operations = (0..3)
+            .map(|i| SubscanOperation {
+                hash: format!("hash{i}"),
+                hash_version: 0,
+                block_number: 100 + i,
+                extrinsic_index: format!("{}-1", 100 + i),
+                call_index: 0,
+                operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+                operation_quantity: 1.5,
+                operation_planck: None,
+                operation_fee: None,
+                operation_era: None,
+                operation_usd: 9.0,
+                operation_type: OperationType::Transfer,
+                from_wallet: "from".to_string(),
+                controller_wallet: "controller".to_string(),
+                to_wallet: "to".to_string(),
+                status: OperationStatus::Finalized,
+                operation_id: None,
+                nomination_targets: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+        let annotations = ExportAnnotations {
+            texts: HashMap::from([("hash1".to_string(), vec!["otc".to_string()])]),
+            prices: HashMap::from([(
+                "hash2".to_string(),
+                ExportPriceAnnotation {
+                    price_before_1h: None,
+                    price_at: Some(6.0),
+                    price_after_1h: None,
+                    price_after_24h: None,
+                },
+            )]),
+        };
+
+        let mut writer = ParquetExportWriter::new(Vec::new(), ExportCompression::Zstd).unwrap();
+        writer.write_operations(operations, &annotations).unwrap();
+        let bytes = writer.into_inner().unwrap();
+
+        let batches = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(bytes))
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 3);
+
+        let block_numbers = batch.column_by_name("block_number").unwrap();
+        let block_numbers = block_numbers
+            .as_any()
+            .downcast_ref::<UInt64Array>()
+            .unwrap();
+        assert_eq!(block_numbers.values().to_vec(), vec![100, 101, 102]);
+
+        let operation_types = batch.column_by_name("operation_type").unwrap();
+        let operation_types = operation_types
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(operation_types.value(0), "Transfer");
+
+        let annotation_texts = batch.column_by_name("annotations").unwrap();
+        let annotation_texts = annotation_texts
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .unwrap();
+        assert_eq!(annotation_texts.value_length(0), 0);
+        assert_eq!(annotation_texts.value_length(1), 1);
+
+        // prices are null until known
+        let prices = batch.column_by_name("price_at").unwrap();
+        let prices = prices.as_any().downcast_ref::<Float64Array>().unwrap();
+        assert!(prices.is_null(0));
+        assert_eq!(prices.value(2), 6.0);
+    }
+}
@@ -0,0 +1,502 @@
+#[cfg(feature = "parquet")]
+use crate::exports::parquet::ParquetExportWriter;
+use crate::{
+    exports::{
+        compression::{CompressedWriter, ExportCompression},
+        csv::{CsvColumn, CsvExportWriter, CsvPreset},
+        get_export_annotations,
+        jsonl::write_jsonl,
+        precision::ExportPrecision,
+        ExportAnnotations,
+    },
+    mongodb_client_subscan::MongoDbClientSubscan,
+    SubscanOperation,
+};
+use bson::Bson;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+use tracing::{error, info};
+
+static DEFAULT_EXPORT_PART_SIZE: usize = 1_000_000;
+static DEFAULT_EXPORT_DIRECTORY: &str = ".";
+static EXPORT_PAGE_SIZE: i64 = 10_000;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+
+    // one json operation per line with full precision
+    Jsonl,
+
+    // columnar with full precision, needs the parquet feature
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => ".csv",
+            ExportFormat::Jsonl => ".jsonl",
+            ExportFormat::Parquet => ".parquet",
+        }
+    }
+
+    // parquet compresses its column chunks with EXPORT_COMPRESSION instead of the whole file
+    pub fn is_compressed_internally(&self) -> bool {
+        *self == ExportFormat::Parquet
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportConfig {
+    pub format: ExportFormat,
+    pub compression: ExportCompression,
+    pub csv_preset: CsvPreset,
+    pub csv_columns: Vec<CsvColumn>,
+
+    // operations per file, a range with more of them is split into several parts
+    pub part_size: usize,
+    pub directory: PathBuf,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::default(),
+            compression: ExportCompression::default(),
+            csv_preset: CsvPreset::default(),
+            csv_columns: CsvColumn::get_default_columns(),
+            part_size: DEFAULT_EXPORT_PART_SIZE,
+            directory: PathBuf::from(DEFAULT_EXPORT_DIRECTORY),
+        }
+    }
+}
+
+impl ExportConfig {
+    // EXPORT_FORMAT, EXPORT_COMPRESSION, EXPORT_CSV_PRESET, EXPORT_CSV_COLUMNS, EXPORT_PART_SIZE
+    // and EXPORT_DIRECTORY
+    pub fn from_env() -> ExportConfig {
+        let format = get_enum_from_env::<ExportFormat>("EXPORT_FORMAT");
+        let compression = get_enum_from_env::<ExportCompression>("EXPORT_COMPRESSION");
+        let csv_preset = get_enum_from_env::<CsvPreset>("EXPORT_CSV_PRESET");
+        let csv_columns = env::var("EXPORT_CSV_COLUMNS")
+            .ok()
+            .filter(|c| !c.trim().is_empty())
+            .and_then(|c| {
+                CsvColumn::parse_columns(&c)
+                    .map_err(|e| error!(target: "exports", "EXPORT_CSV_COLUMNS: {e}, using all."))
+                    .ok()
+            })
+            .unwrap_or_else(CsvColumn::get_default_columns);
+        let part_size = env::var("EXPORT_PART_SIZE")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_EXPORT_PART_SIZE);
+        let directory = env::var("EXPORT_DIRECTORY")
+            .ok()
+            .filter(|d| !d.is_empty())
+            .unwrap_or(DEFAULT_EXPORT_DIRECTORY.to_string());
+
+        Self {
+            format,
+            compression,
+            csv_preset,
+            csv_columns,
+            part_size,
+            directory: PathBuf::from(directory),
+        }
+    }
+
+    pub fn with_compression(mut self, compression: ExportCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn with_part_size(mut self, part_size: usize) -> Self {
+        self.part_size = part_size.max(1);
+        self
+    }
+
+    // operations-1600000000-1700000000.checkpoint.json, see ExportCheckpoint
+    pub fn get_checkpoint_path(&self, from_timestamp: i64, to_timestamp: i64) -> PathBuf {
+        self.directory.join(format!(
+            "operations-{from_timestamp}-{to_timestamp}.checkpoint.json"
+        ))
+    }
+
+    // e.g. operations-1600000000-1700000000.part-0001.csv.zst, parts count from 1
+    pub fn get_part_path(&self, from_timestamp: i64, to_timestamp: i64, part: usize) -> PathBuf {
+        let compression_extension = if self.format.is_compressed_internally() {
+            ""
+        } else {
+            self.compression.get_extension()
+        };
+
+        self.directory.join(format!(
+            "operations-{from_timestamp}-{to_timestamp}.part-{part:04}{}{compression_extension}",
+            self.format.get_extension(),
+        ))
+    }
+}
+
+fn get_enum_from_env<T: FromStr + Default>(key: &str) -> T {
+    let Ok(value) = env::var(key) else {
+        return T::default();
+    };
+
+    T::from_str(value.trim()).unwrap_or_else(|_| {
+        error!(target: "exports", "Unknown {key} {value}, using the default.");
+        T::default()
+    })
+}
+
+type PartFile = CompressedWriter<BufWriter<File>>;
+
+enum PartWriter {
+    // boxed, the csv writer buffers its records inline
+    Csv(Box<CsvExportWriter<PartFile>>),
+    Jsonl(PartFile),
+    #[cfg(feature = "parquet")]
+    Parquet(ParquetExportWriter<BufWriter<File>>),
+}
+
+impl PartWriter {
+    fn create(path: &Path, config: &ExportConfig) -> io::Result<Self> {
+        let get_file = || {
+            config
+                .compression
+                .get_writer(BufWriter::new(File::create(path)?))
+        };
+
+        Ok(match config.format {
+            ExportFormat::Csv => {
+                let options = config
+                    .csv_preset
+                    .options()
+                    .with_columns(config.csv_columns.clone());
+                PartWriter::Csv(Box::new(CsvExportWriter::new(get_file()?, &options)?))
+            }
+            ExportFormat::Jsonl => PartWriter::Jsonl(get_file()?),
+            #[cfg(feature = "parquet")]
+            ExportFormat::Parquet => PartWriter::Parquet(ParquetExportWriter::new(
+                BufWriter::new(File::create(path)?),
+                config.compression,
+            )?),
+            #[cfg(not(feature = "parquet"))]
+            ExportFormat::Parquet => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "built without the parquet feature",
+                ));
+            }
+        })
+    }
+
+    fn write_operations(
+        &mut self,
+        operations: Vec<SubscanOperation>,
+        annotations: &ExportAnnotations,
+    ) -> io::Result<()> {
+        match self {
+            PartWriter::Csv(writer) => writer.write_operations(operations, annotations),
+            PartWriter::Jsonl(writer) => {
+                write_jsonl(writer, operations, annotations, &ExportPrecision::full())
+            }
+            #[cfg(feature = "parquet")]
+            PartWriter::Parquet(writer) => writer.write_operations(operations, annotations),
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        let file = match self {
+            PartWriter::Csv(writer) => writer.into_inner()?,
+            PartWriter::Jsonl(writer) => writer,
+            #[cfg(feature = "parquet")]
+            PartWriter::Parquet(writer) => return writer.into_inner()?.flush(),
+        };
+
+        file.finish()?.flush()
+    }
+}
+
+// last operation of a finished part, the next part starts after it
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExportCursor {
+    pub block_number: u64,
+    pub extrinsic_index: String,
+    pub hash: String,
+}
+
+impl ExportCursor {
+    pub fn new(operation: &SubscanOperation) -> Self {
+        Self {
+            block_number: operation.block_number,
+            extrinsic_index: operation.extrinsic_index.clone(),
+            hash: operation.hash.clone(),
+        }
+    }
+
+    // as MongoDbClientSubscan::get_page_key
+    pub fn get_page_key(&self) -> Vec<Bson> {
+        vec![
+            Bson::Int64(self.block_number as i64),
+            Bson::String(self.extrinsic_index.clone()),
+            Bson::String(self.hash.clone()),
+        ]
+    }
+}
+
+// saved next to the parts whenever one is finished, a resumed export keeps the finished
+// parts and writes the one it was interrupted in again
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ExportCheckpoint {
+    pub from_timestamp: i64,
+    pub to_timestamp: i64,
+    pub format: ExportFormat,
+    pub compression: ExportCompression,
+    pub csv_preset: CsvPreset,
+    #[serde(default = "CsvColumn::get_default_columns")]
+    pub csv_columns: Vec<CsvColumn>,
+    pub part_size: usize,
+    pub parts: Vec<PathBuf>,
+    pub after: Option<ExportCursor>,
+    pub is_done: bool,
+}
+
+impl ExportCheckpoint {
+    pub fn new(from_timestamp: i64, to_timestamp: i64, config: &ExportConfig) -> Self {
+        Self {
+            from_timestamp,
+            to_timestamp,
+            format: config.format,
+            compression: config.compression,
+            csv_preset: config.csv_preset,
+            csv_columns: config.csv_columns.clone(),
+            part_size: config.part_size,
+            parts: Vec::new(),
+            after: None,
+            is_done: false,
+        }
+    }
+
+    // parts written with other options can't be continued
+    pub fn is_compatible(&self, config: &ExportConfig) -> bool {
+        self.format == config.format
+            && self.compression == config.compression
+            && self.csv_preset == config.csv_preset
+            && self.csv_columns == config.csv_columns
+            && self.part_size == config.part_size
+    }
+
+    // none if the export never finished a part
+    pub fn read(path: &Path) -> io::Result<Option<ExportCheckpoint>> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Some(serde_json::from_slice(&bytes)?))
+    }
+
+    // written aside first, an interruption never leaves a torn checkpoint behind
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let temporary_path = path.with_extension("json.tmp");
+        fs::write(&temporary_path, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(temporary_path, path)
+    }
+}
+
+// operations of the range in block order, read page by page so that multi-year ranges never
+// sit in memory at once, returns the paths of the written parts, a resumed export continues
+// after the last finished part of its checkpoint
+pub async fn export_operations(
+    from_timestamp: i64,
+    to_timestamp: i64,
+    config: &ExportConfig,
+    is_resume: bool,
+) -> io::Result<Vec<PathBuf>> {
+    let checkpoint_path = config.get_checkpoint_path(from_timestamp, to_timestamp);
+    let checkpoint = if is_resume {
+        ExportCheckpoint::read(&checkpoint_path)?
+    } else {
+        None
+    };
+    let mut checkpoint = match checkpoint {
+        Some(checkpoint) if !checkpoint.is_compatible(config) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the checkpoint was written with other export options",
+            ));
+        }
+        Some(checkpoint) if checkpoint.is_done => {
+            info!(target: "exports", "Export is done already.");
+            return Ok(checkpoint.parts);
+        }
+        Some(checkpoint) => {
+            info!(target: "exports", "Resuming the export after {} parts.", checkpoint.parts.len());
+            checkpoint
+        }
+        None => ExportCheckpoint::new(from_timestamp, to_timestamp, config),
+    };
+
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let query = MongoDbClientSubscan::get_time_range_query(from_timestamp, Some(to_timestamp));
+
+    let mut part: Option<(PartWriter, PathBuf, usize)> = None;
+    let mut last = checkpoint.after.clone();
+    loop {
+        let after = last.as_ref().map(ExportCursor::get_page_key);
+        let mut operations = mongodb_client_subscan
+            .get_operations_page(query.clone(), after, EXPORT_PAGE_SIZE)
+            .await;
+        if operations.is_empty() {
+            break;
+        }
+        let is_last_page = (operations.len() as i64) < EXPORT_PAGE_SIZE;
+        let annotations = get_export_annotations(&operations).await;
+
+        // a page going past the end of a part is continued in the next one
+        while !operations.is_empty() {
+            let (mut writer, path, written) = match part.take() {
+                Some((writer, path, written)) if written < config.part_size => {
+                    (writer, path, written)
+                }
+                full => {
+                    if let Some((writer, path, _)) = full {
+                        writer.finish()?;
+                        checkpoint.parts.push(path);
+                        checkpoint.after = last.clone();
+                        checkpoint.write(&checkpoint_path)?;
+                    }
+                    let path = config.get_part_path(
+                        from_timestamp,
+                        to_timestamp,
+                        checkpoint.parts.len() + 1,
+                    );
+                    info!(target: "exports", "Writing {}.", path.display());
+                    (PartWriter::create(&path, config)?, path, 0)
+                }
+            };
+
+            let rest = operations.split_off((config.part_size - written).min(operations.len()));
+            let count = operations.len();
+            last = operations.last().map(ExportCursor::new);
+            writer.write_operations(operations, &annotations)?;
+            part = Some((writer, path, written + count));
+            operations = rest;
+        }
+
+        if is_last_page {
+            break;
+        }
+    }
+
+    if let Some((writer, path, _)) = part {
+        writer.finish()?;
+        checkpoint.parts.push(path);
+    }
+    checkpoint.after = last;
+    checkpoint.is_done = true;
+    checkpoint.write(&checkpoint_path)?;
+
+    Ok(checkpoint.parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::exports::{
+        compression::ExportCompression,
+        parts::{ExportCheckpoint, ExportConfig, ExportCursor, ExportFormat},
+    };
+    use bson::Bson;
+    use std::path::PathBuf;
+
+    #[test]
+    fn parts_are_numbered_and_named_after_format_and_compression() {
+        let config = ExportConfig {
+            directory: PathBuf::from("/exports"),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.get_part_path(1_600_000_000, 1_700_000_000, 1),
+            PathBuf::from("/exports/operations-1600000000-1700000000.part-0001.csv")
+        );
+
+        let config = ExportConfig {
+            format: ExportFormat::Jsonl,
+            ..config
+        }
+        .with_compression(ExportCompression::Zstd);
+        assert_eq!(
+            config.get_part_path(0, 1, 12),
+            PathBuf::from("/exports/operations-0-1.part-0012.jsonl.zst")
+        );
+
+        // parquet compresses within the file
+        let config = ExportConfig {
+            format: ExportFormat::Parquet,
+            ..config
+        };
+        assert_eq!(
+            config.get_part_path(0, 1, 1),
+            PathBuf::from("/exports/operations-0-1.part-0001.parquet")
+        );
+    }
+
+    #[test]
+    fn checkpoints_resume_with_the_same_options_only() {
+        let config = ExportConfig::default().with_part_size(100);
+        let mut checkpoint = ExportCheckpoint::new(0, 1, &config);
+        assert!(checkpoint.is_compatible(&config));
+        assert!(!checkpoint.is_compatible(&config.clone().with_part_size(200)));
+        assert!(
+            !checkpoint.is_compatible(&config.clone().with_compression(ExportCompression::Gzip))
+        );
+
+        checkpoint.after = Some(ExportCursor {
+            block_number: 42,
+            extrinsic_index: "42-3".to_string(),
+            hash: "hash".to_string(),
+        });
+        assert_eq!(
+            checkpoint.after.unwrap().get_page_key(),
+            vec![
+                Bson::Int64(42),
+                Bson::String("42-3".to_string()),
+                Bson::String("hash".to_string())
+            ]
+        );
+        assert_eq!(
+            config.get_checkpoint_path(0, 1),
+            PathBuf::from("./operations-0-1.checkpoint.json")
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+
+static DISPLAY_DECIMALS: u32 = 6;
+static MAX_USD_DECIMALS: u32 = 12;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Rounding {
+    #[default]
+    HalfUp,
+    HalfEven,
+    Down,
+    Up,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct ExportPrecision {
+    // none keeps all planck digits
+    pub decimals: Option<u32>,
+    #[serde(default)]
+    pub rounding: Rounding,
+}
+
+impl ExportPrecision {
+    pub fn full() -> Self {
+        Self {
+            decimals: None,
+            rounding: Rounding::default(),
+        }
+    }
+
+    pub fn display() -> Self {
+        Self {
+            decimals: Some(DISPLAY_DECIMALS),
+            rounding: Rounding::HalfUp,
+        }
+    }
+
+    // usd amounts are floats already, they are only rounded to the same decimals
+    pub fn round_usd(&self, usd: f64) -> f64 {
+        let Some(decimals) = self.decimals else {
+            return usd;
+        };
+
+        let unit = 10f64.powi(decimals.min(MAX_USD_DECIMALS) as i32);
+        let scaled = usd * unit;
+        let rounded = match self.rounding {
+            Rounding::Down => scaled.floor(),
+            Rounding::Up => scaled.ceil(),
+            Rounding::HalfUp => scaled.round(),
+            Rounding::HalfEven => scaled.round_ties_even(),
+        };
+        rounded / unit
+    }
+
+    // rounding is done on the integer planck value so no float error leaks into exports,
+    // token_decimals are the ones of the network
+    pub fn format_planck(&self, planck: u128, token_decimals: u32) -> String {
+        let decimals = self.decimals.unwrap_or(token_decimals).min(token_decimals);
+        let step = 10u128.pow(token_decimals - decimals);
+        let quotient = planck / step;
+        let remainder = planck % step;
+
+        let round_up = match self.rounding {
+            Rounding::Down => false,
+            Rounding::Up => remainder > 0,
+            Rounding::HalfUp => remainder * 2 >= step,
+            Rounding::HalfEven => {
+                remainder * 2 > step || (remainder * 2 == step && quotient % 2 == 1)
+            }
+        };
+        let rounded = if round_up { quotient + 1 } else { quotient };
+
+        let unit = 10u128.pow(decimals);
+        if decimals == 0 {
+            return rounded.to_string();
+        }
+        format!(
+            "{}.{:0width$}",
+            rounded / unit,
+            rounded % unit,
+            width = decimals as usize
+        )
+    }
+}
+
+// only for operations without a raw planck amount, the float may be off in the last digits
+pub fn to_planck(quantity: f64, decimals: u32) -> u128 {
+    (quantity * 10f64.powi(decimals as i32)).round() as u128
+}
+
+// decimal token amount like "12.5" as planck, without going through floats
+pub fn parse_decimal_planck(amount: &str, decimals: u32) -> Option<u128> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if fraction.len() > decimals as usize
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+        || (whole.is_empty() && fraction.is_empty())
+    {
+        return None;
+    }
+
+    let whole = if whole.is_empty() {
+        0
+    } else {
+        whole.parse::<u128>().ok()?
+    };
+    let fraction = format!("{fraction:0<width$}", width = decimals as usize);
+
+    whole
+        .checked_mul(10u128.pow(decimals))?
+        .checked_add(fraction.parse::<u128>().ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::exports::precision::{parse_decimal_planck, ExportPrecision, Rounding};
+
+    #[test]
+    fn format_planck_applies_rounding() {
+        let precision = |decimals, rounding| ExportPrecision {
+            decimals: Some(decimals),
+            rounding,
+        };
+
+        assert_eq!(
+            precision(6, Rounding::HalfUp).format_planck(1_234_567_500_000, 12),
+            "1.234568"
+        );
+        assert_eq!(
+            precision(6, Rounding::HalfEven).format_planck(1_234_568_500_000, 12),
+            "1.234568"
+        );
+        assert_eq!(
+            precision(6, Rounding::Down).format_planck(1_234_567_999_999, 12),
+            "1.234567"
+        );
+        assert_eq!(
+            precision(6, Rounding::Up).format_planck(1_234_567_000_001, 12),
+            "1.234568"
+        );
+        assert_eq!(
+            precision(0, Rounding::HalfUp).format_planck(999_999_999_999, 12),
+            "1"
+        );
+        assert_eq!(
+            ExportPrecision::full().format_planck(1_000_000_000_001, 12),
+            "1.000000000001"
+        );
+    }
+
+    #[test]
+    fn decimal_amounts_parse_exactly() {
+        assert_eq!(parse_decimal_planck("12.5", 12), Some(12_500_000_000_000));
+        assert_eq!(parse_decimal_planck("0.000000000001", 12), Some(1));
+        assert_eq!(parse_decimal_planck("7", 12), Some(7_000_000_000_000));
+        assert_eq!(parse_decimal_planck("0.0000000000001", 12), None);
+        assert_eq!(parse_decimal_planck("-1", 12), None);
+        assert_eq!(parse_decimal_planck(".", 12), None);
+
+        // e.g. polkadot
+        assert_eq!(parse_decimal_planck("12.5", 10), Some(125_000_000_000));
+        assert_eq!(parse_decimal_planck("0.000000000001", 10), None);
+    }
+
+    #[test]
+    fn usd_follows_precision() {
+        let precision = ExportPrecision {
+            decimals: Some(2),
+            rounding: Rounding::Down,
+        };
+
+        assert_eq!(precision.round_usd(2_469.259), 2_469.25);
+        assert_eq!(ExportPrecision::display().round_usd(1.23456789), 1.234568);
+        assert_eq!(ExportPrecision::full().round_usd(1.23456789), 1.23456789);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::Module;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, env, str::FromStr, sync::OnceLock};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+use tracing::error;
+
+static FEATURE_FLAGS: OnceLock<FeatureFlags> = OnceLock::new();
+
+// nomination pools were parsed before the flags, so they stay on while EXPERIMENTAL_PARSERS
+// is not set
+static DEFAULT_EXPERIMENTAL_PARSERS: [ExperimentalParser; 1] = [ExperimentalParser::Pools];
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ExperimentalParser {
+    Pools,
+    Contracts,
+    Governance,
+}
+
+impl ExperimentalParser {
+    // modules only parsed with the flag on, contracts and governance are shipped dark
+    // without any module yet
+    pub fn get_modules(&self) -> Vec<Module> {
+        match self {
+            ExperimentalParser::Pools => vec![Module::NominationPools],
+            ExperimentalParser::Contracts | ExperimentalParser::Governance => Vec::new(),
+        }
+    }
+}
+
+// experimental parsers enabled in this deployment
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeatureFlags {
+    enabled: HashSet<ExperimentalParser>,
+}
+
+impl FeatureFlags {
+    pub fn new(enabled: impl IntoIterator<Item = ExperimentalParser>) -> Self {
+        Self {
+            enabled: enabled.into_iter().collect(),
+        }
+    }
+
+    // EXPERIMENTAL_PARSERS=pools,governance enables exactly those, an empty one none of them
+    pub fn from_env() -> FeatureFlags {
+        match env::var("EXPERIMENTAL_PARSERS") {
+            Ok(v) => FeatureFlags::parse(&v),
+            Err(_) => FeatureFlags::new(DEFAULT_EXPERIMENTAL_PARSERS),
+        }
+    }
+
+    fn parse(v: &str) -> FeatureFlags {
+        let enabled = v
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .filter_map(|s| {
+                let parser = ExperimentalParser::from_str(s);
+                if parser.is_err() {
+                    error!(target: "feature_flags", "Unknown experimental parser {s}, skipping it.");
+                }
+                parser.ok()
+            });
+
+        FeatureFlags::new(enabled)
+    }
+
+    // read once, flags change with a restart of the deployment
+    pub fn global() -> &'static FeatureFlags {
+        FEATURE_FLAGS.get_or_init(FeatureFlags::from_env)
+    }
+
+    pub fn is_enabled(&self, parser: ExperimentalParser) -> bool {
+        self.enabled.contains(&parser)
+    }
+
+    // sorted, for the startup log
+    pub fn get_enabled(&self) -> Vec<ExperimentalParser> {
+        self.enabled.iter().copied().sorted().collect()
+    }
+
+    // modules of no experimental parser are always parsed
+    pub fn is_module_enabled(&self, module: &Module) -> bool {
+        ExperimentalParser::iter()
+            .filter(|p| p.get_modules().contains(module))
+            .all(|p| self.is_enabled(p))
+    }
+
+    pub fn get_enabled_modules(&self) -> Vec<Module> {
+        Module::iter()
+            .filter(|m| self.is_module_enabled(m))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        feature_flags::{ExperimentalParser, FeatureFlags, DEFAULT_EXPERIMENTAL_PARSERS},
+        Module,
+    };
+
+    #[test]
+    fn experimental_modules_need_their_flag() {
+        let flags = FeatureFlags::parse("governance, unknown,,contracts");
+        assert_eq!(
+            flags.get_enabled(),
+            vec![
+                ExperimentalParser::Contracts,
+                ExperimentalParser::Governance
+            ]
+        );
+        assert_eq!(flags.get_enabled_modules(), vec![Module::Staking]);
+
+        // pools keep running in deployments which never set the flags
+        let flags = FeatureFlags::new(DEFAULT_EXPERIMENTAL_PARSERS);
+        assert_eq!(
+            flags.get_enabled_modules(),
+            vec![Module::Staking, Module::NominationPools]
+        );
+        assert!(FeatureFlags::parse("").get_enabled().is_empty());
+    }
+}
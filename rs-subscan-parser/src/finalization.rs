@@ -0,0 +1,110 @@
+use crate::{
+    mongodb_client_subscan::MongoDbClientSubscan,
+    subscan_parser::{Network, SubscanParser},
+    OperationStatus, SubscanOperation,
+};
+use std::{env, time::Duration};
+use tokio::time::sleep;
+use tracing::{error, info};
+
+static DEFAULT_FINALIZATION_CHECK_SECONDS: u64 = 12;
+
+// operations of blocks up to the finalized head are final, the newer ones pending
+pub fn set_statuses(operations: &mut [SubscanOperation], finalized_block_number: u64) {
+    for s in operations.iter_mut() {
+        s.status = if s.block_number <= finalized_block_number {
+            OperationStatus::Finalized
+        } else {
+            OperationStatus::Pending
+        };
+    }
+}
+
+// statuses of parsed operations before they are written, all of them stay pending while the
+// finalized head is unknown and the finalization checker flips them later
+pub async fn set_operation_statuses(operations: &mut [SubscanOperation], network: &Network) {
+    let mut subscan_parser = SubscanParser::new(network.clone()).await;
+    match subscan_parser.get_finalized_block_number().await {
+        Ok(finalized_block_number) => set_statuses(operations, finalized_block_number),
+        Err(e) => {
+            error!(target: "finalization", "Finalized block error, operations stay pending: {e}.");
+            set_statuses(operations, 0);
+        }
+    }
+}
+
+// flips stored pending operations once the finalized head passes their blocks, every
+// FINALIZATION_CHECK_SECONDS
+pub async fn check_finalization_periodically() {
+    let check_interval = env::var("FINALIZATION_CHECK_SECONDS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_FINALIZATION_CHECK_SECONDS);
+
+    let mut subscan_parser = SubscanParser::new(Network::from_env()).await;
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let mut last_finalized_block_number = None;
+    loop {
+        match subscan_parser.get_finalized_block_number().await {
+            Ok(finalized_block_number)
+                if last_finalized_block_number != Some(finalized_block_number) =>
+            {
+                let finalized = mongodb_client_subscan
+                    .finalize_operations(finalized_block_number)
+                    .await;
+                if finalized > 0 {
+                    info!(target: "finalization", "Finalized {finalized} operations up to block {finalized_block_number}.");
+                }
+                last_finalized_block_number = Some(finalized_block_number);
+            }
+            Ok(_) => {}
+            Err(e) => error!(target: "finalization", "Finalized block error: {e}."),
+        }
+
+        sleep(Duration::from_secs(check_interval)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{finalization::set_statuses, OperationStatus, OperationType, SubscanOperation};
+    use bson::DateTime;
+
+    fn operation(block_number: u64) -> SubscanOperation {
+        SubscanOperation {
+            hash: block_number.to_string(),
+            hash_version: 0,
+            block_number,
+            extrinsic_index: format!("{block_number}-1"),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+            operation_quantity: 1.0,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 1.0,
+            operation_type: OperationType::Transfer,
+            from_wallet: "from".to_string(),
+            controller_wallet: "from".to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Pending,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn operations_past_the_finalized_head_stay_pending() {
+        let mut operations = vec![operation(98), operation(100), operation(101)];
+        set_statuses(&mut operations, 100);
+        assert_eq!(
+            operations.iter().map(|s| s.status).collect::<Vec<_>>(),
+            vec![
+                OperationStatus::Finalized,
+                OperationStatus::Finalized,
+                OperationStatus::Pending
+            ]
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use crate::subscan_parser::{Network, SubscanParser};
+use std::{env, time::Duration};
+use tokio::time::{sleep, Instant};
+use tracing::error;
+
+static DEFAULT_HEAD_WATCHER_MAX_WAIT_MS: u64 = 60_000;
+static MIN_HEAD_WATCHER_POLL_MS: u64 = 200;
+
+pub fn is_head_watcher_enabled() -> bool {
+    env::var("HEAD_WATCHER").is_ok_and(|v| v == "true")
+}
+
+// HEAD_WATCHER_POLL_MS, half of the block time of the network by default
+fn get_poll_interval(network: &Network) -> Duration {
+    let poll_ms = env::var("HEAD_WATCHER_POLL_MS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(network.get_block_time_ms() as u64 / 2)
+        .max(MIN_HEAD_WATCHER_POLL_MS);
+
+    Duration::from_millis(poll_ms)
+}
+
+// polls only the head block number, so the extrinsics are queried as soon as a new block
+// is indexed instead of on a fixed interval
+pub struct HeadWatcher {
+    subscan_parser: SubscanParser,
+    poll_interval: Duration,
+    max_wait: Duration,
+    last_block: Option<u64>,
+}
+
+impl HeadWatcher {
+    pub async fn new(network: Network) -> HeadWatcher {
+        let max_wait_ms = env::var("HEAD_WATCHER_MAX_WAIT_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_HEAD_WATCHER_MAX_WAIT_MS);
+
+        Self {
+            poll_interval: get_poll_interval(&network),
+            subscan_parser: SubscanParser::new(network).await,
+            max_wait: Duration::from_millis(max_wait_ms),
+            last_block: None,
+        }
+    }
+
+    // returns once subscan indexed a block newer than the last returned one, or after
+    // HEAD_WATCHER_MAX_WAIT_MS so a stuck head doesn't stop the worker
+    pub async fn wait_for_new_block(&mut self) -> Option<u64> {
+        let started = Instant::now();
+        loop {
+            match self.subscan_parser.get_head_block_number().await {
+                Ok(head) if is_new_block(self.last_block, head) => {
+                    self.last_block = Some(head);
+                    return Some(head);
+                }
+                Ok(_) => {}
+                Err(e) => error!(target: "head_watcher", "Head block error: {e}."),
+            }
+
+            if started.elapsed() >= self.max_wait {
+                return None;
+            }
+            sleep(self.poll_interval).await;
+        }
+    }
+}
+
+// the first head seen counts as new, the worker has not queried anything yet
+fn is_new_block(last_block: Option<u64>, head: u64) -> bool {
+    last_block.is_none_or(|last| head > last)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        head_watcher::{get_poll_interval, is_new_block},
+        subscan_parser::{Network, SubscanParser},
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn only_newer_blocks_are_new() {
+        assert!(is_new_block(None, 0));
+        assert!(is_new_block(Some(10), 11));
+        assert!(!is_new_block(Some(10), 10));
+
+        // subscan behind a load balancer can answer with an older head
+        assert!(!is_new_block(Some(10), 9));
+    }
+
+    #[test]
+    fn polls_twice_per_block() {
+        assert_eq!(
+            get_poll_interval(&Network::Polkadot),
+            Duration::from_millis(3_000)
+        );
+        assert_eq!(
+            get_poll_interval(&Network::Alephzero),
+            Duration::from_millis(500)
+        );
+    }
+
+    #[tokio::test]
+    async fn mock_head_is_the_current_slot() {
+        let mut subscan_parser = SubscanParser::new(Network::Mock).await;
+        let head = subscan_parser.get_head_block_number().await.unwrap();
+        assert!(head > 0);
+    }
+}
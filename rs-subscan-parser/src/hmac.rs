@@ -0,0 +1,49 @@
+// block size of sha256
+static HMAC_BLOCK_SIZE: usize = 64;
+
+// rfc 2104 over the sha256 crate, which only hashes
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut key = if key.len() > HMAC_BLOCK_SIZE {
+        hex::decode(sha256::digest(key)).unwrap_or_default()
+    } else {
+        key.to_vec()
+    };
+    key.resize(HMAC_BLOCK_SIZE, 0);
+
+    let inner = key
+        .iter()
+        .map(|b| b ^ 0x36)
+        .chain(message.iter().copied())
+        .collect::<Vec<_>>();
+    let inner_hash = hex::decode(sha256::digest(inner.as_slice())).unwrap_or_default();
+    let outer = key
+        .iter()
+        .map(|b| b ^ 0x5c)
+        .chain(inner_hash)
+        .collect::<Vec<_>>();
+
+    hex::decode(sha256::digest(outer.as_slice())).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hmac::hmac_sha256;
+
+    #[test]
+    fn hmac_sha256_matches_rfc_4231() {
+        // test case 2
+        assert_eq!(
+            hex::encode(hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        // keys longer than a block are hashed first, test case 6
+        assert_eq!(
+            hex::encode(hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use crate::{
+    mongodb_client_latency_histograms::MongoDbClientLatencyHistograms,
+    mongodb_client_operation_latencies::MongoDbClientOperationLatencies, shadow::is_shadow_mode,
+    sinks::Sink, SubscanOperation,
+};
+use bson::DateTime;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, env};
+
+static MILLIS_IN_HOUR: i64 = 60 * 60 * 1_000;
+static DEFAULT_LATENCY_SLO_MS: i64 = 60_000;
+
+// upper bounds of the histogram buckets, slower deliveries go to one more bucket
+pub static LATENCY_BUCKETS_MS: [i64; 10] = [
+    1_000, 2_000, 5_000, 10_000, 20_000, 30_000, 60_000, 120_000, 300_000, 900_000,
+];
+
+// LATENCY_SLO_MS, operations delivered within it after their block are fresh
+pub fn get_latency_slo_ms() -> i64 {
+    env::var("LATENCY_SLO_MS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_LATENCY_SLO_MS)
+}
+
+// latencies from the block to one sink of the operations delivered within an hour
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LatencyHistogram {
+    pub sink: Sink,
+    pub hour: DateTime,
+    pub count: u64,
+    pub sum_ms: i64,
+
+    // deliveries by index in LATENCY_BUCKETS_MS, kept as a document so upserts can $inc them
+    #[serde(default)]
+    pub buckets: HashMap<String, u64>,
+}
+
+impl LatencyHistogram {
+    pub fn get_bucket_count(&self, i: usize) -> u64 {
+        self.buckets
+            .get(&i.to_string())
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+// when the operation reached each sink, by sink name
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct OperationLatency {
+    pub hash: String,
+    pub operation_timestamp: DateTime,
+    #[serde(default)]
+    pub delivered_at: HashMap<String, DateTime>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LatencyBucket {
+    // none for the deliveries slower than every bound
+    pub le_ms: Option<i64>,
+    pub count: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LatencyReport {
+    pub sink: Sink,
+    pub count: u64,
+    pub mean_ms: f64,
+
+    // bounds of the buckets the percentiles fall in, none when beyond every bound
+    pub p50_ms: Option<i64>,
+    pub p95_ms: Option<i64>,
+    pub p99_ms: Option<i64>,
+
+    // deliveries in the buckets up to the slo out of all, exact when the slo is a bound
+    pub slo_ms: i64,
+    pub within_slo: f64,
+    pub buckets: Vec<LatencyBucket>,
+}
+
+// called once the sink accepted the operations
+pub async fn record_deliveries(sink: &Sink, operations: &[SubscanOperation]) {
+    // shadow deliveries never reach the feed
+    if operations.is_empty() || is_shadow_mode() {
+        return;
+    }
+
+    let delivered_at = DateTime::now();
+    let mut mongodb_client_operation_latencies = MongoDbClientOperationLatencies::new().await;
+    mongodb_client_operation_latencies
+        .set_delivered_at(sink, operations, delivered_at)
+        .await;
+
+    let mut mongodb_client_latency_histograms = MongoDbClientLatencyHistograms::new().await;
+    mongodb_client_latency_histograms
+        .increment_histogram(get_histogram(sink, operations, delivered_at))
+        .await;
+}
+
+// clock skew between subscan and us never makes a latency negative
+pub fn get_histogram(
+    sink: &Sink,
+    operations: &[SubscanOperation],
+    delivered_at: DateTime,
+) -> LatencyHistogram {
+    let delivered_at_ms = delivered_at.timestamp_millis();
+    let mut histogram = LatencyHistogram {
+        sink: sink.clone(),
+        hour: DateTime::from_millis(delivered_at_ms - delivered_at_ms.rem_euclid(MILLIS_IN_HOUR)),
+        count: 0,
+        sum_ms: 0,
+        buckets: HashMap::new(),
+    };
+    for s in operations {
+        let latency_ms = (delivered_at_ms - s.operation_timestamp.timestamp_millis()).max(0);
+        histogram.count += 1;
+        histogram.sum_ms += latency_ms;
+        *histogram
+            .buckets
+            .entry(get_bucket(latency_ms).to_string())
+            .or_default() += 1;
+    }
+
+    histogram
+}
+
+fn get_bucket(latency_ms: i64) -> usize {
+    LATENCY_BUCKETS_MS.partition_point(|b| *b < latency_ms)
+}
+
+pub fn build_report(sink: &Sink, histograms: &[LatencyHistogram], slo_ms: i64) -> LatencyReport {
+    let counts = (0..=LATENCY_BUCKETS_MS.len())
+        .map(|i| {
+            histograms
+                .iter()
+                .map(|h| h.get_bucket_count(i))
+                .sum::<u64>()
+        })
+        .collect::<Vec<_>>();
+    let count = counts.iter().sum::<u64>();
+    let sum_ms = histograms.iter().map(|h| h.sum_ms).sum::<i64>();
+
+    let within_slo = LATENCY_BUCKETS_MS
+        .iter()
+        .zip(counts.iter())
+        .filter(|(b, _)| **b <= slo_ms)
+        .map(|(_, c)| c)
+        .sum::<u64>();
+
+    LatencyReport {
+        sink: sink.clone(),
+        count,
+        mean_ms: get_share(sum_ms as f64, count),
+        p50_ms: get_percentile(&counts, 0.5),
+        p95_ms: get_percentile(&counts, 0.95),
+        p99_ms: get_percentile(&counts, 0.99),
+        slo_ms,
+        within_slo: get_share(within_slo as f64, count),
+        buckets: counts
+            .iter()
+            .enumerate()
+            .map(|(i, c)| LatencyBucket {
+                le_ms: LATENCY_BUCKETS_MS.get(i).copied(),
+                count: *c,
+            })
+            .collect(),
+    }
+}
+
+// bound of the first bucket holding the share of the deliveries
+fn get_percentile(counts: &[u64], share: f64) -> Option<i64> {
+    let count = counts.iter().sum::<u64>();
+    if count == 0 {
+        return None;
+    }
+
+    let target = ((count as f64 * share).ceil() as u64).max(1);
+    let mut cumulative = 0;
+    for (i, c) in counts.iter().enumerate() {
+        cumulative += c;
+        if cumulative >= target {
+            return LATENCY_BUCKETS_MS.get(i).copied();
+        }
+    }
+
+    None
+}
+
+fn get_share(value: f64, count: u64) -> f64 {
+    if count == 0 {
+        return 0.0;
+    }
+
+    value / count as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        latency::{build_report, get_bucket, get_histogram, LATENCY_BUCKETS_MS},
+        sinks::Sink,
+        OperationStatus, OperationType, SubscanOperation,
+    };
+    use bson::DateTime;
+
+    static DELIVERED_AT_MS: i64 = 1_700_000_000_000;
+
+    fn operation(latency_ms: i64) -> SubscanOperation {
+        SubscanOperation {
+            hash: latency_ms.to_string(),
+            hash_version: 0,
+            block_number: 1,
+            extrinsic_index: "1-1".to_string(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(DELIVERED_AT_MS - latency_ms),
+            operation_quantity: 1.0,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 1.0,
+            operation_type: OperationType::Transfer,
+            from_wallet: "from".to_string(),
+            controller_wallet: "from".to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn buckets_include_their_bound() {
+        assert_eq!(get_bucket(0), 0);
+        assert_eq!(get_bucket(1_000), 0);
+        assert_eq!(get_bucket(1_001), 1);
+        assert_eq!(get_bucket(900_001), LATENCY_BUCKETS_MS.len());
+    }
+
+    #[test]
+    fn histograms_count_latencies_from_the_block() {
+        let operations = [
+            operation(800),
+            operation(-200),
+            operation(1_500),
+            operation(7_200_000),
+        ];
+        let histogram = get_histogram(
+            &Sink::Nats,
+            &operations,
+            DateTime::from_millis(DELIVERED_AT_MS),
+        );
+
+        assert_eq!(histogram.hour.timestamp_millis() % 3_600_000, 0);
+        assert_eq!(histogram.count, 4);
+        assert_eq!(histogram.sum_ms, 800 + 1_500 + 7_200_000);
+        assert_eq!(histogram.get_bucket_count(0), 2);
+        assert_eq!(histogram.get_bucket_count(1), 1);
+        assert_eq!(histogram.get_bucket_count(LATENCY_BUCKETS_MS.len()), 1);
+    }
+
+    #[test]
+    fn reports_merge_histograms() {
+        let delivered_at = DateTime::from_millis(DELIVERED_AT_MS);
+        let fast = (0..98).map(|_| operation(500)).collect::<Vec<_>>();
+        let histograms = [
+            get_histogram(&Sink::Mongodb, &fast, delivered_at),
+            get_histogram(&Sink::Mongodb, &[operation(45_000)], delivered_at),
+            get_histogram(&Sink::Mongodb, &[operation(3_600_000)], delivered_at),
+        ];
+
+        let report = build_report(&Sink::Mongodb, &histograms, 60_000);
+        assert_eq!(report.count, 100);
+        assert_eq!(report.p50_ms, Some(1_000));
+        assert_eq!(report.p95_ms, Some(1_000));
+        assert_eq!(report.p99_ms, Some(60_000));
+        assert_eq!(report.within_slo, 0.99);
+        assert_eq!(report.buckets.len(), LATENCY_BUCKETS_MS.len() + 1);
+        assert_eq!(report.buckets.last().unwrap().count, 1);
+
+        let empty = build_report(&Sink::Mongodb, &[], 60_000);
+        assert_eq!(
+            (empty.count, empty.p50_ms, empty.within_slo),
+            (0, None, 0.0)
+        );
+    }
+}
@@ -1,16 +1,91 @@
-use bson::DateTime;
+use address::AddressFormat;
+use amount::Balance;
+use bson::{oid::ObjectId, DateTime};
+use event_param::{get_base_type, ParamValue};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+use subscan_parser::{Network, EMPTY_ADDRESS};
 
+pub mod address;
+pub mod amount;
+pub mod backcheck;
+pub mod backfill;
+pub mod circuit_breaker;
+pub mod compaction;
+pub mod confirmations;
+pub mod data_quality;
+pub mod event_matcher;
+pub mod event_param;
+pub mod explorer;
+pub mod exports;
+pub mod feature_flags;
+pub mod finalization;
+pub mod head_watcher;
+pub mod hmac;
+pub mod latency;
+pub mod materialized_views;
+pub mod metrics;
+pub mod mock_network;
+pub mod mongodb_client_backfill_progress;
+pub mod mongodb_client_balance_snapshots;
+pub mod mongodb_client_checkpoints;
 pub mod mongodb_client_identities;
+pub mod mongodb_client_latency_histograms;
+pub mod mongodb_client_operation_annotations;
+pub mod mongodb_client_operation_latencies;
+pub mod mongodb_client_operation_summaries;
+pub mod mongodb_client_operation_totals;
+pub mod mongodb_client_outbox;
+pub mod mongodb_client_pending_operations;
+pub mod mongodb_client_price_annotations;
+pub mod mongodb_client_quarantine;
+pub mod mongodb_client_staking_flow;
 pub mod mongodb_client_subscan;
 pub mod mongodb_client_validator;
+pub mod mongodb_client_volume_anomalies;
+pub mod mongodb_client_wallet_formats;
+pub mod object_storage;
+pub mod operation_ids;
+pub mod operation_prices;
+pub mod operations_watcher;
+pub mod outbox;
+pub mod pagination;
+pub mod parser_diff;
+pub mod pipeline_error;
+pub mod preflight;
+pub mod price_annotations;
+pub mod reconciliation;
+pub mod reports;
+pub mod retry_policy;
+pub mod shadow;
+pub mod sinks;
+pub mod spans;
+pub mod staking_flow;
+pub mod subscan_api;
+pub mod subscan_error;
 pub mod subscan_parser;
+pub mod subscan_scheduler;
 pub mod subscan_stake_parser;
 pub mod subscan_transfer_parser;
+pub mod sync_checkpoint;
+pub mod task_limit;
+pub mod timestamp_validation;
+pub mod validator_concentration;
+pub mod volume_anomalies;
+pub mod wallet_comparison;
+pub mod wallet_formats;
 
 pub static MINIMUM_AZERO_TO_SAVE_TO_DB: f64 = 499.999999;
 
+// bumped whenever the hash inputs change, see SubscanOperation::get_hash
+pub static OPERATION_HASH_VERSION: u32 = 2;
+
+fn get_legacy_hash_version() -> u32 {
+    1
+}
+
 #[derive(
     Clone,
     Debug,
@@ -36,12 +111,321 @@ pub enum OperationType {
     Transfer,
     DepositToExchange,
     WithdrawFromExchange,
+    Reward,
+    Slash,
+}
+
+impl OperationType {
+    // as it reads in one line summaries
+    pub fn get_description(&self) -> &'static str {
+        match self {
+            OperationType::Stake => "Stake",
+            OperationType::ReStake => "Re-stake",
+            OperationType::RequestUnstake => "Unstake request",
+            OperationType::WithdrawUnstaked => "Unstaked withdrawal",
+            OperationType::Transfer => "Transfer",
+            OperationType::DepositToExchange => "Exchange deposit",
+            OperationType::WithdrawFromExchange => "Exchange withdrawal",
+            OperationType::Reward => "Staking reward",
+            OperationType::Slash => "Slash",
+        }
+    }
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum OperationStatus {
+    // its block may still be reverted, shown as provisional
+    Pending,
+
+    // documents stored before statuses were kept come from finalized blocks
+    #[default]
+    Finalized,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
 pub struct Validator {
     pub nominator: String,
+
+    // targets of the nomination, stored under validator as a string when there is only one
+    // like every mapping before multiple targets were kept
+    #[serde(rename = "validator", with = "one_or_many")]
+    pub validators: Vec<String>,
+
+    // mappings stored before versioning are treated as valid since forever
+    #[serde(default = "default_valid_from")]
+    pub valid_from: DateTime,
+    #[serde(default)]
+    pub valid_to: Option<DateTime>,
+
+    // extrinsic which started the version, legacy mappings have none
+    #[serde(default)]
+    pub extrinsic_index: Option<String>,
+}
+
+fn default_valid_from() -> DateTime {
+    DateTime::MIN
+}
+
+impl Validator {
+    // targets are a set, the order a nominate call listed them in doesn't matter
+    pub fn has_same_validators(&self, other: &Validator) -> bool {
+        let mut validators = self.validators.iter().collect::<Vec<_>>();
+        let mut other_validators = other.validators.iter().collect::<Vec<_>>();
+        validators.sort();
+        other_validators.sort();
+
+        validators == other_validators
+    }
+}
+
+mod one_or_many {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    pub fn serialize<S: Serializer>(values: &[String], serializer: S) -> Result<S::Ok, S::Error> {
+        match values {
+            [value] => value.serialize(serializer),
+            _ => values.serialize(serializer),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<String>, D::Error> {
+        match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(value) => Ok(vec![value]),
+            OneOrMany::Many(values) => Ok(values),
+        }
+    }
+}
+
+// daily candle of the cumulative net stake flowing into a validator
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct StakingFlowCandle {
     pub validator: String,
+    pub day: DateTime,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub inflow: f64,
+    pub outflow: f64,
+}
+
+// token price around a large operation, filled in as the later prices become known
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct PriceAnnotation {
+    pub hash: String,
+    pub operation_timestamp: DateTime,
+    pub price_before_1h: Option<f64>,
+    pub price_at: Option<f64>,
+    pub price_after_1h: Option<f64>,
+    pub price_after_24h: Option<f64>,
+}
+
+impl PriceAnnotation {
+    pub fn is_complete(&self) -> bool {
+        self.price_before_1h.is_some()
+            && self.price_at.is_some()
+            && self.price_after_1h.is_some()
+            && self.price_after_24h.is_some()
+    }
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum SummaryDirection {
+    #[default]
+    Out,
+    In,
+}
+
+// hourly totals of one wallet per operation type, kept after raw operations are compacted
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct OperationSummary {
+    pub wallet: String,
+    pub hour: DateTime,
+    pub operation_type: OperationType,
+    pub direction: SummaryDirection,
+    pub count: u64,
+    pub quantity: f64,
+    pub usd: f64,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum TotalsView {
+    // operations sent by the wallet
+    #[default]
+    Wallet,
+    // staking operations targeting the validator
+    Validator,
+}
+
+// all-time totals per operation type, kept up to date as operations are stored
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct OperationTotals {
+    pub view: TotalsView,
+    pub key: String,
+    pub operation_type: OperationType,
+    pub count: u64,
+    pub quantity: f64,
+    pub usd: f64,
+    #[serde(default)]
+    pub checked_at: Option<DateTime>,
+}
+
+// operations of one type stored within an hour
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct HourlyVolume {
+    pub operation_type: OperationType,
+    pub hour: DateTime,
+    pub count: u64,
+}
+
+// operations of one type stored within a range of hours
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct OperationTypeVolume {
+    pub operation_type: OperationType,
+    pub count: u64,
+    pub quantity: f64,
+    pub usd: f64,
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum VolumeAnomalyKind {
+    // usually a whale event
+    #[default]
+    Spike,
+
+    // usually a broken parser
+    Drought,
+}
+
+// balance of a wallet as subscan reported it at a point in time, in tokens, reserved and
+// bonded funds are part of the total
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct BalanceSnapshot {
+    pub wallet: String,
+    pub timestamp: DateTime,
+    pub total: f64,
+    pub reserved: f64,
+    pub bonded: f64,
+}
+
+// hourly volume of an operation type far off its rolling baseline
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct VolumeAnomaly {
+    pub operation_type: OperationType,
+    pub hour: DateTime,
+    pub kind: VolumeAnomalyKind,
+    pub count: u64,
+    pub baseline_mean: f64,
+    pub baseline_stddev: f64,
+    pub z_score: f64,
+    pub detected_at: DateTime,
+}
+
+// free-text note left by an analyst on an operation, keyed by operation hash
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct OperationAnnotation {
+    pub hash: String,
+    pub text: String,
+    #[serde(default)]
+    pub suppress_alerts: bool,
+    pub created_at: DateTime,
+}
+
+// encodings a wallet was seen in besides its canonical one, e.g. hex or another ss58 prefix
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct WalletFormats {
+    pub canonical: String,
+    pub alternates: Vec<String>,
+}
+
+// newest extrinsic of a network the staking parser has stored, later runs only walk back to it
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct SyncCheckpoint {
+    pub network: String,
+    pub block_number: u64,
+    pub extrinsic_index: String,
+    pub updated_at: DateTime,
+}
+
+// how far the full history backfill of a network got, pages count from the newest extrinsics
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct BackfillProgress {
+    pub network: String,
+    pub next_page: u32,
+    pub total_pages: u32,
+    pub is_done: bool,
+    pub updated_at: DateTime,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord, Hash)]
@@ -50,33 +434,181 @@ pub struct Identity {
     pub identity: String,
 }
 
+// names of the stored fields are spelled out, so renaming a field here can't break reading
+// the documents stored before, see fixtures/stored_operations.json
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
 pub struct SubscanOperation {
+    #[serde(rename = "hash")]
     pub hash: String,
+
+    // algorithm the hash was computed with, 0 until set_hash is called and 1 on documents
+    // stored before hashes were versioned
+    #[serde(rename = "hash_version", default = "get_legacy_hash_version")]
+    pub hash_version: u32,
+    #[serde(rename = "block_number")]
     pub block_number: u64,
+    #[serde(rename = "extrinsic_index")]
     pub extrinsic_index: String,
+
+    // position of the operation within its extrinsic, the event index for operations read
+    // from events and 0 for calls
+    #[serde(rename = "call_index", default)]
+    pub call_index: u32,
+    #[serde(rename = "operation_timestamp")]
     pub operation_timestamp: DateTime,
+    #[serde(rename = "operation_quantity")]
     pub operation_quantity: f64,
+
+    // exact amount in planck as reported by subscan, missing on operations stored before,
+    // operation_quantity is derived from it for display and usd amounts
+    #[serde(rename = "operation_planck", default)]
+    pub operation_planck: Option<Balance>,
+
+    // fee paid by the sender in tokens, only known for transfers
+    #[serde(rename = "operation_fee", default)]
+    pub operation_fee: Option<f64>,
+
+    // era paid out or slashed, only known for rewards and slashes
+    #[serde(rename = "operation_era", default)]
+    pub operation_era: Option<u32>,
+    #[serde(rename = "operation_usd")]
     pub operation_usd: f64,
+    #[serde(rename = "operation_type")]
     pub operation_type: OperationType,
+    #[serde(rename = "from_wallet")]
     pub from_wallet: String,
+    #[serde(rename = "controller_wallet")]
     pub controller_wallet: String,
+    #[serde(rename = "to_wallet")]
     pub to_wallet: String,
+
+    // pending until the finalized head passes its block, see finalization
+    #[serde(rename = "status", default)]
+    pub status: OperationStatus,
+
+    // time sortable id of OPERATION_ID_SCHEME next to the dedup hash, see operation_ids
+    #[serde(rename = "operation_id", default)]
+    pub operation_id: Option<String>,
+
+    // every validator a nominate call picked, to_wallet is the first of them, only kept
+    // while parsing to build the nominations
+    #[serde(skip)]
+    pub nomination_targets: Vec<String>,
+}
+
+// operation together with its mongodb id, ids grow in insertion order and serve as stream cursors
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct StoredOperation {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    #[serde(flatten)]
+    pub operation: SubscanOperation,
 }
 
 impl SubscanOperation {
-    pub fn set_hash(&mut self) {
-        self.hash = sha256::digest(format!(
-            "{}_{}_{}_{}_{}",
-            self.operation_timestamp,
-            self.operation_quantity,
+    // identifies the operation on chain, the same on every parse of it,
+    // e.g. "alephzero/1234-2/Transfer/7"
+    pub fn dedup_key(&self, network: &Network) -> String {
+        format!(
+            "{}/{}/{}/{}",
+            network.get_slug(),
+            self.extrinsic_index,
             self.operation_type,
-            self.from_wallet,
-            self.to_wallet,
-        ));
+            self.call_index,
+        )
+    }
+
+    // none for versions this build doesn't know
+    // 1: timestamp, quantity, type and wallets, changed whenever a validator got resolved later
+    // 2: sha256 of the version and dedup_key
+    pub fn get_hash(&self, version: u32, network: &Network) -> Option<String> {
+        match version {
+            1 => Some(sha256::digest(format!(
+                "{}_{}_{}_{}_{}",
+                self.operation_timestamp,
+                self.operation_quantity,
+                self.operation_type,
+                self.from_wallet,
+                self.to_wallet,
+            ))),
+            2 => Some(sha256::digest(format!(
+                "v{version}/{}",
+                self.dedup_key(network)
+            ))),
+            _ => None,
+        }
+    }
+
+    pub fn set_hash(&mut self, network: &Network) {
+        self.hash = self
+            .get_hash(OPERATION_HASH_VERSION, network)
+            .unwrap_or_default();
+        self.hash_version = OPERATION_HASH_VERSION;
+    }
+
+    // hashes under every known version, an operation stored under any of them is not stored again
+    pub fn get_hashes(&self, network: &Network) -> Vec<String> {
+        (1..=OPERATION_HASH_VERSION)
+            .filter_map(|v| self.get_hash(v, network))
+            .collect()
+    }
+
+    // wallets re-encoded for a consumer, storage keeps the canonical encoding,
+    // empty and unreadable wallets are left as they are
+    pub fn with_address_format(mut self, network: &Network, address_format: AddressFormat) -> Self {
+        for wallet in [
+            &mut self.from_wallet,
+            &mut self.controller_wallet,
+            &mut self.to_wallet,
+        ] {
+            if let Some(encoded) = address_format.format(network, wallet) {
+                *wallet = encoded;
+            }
+        }
+        self
+    }
+
+    // e.g. "Stake of 1500.00 AZERO ($750.00) from 5Grwva…utQY to 5FHnea…94ty"
+    pub fn get_description(&self, network: &Network) -> String {
+        let description = format!(
+            "{} of {:.2} {} (${:.2}) from {}",
+            self.operation_type.get_description(),
+            self.operation_quantity,
+            network.get_token_symbol(),
+            self.operation_usd,
+            get_short_wallet(&self.from_wallet),
+        );
+        if self.to_wallet == EMPTY_ADDRESS {
+            return description;
+        }
+
+        format!("{description} to {}", get_short_wallet(&self.to_wallet))
+    }
+
+    // one line for logs and the cli, with the explorer link of the extrinsic if the network has one
+    pub fn summary(&self, network: &Network) -> String {
+        let description = self.get_description(network);
+        match explorer::get_operation_url(network, self) {
+            Some(url) => format!("{description} in block {}, {url}", self.block_number),
+            None => format!("{description} in block {}", self.block_number),
+        }
     }
 }
 
+// first 6 and last 4 characters, enough to tell wallets apart at a glance
+pub fn get_short_wallet(wallet: &str) -> String {
+    let chars = wallet.chars().collect::<Vec<_>>();
+    if chars.len() <= 12 {
+        return wallet.to_string();
+    }
+
+    format!(
+        "{}…{}",
+        chars[..6].iter().collect::<String>(),
+        chars[chars.len() - 4..].iter().collect::<String>()
+    )
+}
+
 #[derive(
     Clone,
     Debug,
@@ -106,22 +638,72 @@ pub enum ExtrinsicsType {
 
     #[strum(to_string = "withdraw_unbonded")]
     WithdrawUnbonded,
+
+    // nomination pools only
+    Join,
+
+    #[strum(to_string = "claim_payout")]
+    ClaimPayout,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct SubscanEventParam {
     pub type_name: String,
-    pub value: String,
+
+    // as subscan returns it, see decode
+    pub value: Value,
     pub name: String,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+impl SubscanEventParam {
+    // type without its path and generics, e.g. T::AccountId is AccountId and BalanceOf<T> is BalanceOf
+    pub fn get_type(&self) -> &str {
+        get_base_type(&self.type_name)
+    }
+
+    pub fn decode(&self) -> ParamValue {
+        ParamValue::decode(&self.type_name, &self.value)
+    }
+
+    // planck amount of a balance param in tokens
+    pub fn as_planck(&self) -> Option<Balance> {
+        self.decode().as_planck()
+    }
+
+    pub fn as_balance(&self, decimals: u32) -> Option<Decimal> {
+        self.as_planck()?.to_decimal(decimals).ok()
+    }
+
+    // account in the encoding of the network, see get_network_address
+    pub fn as_account(&self, network: &Network) -> Option<String> {
+        self.decode().as_account(network)
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.decode().as_u64()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct SubscanEvent {
     pub module_id: String,
+
+    // name of the event in its module, e.g. Bonded
+    #[serde(default)]
+    pub event_id: String,
     pub event_index: String,
     pub event_params: Vec<SubscanEventParam>,
 }
 
+// an extrinsic as subscan reports it now, with the events it emitted
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SubscanExtrinsic {
+    pub extrinsic_index: String,
+    pub block_number: u64,
+    pub is_success: bool,
+    pub events: Vec<SubscanEvent>,
+}
+
 #[derive(
     Clone,
     Debug,
@@ -142,4 +724,367 @@ pub struct SubscanEvent {
 pub enum Module {
     #[default]
     Staking,
+
+    #[serde(rename = "nominationpools")]
+    #[strum(to_string = "nominationpools")]
+    NominationPools,
+}
+
+impl Module {
+    // calls of the module parsed into operations, pools share the staking call names
+    pub fn get_extrinsics_types(&self) -> Vec<ExtrinsicsType> {
+        match self {
+            Module::Staking => vec![
+                ExtrinsicsType::Bond,
+                ExtrinsicsType::BondExtra,
+                ExtrinsicsType::Nominate,
+                ExtrinsicsType::Rebond,
+                ExtrinsicsType::Unbond,
+                ExtrinsicsType::WithdrawUnbonded,
+            ],
+            Module::NominationPools => vec![
+                ExtrinsicsType::Join,
+                ExtrinsicsType::BondExtra,
+                ExtrinsicsType::ClaimPayout,
+                ExtrinsicsType::Unbond,
+                ExtrinsicsType::WithdrawUnbonded,
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        address::AddressFormat,
+        amount::Balance,
+        get_short_wallet,
+        subscan_parser::{Network, EMPTY_ADDRESS},
+        OperationStatus, OperationType, StoredOperation, SubscanEventParam, SubscanOperation,
+        Validator, OPERATION_HASH_VERSION,
+    };
+    use bson::{doc, oid::ObjectId, Bson, DateTime};
+    use rust_decimal::Decimal;
+    use serde_json::Value;
+    use std::str::FromStr;
+
+    fn param(type_name: &str, value: &str) -> SubscanEventParam {
+        SubscanEventParam {
+            type_name: type_name.to_string(),
+            value: Value::from(value),
+            name: "param".to_string(),
+        }
+    }
+
+    #[test]
+    fn stored_operation_reads_plain_documents() {
+        let id = ObjectId::new();
+        let operation = SubscanOperation {
+            hash: "hash".to_string(),
+            hash_version: 0,
+            block_number: 1,
+            extrinsic_index: "1-1".to_string(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+            operation_quantity: 1.5,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 3.0,
+            operation_type: OperationType::Stake,
+            from_wallet: "from".to_string(),
+            controller_wallet: "controller".to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        };
+        let mut document = bson::to_document(&operation).unwrap();
+        document.extend(doc! {"_id": id});
+
+        let stored: StoredOperation = bson::from_document(document).unwrap();
+        assert_eq!(stored.id, id);
+        assert_eq!(stored.operation, operation);
+    }
+
+    #[test]
+    fn stored_documents_survive_a_round_trip() {
+        let fixtures: Value =
+            serde_json::from_str(include_str!("../fixtures/stored_operations.json")).unwrap();
+        let documents = fixtures
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| match Bson::try_from(f.clone()).unwrap() {
+                Bson::Document(document) => document,
+                other => panic!("{other} is not a document"),
+            })
+            .collect::<Vec<_>>();
+
+        for document in documents.iter() {
+            let stored: StoredOperation = bson::from_document(document.clone()).unwrap();
+
+            // every stored field is written back under its name
+            let written = bson::to_document(&stored).unwrap();
+            for key in document.keys() {
+                assert!(written.contains_key(key), "{key} is not written back");
+            }
+            let read: StoredOperation = bson::from_document(written).unwrap();
+            assert_eq!(read, stored);
+        }
+
+        // documents stored before the optional fields existed
+        let legacy: StoredOperation = bson::from_document(documents[0].clone()).unwrap();
+        assert_eq!(legacy.operation.operation_type, OperationType::Stake);
+        assert_eq!(legacy.operation.block_number, 58_123_456);
+        assert_eq!(legacy.operation.hash_version, 1);
+        assert_eq!(legacy.operation.call_index, 0);
+        assert_eq!(legacy.operation.operation_planck, None);
+        assert_eq!(legacy.operation.operation_era, None);
+        assert_eq!(legacy.operation.status, OperationStatus::Finalized);
+
+        let transfer: StoredOperation = bson::from_document(documents[1].clone()).unwrap();
+        assert_eq!(transfer.operation.operation_fee, Some(0.0154));
+        assert_eq!(
+            transfer.operation.operation_planck,
+            Some(Balance(2_750_250_000_000_000))
+        );
+
+        let reward: StoredOperation = bson::from_document(documents[2].clone()).unwrap();
+        assert_eq!(reward.operation.hash_version, 2);
+        assert_eq!(reward.operation.call_index, 14);
+        assert_eq!(reward.operation.operation_era, Some(943));
+        assert_eq!(
+            reward.operation.operation_timestamp,
+            DateTime::from_millis(1_728_978_400_000)
+        );
+    }
+
+    #[test]
+    fn operations_read_as_one_line() {
+        let mut operation = SubscanOperation {
+            hash: "hash".to_string(),
+            hash_version: 0,
+            block_number: 1,
+            extrinsic_index: "1-1".to_string(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+            operation_quantity: 1_500.0,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 750.0,
+            operation_type: OperationType::Stake,
+            from_wallet: "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY".to_string(),
+            controller_wallet: EMPTY_ADDRESS.to_string(),
+            to_wallet: "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        };
+        assert_eq!(
+            operation.get_description(&Network::Alephzero),
+            "Stake of 1500.00 AZERO ($750.00) from 5Grwva…utQY to 5FHneW…94ty"
+        );
+
+        operation.operation_type = OperationType::RequestUnstake;
+        operation.to_wallet = EMPTY_ADDRESS.to_string();
+        assert_eq!(
+            operation.get_description(&Network::Polkadot),
+            "Unstake request of 1500.00 DOT ($750.00) from 5Grwva…utQY"
+        );
+    }
+
+    #[test]
+    fn hashes_depend_on_the_dedup_key_only() {
+        let mut operation = SubscanOperation {
+            hash: String::new(),
+            hash_version: 0,
+            block_number: 1234,
+            extrinsic_index: "1234-2".to_string(),
+            call_index: 7,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+            operation_quantity: 1_500.0,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 750.0,
+            operation_type: OperationType::Transfer,
+            from_wallet: "from".to_string(),
+            controller_wallet: EMPTY_ADDRESS.to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        };
+        assert_eq!(
+            operation.dedup_key(&Network::Alephzero),
+            "alephzero/1234-2/Transfer/7"
+        );
+
+        operation.set_hash(&Network::Alephzero);
+        assert_eq!(operation.hash_version, OPERATION_HASH_VERSION);
+        let hash = operation.hash.clone();
+
+        // a resolved validator or canonical wallet keeps the hash
+        operation.to_wallet = "validator".to_string();
+        operation.set_hash(&Network::Alephzero);
+        assert_eq!(operation.hash, hash);
+
+        // other transfers of the batch and other networks don't
+        operation.call_index = 8;
+        operation.set_hash(&Network::Alephzero);
+        assert_ne!(operation.hash, hash);
+        operation.call_index = 7;
+        operation.set_hash(&Network::Polkadot);
+        assert_ne!(operation.hash, hash);
+
+        // operations stored under the legacy hash are still found
+        let hashes = operation.get_hashes(&Network::Alephzero);
+        assert_eq!(hashes.len(), OPERATION_HASH_VERSION as usize);
+        assert_eq!(hashes.last(), Some(&hash));
+        assert_eq!(
+            operation.get_hash(OPERATION_HASH_VERSION + 1, &Network::Alephzero),
+            None
+        );
+    }
+
+    #[test]
+    fn documents_without_a_hash_version_are_legacy() {
+        let operation = SubscanOperation {
+            hash: "hash".to_string(),
+            hash_version: 0,
+            block_number: 1,
+            extrinsic_index: "1-1".to_string(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+            operation_quantity: 1.5,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 3.0,
+            operation_type: OperationType::Stake,
+            from_wallet: "from".to_string(),
+            controller_wallet: "controller".to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        };
+        let mut document = bson::to_document(&operation).unwrap();
+        document.remove("hash_version");
+        document.remove("call_index");
+
+        let stored: SubscanOperation = bson::from_document(document).unwrap();
+        assert_eq!(stored.hash_version, 1);
+        assert_eq!(stored.call_index, 0);
+    }
+
+    #[test]
+    fn wallets_are_reencoded_for_consumers() {
+        let operation = SubscanOperation {
+            hash: "hash".to_string(),
+            hash_version: 0,
+            block_number: 1,
+            extrinsic_index: "1-1".to_string(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+            operation_quantity: 1.5,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 3.0,
+            operation_type: OperationType::Stake,
+            from_wallet: "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5".to_string(),
+            controller_wallet: EMPTY_ADDRESS.to_string(),
+            to_wallet: "exchange".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        };
+
+        let generic = operation
+            .clone()
+            .with_address_format(&Network::Polkadot, AddressFormat::Generic);
+        assert_eq!(
+            generic.from_wallet,
+            "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+        );
+        assert_eq!(generic.controller_wallet, EMPTY_ADDRESS);
+        assert_eq!(generic.to_wallet, "exchange");
+        assert_eq!(generic.hash, operation.hash);
+
+        let native = generic.with_address_format(&Network::Polkadot, AddressFormat::Native);
+        assert_eq!(native, operation);
+    }
+
+    #[test]
+    fn short_wallets_keep_both_ends() {
+        assert_eq!(
+            get_short_wallet("0xf24ff3a9cf04c71dbc94d0b566f7a27b94566cac"),
+            "0xf24f…6cac"
+        );
+        assert_eq!(get_short_wallet("exchange"), "exchange");
+        assert_eq!(get_short_wallet(EMPTY_ADDRESS), EMPTY_ADDRESS);
+    }
+
+    #[test]
+    fn event_params_decode_by_their_type() {
+        let amount = param("BalanceOf<T>", "1500000000000");
+        assert_eq!(
+            amount.as_balance(12),
+            Some(Decimal::from_str("1.5").unwrap())
+        );
+        assert_eq!(amount.as_u64(), None);
+        assert_eq!(amount.as_account(&Network::Alephzero), None);
+
+        let stash = param(
+            "T::AccountId",
+            "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d",
+        );
+        assert_eq!(
+            stash.as_account(&Network::Alephzero).as_deref(),
+            Some("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY")
+        );
+        assert_eq!(stash.as_balance(12), None);
+
+        let era = param("EraIndex", "1234");
+        assert_eq!(era.as_u64(), Some(1_234));
+        assert_eq!(era.as_balance(12), None);
+        assert_eq!(param("u32", "-1").as_u64(), None);
+    }
+
+    #[test]
+    fn single_validators_are_stored_as_before() {
+        let legacy = doc! {"nominator": "alice", "validator": "first", "valid_from": DateTime::MIN};
+        let validator: Validator = bson::from_document(legacy.clone()).unwrap();
+        assert_eq!(validator.validators, vec!["first"]);
+        assert_eq!(bson::to_document(&validator).unwrap(), {
+            let mut legacy = legacy;
+            legacy.insert("valid_to", Bson::Null);
+            legacy.insert("extrinsic_index", Bson::Null);
+            legacy
+        });
+
+        let multi_target = Validator {
+            validators: vec!["second".to_string(), "first".to_string()],
+            ..validator.clone()
+        };
+        let document = bson::to_document(&multi_target).unwrap();
+        assert_eq!(
+            document.get_array("validator").unwrap(),
+            &vec![Bson::from("second"), Bson::from("first")]
+        );
+        assert_eq!(
+            bson::from_document::<Validator>(document).unwrap(),
+            multi_target
+        );
+
+        assert!(!validator.has_same_validators(&multi_target));
+        let reordered = Validator {
+            validators: vec!["first".to_string(), "second".to_string()],
+            ..validator
+        };
+        assert!(reordered.has_same_validators(&multi_target));
+    }
 }
@@ -1,13 +1,49 @@
 use itertools::Itertools;
-use log::{error, info};
 use rs_subscan_parser::{
-    mongodb_client_identities::MongoDbClientIdentity, mongodb_client_subscan::MongoDbClientSubscan,
-    mongodb_client_validator::MongoDbClientValidator, subscan_stake_parser::parse_staking,
+    circuit_breaker::{CircuitBreaker, CircuitState},
+    compaction::compact_operations,
+    confirmations::{
+        get_min_confirmations, release_confirmed_operations, remove_released_operations,
+    },
+    data_quality::validate_operations,
+    feature_flags::FeatureFlags,
+    finalization::{check_finalization_periodically, set_operation_statuses},
+    head_watcher::{is_head_watcher_enabled, HeadWatcher},
+    materialized_views::recheck_totals,
+    metrics::{get_metrics_port, record_parsed_operations, serve_metrics},
+    mongodb_client_checkpoints::MongoDbClientCheckpoints,
+    mongodb_client_identities::MongoDbClientIdentity,
+    mongodb_client_latency_histograms::MongoDbClientLatencyHistograms,
+    mongodb_client_operation_latencies::MongoDbClientOperationLatencies,
+    mongodb_client_operation_summaries::MongoDbClientOperationSummaries,
+    mongodb_client_operation_totals::MongoDbClientOperationTotals,
+    mongodb_client_pending_operations::MongoDbClientPendingOperations,
+    mongodb_client_price_annotations::MongoDbClientPriceAnnotations,
+    mongodb_client_quarantine::MongoDbClientQuarantine,
+    mongodb_client_staking_flow::MongoDbClientStakingFlow,
+    mongodb_client_subscan::MongoDbClientSubscan,
+    mongodb_client_validator::MongoDbClientValidator,
+    mongodb_client_wallet_formats::MongoDbClientWalletFormats,
+    object_storage::ObjectStorageConfig,
+    operation_ids::set_operation_ids,
+    operations_watcher::{is_change_streams_enabled, process_stored_operations},
+    outbox::{is_outbox_enabled, write_operations_with_outbox},
+    preflight::preflight,
+    reconciliation::{get_reconciliation_wallets, take_balance_snapshots_periodically},
+    reports::{deliver_reports_periodically, get_scheduled_reports},
+    shadow::is_shadow_mode,
+    sinks::Sink,
+    spans::get_run_span,
+    subscan_parser::Network,
+    subscan_stake_parser::{parse_staking, StakingParserConfig},
     subscan_transfer_parser::parse_transfers,
+    sync_checkpoint::save_checkpoint,
+    volume_anomalies::detect_volume_anomalies_periodically,
 };
 use rs_utils::utils::logger::initialize_logger;
+use tracing::{error, info, info_span, Instrument};
 // use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
-use std::time::Duration;
+use std::{process, time::Duration};
 use tokio::time::sleep;
 
 #[tokio::main(worker_threads = 100)]
@@ -22,6 +58,15 @@ async fn main() {
     initialize_logger().expect("failed to initialize logging.");
 
     info!(target: "subscan_parser", "Started subscan parser worker.");
+    if is_shadow_mode() {
+        info!(target: "subscan_parser", "Shadow mode, writing to the shadow collections only.");
+    }
+    info!(target: "subscan_parser", "Experimental parsers enabled: {:?}.", FeatureFlags::global().get_enabled());
+
+    if let Err(e) = preflight(Network::from_env()).await {
+        error!(target: "subscan_parser", "Preflight check failed: {e}");
+        process::exit(1);
+    }
 
     start_worker().await;
 }
@@ -36,37 +81,174 @@ async fn start_worker() {
     let mut mongodb_client_identity = MongoDbClientIdentity::new().await;
     mongodb_client_identity.create_index().await;
 
+    let mut mongodb_client_staking_flow = MongoDbClientStakingFlow::new().await;
+    mongodb_client_staking_flow.create_index().await;
+
+    let mut mongodb_client_price_annotations = MongoDbClientPriceAnnotations::new().await;
+    mongodb_client_price_annotations.create_index().await;
+
+    let mut mongodb_client_operation_summaries = MongoDbClientOperationSummaries::new().await;
+    mongodb_client_operation_summaries.create_index().await;
+
+    let mut mongodb_client_operation_totals = MongoDbClientOperationTotals::new().await;
+    mongodb_client_operation_totals.create_index().await;
+
+    let mut mongodb_client_quarantine = MongoDbClientQuarantine::new().await;
+    mongodb_client_quarantine.create_index().await;
+
+    let mut mongodb_client_wallet_formats = MongoDbClientWalletFormats::new().await;
+    mongodb_client_wallet_formats.create_index().await;
+
+    let mut mongodb_client_operation_latencies = MongoDbClientOperationLatencies::new().await;
+    mongodb_client_operation_latencies.create_index().await;
+
+    let mut mongodb_client_latency_histograms = MongoDbClientLatencyHistograms::new().await;
+    mongodb_client_latency_histograms.create_index().await;
+
+    let mut mongodb_client_checkpoints = MongoDbClientCheckpoints::new().await;
+    mongodb_client_checkpoints.create_index().await;
+
+    if get_min_confirmations() > 0 {
+        let mut mongodb_client_pending_operations = MongoDbClientPendingOperations::new().await;
+        mongodb_client_pending_operations.create_index().await;
+    }
+
+    // hourly volumes come from the summaries, whichever process keeps them up to date, shadow
+    // runs leave the anomalies, the stats and the reports to production
+    let is_shadow_mode = is_shadow_mode();
+    if !is_shadow_mode {
+        tokio::spawn(detect_volume_anomalies_periodically());
+
+        if let Some(config) = ObjectStorageConfig::from_env() {
+            tokio::spawn(deliver_reports_periodically(
+                config,
+                get_scheduled_reports(),
+            ));
+        }
+        tokio::spawn(take_balance_snapshots_periodically(
+            get_reconciliation_wallets(),
+        ));
+    }
+
+    if let Some(port) = get_metrics_port() {
+        tokio::spawn(serve_metrics(port));
+    }
+
+    // stored operations turn final whichever run parsed them
+    tokio::spawn(check_finalization_periodically());
+
+    let mut head_watcher = if is_head_watcher_enabled() {
+        Some(HeadWatcher::new(Network::from_env()).await)
+    } else {
+        None
+    };
+
+    let staking_parser_config = StakingParserConfig::from_env();
     loop {
-        let subscan_operations_task = tokio::spawn(async move { parse_staking().await });
-        let subscan_transfers_task = tokio::spawn(async move { parse_transfers().await });
+        if !is_shadow_mode {
+            compact_operations().await;
+            recheck_totals().await;
+        }
+
+        // every log line of the pass carries its correlation id
+        let run_span = get_run_span(&staking_parser_config.network);
+        let enrich_span = info_span!(parent: &run_span, "enrich");
+        let store_span = info_span!(parent: &run_span, "store");
+
+        // staking and transfers share the task limit of the staking config
+        let config = staking_parser_config.clone();
+        let subscan_operations_task = tokio::spawn(
+            async move { parse_staking(&config).await }
+                .instrument(info_span!(parent: &run_span, "fetch", parser = "staking")),
+        );
+        let task_limit = staking_parser_config.task_limit.clone();
+        let subscan_transfers_task = tokio::spawn(
+            async move { parse_transfers(&task_limit).await }
+                .instrument(info_span!(parent: &run_span, "fetch", parser = "transfers")),
+        );
 
-        let subscan_operations = subscan_operations_task.await.ok();
-        let subscan_transfers = subscan_transfers_task.await.ok();
+        let (subscan_operations, checkpoint) = match subscan_operations_task.await.ok().flatten() {
+            Some((subscan_operations, checkpoint)) => (Some(subscan_operations), checkpoint),
+            None => (None, None),
+        };
+        let subscan_transfers = subscan_transfers_task.await.ok().flatten();
 
         let subscan_operations = vec![subscan_operations, subscan_transfers]
             .into_iter()
             .flatten()
             .flatten()
-            .flatten()
             .collect_vec();
+        record_parsed_operations(&subscan_operations);
+        let subscan_operations = validate_operations(subscan_operations)
+            .instrument(enrich_span.clone())
+            .await;
+
+        // held back until the head is MIN_CONFIRMATIONS blocks past them
+        let mut subscan_operations =
+            release_confirmed_operations(subscan_operations, &staking_parser_config.network)
+                .instrument(enrich_span.clone())
+                .await;
         if subscan_operations.is_empty() {
             error!(
-                target: "subscan_parser", "Nothing found",
+                target: "subscan_parser", parent: &run_span, "Nothing found",
             );
-            sleep(Duration::from_millis(1_000)).await;
+
+            let circuit_status = CircuitBreaker::global().get_status();
+            if circuit_status.state != CircuitState::Closed {
+                error!(target: "subscan_parser", parent: &run_span, "Subscan circuit {}: {} consecutive failures, opened {} times, retrying in {} ms.", circuit_status.state, circuit_status.consecutive_failures, circuit_status.times_opened, circuit_status.retry_in_ms);
+            }
+
+            // nothing to store, the walked extrinsics are done with
+            save_checkpoint(checkpoint.as_ref()).await;
+            wait_for_new_blocks(&mut head_watcher).await;
             continue;
         };
 
-        let subscan_operations_len = subscan_operations.len();
-        let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
-        mongodb_client_subscan
-            .import_subscan_operations(subscan_operations)
+        set_operation_statuses(&mut subscan_operations, &staking_parser_config.network)
+            .instrument(enrich_span)
             .await;
+        set_operation_ids(&mut subscan_operations);
+
+        let subscan_operations_len = subscan_operations.len();
+        let released_operations = subscan_operations.clone();
+        let mut stored_operations = Vec::new();
+        if is_outbox_enabled() {
+            stored_operations =
+                write_operations_with_outbox(subscan_operations, checkpoint.as_ref())
+                    .instrument(store_span)
+                    .await;
+        } else {
+            for sink in Sink::from_env() {
+                let stored = sink
+                    .write_operations(subscan_operations.clone())
+                    .instrument(store_span.clone())
+                    .await;
+                stored_operations.extend(stored);
+            }
+            save_checkpoint(checkpoint.as_ref()).await;
+        }
+
+        remove_released_operations(&released_operations).await;
+
+        // with change streams stats are built by the operations watcher from any writer
+        if !is_change_streams_enabled() {
+            process_stored_operations(&stored_operations).await;
+        }
 
         info!(
-            target: "subscan_parser", "Imported {} items",
+            target: "subscan_parser", parent: &run_span, "Imported {} items",
             subscan_operations_len,
         );
-        sleep(Duration::from_millis(1_000)).await;
+        wait_for_new_blocks(&mut head_watcher).await;
+    }
+}
+
+// without the head watcher the extrinsics are queried every second
+async fn wait_for_new_blocks(head_watcher: &mut Option<HeadWatcher>) {
+    match head_watcher {
+        Some(head_watcher) => {
+            head_watcher.wait_for_new_block().await;
+        }
+        None => sleep(Duration::from_millis(1_000)).await,
     }
 }
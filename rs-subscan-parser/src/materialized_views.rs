@@ -0,0 +1,276 @@
+use crate::{
+    mongodb_client_operation_summaries::MongoDbClientOperationSummaries,
+    mongodb_client_operation_totals::MongoDbClientOperationTotals,
+    mongodb_client_subscan::MongoDbClientSubscan, subscan_parser::SubscanParser, OperationSummary,
+    OperationTotals, OperationType, SubscanOperation, SummaryDirection, TotalsView,
+};
+use bson::DateTime;
+use itertools::Itertools;
+use std::collections::HashMap;
+use tracing::warn;
+
+// totals re-checked against stored operations per call
+static RECHECK_BATCH_SIZE: i64 = 10;
+
+fn is_staking_operation(operation_type: &OperationType) -> bool {
+    matches!(
+        operation_type,
+        OperationType::Stake
+            | OperationType::ReStake
+            | OperationType::RequestUnstake
+            | OperationType::WithdrawUnstaked
+    )
+}
+
+// must be called only with newly stored operations, duplicates would be counted twice
+pub async fn apply_operations(operations: &[SubscanOperation]) {
+    if operations.is_empty() {
+        return;
+    }
+
+    let mut mongodb_client_operation_totals = MongoDbClientOperationTotals::new().await;
+    mongodb_client_operation_totals
+        .increment_totals(group_totals(operations))
+        .await;
+}
+
+// recomputes the least recently checked totals from raw operations and compacted summaries
+pub async fn recheck_totals() {
+    let mut mongodb_client_operation_totals = MongoDbClientOperationTotals::new().await;
+    let keys = mongodb_client_operation_totals
+        .get_least_recently_checked(RECHECK_BATCH_SIZE)
+        .await
+        .into_iter()
+        .map(|t| (t.view, t.key))
+        .unique()
+        .collect_vec();
+
+    for (view, key) in keys {
+        recheck_key(&view, &key).await;
+    }
+}
+
+// also used to pick up manual corrections of stored operations right away
+pub async fn recheck_key(view: &TotalsView, key: &str) {
+    if SubscanParser::is_address_empty(key) {
+        return;
+    }
+
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let mut mongodb_client_operation_summaries = MongoDbClientOperationSummaries::new().await;
+    let mut mongodb_client_operation_totals = MongoDbClientOperationTotals::new().await;
+
+    let operations = match view {
+        TotalsView::Wallet => {
+            mongodb_client_subscan
+                .get_wallet_operations(key, None)
+                .await
+        }
+        TotalsView::Validator => mongodb_client_subscan.get_validator_operations(key).await,
+    };
+    let summaries = mongodb_client_operation_summaries
+        .get_wallet_summaries(key, 0, i64::MAX / 1000)
+        .await;
+    let mut expected = build_expected_totals(view, key, &operations, &summaries);
+
+    // operation types no longer backed by any operation drop to zero
+    let stored = mongodb_client_operation_totals.get_totals(view, key).await;
+    for s in &stored {
+        if !expected
+            .iter()
+            .any(|e| e.operation_type == s.operation_type)
+        {
+            expected.push(empty_totals(view, key, &s.operation_type));
+        }
+    }
+
+    let drifted = expected
+        .iter()
+        .filter(|e| {
+            !stored.iter().any(|s| {
+                s.operation_type == e.operation_type
+                    && s.count == e.count
+                    && is_close(s.quantity, e.quantity)
+                    && is_close(s.usd, e.usd)
+            })
+        })
+        .count();
+    if drifted > 0 {
+        warn!(target: "materialized_views", "Fixed {drifted} drifted {view} totals of {key}.");
+    }
+
+    mongodb_client_operation_totals
+        .replace_totals(expected, DateTime::now())
+        .await;
+}
+
+fn is_close(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-6 * a.abs().max(b.abs()).max(1.0)
+}
+
+fn empty_totals(view: &TotalsView, key: &str, operation_type: &OperationType) -> OperationTotals {
+    OperationTotals {
+        view: view.clone(),
+        key: key.to_string(),
+        operation_type: operation_type.clone(),
+        count: 0,
+        quantity: 0.0,
+        usd: 0.0,
+        checked_at: None,
+    }
+}
+
+pub fn group_totals(operations: &[SubscanOperation]) -> Vec<OperationTotals> {
+    let mut totals: HashMap<_, OperationTotals> = HashMap::new();
+
+    for operation in operations {
+        let mut views = vec![(TotalsView::Wallet, &operation.from_wallet)];
+        if is_staking_operation(&operation.operation_type) {
+            views.push((TotalsView::Validator, &operation.to_wallet));
+        }
+
+        for (view, key) in views {
+            if SubscanParser::is_address_empty(key) {
+                continue;
+            }
+
+            let t = totals
+                .entry((view.clone(), key.clone(), operation.operation_type.clone()))
+                .or_insert_with(|| empty_totals(&view, key, &operation.operation_type));
+            t.count += 1;
+            t.quantity += operation.operation_quantity;
+            t.usd += operation.operation_usd;
+        }
+    }
+
+    totals.into_values().collect()
+}
+
+// compacted operations only survive in summaries, so both are needed for the full history
+pub fn build_expected_totals(
+    view: &TotalsView,
+    key: &str,
+    operations: &[SubscanOperation],
+    summaries: &[OperationSummary],
+) -> Vec<OperationTotals> {
+    let mut totals = group_totals(operations)
+        .into_iter()
+        .filter(|t| &t.view == view && t.key == key)
+        .collect_vec();
+
+    for summary in summaries {
+        let included = match view {
+            TotalsView::Wallet => summary.direction == SummaryDirection::Out,
+            TotalsView::Validator => {
+                summary.direction == SummaryDirection::In
+                    && is_staking_operation(&summary.operation_type)
+            }
+        };
+        if !included || summary.wallet != key {
+            continue;
+        }
+
+        let position = totals
+            .iter()
+            .position(|t| t.operation_type == summary.operation_type);
+        let t = match position {
+            Some(position) => &mut totals[position],
+            None => {
+                totals.push(empty_totals(view, key, &summary.operation_type));
+                totals.last_mut().unwrap()
+            }
+        };
+        t.count += summary.count;
+        t.quantity += summary.quantity;
+        t.usd += summary.usd;
+    }
+
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        materialized_views::{build_expected_totals, group_totals},
+        subscan_parser::EMPTY_ADDRESS,
+        OperationStatus, OperationSummary, OperationType, SubscanOperation, SummaryDirection,
+        TotalsView,
+    };
+    use bson::DateTime;
+
+    fn operation(operation_type: OperationType, to_wallet: &str) -> SubscanOperation {
+        SubscanOperation {
+            hash: String::new(),
+            hash_version: 0,
+            block_number: 0,
+            extrinsic_index: String::new(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(0),
+            operation_quantity: 100.0,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 50.0,
+            operation_type,
+            from_wallet: "nominator".to_string(),
+            controller_wallet: EMPTY_ADDRESS.to_string(),
+            to_wallet: to_wallet.to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn validators_only_get_staking_operations() {
+        let operations = vec![
+            operation(OperationType::Stake, "validator"),
+            operation(OperationType::Stake, "validator"),
+            operation(OperationType::Transfer, "friend"),
+        ];
+
+        let totals = group_totals(&operations);
+        assert_eq!(totals.len(), 3);
+
+        let validator = totals
+            .iter()
+            .find(|t| t.view == TotalsView::Validator)
+            .unwrap();
+        assert_eq!(validator.key, "validator");
+        assert_eq!(validator.count, 2);
+        assert_eq!(validator.quantity, 200.0);
+        assert!(!totals.iter().any(|t| t.key == "friend"));
+    }
+
+    #[test]
+    fn expected_totals_include_compacted_summaries() {
+        let operations = vec![operation(OperationType::Stake, "validator")];
+        let summaries = vec![
+            OperationSummary {
+                wallet: "validator".to_string(),
+                hour: DateTime::from_millis(0),
+                operation_type: OperationType::Stake,
+                direction: SummaryDirection::In,
+                count: 3,
+                quantity: 300.0,
+                usd: 150.0,
+            },
+            OperationSummary {
+                wallet: "validator".to_string(),
+                hour: DateTime::from_millis(0),
+                operation_type: OperationType::Transfer,
+                direction: SummaryDirection::In,
+                count: 1,
+                quantity: 1.0,
+                usd: 1.0,
+            },
+        ];
+
+        let totals =
+            build_expected_totals(&TotalsView::Validator, "validator", &operations, &summaries);
+        assert_eq!(totals.len(), 1);
+        assert_eq!(totals[0].count, 4);
+        assert_eq!(totals[0].quantity, 400.0);
+        assert_eq!(totals[0].usd, 200.0);
+    }
+}
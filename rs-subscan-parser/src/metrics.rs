@@ -0,0 +1,319 @@
+use crate::SubscanOperation;
+use itertools::Itertools;
+use std::{
+    collections::BTreeMap,
+    env,
+    fmt::Write,
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter, IntoStaticStr};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{error, info};
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+// upper bounds of the histogram buckets in seconds, prometheus adds +Inf
+pub static DURATION_BUCKETS_SECONDS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+// requests of scrapers are a single line and a few headers
+static MAX_REQUEST_BYTES: usize = 8_192;
+static METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+#[derive(Clone, Copy, Debug, EnumIter, Display, IntoStaticStr, PartialEq, Eq, PartialOrd, Ord)]
+#[strum(serialize_all = "snake_case")]
+pub enum Metric {
+    SubscanRequestsTotal,
+    SubscanErrorsTotal,
+    SubscanRetriesTotal,
+    SubscanRequestDurationSeconds,
+    OperationsParsedTotal,
+    MongodbWriteDurationSeconds,
+}
+
+impl Metric {
+    pub fn get_help(&self) -> &'static str {
+        match self {
+            Metric::SubscanRequestsTotal => "Requests sent to subscan by endpoint.",
+            Metric::SubscanErrorsTotal => "Failed subscan requests by endpoint and error code.",
+            Metric::SubscanRetriesTotal => "Subscan requests sent again after a failure.",
+            Metric::SubscanRequestDurationSeconds => "Subscan response times by endpoint.",
+            Metric::OperationsParsedTotal => "Operations parsed by operation type.",
+            Metric::MongodbWriteDurationSeconds => "Mongodb write times by collection.",
+        }
+    }
+
+    pub fn is_histogram(&self) -> bool {
+        matches!(
+            self,
+            Metric::SubscanRequestDurationSeconds | Metric::MongodbWriteDurationSeconds
+        )
+    }
+}
+
+type Labels = Vec<(&'static str, String)>;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Histogram {
+    // observations by index in DURATION_BUCKETS_SECONDS, not cumulative
+    buckets: [u64; 12],
+    count: u64,
+    sum: f64,
+}
+
+// process wide counters and histograms, read by the /metrics listener
+#[derive(Debug, Default)]
+pub struct Metrics {
+    counters: Mutex<BTreeMap<(Metric, Labels), u64>>,
+    histograms: Mutex<BTreeMap<(Metric, Labels), Histogram>>,
+}
+
+impl Metrics {
+    pub fn global() -> &'static Metrics {
+        METRICS.get_or_init(Metrics::default)
+    }
+
+    pub fn increment(&self, metric: Metric, labels: &[(&'static str, &str)]) {
+        self.increment_by(metric, labels, 1);
+    }
+
+    pub fn increment_by(&self, metric: Metric, labels: &[(&'static str, &str)], value: u64) {
+        *self
+            .counters
+            .lock()
+            .unwrap()
+            .entry((metric, get_labels(labels)))
+            .or_default() += value;
+    }
+
+    pub fn observe(&self, metric: Metric, labels: &[(&'static str, &str)], duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let mut histograms = self.histograms.lock().unwrap();
+        let histogram = histograms.entry((metric, get_labels(labels))).or_default();
+        histogram.buckets[DURATION_BUCKETS_SECONDS.partition_point(|b| *b < seconds)] += 1;
+        histogram.count += 1;
+        histogram.sum += seconds;
+    }
+
+    // prometheus text format, every metric is listed even before its first sample
+    pub fn render(&self) -> String {
+        let counters = self.counters.lock().unwrap().clone();
+        let histograms = self.histograms.lock().unwrap().clone();
+
+        let mut text = String::new();
+        for metric in Metric::iter() {
+            let kind = if metric.is_histogram() {
+                "histogram"
+            } else {
+                "counter"
+            };
+            let _ = writeln!(text, "# HELP {metric} {}", metric.get_help());
+            let _ = writeln!(text, "# TYPE {metric} {kind}");
+
+            for ((_, labels), value) in counters.iter().filter(|((m, _), _)| *m == metric) {
+                let _ = writeln!(text, "{metric}{} {value}", format_labels(labels, None));
+            }
+
+            for ((_, labels), histogram) in histograms.iter().filter(|((m, _), _)| *m == metric) {
+                let mut cumulative = 0;
+                for (i, count) in histogram.buckets.iter().enumerate() {
+                    cumulative += count;
+                    let le = DURATION_BUCKETS_SECONDS
+                        .get(i)
+                        .map(|b| b.to_string())
+                        .unwrap_or("+Inf".to_string());
+                    let labels = format_labels(labels, Some(&le));
+                    let _ = writeln!(text, "{metric}_bucket{labels} {cumulative}");
+                }
+                let labels = format_labels(labels, None);
+                let _ = writeln!(text, "{metric}_sum{labels} {}", histogram.sum);
+                let _ = writeln!(text, "{metric}_count{labels} {}", histogram.count);
+            }
+        }
+
+        text
+    }
+}
+
+pub fn record_parsed_operations(operations: &[SubscanOperation]) {
+    let counts = operations
+        .iter()
+        .counts_by(|s| s.operation_type.to_string());
+    for (operation_type, count) in counts {
+        Metrics::global().increment_by(
+            Metric::OperationsParsedTotal,
+            &[("operation_type", &operation_type)],
+            count as u64,
+        );
+    }
+}
+
+fn get_labels(labels: &[(&'static str, &str)]) -> Labels {
+    labels.iter().map(|(k, v)| (*k, v.to_string())).collect()
+}
+
+fn format_labels(labels: &Labels, le: Option<&str>) -> String {
+    let values = labels
+        .iter()
+        .map(|(k, v)| (*k, v.as_str()))
+        .chain(le.map(|le| ("le", le)))
+        .map(|(k, v)| format!("{k}=\"{}\"", escape_label_value(v)))
+        .collect::<Vec<_>>();
+    if values.is_empty() {
+        return String::new();
+    }
+
+    format!("{{{}}}", values.join(","))
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// METRICS_PORT, metrics are only kept in memory if not set
+pub fn get_metrics_port() -> Option<u16> {
+    env::var("METRICS_PORT")
+        .ok()
+        .and_then(|v| v.parse::<u16>().ok())
+        .filter(|v| *v > 0)
+}
+
+// answers GET /metrics, anything else is a 404
+pub async fn serve_metrics(port: u16) {
+    let listener = match TcpListener::bind(("0.0.0.0", port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!(target: "metrics", "Failed to listen on port {port}: {e}.");
+            return;
+        }
+    };
+    info!(target: "metrics", "Serving metrics on port {port}.");
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(respond(stream));
+            }
+            Err(e) => error!(target: "metrics", "Accept error: {e}."),
+        }
+    }
+}
+
+async fn respond(mut stream: TcpStream) {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1_024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < MAX_REQUEST_BYTES {
+        match stream.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => request.extend_from_slice(&buffer[..n]),
+        }
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let response = if is_metrics_request(&request) {
+        get_response("200 OK", &Metrics::global().render())
+    } else {
+        get_response("404 Not Found", "")
+    };
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+fn is_metrics_request(request: &str) -> bool {
+    let mut parts = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let (method, path) = (parts.next(), parts.next().unwrap_or_default());
+
+    method == Some("GET") && path.split('?').next() == Some("/metrics")
+}
+
+fn get_response(status: &str, body: &str) -> String {
+    let headers = [
+        format!("HTTP/1.1 {status}"),
+        format!("Content-Type: {METRICS_CONTENT_TYPE}"),
+        format!("Content-Length: {}", body.len()),
+        "Connection: close".to_string(),
+    ];
+
+    format!("{}\r\n\r\n{body}", headers.join("\r\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::{is_metrics_request, Metric, Metrics};
+    use std::time::Duration;
+
+    #[test]
+    fn metrics_render_in_prometheus_text_format() {
+        let metrics = Metrics::default();
+        metrics.increment(Metric::SubscanRequestsTotal, &[("endpoint", "extrinsics")]);
+        metrics.increment_by(
+            Metric::SubscanRequestsTotal,
+            &[("endpoint", "extrinsics")],
+            2,
+        );
+        metrics.increment(
+            Metric::SubscanErrorsTotal,
+            &[("endpoint", "events"), ("code", "say \"hi\"")],
+        );
+        metrics.observe(
+            Metric::MongodbWriteDurationSeconds,
+            &[("collection", "subscan")],
+            Duration::from_millis(20),
+        );
+        metrics.observe(
+            Metric::MongodbWriteDurationSeconds,
+            &[("collection", "subscan")],
+            Duration::from_secs(20),
+        );
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE subscan_requests_total counter\n"));
+        assert!(text.contains("subscan_requests_total{endpoint=\"extrinsics\"} 3\n"));
+        assert!(
+            text.contains("subscan_errors_total{endpoint=\"events\",code=\"say \\\"hi\\\"\"} 1\n")
+        );
+
+        // buckets are cumulative, the slow write is only in +Inf
+        assert!(text.contains("# TYPE mongodb_write_duration_seconds histogram\n"));
+        assert!(text.contains(
+            "mongodb_write_duration_seconds_bucket{collection=\"subscan\",le=\"0.01\"} 0\n"
+        ));
+        assert!(text.contains(
+            "mongodb_write_duration_seconds_bucket{collection=\"subscan\",le=\"0.025\"} 1\n"
+        ));
+        assert!(text.contains(
+            "mongodb_write_duration_seconds_bucket{collection=\"subscan\",le=\"10\"} 1\n"
+        ));
+        assert!(text.contains(
+            "mongodb_write_duration_seconds_bucket{collection=\"subscan\",le=\"+Inf\"} 2\n"
+        ));
+        assert!(text.contains("mongodb_write_duration_seconds_count{collection=\"subscan\"} 2\n"));
+
+        // metrics without samples are listed still
+        assert!(text.contains("# TYPE operations_parsed_total counter\n"));
+    }
+
+    #[test]
+    fn only_metrics_path_is_served() {
+        assert!(is_metrics_request(
+            "GET /metrics HTTP/1.1\r\nHost: a\r\n\r\n"
+        ));
+        assert!(is_metrics_request("GET /metrics?x=1 HTTP/1.1\r\n\r\n"));
+        assert!(!is_metrics_request("POST /metrics HTTP/1.1\r\n\r\n"));
+        assert!(!is_metrics_request("GET / HTTP/1.1\r\n\r\n"));
+        assert!(!is_metrics_request(""));
+    }
+}
@@ -0,0 +1,460 @@
+use crate::{
+    address,
+    amount::Balance,
+    exports::precision::to_planck,
+    subscan_parser::{Network, EMPTY_ADDRESS},
+    subscan_scheduler::SubscanEndpoint,
+    ExtrinsicsType, OperationStatus, OperationType, SubscanOperation,
+};
+use bson::DateTime;
+use chrono::Utc;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rs_exchanges_parser::ExchangesWallets;
+use serde_json::{json, Value};
+use sp_core::crypto::AccountId32;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+
+pub static MOCK_AZERO_USD_PRICE: f64 = 1.25;
+pub static MOCK_SLOT_SECONDS: i64 = 30;
+static MOCK_NOMINATORS: u64 = 50;
+static MOCK_VALIDATORS: u64 = 10;
+static MOCK_BATCH_ALL_INDEX: u64 = 99;
+static MOCK_TRANSFER_INDEX: u64 = 100;
+static MOCK_REWARD_SLASH_INDEX: u64 = 101;
+static MOCK_ERA_SLOTS: i64 = 2_880;
+static MIN_MOCK_AZERO: f64 = 50.0;
+static MAX_MOCK_AZERO: f64 = 200_000.0;
+static MIN_MOCK_FEE_PLANCK: u64 = 1_000_000_000;
+static MAX_MOCK_FEE_PLANCK: u64 = 50_000_000_000;
+static MOCK_NOMINATION_AGE_SLOTS: i64 = 10_000;
+
+// the newest blocks stay pending for a while
+static MOCK_FINALITY_LAG_SLOTS: i64 = 2;
+
+// staking extrinsic generated for a slot, everything derives from (slot, index) only
+struct MockStaking {
+    nominator: u64,
+    controller: u64,
+    validator: u64,
+    planck: u128,
+}
+
+impl MockStaking {
+    fn new(slot: i64, index: u64) -> Self {
+        let mut rng = get_rng(slot, index);
+
+        Self {
+            nominator: rng.gen_range(0..MOCK_NOMINATORS),
+            controller: rng.gen_range(0..MOCK_NOMINATORS),
+            validator: rng.gen_range(0..MOCK_VALIDATORS),
+            planck: to_planck(get_amount(&mut rng), Network::Mock.get_decimals()),
+        }
+    }
+}
+
+// answers subscan requests of the mock network the same way the real api is shaped
+pub fn respond(endpoint: SubscanEndpoint, payload: &Value) -> Value {
+    let data = match endpoint {
+        SubscanEndpoint::Extrinsics => get_extrinsics(payload),
+        SubscanEndpoint::ExtrinsicDetail => get_extrinsic_detail(payload),
+        SubscanEndpoint::Transfers => get_transfers(payload),
+        SubscanEndpoint::Events => json!([]),
+        SubscanEndpoint::RewardSlash => get_reward_slash(payload),
+        SubscanEndpoint::Account => get_account_balance(payload),
+        SubscanEndpoint::Metadata => {
+            let slot = get_current_slot();
+            json!({
+                "blockNum": slot.to_string(),
+                "finalized_blockNum": (slot - MOCK_FINALITY_LAG_SLOTS).to_string(),
+            })
+        }
+    };
+
+    json!({"code": 0, "message": "Success", "data": data})
+}
+
+// stored operation generated directly, for load tests which skip the parsing
+pub fn get_synthetic_operation(i: u64, operation_timestamp: DateTime) -> SubscanOperation {
+    let mut rng = StdRng::seed_from_u64(i);
+    let operation_type = OperationType::iter()
+        .nth(rng.gen_range(0..OperationType::iter().count()))
+        .unwrap_or_default();
+    let operation_quantity = get_amount(&mut rng);
+    let to_wallet = match operation_type {
+        OperationType::Stake | OperationType::ReStake | OperationType::RequestUnstake => {
+            get_address(&get_account("validator", rng.gen_range(0..MOCK_VALIDATORS)))
+        }
+        OperationType::WithdrawUnstaked => EMPTY_ADDRESS.to_string(),
+        _ => get_address(&get_account("nominator", rng.gen_range(0..MOCK_NOMINATORS))),
+    };
+
+    let mut operation = SubscanOperation {
+        hash: String::new(),
+        hash_version: 0,
+        block_number: i,
+        extrinsic_index: format!("synthetic-{i}"),
+        call_index: 0,
+        operation_timestamp,
+        operation_quantity,
+        operation_planck: Some(Balance(to_planck(
+            operation_quantity,
+            Network::Mock.get_decimals(),
+        ))),
+        operation_fee: None,
+        operation_era: None,
+        operation_usd: operation_quantity * MOCK_AZERO_USD_PRICE,
+        operation_type,
+        from_wallet: get_address(&get_account("nominator", rng.gen_range(0..MOCK_NOMINATORS))),
+        controller_wallet: EMPTY_ADDRESS.to_string(),
+        to_wallet,
+        status: OperationStatus::Finalized,
+        operation_id: None,
+        nomination_targets: Vec::new(),
+    };
+    operation.set_hash(&Network::Mock);
+
+    operation
+}
+
+fn get_extrinsics(payload: &Value) -> Value {
+    let address = payload
+        .get("address")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    let module = payload
+        .get("module")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let call = payload.get("call").and_then(|v| v.as_str()).unwrap_or("");
+
+    let extrinsics = match (module.as_str(), address.is_empty()) {
+        ("identity", _) => get_identity(address),
+        ("utility", true) => get_head_slots(payload)
+            .into_iter()
+            .map(get_batch_all)
+            .collect(),
+        ("staking", true) => {
+            let Ok(extrinsics_type) = ExtrinsicsType::from_str(call) else {
+                return json!({"extrinsics": []});
+            };
+            get_head_slots(payload)
+                .into_iter()
+                .map(|slot| get_staking(slot, &extrinsics_type))
+                .collect()
+        }
+        ("staking", false) if call == ExtrinsicsType::Nominate.to_string() => {
+            vec![get_nomination(address)]
+        }
+        _ => Vec::new(),
+    };
+
+    json!({ "extrinsics": extrinsics })
+}
+
+fn get_staking(slot: i64, extrinsics_type: &ExtrinsicsType) -> Value {
+    let index = get_extrinsics_type_index(extrinsics_type);
+    let staking = MockStaking::new(slot, index);
+
+    let params = match extrinsics_type {
+        ExtrinsicsType::Bond => json!([
+            {"name": "controller", "value": {"Id": get_hex(&get_account("nominator", staking.controller))}},
+            {"name": "value", "value": staking.planck.to_string()},
+        ]),
+        ExtrinsicsType::Nominate => json!([
+            {"name": "targets", "value": [{"Id": get_hex(&get_account("validator", staking.validator))}]},
+        ]),
+        _ => json!([
+            {"name": "value", "value": staking.planck.to_string()},
+        ]),
+    };
+
+    get_extrinsic(
+        slot,
+        index,
+        &get_address(&get_account("nominator", staking.nominator)),
+        params,
+    )
+}
+
+fn get_batch_all(slot: i64) -> Value {
+    let staking = MockStaking::new(slot, MOCK_BATCH_ALL_INDEX);
+    let params = json!([{"name": "calls", "value": [
+        {"call_name": "bond_extra", "params": [
+            {"name": "max_additional", "value": staking.planck.to_string()},
+        ]},
+        {"call_name": "nominate", "params": [
+            {"name": "targets", "value": [{"Id": get_hex(&get_account("validator", staking.validator))}]},
+        ]},
+    ]}]);
+
+    get_extrinsic(
+        slot,
+        MOCK_BATCH_ALL_INDEX,
+        &get_address(&get_account("nominator", staking.nominator)),
+        params,
+    )
+}
+
+// nominators looked up by address get a validator derived from the address itself
+fn get_nomination(address: &str) -> Value {
+    let seed = u64::from_str_radix(&sha256::digest(address)[..16], 16).unwrap_or_default();
+    let validator = StdRng::seed_from_u64(seed).gen_range(0..MOCK_VALIDATORS);
+    let params = json!([
+        {"name": "targets", "value": [{"Id": get_hex(&get_account("validator", validator))}]},
+    ]);
+
+    let slot = get_current_slot() - MOCK_NOMINATION_AGE_SLOTS;
+    let index = get_extrinsics_type_index(&ExtrinsicsType::Nominate);
+    get_extrinsic(slot, index, address, params)
+}
+
+fn get_extrinsic(slot: i64, index: u64, account_id: &str, params: Value) -> Value {
+    json!({
+        "success": true,
+        "block_timestamp": slot * MOCK_SLOT_SECONDS,
+        "block_num": slot,
+        "account_id": account_id,
+        "extrinsic_index": format!("{slot}-{index}"),
+        "params": params.to_string(),
+    })
+}
+
+fn get_extrinsic_detail(payload: &Value) -> Value {
+    let extrinsic_index = payload
+        .get("extrinsic_index")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    let Some((slot, index)) = extrinsic_index.split_once('-') else {
+        return json!({"event": []});
+    };
+    let (Ok(slot), Ok(index)) = (slot.parse::<i64>(), index.parse::<u64>()) else {
+        return json!({"event": []});
+    };
+
+    // nominations carry no staking event, same as on the real chain
+    let nominate_index = get_extrinsics_type_index(&ExtrinsicsType::Nominate);
+    if index == nominate_index || index >= ExtrinsicsType::iter().count() as u64 {
+        return json!({"event": []});
+    }
+
+    let event_id = match ExtrinsicsType::iter().nth(index as usize) {
+        Some(ExtrinsicsType::Unbond) => "Unbonded",
+        Some(ExtrinsicsType::WithdrawUnbonded) => "Withdrawn",
+        _ => "Bonded",
+    };
+
+    let staking = MockStaking::new(slot, index);
+    let params = json!([
+        {"type_name": "AccountId", "name": "stash", "value": get_hex(&get_account("nominator", staking.nominator))},
+        {"type_name": "Balance", "name": "amount", "value": staking.planck.to_string()},
+    ]);
+
+    json!({"event": [{
+        "module_id": "staking",
+        "event_id": event_id,
+        "event_index": format!("{slot}-{index}"),
+        "params": params.to_string(),
+    }]})
+}
+
+fn get_transfers(payload: &Value) -> Value {
+    let transfers = get_head_slots(payload)
+        .into_iter()
+        .map(|slot| {
+            let mut rng = get_rng(slot, MOCK_TRANSFER_INDEX);
+            let from = get_address(&get_account("nominator", rng.gen_range(0..MOCK_NOMINATORS)));
+            let to = get_address(&get_account("nominator", rng.gen_range(0..MOCK_NOMINATORS)));
+
+            // some transfers go through exchanges so deposit/withdraw alerts fire as well
+            let exchange = ExchangesWallets::iter()
+                .nth(rng.gen_range(0..ExchangesWallets::iter().count()))
+                .unwrap_or_default()
+                .to_string();
+            let (from, to) = match rng.gen_range(0..10) {
+                0 => (from, exchange),
+                1 => (exchange, to),
+                _ => (from, to),
+            };
+
+            json!({
+                "success": true,
+                "block_timestamp": slot * MOCK_SLOT_SECONDS,
+                "block_num": slot,
+                "extrinsic_index": format!("{slot}-{MOCK_TRANSFER_INDEX}"),
+                "from": from,
+                "to": to,
+                "amount": format!("{:.4}", get_amount(&mut rng)),
+                "fee": rng.gen_range(MIN_MOCK_FEE_PLANCK..MAX_MOCK_FEE_PLANCK).to_string(),
+                "from_account_display": get_account_display(&from),
+                "to_account_display": get_account_display(&to),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({ "transfers": transfers })
+}
+
+// rewards are a small share of a stake, about one record in ten is a slash
+fn get_reward_slash(payload: &Value) -> Value {
+    let address = payload
+        .get("address")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    if address.is_empty() {
+        return json!({"list": null, "count": 0});
+    }
+
+    let list = get_head_slots(payload)
+        .into_iter()
+        .map(|slot| {
+            let mut rng = get_rng(slot, MOCK_REWARD_SLASH_INDEX);
+            let event_id = if rng.gen_range(0..10) == 0 {
+                "Slashed"
+            } else {
+                "Rewarded"
+            };
+            let amount = get_amount(&mut rng) / 1_000.0;
+
+            json!({
+                "account": address,
+                "amount": to_planck(amount, Network::Mock.get_decimals()).to_string(),
+                "block_num": slot,
+                "block_timestamp": slot * MOCK_SLOT_SECONDS,
+                "era": slot / MOCK_ERA_SLOTS,
+                "event_id": event_id,
+                "event_index": format!("{slot}-{MOCK_REWARD_SLASH_INDEX}"),
+                "extrinsic_index": "",
+                "validator_stash": get_address(&get_account("validator", rng.gen_range(0..MOCK_VALIDATORS))),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({"count": list.len(), "list": list})
+}
+
+// balances stay put, the mock network doesn't keep track of what its operations move
+fn get_account_balance(payload: &Value) -> Value {
+    let address = payload.get("key").and_then(|v| v.as_str()).unwrap_or("");
+    let seed = address
+        .bytes()
+        .fold(0u64, |seed, b| seed.wrapping_mul(31).wrapping_add(b as u64));
+    let mut rng = StdRng::seed_from_u64(seed);
+    let balance = get_amount(&mut rng);
+    let bonded = balance * rng.gen_range(0.0..1.0);
+
+    json!({
+        "account": {
+            "address": address,
+            "balance": format!("{balance:.6}"),
+            "reserved": "0",
+            "bonded": format!("{bonded:.6}"),
+        }
+    })
+}
+
+fn get_identity(address: &str) -> Vec<Value> {
+    let display = get_account_display(address);
+    if display.get("display").is_none() {
+        return Vec::new();
+    }
+
+    vec![json!({
+        "success": true,
+        "account_display": {
+            "address": address,
+            "display": display.get("display"),
+            "identity": true,
+        },
+    })]
+}
+
+fn get_account_display(address: &str) -> Value {
+    let pools = [
+        ("nominator", "Mock Nominator", MOCK_NOMINATORS),
+        ("validator", "Mock Validator", MOCK_VALIDATORS),
+    ];
+    for (kind, name, len) in pools {
+        if let Some(i) = (0..len).find(|i| get_address(&get_account(kind, *i)) == address) {
+            return json!({"address": address, "display": format!("{name} {i}")});
+        }
+    }
+
+    json!({ "address": address })
+}
+
+// newest slots first, as subscan lists them
+fn get_head_slots(payload: &Value) -> Vec<i64> {
+    let row = payload.get("row").and_then(|v| v.as_i64()).unwrap_or(10);
+    let page = payload.get("page").and_then(|v| v.as_i64()).unwrap_or(0);
+    let head = get_current_slot() - page * row;
+
+    (0..row).map(|i| head - i).filter(|s| *s >= 0).collect()
+}
+
+fn get_current_slot() -> i64 {
+    Utc::now().timestamp() / MOCK_SLOT_SECONDS
+}
+
+fn get_extrinsics_type_index(extrinsics_type: &ExtrinsicsType) -> u64 {
+    ExtrinsicsType::iter()
+        .position(|e| &e == extrinsics_type)
+        .unwrap_or_default() as u64
+}
+
+fn get_rng(slot: i64, index: u64) -> StdRng {
+    StdRng::seed_from_u64((slot as u64).wrapping_mul(1_000).wrapping_add(index))
+}
+
+// log-uniform, so most operations are small and a few are whales
+fn get_amount(rng: &mut StdRng) -> f64 {
+    rng.gen_range(MIN_MOCK_AZERO.ln()..MAX_MOCK_AZERO.ln())
+        .exp()
+}
+
+fn get_account(kind: &str, i: u64) -> [u8; 32] {
+    let digest = hex::decode(sha256::digest(format!("mock_{kind}_{i}"))).unwrap_or_default();
+    digest.try_into().unwrap_or_default()
+}
+
+fn get_address(account: &[u8; 32]) -> String {
+    address::from_account_id(&Network::Mock, &AccountId32::from(*account))
+}
+
+fn get_hex(account: &[u8; 32]) -> String {
+    format!("0x{}", hex::encode(account))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        event_param::ParamValue,
+        mock_network::{get_staking, respond},
+        subscan_parser::Network,
+        subscan_scheduler::SubscanEndpoint,
+        ExtrinsicsType,
+    };
+    use serde_json::{json, Value};
+
+    #[test]
+    fn mock_extrinsic_detail_matches_extrinsic() {
+        let extrinsic = get_staking(1_000, &ExtrinsicsType::Bond);
+        assert_eq!(extrinsic, get_staking(1_000, &ExtrinsicsType::Bond));
+
+        let detail = respond(
+            SubscanEndpoint::ExtrinsicDetail,
+            &json!({"extrinsic_index": extrinsic["extrinsic_index"]}),
+        );
+        let event = &detail["data"]["event"][0];
+        assert_eq!(event["module_id"], "staking");
+        assert_eq!(event["event_id"], "Bonded");
+
+        let params: Value = serde_json::from_str(event["params"].as_str().unwrap()).unwrap();
+        let stash = ParamValue::decode("AccountId", &params[0]["value"]);
+        assert_eq!(
+            stash.as_account(&Network::Mock),
+            extrinsic["account_id"].as_str().map(String::from)
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use crate::{shadow::get_collection_name, BackfillProgress};
+use bson::doc;
+use mongodb::{
+    options::{IndexOptions, UpdateOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientBackfillProgress {
+    pub client_backfill_progress: MongoDbClient<BackfillProgress>,
+}
+
+impl MongoDbClientBackfillProgress {
+    pub async fn new() -> MongoDbClientBackfillProgress {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &get_collection_name("MONGODB_COLLECTION_BACKFILL_PROGRESS");
+        let client_name = "mongodb_backfill_progress";
+        let client_backfill_progress = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_backfill_progress,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"network": 1u32})
+            .options(options)
+            .build();
+        self.client_backfill_progress
+            .create_index(model, None)
+            .await;
+    }
+
+    pub async fn get_progress(&mut self, network: &str) -> Option<BackfillProgress> {
+        self.client_backfill_progress
+            .find_one(doc! {"network": network}, None)
+            .await
+    }
+
+    pub async fn save_progress(&mut self, progress: &BackfillProgress) {
+        let query = doc! {"network": &progress.network};
+        let update = doc! {
+            "$set": {
+                "next_page": progress.next_page as i64,
+                "total_pages": progress.total_pages as i64,
+                "is_done": progress.is_done,
+                "updated_at": progress.updated_at,
+            }
+        };
+        let options = Some(UpdateOptions::builder().upsert(true).build());
+        self.client_backfill_progress
+            .update_one(query, update, options)
+            .await;
+    }
+}
@@ -0,0 +1,72 @@
+use crate::BalanceSnapshot;
+use bson::{doc, DateTime};
+use mongodb::{
+    options::{FindOptions, IndexOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientBalanceSnapshots {
+    pub client_balance_snapshots: MongoDbClient<BalanceSnapshot>,
+}
+
+impl MongoDbClientBalanceSnapshots {
+    pub async fn new() -> MongoDbClientBalanceSnapshots {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_BALANCE_SNAPSHOTS").unwrap();
+        let client_name = "mongodb_balance_snapshots";
+        let client_balance_snapshots = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_balance_snapshots,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"wallet": 1u32, "timestamp": 1u32})
+            .options(options)
+            .build();
+        self.client_balance_snapshots
+            .create_index(model, None)
+            .await;
+    }
+
+    pub async fn import_snapshots(&mut self, snapshots: Vec<BalanceSnapshot>) {
+        self.client_balance_snapshots
+            .insert_many(&snapshots, None)
+            .await;
+    }
+
+    // oldest first
+    pub async fn get_snapshots(
+        &mut self,
+        wallet: &str,
+        from: DateTime,
+        to: DateTime,
+    ) -> Vec<BalanceSnapshot> {
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"timestamp": 1i32})
+                .build(),
+        );
+        let query = doc! {
+            "wallet": wallet,
+            "timestamp": {"$gte": from, "$lte": to},
+        };
+
+        self.client_balance_snapshots.find(query, options).await
+    }
+
+    pub async fn get_wallets(&mut self) -> Vec<String> {
+        self.client_balance_snapshots
+            .distinct("wallet")
+            .await
+            .into_iter()
+            .filter_map(|w| w.as_str().map(String::from))
+            .collect()
+    }
+}
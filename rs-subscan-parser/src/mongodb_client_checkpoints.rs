@@ -0,0 +1,61 @@
+use crate::{shadow::get_collection_name, SyncCheckpoint};
+use bson::{doc, Document};
+use mongodb::{
+    options::{IndexOptions, UpdateOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+// query and upsert of a checkpoint, shared with the outbox transaction
+pub fn get_checkpoint_update(checkpoint: &SyncCheckpoint) -> (Document, Document) {
+    let query = doc! {"network": &checkpoint.network};
+    let update = doc! {
+        "$set": {
+            "block_number": checkpoint.block_number as i64,
+            "extrinsic_index": &checkpoint.extrinsic_index,
+            "updated_at": checkpoint.updated_at,
+        }
+    };
+
+    (query, update)
+}
+
+pub struct MongoDbClientCheckpoints {
+    pub client_checkpoints: MongoDbClient<SyncCheckpoint>,
+}
+
+impl MongoDbClientCheckpoints {
+    pub async fn new() -> MongoDbClientCheckpoints {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &get_collection_name("MONGODB_COLLECTION_CHECKPOINTS");
+        let client_name = "mongodb_checkpoints";
+        let client_checkpoints = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self { client_checkpoints }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"network": 1u32})
+            .options(options)
+            .build();
+        self.client_checkpoints.create_index(model, None).await;
+    }
+
+    pub async fn get_checkpoint(&mut self, network: &str) -> Option<SyncCheckpoint> {
+        self.client_checkpoints
+            .find_one(doc! {"network": network}, None)
+            .await
+    }
+
+    pub async fn save_checkpoint(&mut self, checkpoint: &SyncCheckpoint) {
+        let (query, update) = get_checkpoint_update(checkpoint);
+        let options = Some(UpdateOptions::builder().upsert(true).build());
+        self.client_checkpoints
+            .update_one(query, update, options)
+            .await;
+    }
+}
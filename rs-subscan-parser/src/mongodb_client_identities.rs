@@ -67,6 +67,33 @@ impl MongoDbClientIdentity {
         self.client_identity.find_one(query, None).await
     }
 
+    pub async fn get_addresses(&mut self) -> Vec<String> {
+        self.client_identity
+            .distinct("address")
+            .await
+            .into_iter()
+            .filter_map(|a| a.as_str().map(|a| a.to_string()))
+            .collect()
+    }
+
+    // an identity already stored under the canonical address wins over the alternate one
+    pub async fn replace_address(&mut self, alternate: &str, canonical: &str) {
+        if self.get_identity_by_address(canonical).await.is_some() {
+            self.client_identity
+                .delete_one(doc! { "address": alternate }, None)
+                .await;
+            return;
+        }
+
+        self.client_identity
+            .update_one(
+                doc! { "address": alternate },
+                doc! { "$set": { "address": canonical }},
+                None,
+            )
+            .await;
+    }
+
     pub async fn get_not_existing_addresses(&mut self, addresses: Vec<String>) -> Vec<String> {
         if addresses.is_empty() {
             return Vec::new();
@@ -0,0 +1,78 @@
+use crate::{latency::LatencyHistogram, sinks::Sink};
+use bson::{doc, DateTime};
+use mongodb::{
+    options::{FindOptions, IndexOptions, UpdateOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientLatencyHistograms {
+    pub client_latency_histograms: MongoDbClient<LatencyHistogram>,
+}
+
+impl MongoDbClientLatencyHistograms {
+    pub async fn new() -> MongoDbClientLatencyHistograms {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_LATENCY_HISTOGRAMS").unwrap();
+        let client_name = "mongodb_latency_histograms";
+        let client_latency_histograms = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_latency_histograms,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"sink": 1u32, "hour": 1u32})
+            .options(options)
+            .build();
+        self.client_latency_histograms
+            .create_index(model, None)
+            .await;
+    }
+
+    pub async fn increment_histogram(&mut self, histogram: LatencyHistogram) {
+        let mut inc = doc! {
+            "count": histogram.count as i64,
+            "sum_ms": histogram.sum_ms,
+        };
+        for (i, count) in histogram.buckets {
+            inc.insert(format!("buckets.{i}"), count as i64);
+        }
+
+        let options = Some(UpdateOptions::builder().upsert(true).build());
+        self.client_latency_histograms
+            .update_one(
+                doc! {
+                    "sink": bson::to_bson(&histogram.sink).unwrap_or_default(),
+                    "hour": histogram.hour,
+                },
+                doc! { "$inc": inc },
+                options,
+            )
+            .await;
+    }
+
+    // hours from..to, both included
+    pub async fn get_histograms(
+        &mut self,
+        sink: &Sink,
+        from: DateTime,
+        to: DateTime,
+    ) -> Vec<LatencyHistogram> {
+        let options = Some(FindOptions::builder().sort(doc! {"hour": 1i32}).build());
+        let query = doc! {
+            "sink": bson::to_bson(sink).unwrap_or_default(),
+            "hour": {
+                "$gte": from,
+                "$lte": to,
+            },
+        };
+
+        self.client_latency_histograms.find(query, options).await
+    }
+}
@@ -0,0 +1,86 @@
+use crate::OperationAnnotation;
+use bson::{doc, Bson};
+use mongodb::{options::FindOptions, IndexModel};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientOperationAnnotations {
+    pub client_operation_annotations: MongoDbClient<OperationAnnotation>,
+}
+
+impl MongoDbClientOperationAnnotations {
+    pub async fn new() -> MongoDbClientOperationAnnotations {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_OPERATION_ANNOTATIONS").unwrap();
+        let client_name = "mongodb_operation_annotations";
+        let client_operation_annotations = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_operation_annotations,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let model = IndexModel::builder()
+            .keys(doc! {"hash": 1u32})
+            .options(None)
+            .build();
+        self.client_operation_annotations
+            .create_index(model, None)
+            .await;
+    }
+
+    pub async fn import_annotation(&mut self, annotation: OperationAnnotation) {
+        self.client_operation_annotations
+            .insert_one(annotation, None)
+            .await;
+    }
+
+    pub async fn get_annotations(&mut self, hashes: Vec<String>) -> Vec<OperationAnnotation> {
+        if hashes.is_empty() {
+            return Vec::new();
+        }
+
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"created_at": 1i32})
+                .build(),
+        );
+        let query = doc! {
+            "hash": {
+                "$in": hashes
+            }
+        };
+
+        self.client_operation_annotations.find(query, options).await
+    }
+
+    pub async fn get_annotations_page(
+        &mut self,
+        hash: &str,
+        after: Option<Vec<Bson>>,
+        limit: i64,
+    ) -> Vec<OperationAnnotation> {
+        let sort = doc! {"created_at": 1i32, "text": 1i32};
+        self.client_operation_annotations
+            .find_page(doc! {"hash": hash}, sort, after, limit, None)
+            .await
+    }
+
+    pub fn get_page_key(annotation: &OperationAnnotation) -> Vec<Bson> {
+        vec![
+            Bson::DateTime(annotation.created_at),
+            Bson::String(annotation.text.clone()),
+        ]
+    }
+
+    pub async fn get_suppressed_hashes(&mut self, hashes: Vec<String>) -> Vec<String> {
+        self.get_annotations(hashes)
+            .await
+            .into_iter()
+            .filter(|a| a.suppress_alerts)
+            .map(|a| a.hash)
+            .collect()
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{latency::OperationLatency, sinks::Sink, SubscanOperation};
+use bson::{doc, DateTime};
+use mongodb::{
+    options::{IndexOptions, UpdateOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientOperationLatencies {
+    pub client_operation_latencies: MongoDbClient<OperationLatency>,
+}
+
+impl MongoDbClientOperationLatencies {
+    pub async fn new() -> MongoDbClientOperationLatencies {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_OPERATION_LATENCIES").unwrap();
+        let client_name = "mongodb_operation_latencies";
+        let client_operation_latencies = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_operation_latencies,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"hash": 1u32})
+            .options(options)
+            .build();
+        self.client_operation_latencies
+            .create_index(model, None)
+            .await;
+    }
+
+    // redeliveries of the outbox keep the first delivery
+    pub async fn set_delivered_at(
+        &mut self,
+        sink: &Sink,
+        operations: &[SubscanOperation],
+        delivered_at: DateTime,
+    ) {
+        let mut min = doc! {};
+        min.insert(format!("delivered_at.{sink}"), delivered_at);
+
+        for s in operations {
+            let options = Some(UpdateOptions::builder().upsert(true).build());
+            self.client_operation_latencies
+                .update_one(
+                    doc! {"hash": &s.hash},
+                    doc! {
+                        "$min": min.clone(),
+                        "$setOnInsert": { "operation_timestamp": s.operation_timestamp },
+                    },
+                    options,
+                )
+                .await;
+        }
+    }
+
+    pub async fn get_latency(&mut self, hash: &str) -> Option<OperationLatency> {
+        self.client_operation_latencies
+            .find_one(doc! {"hash": hash}, None)
+            .await
+    }
+}
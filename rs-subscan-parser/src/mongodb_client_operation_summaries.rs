@@ -0,0 +1,225 @@
+use crate::{HourlyVolume, OperationSummary, OperationTypeVolume, SummaryDirection};
+use bson::{doc, Bson, DateTime, Document};
+use mongodb::{
+    options::{FindOptions, IndexOptions, UpdateOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientOperationSummaries {
+    pub client_operation_summaries: MongoDbClient<OperationSummary>,
+}
+
+impl MongoDbClientOperationSummaries {
+    pub async fn new() -> MongoDbClientOperationSummaries {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_OPERATION_SUMMARIES").unwrap();
+        let client_name = "mongodb_operation_summaries";
+        let client_operation_summaries = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_operation_summaries,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"wallet": 1u32, "hour": 1u32, "operation_type": 1u32, "direction": 1u32})
+            .options(options)
+            .build();
+        self.client_operation_summaries
+            .create_index(model, None)
+            .await;
+
+        let model = IndexModel::builder()
+            .keys(doc! {"hour": 1u32})
+            .options(None)
+            .build();
+        self.client_operation_summaries
+            .create_index(model, None)
+            .await;
+    }
+
+    // every operation is counted once, on the sending side
+    pub async fn get_hourly_volumes(&mut self, from: DateTime, to: DateTime) -> Vec<HourlyVolume> {
+        let pipeline = vec![
+            doc! {"$match": {
+                "hour": {"$gte": from, "$lt": to},
+                "direction": SummaryDirection::Out.to_string(),
+            }},
+            doc! {"$group": {
+                "_id": {"operation_type": "$operation_type", "hour": "$hour"},
+                "count": {"$sum": "$count"},
+            }},
+            doc! {"$project": {
+                "_id": 0i32,
+                "operation_type": "$_id.operation_type",
+                "hour": "$_id.hour",
+                "count": 1i32,
+            }},
+        ];
+
+        self.client_operation_summaries
+            .aggregate(pipeline)
+            .await
+            .into_iter()
+            .filter_map(|d| bson::from_document(d).ok())
+            .collect()
+    }
+
+    // totals per operation type, counted on the sending side as well
+    pub async fn get_operation_type_volumes(
+        &mut self,
+        from: DateTime,
+        to: DateTime,
+    ) -> Vec<OperationTypeVolume> {
+        let pipeline = vec![
+            doc! {"$match": {
+                "hour": {"$gte": from, "$lt": to},
+                "direction": SummaryDirection::Out.to_string(),
+            }},
+            doc! {"$group": {
+                "_id": "$operation_type",
+                "count": {"$sum": "$count"},
+                "quantity": {"$sum": "$quantity"},
+                "usd": {"$sum": "$usd"},
+            }},
+            doc! {"$project": {
+                "_id": 0i32,
+                "operation_type": "$_id",
+                "count": 1i32,
+                "quantity": 1i32,
+                "usd": 1i32,
+            }},
+            doc! {"$sort": {"operation_type": 1i32}},
+        ];
+
+        self.client_operation_summaries
+            .aggregate(pipeline)
+            .await
+            .into_iter()
+            .filter_map(|d| bson::from_document(d).ok())
+            .collect()
+    }
+
+    pub async fn import_or_update_summaries(&mut self, summaries: Vec<OperationSummary>) {
+        for summary in summaries {
+            let options = Some(UpdateOptions::builder().upsert(true).build());
+            self.client_operation_summaries
+                .update_one(
+                    doc! {
+                        "wallet": summary.wallet,
+                        "hour": summary.hour,
+                        "operation_type": summary.operation_type.to_string(),
+                        "direction": summary.direction.to_string(),
+                    },
+                    doc! { "$set": {
+                        "count": summary.count as i64,
+                        "quantity": summary.quantity,
+                        "usd": summary.usd,
+                    }},
+                    options,
+                )
+                .await;
+        }
+    }
+
+    pub async fn get_wallet_summaries_page(
+        &mut self,
+        wallet: &str,
+        from_timestamp: i64,
+        to_timestamp: i64,
+        after: Option<Vec<Bson>>,
+        limit: i64,
+    ) -> Vec<OperationSummary> {
+        let sort = doc! {"hour": 1i32, "operation_type": 1i32, "direction": 1i32};
+        self.client_operation_summaries
+            .find_page(
+                get_wallet_query(wallet, from_timestamp, to_timestamp),
+                sort,
+                after,
+                limit,
+                None,
+            )
+            .await
+    }
+
+    pub fn get_page_key(summary: &OperationSummary) -> Vec<Bson> {
+        vec![
+            Bson::DateTime(summary.hour),
+            bson::to_bson(&summary.operation_type).unwrap_or_default(),
+            bson::to_bson(&summary.direction).unwrap_or_default(),
+        ]
+    }
+
+    pub async fn get_wallet_summaries(
+        &mut self,
+        wallet: &str,
+        from_timestamp: i64,
+        to_timestamp: i64,
+    ) -> Vec<OperationSummary> {
+        let options = Some(FindOptions::builder().sort(doc! {"hour": 1i32}).build());
+        let query = get_wallet_query(wallet, from_timestamp, to_timestamp);
+
+        self.client_operation_summaries.find(query, options).await
+    }
+
+    pub async fn get_wallets(&mut self) -> Vec<String> {
+        self.client_operation_summaries
+            .distinct("wallet")
+            .await
+            .into_iter()
+            .filter_map(|w| w.as_str().map(|w| w.to_string()))
+            .collect()
+    }
+
+    // summaries of both encodings for the same hour are added up
+    pub async fn replace_wallet(&mut self, alternate: &str, canonical: &str) -> u64 {
+        let summaries = self
+            .get_wallet_summaries(alternate, 0, i64::MAX / 1000)
+            .await;
+        let options = Some(UpdateOptions::builder().upsert(true).build());
+        for summary in summaries.iter() {
+            let key = doc! {
+                "hour": summary.hour,
+                "operation_type": summary.operation_type.to_string(),
+                "direction": summary.direction.to_string(),
+            };
+
+            let mut canonical_key = key.clone();
+            canonical_key.insert("wallet", canonical);
+            self.client_operation_summaries
+                .update_one(
+                    canonical_key,
+                    doc! { "$inc": {
+                        "count": summary.count as i64,
+                        "quantity": summary.quantity,
+                        "usd": summary.usd,
+                    }},
+                    options.clone(),
+                )
+                .await;
+
+            let mut alternate_key = key;
+            alternate_key.insert("wallet", alternate);
+            self.client_operation_summaries
+                .delete_one(alternate_key, None)
+                .await;
+        }
+
+        summaries.len() as u64
+    }
+}
+
+fn get_wallet_query(wallet: &str, from_timestamp: i64, to_timestamp: i64) -> Document {
+    doc! {
+        "wallet": wallet,
+        "hour": {
+            "$gte": DateTime::from_millis(from_timestamp * 1000),
+            "$lt": DateTime::from_millis(to_timestamp * 1000),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{OperationTotals, OperationType, TotalsView};
+use bson::{doc, Bson, DateTime};
+use mongodb::{
+    options::{FindOptions, IndexOptions, UpdateOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientOperationTotals {
+    pub client_operation_totals: MongoDbClient<OperationTotals>,
+}
+
+impl MongoDbClientOperationTotals {
+    pub async fn new() -> MongoDbClientOperationTotals {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_OPERATION_TOTALS").unwrap();
+        let client_name = "mongodb_operation_totals";
+        let client_operation_totals = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_operation_totals,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"view": 1u32, "key": 1u32, "operation_type": 1u32})
+            .options(options)
+            .build();
+        self.client_operation_totals.create_index(model, None).await;
+
+        let model = IndexModel::builder()
+            .keys(doc! {"checked_at": 1u32})
+            .options(None)
+            .build();
+        self.client_operation_totals.create_index(model, None).await;
+    }
+
+    pub async fn increment_totals(&mut self, totals: Vec<OperationTotals>) {
+        for t in totals {
+            let options = Some(UpdateOptions::builder().upsert(true).build());
+            self.client_operation_totals
+                .update_one(
+                    doc! {
+                        "view": t.view.to_string(),
+                        "key": t.key,
+                        "operation_type": t.operation_type.to_string(),
+                    },
+                    doc! { "$inc": {
+                        "count": t.count as i64,
+                        "quantity": t.quantity,
+                        "usd": t.usd,
+                    }},
+                    options,
+                )
+                .await;
+        }
+    }
+
+    pub async fn replace_totals(&mut self, totals: Vec<OperationTotals>, checked_at: DateTime) {
+        for t in totals {
+            let options = Some(UpdateOptions::builder().upsert(true).build());
+            self.client_operation_totals
+                .update_one(
+                    doc! {
+                        "view": t.view.to_string(),
+                        "key": t.key,
+                        "operation_type": t.operation_type.to_string(),
+                    },
+                    doc! { "$set": {
+                        "count": t.count as i64,
+                        "quantity": t.quantity,
+                        "usd": t.usd,
+                        "checked_at": checked_at,
+                    }},
+                    options,
+                )
+                .await;
+        }
+    }
+
+    pub async fn get_totals(&mut self, view: &TotalsView, key: &str) -> Vec<OperationTotals> {
+        let query = doc! {
+            "view": view.to_string(),
+            "key": key,
+        };
+
+        self.client_operation_totals.find(query, None).await
+    }
+
+    // totals of the operation types for many keys, one query for all of them
+    pub async fn get_totals_of_keys(
+        &mut self,
+        view: &TotalsView,
+        keys: Vec<String>,
+        operation_types: &[OperationType],
+    ) -> Vec<OperationTotals> {
+        if keys.is_empty() {
+            return Vec::new();
+        }
+
+        let query = doc! {
+            "view": view.to_string(),
+            "key": { "$in": keys },
+            "operation_type": {
+                "$in": operation_types.iter().map(|t| t.to_string()).collect::<Vec<_>>()
+            },
+        };
+
+        self.client_operation_totals.find(query, None).await
+    }
+
+    pub async fn get_totals_page(
+        &mut self,
+        view: &TotalsView,
+        key: &str,
+        after: Option<Vec<Bson>>,
+        limit: i64,
+    ) -> Vec<OperationTotals> {
+        let query = doc! {
+            "view": view.to_string(),
+            "key": key,
+        };
+
+        self.client_operation_totals
+            .find_page(query, doc! {"operation_type": 1i32}, after, limit, None)
+            .await
+    }
+
+    pub fn get_page_key(totals: &OperationTotals) -> Vec<Bson> {
+        vec![bson::to_bson(&totals.operation_type).unwrap_or_default()]
+    }
+
+    pub async fn delete_totals(&mut self, key: &str) -> u64 {
+        self.client_operation_totals
+            .delete_many(doc! {"key": key}, None)
+            .await
+            .deleted_count
+    }
+
+    // never checked totals come first
+    pub async fn get_least_recently_checked(&mut self, limit: i64) -> Vec<OperationTotals> {
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"checked_at": 1i32})
+                .limit(limit)
+                .build(),
+        );
+
+        self.client_operation_totals.find(doc! {}, options).await
+    }
+}
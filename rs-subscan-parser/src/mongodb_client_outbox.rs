@@ -0,0 +1,149 @@
+use crate::{
+    mongodb_client_checkpoints::get_checkpoint_update, outbox::OutboxEntry, sinks::Sink,
+    SubscanOperation, SyncCheckpoint,
+};
+use bson::{doc, oid::ObjectId, DateTime};
+use mongodb::{
+    options::{FindOptions, IndexOptions, UpdateOptions},
+    Collection, IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::{env, time::Duration};
+use tokio::time::sleep;
+use tracing::error;
+
+static DELAY_MS: u64 = 100;
+
+pub struct MongoDbClientOutbox {
+    pub client_outbox: MongoDbClient<OutboxEntry>,
+
+    // same client as the outbox, a transaction cannot span clients
+    pub col_subscan: Collection<SubscanOperation>,
+}
+
+impl MongoDbClientOutbox {
+    pub async fn new() -> MongoDbClientOutbox {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_OUTBOX").unwrap();
+        let col_subscan = &env::var("MONGODB_COLLECTION_SUBSCAN").unwrap();
+        let client_name = "mongodb_outbox";
+        let client_outbox = MongoDbClient::new(uri, client_name, db, col).await;
+        let col_subscan = client_outbox.db.collection(col_subscan);
+
+        Self {
+            client_outbox,
+            col_subscan,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let model = IndexModel::builder()
+            .keys(doc! {"created_at": 1u32})
+            .options(IndexOptions::builder().unique(false).build())
+            .build();
+        self.client_outbox.create_index(model, None).await;
+    }
+
+    // operations, their deliveries and the checkpoint of the run are committed together or not
+    // at all, returns the operations that were not stored before
+    pub async fn import_with_outbox(
+        &mut self,
+        operations: &[SubscanOperation],
+        sinks: &[Sink],
+        checkpoint: Option<&SyncCheckpoint>,
+    ) -> Vec<SubscanOperation> {
+        loop {
+            let res = self
+                .try_import_with_outbox(operations, sinks, checkpoint)
+                .await;
+            if let Err(e) = res {
+                error!(target: "mongodb_client_outbox", client = %self.client_outbox.client_name, "import_with_outbox error: {e}; Sleeping {DELAY_MS} ms.");
+
+                sleep(Duration::from_millis(DELAY_MS)).await;
+                continue;
+            }
+
+            return res.unwrap();
+        }
+    }
+
+    async fn try_import_with_outbox(
+        &mut self,
+        operations: &[SubscanOperation],
+        sinks: &[Sink],
+        checkpoint: Option<&SyncCheckpoint>,
+    ) -> mongodb::error::Result<Vec<SubscanOperation>> {
+        let mut session = self.client_outbox.client.start_session(None).await?;
+        session.start_transaction(None).await?;
+
+        // duplicate key errors would abort the transaction, known hashes are skipped by upserts
+        let options = UpdateOptions::builder().upsert(true).build();
+        let mut stored = Vec::new();
+        for operation in operations {
+            let res = self
+                .col_subscan
+                .update_one_with_session(
+                    doc! {"hash": &operation.hash},
+                    doc! {"$setOnInsert": bson::to_document(operation)?},
+                    options.clone(),
+                    &mut session,
+                )
+                .await?;
+            if res.upserted_id.is_some() {
+                stored.push(operation.clone());
+            }
+        }
+
+        let created_at = DateTime::now();
+        let entries = stored
+            .iter()
+            .flat_map(|operation| {
+                sinks.iter().map(|sink| OutboxEntry {
+                    id: ObjectId::new(),
+                    sink: sink.clone(),
+                    operation: operation.clone(),
+                    created_at,
+                })
+            })
+            .collect::<Vec<_>>();
+        if !entries.is_empty() {
+            self.client_outbox
+                .col
+                .insert_many_with_session(&entries, None, &mut session)
+                .await?;
+        }
+
+        if let Some(checkpoint) = checkpoint {
+            let col = &env::var("MONGODB_COLLECTION_CHECKPOINTS").unwrap();
+            let (query, update) = get_checkpoint_update(checkpoint);
+            self.client_outbox
+                .db
+                .collection::<SyncCheckpoint>(col)
+                .update_one_with_session(query, update, options.clone(), &mut session)
+                .await?;
+        }
+
+        session.commit_transaction().await?;
+
+        Ok(stored)
+    }
+
+    // oldest deliveries first
+    pub async fn get_pending_entries(&mut self, limit: i64) -> Vec<OutboxEntry> {
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"created_at": 1i32, "_id": 1i32})
+                .limit(limit)
+                .build(),
+        );
+
+        self.client_outbox.find(doc! {}, options).await
+    }
+
+    pub async fn delete_entries(&mut self, ids: Vec<ObjectId>) {
+        self.client_outbox
+            .delete_many(doc! {"_id": {"$in": ids}}, None)
+            .await;
+    }
+}
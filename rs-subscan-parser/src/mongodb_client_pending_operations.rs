@@ -0,0 +1,83 @@
+use crate::{shadow::get_collection_name, SubscanOperation};
+use bson::doc;
+use mongodb::{
+    options::{FindOptions, IndexOptions, UpdateOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientPendingOperations {
+    pub client_pending_operations: MongoDbClient<SubscanOperation>,
+}
+
+impl MongoDbClientPendingOperations {
+    pub async fn new() -> MongoDbClientPendingOperations {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &get_collection_name("MONGODB_COLLECTION_PENDING_OPERATIONS");
+        let client_name = "mongodb_pending_operations";
+        let client_pending_operations = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_pending_operations,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"hash": 1u32})
+            .options(options)
+            .build();
+        self.client_pending_operations
+            .create_index(model, None)
+            .await;
+
+        let options = IndexOptions::builder().unique(false).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"block_number": 1u32})
+            .options(options)
+            .build();
+        self.client_pending_operations
+            .create_index(model, None)
+            .await;
+    }
+
+    // operations parsed again while they wait keep their first copy
+    pub async fn import_operations(&mut self, operations: &[SubscanOperation]) {
+        for s in operations {
+            let Ok(operation) = bson::to_document(s) else {
+                continue;
+            };
+
+            let options = Some(UpdateOptions::builder().upsert(true).build());
+            self.client_pending_operations
+                .update_one(
+                    doc! {"hash": &s.hash},
+                    doc! {"$setOnInsert": operation},
+                    options,
+                )
+                .await;
+        }
+    }
+
+    // oldest block first
+    pub async fn get_operations_up_to(&mut self, max_block_number: u64) -> Vec<SubscanOperation> {
+        let options = FindOptions::builder()
+            .sort(doc! {"block_number": 1i32})
+            .build();
+        self.client_pending_operations
+            .find(
+                doc! {"block_number": {"$lte": max_block_number as i64}},
+                Some(options),
+            )
+            .await
+    }
+
+    pub async fn delete_operations(&mut self, hashes: Vec<String>) {
+        self.client_pending_operations
+            .delete_many(doc! {"hash": {"$in": hashes}}, None)
+            .await;
+    }
+}
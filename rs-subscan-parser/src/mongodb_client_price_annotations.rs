@@ -0,0 +1,76 @@
+use crate::PriceAnnotation;
+use bson::doc;
+use mongodb::{
+    options::{IndexOptions, UpdateOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientPriceAnnotations {
+    pub client_price_annotations: MongoDbClient<PriceAnnotation>,
+}
+
+impl MongoDbClientPriceAnnotations {
+    pub async fn new() -> MongoDbClientPriceAnnotations {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_PRICE_ANNOTATIONS").unwrap();
+        let client_name = "mongodb_price_annotations";
+        let client_price_annotations = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_price_annotations,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"hash": 1u32})
+            .options(options)
+            .build();
+        self.client_price_annotations
+            .create_index(model, None)
+            .await;
+    }
+
+    pub async fn import_or_update_annotation(&mut self, annotation: PriceAnnotation) {
+        let options = Some(UpdateOptions::builder().upsert(true).build());
+        self.client_price_annotations
+            .update_one(
+                doc! { "hash": annotation.hash },
+                doc! { "$set": {
+                    "operation_timestamp": annotation.operation_timestamp,
+                    "price_before_1h": annotation.price_before_1h,
+                    "price_at": annotation.price_at,
+                    "price_after_1h": annotation.price_after_1h,
+                    "price_after_24h": annotation.price_after_24h,
+                }},
+                options,
+            )
+            .await;
+    }
+
+    pub async fn get_annotation(&mut self, hash: &str) -> Option<PriceAnnotation> {
+        let query = doc! {
+            "hash": hash,
+        };
+
+        self.client_price_annotations.find_one(query, None).await
+    }
+
+    pub async fn get_annotations(&mut self, hashes: Vec<String>) -> Vec<PriceAnnotation> {
+        if hashes.is_empty() {
+            return Vec::new();
+        }
+
+        let query = doc! {
+            "hash": {
+                "$in": hashes
+            }
+        };
+
+        self.client_price_annotations.find(query, None).await
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{
+    data_quality::QuarantinedRecord, mongodb_client_subscan::RECORDS_TTL_SECONDS,
+    shadow::get_collection_name,
+};
+use bson::doc;
+use mongodb::{options::IndexOptions, IndexModel};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::{env, time::Duration};
+
+pub struct MongoDbClientQuarantine {
+    pub client_quarantine: MongoDbClient<QuarantinedRecord>,
+}
+
+impl MongoDbClientQuarantine {
+    pub async fn new() -> MongoDbClientQuarantine {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &get_collection_name("MONGODB_COLLECTION_QUARANTINE");
+        let client_name = "mongodb_quarantine";
+        let client_quarantine = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self { client_quarantine }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder()
+            .unique(false)
+            .expire_after(Duration::from_secs(RECORDS_TTL_SECONDS))
+            .build();
+        let model = IndexModel::builder()
+            .keys(doc! {"quarantined_at": 1u32})
+            .options(options)
+            .build();
+        self.client_quarantine.create_index(model, None).await;
+
+        let model = IndexModel::builder()
+            .keys(doc! {"source": 1u32})
+            .options(None)
+            .build();
+        self.client_quarantine.create_index(model, None).await;
+    }
+
+    pub async fn import_records(&mut self, records: Vec<QuarantinedRecord>) {
+        self.client_quarantine.insert_many(&records, None).await;
+    }
+}
@@ -0,0 +1,94 @@
+use crate::StakingFlowCandle;
+use bson::{doc, Bson, DateTime};
+use mongodb::{
+    options::{FindOneOptions, IndexOptions, UpdateOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientStakingFlow {
+    pub client_staking_flow: MongoDbClient<StakingFlowCandle>,
+}
+
+impl MongoDbClientStakingFlow {
+    pub async fn new() -> MongoDbClientStakingFlow {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_STAKING_FLOW").unwrap();
+        let client_name = "mongodb_staking_flow";
+        let client_staking_flow = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_staking_flow,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"validator": 1u32, "day": 1u32})
+            .options(options)
+            .build();
+        self.client_staking_flow.create_index(model, None).await;
+    }
+
+    pub async fn import_or_update_candles(&mut self, candles: Vec<StakingFlowCandle>) {
+        for candle in candles {
+            let options = Some(UpdateOptions::builder().upsert(true).build());
+            self.client_staking_flow
+                .update_one(
+                    doc! { "validator": candle.validator, "day": candle.day },
+                    doc! { "$set": {
+                        "open": candle.open,
+                        "high": candle.high,
+                        "low": candle.low,
+                        "close": candle.close,
+                        "inflow": candle.inflow,
+                        "outflow": candle.outflow,
+                    }},
+                    options,
+                )
+                .await;
+        }
+    }
+
+    pub async fn get_last_candle_before(
+        &mut self,
+        validator: &str,
+        day: DateTime,
+    ) -> Option<StakingFlowCandle> {
+        let options = Some(FindOneOptions::builder().sort(doc! {"day": -1i32}).build());
+        let query = doc! {
+            "validator": validator,
+            "day": { "$lt": day },
+        };
+
+        self.client_staking_flow.find_one(query, options).await
+    }
+
+    pub async fn get_candles(
+        &mut self,
+        validator: &str,
+        from_timestamp: i64,
+        to_timestamp: i64,
+        after: Option<Vec<Bson>>,
+        limit: i64,
+    ) -> Vec<StakingFlowCandle> {
+        let query = doc! {
+            "validator": validator,
+            "day": {
+                "$gte": DateTime::from_millis(from_timestamp * 1000),
+                "$lt": DateTime::from_millis(to_timestamp * 1000),
+            }
+        };
+
+        self.client_staking_flow
+            .find_page(query, doc! {"day": 1i32}, after, limit, None)
+            .await
+    }
+
+    pub fn get_page_key(candle: &StakingFlowCandle) -> Vec<Bson> {
+        vec![Bson::DateTime(candle.day)]
+    }
+}
@@ -1,14 +1,52 @@
-use crate::SubscanOperation;
-use bson::{doc, DateTime};
+use crate::{
+    metrics::{Metric, Metrics},
+    shadow::get_collection_name,
+    subscan_parser::Network,
+    OperationStatus, OperationType, StoredOperation, SubscanOperation, Validator,
+};
+use bson::{doc, oid::ObjectId, Bson, DateTime, Document};
 use chrono::Utc;
 use mongodb::{
-    options::{FindOptions, IndexOptions},
+    change_stream::{
+        event::{ChangeStreamEvent, ResumeToken},
+        ChangeStream,
+    },
+    options::{
+        ChangeStreamOptions, Collation, FindOneOptions, FindOptions, FullDocumentType,
+        IndexOptions, InsertManyOptions,
+    },
     IndexModel,
 };
 use rs_utils::clients::mongodb_client::MongoDbClient;
-use std::{env, time::Duration};
+use std::{
+    collections::HashSet,
+    env,
+    time::{Duration, Instant},
+};
 
-static RECORDS_TTL_SECONDS: u64 = 90 * 24 * 60 * 60;
+pub static RECORDS_TTL_SECONDS: u64 = 90 * 24 * 60 * 60;
+
+// extrinsic indexes like "42-10" must sort after "42-3"
+fn get_page_collation() -> Collation {
+    Collation::builder()
+        .locale("en")
+        .numeric_ordering(true)
+        .build()
+}
+
+fn get_page_sort() -> Document {
+    doc! {"block_number": 1i32, "extrinsic_index": 1i32, "hash": 1i32}
+}
+
+// newest first, operations of the same block in reverse extrinsic order
+fn get_timeline_sort() -> Document {
+    doc! {
+        "operation_timestamp": -1i32,
+        "block_number": -1i32,
+        "extrinsic_index": -1i32,
+        "hash": -1i32,
+    }
+}
 
 pub struct MongoDbClientSubscan {
     pub client_subscan: MongoDbClient<SubscanOperation>,
@@ -18,7 +56,7 @@ impl MongoDbClientSubscan {
     pub async fn new() -> MongoDbClientSubscan {
         let uri = &env::var("MONGODB_URI").unwrap();
         let db = &env::var("MONGODB_DATABASE").unwrap();
-        let col = &env::var("MONGODB_COLLECTION_SUBSCAN").unwrap();
+        let col = &get_collection_name("MONGODB_COLLECTION_SUBSCAN");
         let client_name = "mongodb_subscan";
         let client_subscan = MongoDbClient::new(uri, client_name, db, col).await;
 
@@ -46,6 +84,7 @@ impl MongoDbClientSubscan {
         let indexes = vec![
             "operation_type",
             "from_wallet",
+            "controller_wallet",
             "to_wallet",
             "extrinsic_index",
         ];
@@ -56,14 +95,164 @@ impl MongoDbClientSubscan {
                 .build();
             self.client_subscan.create_index(model, None).await;
         }
+
+        let options = IndexOptions::builder()
+            .collation(get_page_collation())
+            .build();
+        let model = IndexModel::builder()
+            .keys(doc! {"block_number": 1u32, "extrinsic_index": 1u32, "hash": 1u32})
+            .options(options)
+            .build();
+        self.client_subscan.create_index(model, None).await;
+
+        let model = IndexModel::builder()
+            .keys(doc! {"status": 1u32, "block_number": 1u32})
+            .options(None)
+            .build();
+        self.client_subscan.create_index(model, None).await;
+    }
+
+    // one page of matching operations after the given page key in block order
+    pub async fn get_operations_page(
+        &mut self,
+        query: Document,
+        after: Option<Vec<Bson>>,
+        limit: i64,
+    ) -> Vec<SubscanOperation> {
+        self.client_subscan
+            .find_page(
+                query,
+                get_page_sort(),
+                after,
+                limit,
+                Some(get_page_collation()),
+            )
+            .await
+    }
+
+    pub fn get_page_key(operation: &SubscanOperation) -> Vec<Bson> {
+        vec![
+            Bson::Int64(operation.block_number as i64),
+            Bson::String(operation.extrinsic_index.clone()),
+            Bson::String(operation.hash.clone()),
+        ]
+    }
+
+    // one page of operations the wallet took part in, in any role, newest first
+    pub async fn get_timeline_page(
+        &mut self,
+        wallet: &str,
+        after: Option<Vec<Bson>>,
+        limit: i64,
+    ) -> Vec<SubscanOperation> {
+        self.client_subscan
+            .find_page(
+                Self::get_timeline_query(wallet),
+                get_timeline_sort(),
+                after,
+                limit,
+                Some(get_page_collation()),
+            )
+            .await
+    }
+
+    pub fn get_timeline_key(operation: &SubscanOperation) -> Vec<Bson> {
+        vec![
+            Bson::DateTime(operation.operation_timestamp),
+            Bson::Int64(operation.block_number as i64),
+            Bson::String(operation.extrinsic_index.clone()),
+            Bson::String(operation.hash.clone()),
+        ]
+    }
+
+    // rewards are sent to the wallet by its validator, slashes are taken from it
+    pub fn get_timeline_query(wallet: &str) -> Document {
+        doc! {
+            "$or": [
+                {"from_wallet": wallet},
+                {"controller_wallet": wallet},
+                {"to_wallet": wallet},
+            ]
+        }
+    }
+
+    pub fn get_time_range_query(from_timestamp: i64, to_timestamp: Option<i64>) -> Document {
+        let to_timestamp = to_timestamp.unwrap_or(Utc::now().timestamp());
+        doc! {
+            "operation_timestamp": {
+                "$gte": DateTime::from_millis(from_timestamp * 1000),
+                "$lt": DateTime::from_millis(to_timestamp * 1000),
+            }
+        }
     }
 
-    pub async fn import_subscan_operations(&mut self, subscan: Vec<SubscanOperation>) {
-        for doc in subscan {
-            self.client_subscan.insert_one(doc, None).await;
+    // operations of the extrinsics which started a version of the nominations, batch_all calls as
+    // well as nominate ones, legacy versions have no operation
+    pub fn get_nominations_query(history: &[Validator]) -> Document {
+        let extrinsic_indexes = history
+            .iter()
+            .filter_map(|v| v.extrinsic_index.clone())
+            .collect::<Vec<_>>();
+        doc! {
+            "extrinsic_index": {"$in": extrinsic_indexes},
         }
     }
 
+    pub fn get_wallet_query(wallet: &str, operation_type: Option<OperationType>) -> Document {
+        let mut query = doc! {
+            "from_wallet": wallet,
+        };
+        if let Some(operation_type) = operation_type {
+            query.insert("operation_type", operation_type.to_string());
+        }
+
+        query
+    }
+
+    // returns the operations that were not stored before, under the current hash version or
+    // an older one, the unique index only catches the current one
+    pub async fn import_subscan_operations(
+        &mut self,
+        subscan: Vec<SubscanOperation>,
+    ) -> Vec<SubscanOperation> {
+        let network = Network::from_env();
+        let hashes = subscan
+            .iter()
+            .flat_map(|s| s.get_hashes(&network))
+            .collect::<Vec<_>>();
+        let stored = self
+            .client_subscan
+            .distinct_huge(doc! {"hash": {"$in": hashes}}, "hash")
+            .await
+            .into_iter()
+            .collect::<HashSet<_>>();
+        let subscan = subscan
+            .into_iter()
+            .filter(|s| !s.get_hashes(&network).iter().any(|h| stored.contains(h)))
+            .collect::<Vec<_>>();
+
+        let options = Some(InsertManyOptions::builder().ordered(false).build());
+        let started_at = Instant::now();
+        let inserted = self
+            .client_subscan
+            .insert_many(&subscan, options)
+            .await
+            .into_iter()
+            .collect::<HashSet<_>>();
+        Metrics::global().observe(
+            Metric::MongodbWriteDurationSeconds,
+            &[("collection", "subscan")],
+            started_at.elapsed(),
+        );
+
+        subscan
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| inserted.contains(i))
+            .map(|(_, s)| s)
+            .collect()
+    }
+
     pub async fn get_filtered_operations(
         &mut self,
         from_timestamp: i64,
@@ -74,18 +263,244 @@ impl MongoDbClientSubscan {
                 .sort(doc! {"operation_timestamp": 1i32})
                 .build(),
         );
-        let to_timestamp = to_timestamp.unwrap_or(Utc::now().timestamp());
+        let query = Self::get_time_range_query(from_timestamp, to_timestamp);
+
+        self.client_subscan.find(query, options).await
+    }
+
+    pub async fn get_wallet_operations(
+        &mut self,
+        wallet: &str,
+        operation_type: Option<OperationType>,
+    ) -> Vec<SubscanOperation> {
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"operation_timestamp": 1i32})
+                .build(),
+        );
+        let query = Self::get_wallet_query(wallet, operation_type);
+
+        self.client_subscan.find(query, options).await
+    }
+
+    // operations sent or received by the wallet within the range, oldest first
+    pub async fn get_wallet_range_operations(
+        &mut self,
+        wallet: &str,
+        from_timestamp: i64,
+        to_timestamp: i64,
+    ) -> Vec<SubscanOperation> {
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"operation_timestamp": 1i32})
+                .build(),
+        );
+        let mut query = Self::get_time_range_query(from_timestamp, Some(to_timestamp));
+        query.insert(
+            "$or",
+            vec![doc! {"from_wallet": wallet}, doc! {"to_wallet": wallet}],
+        );
+
+        self.client_subscan.find(query, options).await
+    }
+
+    pub async fn get_validator_operations(&mut self, validator: &str) -> Vec<SubscanOperation> {
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"operation_timestamp": 1i32})
+                .build(),
+        );
+        let query = doc! {
+            "to_wallet": validator,
+        };
+
+        self.client_subscan.find(query, options).await
+    }
+
+    pub async fn get_validator_operations_from(
+        &mut self,
+        validator: &str,
+        from: DateTime,
+    ) -> Vec<SubscanOperation> {
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"operation_timestamp": 1i32})
+                .build(),
+        );
+        let query = doc! {
+            "to_wallet": validator,
+            "operation_timestamp": { "$gte": from },
+        };
+
+        self.client_subscan.find(query, options).await
+    }
+
+    pub async fn get_large_operations(
+        &mut self,
+        from_timestamp: i64,
+        min_usd: f64,
+    ) -> Vec<SubscanOperation> {
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"operation_timestamp": 1i32})
+                .build(),
+        );
         let query = doc! {
             "operation_timestamp": {
                 "$gte": DateTime::from_millis(from_timestamp * 1000),
-                "$lt": DateTime::from_millis(to_timestamp * 1000),
+            },
+            "operation_usd": {
+                "$gte": min_usd,
             }
-
         };
 
         self.client_subscan.find(query, options).await
     }
 
+    // random operations since the timestamp, pending ones can still be dropped with their block
+    pub async fn get_sample_operations(
+        &mut self,
+        from_timestamp: i64,
+        size: u64,
+    ) -> Vec<SubscanOperation> {
+        let pipeline = vec![
+            doc! {"$match": {
+                "operation_timestamp": {
+                    "$gte": DateTime::from_millis(from_timestamp * 1000),
+                },
+                "status": {"$ne": OperationStatus::Pending.to_string()},
+            }},
+            doc! {"$sample": {"size": size as i64}},
+        ];
+
+        self.client_subscan
+            .aggregate(pipeline)
+            .await
+            .into_iter()
+            .filter_map(|d| bson::from_document(d).ok())
+            .collect()
+    }
+
+    // inserts and manual corrections, deletions by ttl and compaction are not watched
+    pub async fn watch_operations(
+        &mut self,
+        resume_after: Option<ResumeToken>,
+    ) -> Option<ChangeStream<ChangeStreamEvent<SubscanOperation>>> {
+        let pipeline = vec![doc! {
+            "$match": {
+                "operationType": { "$in": ["insert", "update", "replace"] }
+            }
+        }];
+        let options = Some(
+            ChangeStreamOptions::builder()
+                .full_document(Some(FullDocumentType::UpdateLookup))
+                .resume_after(resume_after)
+                .build(),
+        );
+
+        self.client_subscan.watch(pipeline, options).await
+    }
+
+    // operations stored after the given id in insertion order
+    pub async fn get_operations_after(
+        &mut self,
+        after: ObjectId,
+        limit: i64,
+    ) -> Vec<StoredOperation> {
+        let mut client_stored = MongoDbClient {
+            client_name: self.client_subscan.client_name.clone(),
+            client: self.client_subscan.client.clone(),
+            db: self.client_subscan.db.clone(),
+            col: self.client_subscan.col.clone_with_type::<StoredOperation>(),
+        };
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"_id": 1i32})
+                .limit(limit)
+                .build(),
+        );
+        let query = doc! {
+            "_id": { "$gt": after }
+        };
+
+        client_stored.find(query, options).await
+    }
+
+    pub async fn get_wallets(&mut self) -> Vec<String> {
+        let mut wallets = Vec::new();
+        for field in ["from_wallet", "controller_wallet", "to_wallet"] {
+            wallets.extend(self.client_subscan.distinct_huge(doc! {}, field).await);
+        }
+        wallets.sort();
+        wallets.dedup();
+
+        wallets
+    }
+
+    // hashes are left as they are, they identify the operation since it was first stored
+    pub async fn replace_wallet(&mut self, alternate: &str, canonical: &str) -> u64 {
+        let mut replaced = 0;
+        for field in ["from_wallet", "controller_wallet", "to_wallet"] {
+            replaced += self
+                .client_subscan
+                .update_many(
+                    doc! { field: alternate },
+                    doc! { "$set": { field: canonical }},
+                    None,
+                )
+                .await
+                .modified_count;
+        }
+
+        replaced
+    }
+
+    // pending operations of blocks up to the finalized head, returns how many were flipped
+    pub async fn finalize_operations(&mut self, max_block_number: u64) -> u64 {
+        let query = doc! {
+            "status": OperationStatus::Pending.to_string(),
+            "block_number": {"$lte": max_block_number as i64},
+        };
+        self.client_subscan
+            .update_many(
+                query,
+                doc! {"$set": {"status": OperationStatus::Finalized.to_string()}},
+                None,
+            )
+            .await
+            .modified_count
+    }
+
+    pub async fn get_operation_by_hash(&mut self, hash: &str) -> Option<SubscanOperation> {
+        self.client_subscan
+            .find_one(doc! {"hash": hash}, None)
+            .await
+    }
+
+    pub async fn get_oldest_operation(&mut self) -> Option<SubscanOperation> {
+        let options = Some(
+            FindOneOptions::builder()
+                .sort(doc! {"operation_timestamp": 1i32})
+                .build(),
+        );
+
+        self.client_subscan.find_one(doc! {}, options).await
+    }
+
+    pub async fn delete_operations(&mut self, from_timestamp: i64, to_timestamp: i64) -> u64 {
+        let query = doc! {
+            "operation_timestamp": {
+                "$gte": DateTime::from_millis(from_timestamp * 1000),
+                "$lt": DateTime::from_millis(to_timestamp * 1000),
+            }
+        };
+
+        self.client_subscan
+            .delete_many(query, None)
+            .await
+            .deleted_count
+    }
+
     pub async fn get_not_existing_operations(
         &mut self,
         subscan_operations: Vec<SubscanOperation>,
@@ -118,3 +533,82 @@ impl MongoDbClientSubscan {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        mongodb_client_subscan::{get_timeline_sort, MongoDbClientSubscan},
+        OperationStatus, OperationType, SubscanOperation, Validator,
+    };
+    use bson::{doc, DateTime};
+    use rs_utils::clients::mongodb_client::get_after_query;
+
+    #[test]
+    fn timeline_pages_continue_with_older_operations() {
+        let timestamp = DateTime::from_millis(1_700_000_000_000);
+        let operation = SubscanOperation {
+            hash: "hash".to_string(),
+            hash_version: 0,
+            block_number: 42,
+            extrinsic_index: "42-3".to_string(),
+            call_index: 0,
+            operation_timestamp: timestamp,
+            operation_quantity: 1.5,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 3.0,
+            operation_type: OperationType::Transfer,
+            from_wallet: "from".to_string(),
+            controller_wallet: "controller".to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        };
+
+        let after = MongoDbClientSubscan::get_timeline_key(&operation);
+        assert_eq!(
+            get_after_query(&get_timeline_sort(), &after),
+            doc! {"$or": [
+                {"operation_timestamp": {"$lt": timestamp}},
+                {"operation_timestamp": timestamp, "block_number": {"$lt": 42i64}},
+                {
+                    "operation_timestamp": timestamp,
+                    "block_number": 42i64,
+                    "extrinsic_index": {"$lt": "42-3"},
+                },
+                {
+                    "operation_timestamp": timestamp,
+                    "block_number": 42i64,
+                    "extrinsic_index": "42-3",
+                    "hash": {"$lt": "hash"},
+                },
+            ]}
+        );
+    }
+
+    #[test]
+    fn nominate_operations_are_the_starts_of_the_versions() {
+        let version = |millis: i64, extrinsic_index: Option<&str>| Validator {
+            nominator: "alice".to_string(),
+            validators: vec!["validator".to_string()],
+            valid_from: DateTime::from_millis(millis),
+            valid_to: None,
+            extrinsic_index: extrinsic_index.map(str::to_string),
+        };
+        // other operations of the wallet in the same second aren't part of the history
+        let history = vec![
+            version(0, None),
+            version(1_700_000_000_000, Some("100-2")),
+            version(1_700_000_600_000, Some("200-1")),
+        ];
+
+        assert_eq!(
+            MongoDbClientSubscan::get_nominations_query(&history),
+            doc! {
+                "extrinsic_index": {"$in": ["100-2", "200-1"]},
+            }
+        );
+    }
+}
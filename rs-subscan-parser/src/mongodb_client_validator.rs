@@ -1,8 +1,78 @@
 use crate::Validator;
-use bson::doc;
-use mongodb::{options::IndexOptions, IndexModel};
+use bson::{doc, Bson, DateTime};
+use itertools::Itertools;
+use mongodb::{
+    options::{FindOneOptions, FindOptions, IndexOptions},
+    IndexModel,
+};
 use rs_utils::clients::mongodb_client::MongoDbClient;
-use std::env;
+use std::{collections::HashMap, env};
+
+static LEGACY_NOMINATOR_INDEX: &str = "nominator_1";
+
+// what storing a version changes in the stored history of its nominator
+#[derive(Clone, Debug, PartialEq)]
+enum VersionWrite {
+    // the version starting at the same time gets other validators
+    Replace(Validator),
+
+    // the version the new one falls into ends where the new one starts
+    Close {
+        nominator: String,
+        valid_from: DateTime,
+        valid_to: DateTime,
+    },
+    Insert(Validator),
+}
+
+// history sorted by valid_from is kept up to date with the writes, versions could arrive out of
+// order, one then ends where the next stored version starts
+fn add_version(history: &mut Vec<Validator>, mut doc: Validator) -> Vec<VersionWrite> {
+    let current = history.iter().rposition(|v| {
+        v.valid_from <= doc.valid_from && !matches!(v.valid_to, Some(t) if t <= doc.valid_from)
+    });
+    if let Some(i) = current {
+        if history[i].has_same_validators(&doc) {
+            return Vec::new();
+        }
+    }
+
+    doc.valid_to = history
+        .iter()
+        .find(|v| v.valid_from > doc.valid_from)
+        .map(|v| v.valid_from);
+
+    let mut writes = Vec::new();
+    match current {
+        Some(i) if history[i].valid_from == doc.valid_from => {
+            history[i] = doc.clone();
+            return vec![VersionWrite::Replace(doc)];
+        }
+        Some(i) => {
+            history[i].valid_to = Some(doc.valid_from);
+            writes.push(VersionWrite::Close {
+                nominator: doc.nominator.clone(),
+                valid_from: history[i].valid_from,
+                valid_to: doc.valid_from,
+            });
+        }
+        None => {}
+    }
+
+    let i = history.partition_point(|v| v.valid_from < doc.valid_from);
+    history.insert(i, doc.clone());
+    writes.push(VersionWrite::Insert(doc));
+
+    writes
+}
+
+// validator field as it is stored, a string or an array
+fn get_stored_validators(validator: &Validator) -> Bson {
+    bson::to_document(validator)
+        .ok()
+        .and_then(|d| d.get("validator").cloned())
+        .unwrap_or_default()
+}
 
 pub struct MongoDbClientValidator {
     pub client_validator: MongoDbClient<Validator>,
@@ -20,14 +90,31 @@ impl MongoDbClientValidator {
     }
 
     pub async fn create_index(&mut self) {
+        // before versioning nominator was unique, it has to go to keep the history
+        let index_names = self.client_validator.list_index_names().await;
+        if index_names.iter().any(|n| n == LEGACY_NOMINATOR_INDEX) {
+            self.client_validator
+                .drop_index(LEGACY_NOMINATOR_INDEX)
+                .await;
+        }
+
+        // legacy mappings get an explicit start so range queries on valid_from match them
+        self.client_validator
+            .update_many(
+                doc! {"valid_from": {"$exists": false}},
+                doc! {"$set": {"valid_from": DateTime::MIN, "valid_to": null}},
+                None,
+            )
+            .await;
+
         let options = IndexOptions::builder().unique(true).build();
         let model = IndexModel::builder()
-            .keys(doc! {"nominator": 1u32})
+            .keys(doc! {"nominator": 1u32, "valid_from": 1u32})
             .options(options)
             .build();
         self.client_validator.create_index(model, None).await;
 
-        let indexes = vec!["validator"];
+        let indexes = vec!["validator", "valid_to"];
         for index in indexes {
             let model = IndexModel::builder()
                 .keys(doc! {index: 1u32})
@@ -37,34 +124,205 @@ impl MongoDbClientValidator {
         }
     }
 
-    pub async fn import_or_update_validators(&mut self, validator: Vec<Validator>) {
-        for doc in validator {
-            if self
-                .client_validator
-                .find_one(doc! { "nominator": doc.nominator.clone() }, None)
-                .await
-                .is_none()
-            {
+    // every mapping is kept as a version valid in [valid_from, valid_to)
+    pub async fn import_or_update_validators(&mut self, mut validators: Vec<Validator>) {
+        validators.sort_by_key(|v| v.valid_from);
+        let nominators = validators
+            .iter()
+            .map(|v| v.nominator.clone())
+            .unique()
+            .collect();
+        let mut histories = self.get_nominations_histories(nominators).await;
+
+        for doc in validators {
+            let history = histories.entry(doc.nominator.clone()).or_default();
+            for write in add_version(history, doc) {
+                self.write_version(write).await;
+            }
+        }
+    }
+
+    async fn write_version(&mut self, write: VersionWrite) {
+        match write {
+            VersionWrite::Replace(doc) => {
+                self.client_validator
+                    .update_one(
+                        doc! { "nominator": doc.nominator.clone(), "valid_from": doc.valid_from },
+                        doc! { "$set": {
+                            "validator": get_stored_validators(&doc),
+                            "valid_to": doc.valid_to,
+                            "extrinsic_index": doc.extrinsic_index,
+                        }},
+                        None,
+                    )
+                    .await;
+            }
+            VersionWrite::Close {
+                nominator,
+                valid_from,
+                valid_to,
+            } => {
+                self.client_validator
+                    .update_one(
+                        doc! { "nominator": nominator, "valid_from": valid_from },
+                        doc! { "$set": { "valid_to": valid_to }},
+                        None,
+                    )
+                    .await;
+            }
+            VersionWrite::Insert(doc) => {
                 self.client_validator.insert_one(doc, None).await;
-                continue;
             }
+        }
+    }
 
-            self.client_validator
-                .update_one(
-                    doc! { "nominator": doc.nominator },
-                    doc! { "$set": { "validator": doc.validator }},
-                    None,
-                )
-                .await;
+    // validators the nominator currently nominates
+    pub async fn get_validators_by_nominator(&mut self, nominator: &str) -> Vec<String> {
+        let query = doc! {
+            "nominator": nominator,
+            "valid_to": null,
+        };
+
+        self.client_validator
+            .find_one(query, None)
+            .await
+            .map(|v| v.validators)
+            .unwrap_or_default()
+    }
+
+    // current version of every nominator
+    pub async fn get_current_nominations(&mut self) -> Vec<Validator> {
+        self.client_validator
+            .find(doc! { "valid_to": null }, None)
+            .await
+    }
+
+    pub async fn get_validator_by_nominator_at(
+        &mut self,
+        nominator: &str,
+        timestamp: DateTime,
+    ) -> Option<Validator> {
+        let options = Some(
+            FindOneOptions::builder()
+                .sort(doc! {"valid_from": -1i32})
+                .build(),
+        );
+        let query = doc! {
+            "nominator": nominator,
+            "valid_from": { "$lte": timestamp },
+            "$or": [
+                { "valid_to": null },
+                { "valid_to": { "$gt": timestamp } },
+            ],
+        };
+
+        self.client_validator.find_one(query, options).await
+    }
+
+    // versions of every nominator sorted by valid_from, one query for all of them
+    pub async fn get_nominations_histories(
+        &mut self,
+        nominators: Vec<String>,
+    ) -> HashMap<String, Vec<Validator>> {
+        if nominators.is_empty() {
+            return HashMap::new();
         }
+
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"valid_from": 1i32})
+                .build(),
+        );
+        let query = doc! {
+            "nominator": {
+                "$in": nominators
+            }
+        };
+
+        self.client_validator
+            .find(query, options)
+            .await
+            .into_iter()
+            .into_group_map_by(|v| v.nominator.clone())
     }
 
-    pub async fn get_validator_by_nominator(&mut self, nominator: &str) -> Option<Validator> {
+    pub async fn get_nominations_history(&mut self, nominator: &str) -> Vec<Validator> {
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"valid_from": 1i32})
+                .build(),
+        );
         let query = doc! {
             "nominator": nominator
         };
 
-        self.client_validator.find_one(query, None).await
+        self.client_validator.find(query, options).await
+    }
+
+    pub async fn get_wallets(&mut self) -> Vec<String> {
+        let mut wallets = Vec::new();
+        for field in ["nominator", "validator"] {
+            wallets.extend(
+                self.client_validator
+                    .distinct(field)
+                    .await
+                    .into_iter()
+                    .filter_map(|w| w.as_str().map(|w| w.to_string())),
+            );
+        }
+        wallets.sort();
+        wallets.dedup();
+
+        wallets
+    }
+
+    // versions already stored under the canonical nominator win over the alternate ones
+    pub async fn replace_wallet(&mut self, alternate: &str, canonical: &str) -> u64 {
+        let mut replaced = 0;
+        for version in self.get_nominations_history(alternate).await {
+            let query = doc! { "nominator": alternate, "valid_from": version.valid_from };
+            if self
+                .client_validator
+                .find_one(
+                    doc! { "nominator": canonical, "valid_from": version.valid_from },
+                    None,
+                )
+                .await
+                .is_some()
+            {
+                self.client_validator.delete_one(query, None).await;
+            } else {
+                self.client_validator
+                    .update_one(query, doc! { "$set": { "nominator": canonical }}, None)
+                    .await;
+            }
+            replaced += 1;
+        }
+
+        // single targets are stored as strings, more as arrays
+        replaced += self
+            .client_validator
+            .update_many(
+                doc! { "validator": alternate, "validator.0": { "$exists": false }},
+                doc! { "$set": { "validator": canonical }},
+                None,
+            )
+            .await
+            .modified_count;
+        replaced
+            + self
+                .client_validator
+                .update_many(
+                    doc! { "validator": alternate, "validator.0": { "$exists": true }},
+                    doc! { "$set": { "validator.$": canonical }},
+                    None,
+                )
+                .await
+                .modified_count
+    }
+
+    pub async fn get_all_validators(&mut self) -> Vec<Validator> {
+        self.client_validator.find(doc! {}, None).await
     }
 
     pub async fn get_not_existing_nominators(&mut self, nominators: Vec<String>) -> Vec<String> {
@@ -92,3 +350,73 @@ impl MongoDbClientValidator {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        mongodb_client_validator::{add_version, VersionWrite},
+        Validator,
+    };
+    use bson::DateTime;
+
+    fn version(validator: &str, valid_from: i64) -> Validator {
+        Validator {
+            nominator: "alice".to_string(),
+            validators: vec![validator.to_string()],
+            valid_from: DateTime::from_millis(valid_from),
+            valid_to: None,
+            extrinsic_index: None,
+        }
+    }
+
+    fn get_current(history: &[Validator]) -> Option<&str> {
+        history
+            .iter()
+            .find(|v| v.valid_to.is_none())
+            .and_then(|v| v.validators.first())
+            .map(String::as_str)
+    }
+
+    #[test]
+    fn newer_versions_close_the_current_one() {
+        let mut history = vec![version("first", 10)];
+
+        let writes = add_version(&mut history, version("second", 20));
+        assert_eq!(
+            writes,
+            vec![
+                VersionWrite::Close {
+                    nominator: "alice".to_string(),
+                    valid_from: DateTime::from_millis(10),
+                    valid_to: DateTime::from_millis(20),
+                },
+                VersionWrite::Insert(version("second", 20)),
+            ]
+        );
+        assert_eq!(get_current(&history), Some("second"));
+
+        // the same validators again change nothing
+        assert!(add_version(&mut history, version("second", 30)).is_empty());
+    }
+
+    #[test]
+    fn legacy_mappings_hold_until_the_next_version() {
+        // legacy mappings have an unknown start, they answer for every earlier operation
+        let mut history = vec![version("legacy", DateTime::MIN.timestamp_millis())];
+
+        let writes = add_version(&mut history, version("second", 100));
+        assert_eq!(
+            writes,
+            vec![
+                VersionWrite::Close {
+                    nominator: "alice".to_string(),
+                    valid_from: DateTime::MIN,
+                    valid_to: DateTime::from_millis(100),
+                },
+                VersionWrite::Insert(version("second", 100)),
+            ]
+        );
+        assert_eq!(get_current(&history), Some("second"));
+        assert_eq!(history[0].validators, vec!["legacy".to_string()]);
+    }
+}
@@ -0,0 +1,114 @@
+use crate::VolumeAnomaly;
+use bson::{doc, Bson, DateTime};
+use mongodb::{
+    options::{FindOptions, IndexOptions, UpdateOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientVolumeAnomalies {
+    pub client_volume_anomalies: MongoDbClient<VolumeAnomaly>,
+}
+
+impl MongoDbClientVolumeAnomalies {
+    pub async fn new() -> MongoDbClientVolumeAnomalies {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_VOLUME_ANOMALIES").unwrap();
+        let client_name = "mongodb_volume_anomalies";
+        let client_volume_anomalies = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_volume_anomalies,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"hour": 1u32, "operation_type": 1u32})
+            .options(options)
+            .build();
+        self.client_volume_anomalies.create_index(model, None).await;
+
+        let model = IndexModel::builder()
+            .keys(doc! {"detected_at": 1u32})
+            .options(None)
+            .build();
+        self.client_volume_anomalies.create_index(model, None).await;
+    }
+
+    // re-detecting an hour keeps a single anomaly with the latest baseline
+    pub async fn import_or_update_anomaly(&mut self, anomaly: VolumeAnomaly) {
+        let options = Some(UpdateOptions::builder().upsert(true).build());
+        self.client_volume_anomalies
+            .update_one(
+                doc! {
+                    "hour": anomaly.hour,
+                    "operation_type": anomaly.operation_type.to_string(),
+                },
+                doc! {
+                    "$set": {
+                        "kind": anomaly.kind.to_string(),
+                        "count": anomaly.count as i64,
+                        "baseline_mean": anomaly.baseline_mean,
+                        "baseline_stddev": anomaly.baseline_stddev,
+                        "z_score": anomaly.z_score,
+                    },
+                    "$setOnInsert": {
+                        "detected_at": anomaly.detected_at,
+                    },
+                },
+                options,
+            )
+            .await;
+    }
+
+    pub async fn get_detected_anomalies(&mut self, since: DateTime) -> Vec<VolumeAnomaly> {
+        let options = Some(
+            FindOptions::builder()
+                .sort(doc! {"detected_at": 1i32})
+                .build(),
+        );
+        let query = doc! {
+            "detected_at": {
+                "$gte": since,
+            }
+        };
+
+        self.client_volume_anomalies.find(query, options).await
+    }
+
+    pub async fn get_anomalies_page(
+        &mut self,
+        from_timestamp: i64,
+        to_timestamp: i64,
+        after: Option<Vec<Bson>>,
+        limit: i64,
+    ) -> Vec<VolumeAnomaly> {
+        let query = doc! {
+            "hour": {
+                "$gte": DateTime::from_millis(from_timestamp * 1000),
+                "$lt": DateTime::from_millis(to_timestamp * 1000),
+            }
+        };
+
+        self.client_volume_anomalies
+            .find_page(
+                query,
+                doc! {"hour": 1i32, "operation_type": 1i32},
+                after,
+                limit,
+                None,
+            )
+            .await
+    }
+
+    pub fn get_page_key(anomaly: &VolumeAnomaly) -> Vec<Bson> {
+        vec![
+            Bson::DateTime(anomaly.hour),
+            bson::to_bson(&anomaly.operation_type).unwrap_or_default(),
+        ]
+    }
+}
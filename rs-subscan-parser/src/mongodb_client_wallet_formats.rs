@@ -0,0 +1,66 @@
+use crate::WalletFormats;
+use bson::doc;
+use mongodb::{
+    options::{IndexOptions, UpdateOptions},
+    IndexModel,
+};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientWalletFormats {
+    pub client_wallet_formats: MongoDbClient<WalletFormats>,
+}
+
+impl MongoDbClientWalletFormats {
+    pub async fn new() -> MongoDbClientWalletFormats {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_WALLET_FORMATS").unwrap();
+        let client_name = "mongodb_wallet_formats";
+        let client_wallet_formats = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self {
+            client_wallet_formats,
+        }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"canonical": 1u32})
+            .options(options)
+            .build();
+        self.client_wallet_formats.create_index(model, None).await;
+
+        let model = IndexModel::builder()
+            .keys(doc! {"alternates": 1u32})
+            .options(None)
+            .build();
+        self.client_wallet_formats.create_index(model, None).await;
+    }
+
+    // alternates are only ever added, a wallet keeps every encoding it was seen in
+    pub async fn import_or_update_wallet_formats(&mut self, wallet_formats: Vec<WalletFormats>) {
+        let options = Some(UpdateOptions::builder().upsert(true).build());
+        for doc in wallet_formats {
+            self.client_wallet_formats
+                .update_one(
+                    doc! { "canonical": doc.canonical },
+                    doc! { "$addToSet": { "alternates": { "$each": doc.alternates }}},
+                    options.clone(),
+                )
+                .await;
+        }
+    }
+
+    pub async fn get_wallet_formats_by_alternate(
+        &mut self,
+        address: &str,
+    ) -> Option<WalletFormats> {
+        let query = doc! {
+            "alternates": address
+        };
+
+        self.client_wallet_formats.find_one(query, None).await
+    }
+}
@@ -0,0 +1,234 @@
+use crate::hmac::hmac_sha256;
+use chrono::{DateTime, Utc};
+use reqwest::{Client, StatusCode};
+use std::{env, time::Duration};
+
+static DEFAULT_OBJECT_STORAGE_REGION: &str = "us-east-1";
+static OBJECT_STORAGE_TIMEOUT_SECONDS: u64 = 300;
+static SIGNING_ALGORITHM: &str = "AWS4-HMAC-SHA256";
+static SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+// s3 or anything speaking its api, gcs buckets through storage.googleapis.com with hmac keys
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ObjectStorageConfig {
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+impl ObjectStorageConfig {
+    // OBJECT_STORAGE_BUCKET, OBJECT_STORAGE_ACCESS_KEY_ID and OBJECT_STORAGE_SECRET_ACCESS_KEY,
+    // OBJECT_STORAGE_REGION and OBJECT_STORAGE_ENDPOINT default to aws, none without a bucket
+    pub fn from_env() -> Option<ObjectStorageConfig> {
+        let bucket = env::var("OBJECT_STORAGE_BUCKET")
+            .ok()
+            .filter(|b| !b.is_empty())?;
+        let access_key_id = env::var("OBJECT_STORAGE_ACCESS_KEY_ID").unwrap_or_default();
+        let secret_access_key = env::var("OBJECT_STORAGE_SECRET_ACCESS_KEY").unwrap_or_default();
+        let region = env::var("OBJECT_STORAGE_REGION")
+            .ok()
+            .filter(|r| !r.is_empty())
+            .unwrap_or(DEFAULT_OBJECT_STORAGE_REGION.to_string());
+        let endpoint = env::var("OBJECT_STORAGE_ENDPOINT")
+            .ok()
+            .filter(|e| !e.is_empty())
+            .unwrap_or(format!("https://s3.{region}.amazonaws.com"));
+
+        Some(Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            bucket,
+            region,
+            access_key_id,
+            secret_access_key,
+        })
+    }
+
+    // host and port of the endpoint, as sent in the host header
+    pub fn get_host(&self) -> &str {
+        let host = self
+            .endpoint
+            .split_once("://")
+            .map(|(_, h)| h)
+            .unwrap_or(&self.endpoint);
+        host.split('/').next().unwrap_or_default()
+    }
+
+    // path style, bucket names with dots break virtual hosted style over https
+    pub fn get_path(&self, key: &str) -> String {
+        uri_encode(&format!("/{}/{}", self.bucket, key.trim_start_matches('/')))
+    }
+}
+
+pub struct ObjectStorage {
+    pub config: ObjectStorageConfig,
+    pub client: Client,
+}
+
+impl ObjectStorage {
+    pub fn new(config: ObjectStorageConfig) -> ObjectStorage {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(OBJECT_STORAGE_TIMEOUT_SECONDS))
+            .build()
+            .unwrap_or_default();
+
+        Self { config, client }
+    }
+
+    pub async fn has_object(&self, key: &str) -> Result<bool, String> {
+        let status = self.send("HEAD", key, Vec::new(), None).await?;
+        match status {
+            s if s.is_success() => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            s => Err(format!("HEAD {key} returned {s}")),
+        }
+    }
+
+    pub async fn put_object(
+        &self,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+    ) -> Result<(), String> {
+        let status = self.send("PUT", key, body, Some(content_type)).await?;
+        if !status.is_success() {
+            return Err(format!("PUT {key} returned {status}"));
+        }
+
+        Ok(())
+    }
+
+    async fn send(
+        &self,
+        method: &str,
+        key: &str,
+        body: Vec<u8>,
+        content_type: Option<&str>,
+    ) -> Result<StatusCode, String> {
+        let path = self.config.get_path(key);
+        let payload_hash = sha256::digest(body.as_slice());
+        let headers = get_signed_headers(&self.config, method, &path, &payload_hash, Utc::now());
+
+        let method = reqwest::Method::from_bytes(method.as_bytes()).map_err(|e| e.to_string())?;
+        let mut request = self
+            .client
+            .request(method, format!("{}{path}", self.config.endpoint))
+            .body(body);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        if let Some(content_type) = content_type {
+            request = request.header("content-type", content_type);
+        }
+
+        request
+            .send()
+            .await
+            .map(|r| r.status())
+            .map_err(|e| e.to_string())
+    }
+}
+
+// aws signature version 4, only host and the x-amz headers are signed
+pub fn get_signed_headers(
+    config: &ObjectStorageConfig,
+    method: &str,
+    path: &str,
+    payload_hash: &str,
+    now: DateTime<Utc>,
+) -> Vec<(&'static str, String)> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let scope = format!("{date}/{}/s3/aws4_request", config.region);
+
+    let canonical_request = [
+        method.to_string(),
+        path.to_string(),
+        String::new(),
+        format!("host:{}", config.get_host()),
+        format!("x-amz-content-sha256:{payload_hash}"),
+        format!("x-amz-date:{amz_date}\n"),
+        SIGNED_HEADERS.to_string(),
+        payload_hash.to_string(),
+    ]
+    .join("\n");
+    let string_to_sign = [
+        SIGNING_ALGORITHM.to_string(),
+        amz_date.clone(),
+        scope.clone(),
+        sha256::digest(canonical_request),
+    ]
+    .join("\n");
+
+    let signing_key = get_signing_key(&config.secret_access_key, &date, &config.region, "s3");
+    let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+    let authorization = format!(
+        "{SIGNING_ALGORITHM} Credential={}/{scope}, SignedHeaders={SIGNED_HEADERS}, \
+         Signature={signature}",
+        config.access_key_id
+    );
+
+    vec![
+        ("authorization", authorization),
+        ("x-amz-content-sha256", payload_hash.to_string()),
+        ("x-amz-date", amz_date),
+    ]
+}
+
+pub fn get_signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    [date, region, service, "aws4_request"]
+        .iter()
+        .fold(format!("AWS4{secret}").into_bytes(), |key, part| {
+            hmac_sha256(&key, part.as_bytes())
+        })
+}
+
+// percent encodes everything but unreserved characters and the slashes between segments
+pub fn uri_encode(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            b => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_storage::{get_signing_key, uri_encode, ObjectStorageConfig};
+
+    #[test]
+    fn signing_keys_match_the_aws_example() {
+        let signing_key = get_signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex::encode(signing_key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn object_paths_are_encoded_per_segment() {
+        assert_eq!(
+            uri_encode("/reports/polkadot/2024-01-01 (1).csv"),
+            "/reports/polkadot/2024-01-01%20%281%29.csv"
+        );
+
+        let config = ObjectStorageConfig {
+            endpoint: "https://storage.googleapis.com".to_string(),
+            bucket: "feeds".to_string(),
+            region: "auto".to_string(),
+            access_key_id: String::new(),
+            secret_access_key: String::new(),
+        };
+        assert_eq!(config.get_host(), "storage.googleapis.com");
+        assert_eq!(config.get_path("/daily/a.csv"), "/feeds/daily/a.csv");
+    }
+}
@@ -0,0 +1,255 @@
+use crate::SubscanOperation;
+use chrono::Utc;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    str::FromStr,
+    sync::{Mutex, OnceLock},
+};
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+use tracing::error;
+
+static OPERATION_ID_GENERATOR: OnceLock<Option<Box<dyn OperationIdGenerator>>> = OnceLock::new();
+
+// 2023-11-14, leaves the 41 bits of milliseconds room until 2093
+static SNOWFLAKE_EPOCH_MS: u64 = 1_700_000_000_000;
+static SNOWFLAKE_MAX_WORKER_ID: u16 = 0x3ff;
+static SNOWFLAKE_MAX_SEQUENCE: u64 = 0xfff;
+
+// the counter takes the 12 bits of rand_a, see rfc 9562
+static UUIDV7_MAX_SEQUENCE: u64 = 0xfff;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum OperationIdScheme {
+    Uuidv7,
+
+    // 64 bit integers of milliseconds, SNOWFLAKE_WORKER_ID and a sequence
+    Snowflake,
+}
+
+// ids handed to operations next to their dedup hash, they have to be unique across
+// deployments and sort by the time they were generated
+pub trait OperationIdGenerator: Send + Sync {
+    fn next_id(&self) -> String;
+}
+
+// milliseconds with a sequence within them, a full sequence moves on to the next millisecond
+// and a clock going back keeps the last one, so ids never go backwards
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct IdClock {
+    last_ms: u64,
+    sequence: u64,
+}
+
+impl IdClock {
+    fn next(&mut self, now_ms: u64, max_sequence: u64) -> (u64, u64) {
+        if now_ms > self.last_ms {
+            self.last_ms = now_ms;
+            self.sequence = 0;
+        } else if self.sequence < max_sequence {
+            self.sequence += 1;
+        } else {
+            self.last_ms += 1;
+            self.sequence = 0;
+        }
+
+        (self.last_ms, self.sequence)
+    }
+}
+
+fn get_now_ms() -> u64 {
+    Utc::now().timestamp_millis().try_into().unwrap_or_default()
+}
+
+#[derive(Debug, Default)]
+pub struct Uuidv7Generator {
+    clock: Mutex<IdClock>,
+}
+
+impl Uuidv7Generator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl OperationIdGenerator for Uuidv7Generator {
+    fn next_id(&self) -> String {
+        let (ms, sequence) = self
+            .clock
+            .lock()
+            .unwrap()
+            .next(get_now_ms(), UUIDV7_MAX_SEQUENCE);
+        get_uuidv7(ms, sequence, rand::thread_rng().gen())
+    }
+}
+
+// 48 bits of milliseconds, version, 12 bits of sequence, variant and 62 random bits
+fn get_uuidv7(ms: u64, sequence: u64, random: u64) -> String {
+    let uuid = ((ms as u128 & 0xffff_ffff_ffff) << 80)
+        | (0x7 << 76)
+        | ((sequence as u128 & 0xfff) << 64)
+        | (0b10 << 62)
+        | (random as u128 & 0x3fff_ffff_ffff_ffff);
+    let hex = format!("{uuid:032x}");
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+#[derive(Debug)]
+pub struct SnowflakeGenerator {
+    worker_id: u16,
+    clock: Mutex<IdClock>,
+}
+
+impl SnowflakeGenerator {
+    // worker ids above 1023 don't fit into their 10 bits and are cut
+    pub fn new(worker_id: u16) -> Self {
+        Self {
+            worker_id: worker_id & SNOWFLAKE_MAX_WORKER_ID,
+            clock: Mutex::new(IdClock::default()),
+        }
+    }
+
+    // SNOWFLAKE_WORKER_ID, 0 if not set
+    pub fn from_env() -> Self {
+        let worker_id = env::var("SNOWFLAKE_WORKER_ID")
+            .ok()
+            .and_then(|v| v.parse::<u16>().ok())
+            .filter(|v| *v <= SNOWFLAKE_MAX_WORKER_ID)
+            .unwrap_or_default();
+
+        Self::new(worker_id)
+    }
+}
+
+impl OperationIdGenerator for SnowflakeGenerator {
+    fn next_id(&self) -> String {
+        let (ms, sequence) = self
+            .clock
+            .lock()
+            .unwrap()
+            .next(get_now_ms(), SNOWFLAKE_MAX_SEQUENCE);
+        get_snowflake(ms, self.worker_id, sequence).to_string()
+    }
+}
+
+fn get_snowflake(ms: u64, worker_id: u16, sequence: u64) -> u64 {
+    (ms.saturating_sub(SNOWFLAKE_EPOCH_MS) << 22)
+        | (((worker_id & SNOWFLAKE_MAX_WORKER_ID) as u64) << 12)
+        | (sequence & SNOWFLAKE_MAX_SEQUENCE)
+}
+
+// OPERATION_ID_SCHEME=uuidv7 or snowflake, operations only carry their hash if not set
+fn get_generator_from_env() -> Option<Box<dyn OperationIdGenerator>> {
+    let scheme = env::var("OPERATION_ID_SCHEME").ok()?;
+    match OperationIdScheme::from_str(scheme.trim()) {
+        Ok(OperationIdScheme::Uuidv7) => Some(Box::new(Uuidv7Generator::new())),
+        Ok(OperationIdScheme::Snowflake) => Some(Box::new(SnowflakeGenerator::from_env())),
+        Err(_) => {
+            error!(target: "operation_ids", "Unknown OPERATION_ID_SCHEME {scheme}, no ids are set.");
+            None
+        }
+    }
+}
+
+// deployments with their own scheme plug it in before the first operations are stored,
+// false once the generator is set or OPERATION_ID_SCHEME was read already
+pub fn set_id_generator(generator: Box<dyn OperationIdGenerator>) -> bool {
+    OPERATION_ID_GENERATOR.set(Some(generator)).is_ok()
+}
+
+pub fn get_id_generator() -> Option<&'static dyn OperationIdGenerator> {
+    OPERATION_ID_GENERATOR
+        .get_or_init(get_generator_from_env)
+        .as_deref()
+}
+
+// ids of operations without one, the stored operation keeps the id of its first write since
+// operations are deduplicated by hash
+pub fn set_operation_ids(operations: &mut [SubscanOperation]) {
+    let Some(generator) = get_id_generator() else {
+        return;
+    };
+
+    for operation in operations.iter_mut().filter(|o| o.operation_id.is_none()) {
+        operation.operation_id = Some(generator.next_id());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::operation_ids::{
+        get_snowflake, get_uuidv7, IdClock, OperationIdGenerator, SnowflakeGenerator,
+        Uuidv7Generator,
+    };
+
+    #[test]
+    fn clock_never_goes_backwards() {
+        let mut clock = IdClock::default();
+        assert_eq!(clock.next(10, 1), (10, 0));
+        assert_eq!(clock.next(10, 1), (10, 1));
+
+        // a full sequence borrows the next millisecond
+        assert_eq!(clock.next(10, 1), (11, 0));
+        assert_eq!(clock.next(9, 1), (11, 1));
+        assert_eq!(clock.next(12, 1), (12, 0));
+    }
+
+    #[test]
+    fn uuidv7_layout() {
+        assert_eq!(
+            get_uuidv7(0x0123_4567_89ab, 0x5, u64::MAX),
+            "01234567-89ab-7005-bfff-ffffffffffff"
+        );
+        assert_eq!(get_uuidv7(0, 0, 0), "00000000-0000-7000-8000-000000000000");
+    }
+
+    #[test]
+    fn snowflake_layout() {
+        assert_eq!(
+            get_snowflake(1_700_000_000_001, 3, 2),
+            (1 << 22) | (3 << 12) | 2
+        );
+
+        // worker ids are cut to their 10 bits
+        assert_eq!(get_snowflake(1_700_000_000_000, 1024, 0), 0);
+    }
+
+    #[test]
+    fn generated_ids_sort_by_generation() {
+        let generator = Uuidv7Generator::new();
+        let ids = (0..100).map(|_| generator.next_id()).collect::<Vec<_>>();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+
+        let generator = SnowflakeGenerator::new(1);
+        let ids = (0..100)
+            .map(|_| generator.next_id().parse::<u64>().unwrap())
+            .collect::<Vec<_>>();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{subscan_parser::Network, SubscanOperation};
+use itertools::Itertools;
+use rs_exchanges_parser::{
+    mongodb_client_exchanges::MongoDbClientExchanges, PrimaryToken, SecondaryToken,
+};
+use std::{collections::HashMap, env};
+use tracing::warn;
+
+static DEFAULT_OPERATION_PRICE_MAX_AGE_SECONDS: i64 = 15 * 60;
+
+// values operations at the price of their timestamp, backfilled ones included, operations
+// without a trade at most OPERATION_PRICE_MAX_AGE_SECONDS before them, e.g. newer than the
+// last stored trade, are valued at the current price
+pub async fn set_operation_prices(
+    operations: &mut [SubscanOperation],
+    current_price: f64,
+    network: &Network,
+) {
+    // mock network runs without exchanges data
+    if *network == Network::Mock {
+        set_current_price(operations, current_price);
+        return;
+    }
+
+    let max_price_age = env::var("OPERATION_PRICE_MAX_AGE_SECONDS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_OPERATION_PRICE_MAX_AGE_SECONDS);
+
+    // operations of a block share their timestamp
+    let mut mongodb_client_exchanges = MongoDbClientExchanges::new().await;
+    let mut prices = HashMap::new();
+    for timestamp in operations.iter().map(get_timestamp).unique() {
+        let price = mongodb_client_exchanges
+            .get_usd_price_at(
+                PrimaryToken::Azero,
+                SecondaryToken::Usdt,
+                timestamp,
+                max_price_age,
+            )
+            .await;
+        if let Some(price) = price {
+            prices.insert(timestamp, price);
+        }
+    }
+
+    let fallbacks = apply_prices(operations, &prices, current_price);
+    if fallbacks > 0 {
+        warn!(target: "subscan_parser", "No historical price for {fallbacks} operations, valued them at the current price.");
+    }
+}
+
+// values every operation at the same price, without looking up historical ones
+pub fn set_current_price(operations: &mut [SubscanOperation], price: f64) {
+    apply_prices(operations, &HashMap::new(), price);
+}
+
+fn get_timestamp(operation: &SubscanOperation) -> i64 {
+    operation.operation_timestamp.timestamp_millis() / 1000
+}
+
+// prices by timestamp in seconds, returns how many operations fell back to the current price
+fn apply_prices(
+    operations: &mut [SubscanOperation],
+    prices: &HashMap<i64, f64>,
+    current_price: f64,
+) -> usize {
+    let mut fallbacks = 0;
+    for s in operations.iter_mut() {
+        let price = match prices.get(&get_timestamp(s)) {
+            Some(price) => *price,
+            None => {
+                fallbacks += 1;
+                current_price
+            }
+        };
+        s.operation_usd = s.operation_quantity * price;
+    }
+
+    fallbacks
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{operation_prices::apply_prices, OperationStatus, OperationType, SubscanOperation};
+    use bson::DateTime;
+    use std::collections::HashMap;
+
+    fn operation(timestamp: i64, operation_quantity: f64) -> SubscanOperation {
+        SubscanOperation {
+            hash: timestamp.to_string(),
+            hash_version: 0,
+            block_number: 1,
+            extrinsic_index: "1-1".to_string(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(timestamp * 1000),
+            operation_quantity,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 0.0,
+            operation_type: OperationType::Stake,
+            from_wallet: "from".to_string(),
+            controller_wallet: "controller".to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn operations_are_valued_at_their_timestamp() {
+        let mut operations = vec![
+            operation(1_600_000_000, 100.0),
+            operation(1_700_000_000, 100.0),
+            operation(1_700_000_000, 50.0),
+            operation(1_800_000_000, 100.0),
+        ];
+        let prices = HashMap::from([(1_600_000_000, 2.5), (1_700_000_000, 1.5)]);
+
+        assert_eq!(apply_prices(&mut operations, &prices, 0.5), 1);
+        assert_eq!(
+            operations
+                .iter()
+                .map(|s| s.operation_usd)
+                .collect::<Vec<_>>(),
+            vec![250.0, 150.0, 75.0, 50.0]
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{
+    materialized_views::{apply_operations, recheck_key},
+    mongodb_client_subscan::MongoDbClientSubscan,
+    pipeline_error::{ErrorCode, PipelineError},
+    price_annotations::annotate_large_operations,
+    shadow::is_shadow_mode,
+    staking_flow::update_staking_flow,
+    subscan_parser::Network,
+    SubscanOperation, TotalsView,
+};
+use futures::StreamExt;
+use itertools::Itertools;
+use mongodb::change_stream::event::OperationType;
+use std::env;
+use tracing::{error, info};
+
+// CHANGE_STREAMS=true moves stats out of the subscan parser into the operations watcher,
+// mongodb has to run as a replica set for it
+pub fn is_change_streams_enabled() -> bool {
+    env::var("CHANGE_STREAMS").is_ok_and(|v| v == "true")
+}
+
+// must be called only with newly stored operations, the stats are production ones so shadow
+// runs skip them
+pub async fn process_stored_operations(operations: &[SubscanOperation]) {
+    if is_shadow_mode() {
+        return;
+    }
+
+    apply_operations(operations).await;
+
+    // validators receiving new operations get their daily candles refreshed
+    update_staking_flow(operations).await;
+    annotate_large_operations().await;
+}
+
+// wallets a corrected operation was moved away from are fixed by the periodic re-check
+pub async fn process_corrected_operations(operations: &[SubscanOperation]) {
+    for operation in operations {
+        recheck_key(&TotalsView::Wallet, &operation.from_wallet).await;
+        recheck_key(&TotalsView::Validator, &operation.to_wallet).await;
+    }
+
+    update_staking_flow(operations).await;
+}
+
+// returns only if change streams are not supported by the server
+pub async fn watch_operations() {
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let mut resume_token = None;
+
+    loop {
+        let Some(mut stream) = mongodb_client_subscan
+            .watch_operations(resume_token.clone())
+            .await
+        else {
+            let pipeline_error = PipelineError::new(
+                ErrorCode::DatabaseError,
+                "change streams are not supported",
+                &Network::from_env(),
+            );
+            error!(target: "operations_watcher", "Watch error: {}.", pipeline_error.to_json());
+            return;
+        };
+
+        while let Some(event) = stream.next().await {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    let pipeline_error = PipelineError::new(
+                        ErrorCode::DatabaseError,
+                        &e.to_string(),
+                        &Network::from_env(),
+                    );
+                    error!(target: "operations_watcher", "Watch error: {}.", pipeline_error.to_json());
+                    break;
+                }
+            };
+
+            // everything already buffered is processed as one batch
+            let mut events = vec![event];
+            while let Ok(Some(event)) = stream.next_if_any().await {
+                events.push(event);
+            }
+
+            let (inserted, corrected): (Vec<_>, Vec<_>) = events
+                .into_iter()
+                .filter_map(|e| Some((e.operation_type, e.full_document?)))
+                .partition(|(t, _)| *t == OperationType::Insert);
+            let inserted = inserted.into_iter().map(|(_, s)| s).collect_vec();
+            let corrected = corrected.into_iter().map(|(_, s)| s).collect_vec();
+
+            process_stored_operations(&inserted).await;
+            process_corrected_operations(&corrected).await;
+            resume_token = stream.resume_token();
+
+            info!(
+                target: "operations_watcher",
+                "Processed {} inserted and {} corrected operations.",
+                inserted.len(),
+                corrected.len(),
+            );
+        }
+
+        error!(target: "operations_watcher", "Change stream closed, reopening it.");
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{
+    latency::record_deliveries,
+    mongodb_client_outbox::MongoDbClientOutbox,
+    shadow::is_shadow_mode,
+    sinks::{batching::SinkBatching, Sink},
+    SubscanOperation, SyncCheckpoint,
+};
+use bson::{oid::ObjectId, DateTime};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::{env, time::Duration};
+use tokio::time::sleep;
+use tracing::{error, info};
+
+static DISPATCH_BATCH_SIZE: i64 = 1_000;
+static DISPATCH_INTERVAL_MS: u64 = 1_000;
+
+// OUTBOX=true stores operations together with one delivery per other sink in a transaction,
+// the outbox dispatcher publishes them, mongodb has to run as a replica set for it, shadow runs
+// have no other sinks
+pub fn is_outbox_enabled() -> bool {
+    env::var("OUTBOX").is_ok_and(|v| v == "true") && !is_shadow_mode()
+}
+
+// a pending delivery of an operation to a sink, removed once the sink accepted it
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct OutboxEntry {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub sink: Sink,
+    pub operation: SubscanOperation,
+    pub created_at: DateTime,
+}
+
+// mongodb is always written since the outbox lives there, returns operations stored for the first time
+pub async fn write_operations_with_outbox(
+    operations: Vec<SubscanOperation>,
+    checkpoint: Option<&SyncCheckpoint>,
+) -> Vec<SubscanOperation> {
+    let sinks = Sink::from_env()
+        .into_iter()
+        .filter(|s| *s != Sink::Mongodb)
+        .collect::<Vec<_>>();
+
+    let mut mongodb_client_outbox = MongoDbClientOutbox::new().await;
+    let stored = mongodb_client_outbox
+        .import_with_outbox(&operations, &sinks, checkpoint)
+        .await;
+    record_deliveries(&Sink::Mongodb, &stored).await;
+
+    stored
+}
+
+// deliveries are at least once, an entry is deleted only after its sink returned, sinks with
+// a batch window get their entries once a batch is full or the oldest one waited the window
+pub async fn dispatch_outbox() {
+    let mut mongodb_client_outbox = MongoDbClientOutbox::new().await;
+    mongodb_client_outbox.create_index().await;
+
+    loop {
+        let entries = mongodb_client_outbox
+            .get_pending_entries(DISPATCH_BATCH_SIZE)
+            .await;
+
+        // a full page goes out at once, held back entries would block the ones behind them
+        let is_page_full = entries.len() as i64 >= DISPATCH_BATCH_SIZE;
+        let mut is_dispatched = false;
+        for (sink, entries) in group_by_sink(entries) {
+            let oldest_age = entries
+                .first()
+                .map(|e| DateTime::now().timestamp_millis() - e.created_at.timestamp_millis())
+                .and_then(|a| u64::try_from(a).ok())
+                .map(Duration::from_millis)
+                .unwrap_or_default();
+            if !is_page_full && !SinkBatching::from_env(&sink).is_due(entries.len(), oldest_age) {
+                continue;
+            }
+
+            is_dispatched = true;
+            if sink == Sink::Mongodb {
+                error!(target: "outbox", "Mongodb entries are stored already, dropping {} of them.", entries.len());
+            } else {
+                info!(target: "outbox", "Dispatching {} operations to {sink}.", entries.len());
+                let operations = entries.iter().map(|e| e.operation.clone()).collect();
+                sink.write_operations(operations).await;
+            }
+
+            let ids = entries.into_iter().map(|e| e.id).collect();
+            mongodb_client_outbox.delete_entries(ids).await;
+        }
+
+        if !is_dispatched {
+            sleep(Duration::from_millis(DISPATCH_INTERVAL_MS)).await;
+        }
+    }
+}
+
+// keeps the order of the entries within each sink
+fn group_by_sink(entries: Vec<OutboxEntry>) -> Vec<(Sink, Vec<OutboxEntry>)> {
+    entries
+        .into_iter()
+        .into_group_map_by(|e| e.sink.clone())
+        .into_iter()
+        .sorted_by(|a, b| a.0.cmp(&b.0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        outbox::{group_by_sink, OutboxEntry},
+        sinks::Sink,
+        OperationStatus, OperationType, SubscanOperation,
+    };
+    use bson::{oid::ObjectId, DateTime};
+
+    #[test]
+    fn entries_are_grouped_by_sink_in_order() {
+        let entry = |sink: Sink, hash: &str| OutboxEntry {
+            id: ObjectId::new(),
+            sink,
+            operation: SubscanOperation {
+                hash: hash.to_string(),
+                hash_version: 0,
+                block_number: 1,
+                extrinsic_index: "1-1".to_string(),
+                call_index: 0,
+                operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+                operation_quantity: 1.0,
+                operation_planck: None,
+                operation_fee: None,
+                operation_era: None,
+                operation_usd: 1.0,
+                operation_type: OperationType::Transfer,
+                from_wallet: "from".to_string(),
+                controller_wallet: "from".to_string(),
+                to_wallet: "to".to_string(),
+                status: OperationStatus::Finalized,
+                operation_id: None,
+                nomination_targets: Vec::new(),
+            },
+            created_at: DateTime::from_millis(0),
+        };
+        let entries = vec![
+            entry(Sink::Nats, "a"),
+            entry(Sink::Mqtt, "a"),
+            entry(Sink::Nats, "b"),
+        ];
+
+        let groups = group_by_sink(entries)
+            .into_iter()
+            .map(|(sink, entries)| {
+                let hashes = entries
+                    .into_iter()
+                    .map(|e| e.operation.hash)
+                    .collect::<Vec<_>>();
+                (sink, hashes)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            groups,
+            vec![
+                (Sink::Nats, vec!["a".to_string(), "b".to_string()]),
+                (Sink::Mqtt, vec!["a".to_string()]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use crate::timestamp_validation::get_block_timestamp;
+use bson::DateTime;
+use serde_json::Value;
+use std::env;
+
+// subscan rejects larger rows
+pub static MAX_SUBSCAN_PAGE_SIZE: u32 = 100;
+static DEFAULT_SUBSCAN_PAGE_SIZE: u32 = 100;
+static DEFAULT_SUBSCAN_MAX_PAGES: u32 = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pagination {
+    pub page_size: u32,
+
+    // bounds the requests of a walk that never reaches its cutoff
+    pub max_pages: u32,
+
+    // extrinsics from older blocks or from before the timestamp end the walk
+    pub min_block_number: Option<u64>,
+    pub min_timestamp: Option<DateTime>,
+}
+
+impl Pagination {
+    // SUBSCAN_PAGE_SIZE and SUBSCAN_MAX_PAGES, walks until the pages run out
+    pub fn from_env() -> Pagination {
+        let page_size = env::var("SUBSCAN_PAGE_SIZE")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_SUBSCAN_PAGE_SIZE)
+            .min(MAX_SUBSCAN_PAGE_SIZE);
+        let max_pages = env::var("SUBSCAN_MAX_PAGES")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_SUBSCAN_MAX_PAGES);
+
+        Self {
+            page_size,
+            max_pages,
+            min_block_number: None,
+            min_timestamp: None,
+        }
+    }
+
+    pub fn with_min_block_number(self, min_block_number: u64) -> Pagination {
+        Self {
+            min_block_number: Some(min_block_number),
+            ..self
+        }
+    }
+
+    pub fn with_min_timestamp(self, min_timestamp: DateTime) -> Pagination {
+        Self {
+            min_timestamp: Some(min_timestamp),
+            ..self
+        }
+    }
+
+    // records without a block or timestamp never end the walk, the parser quarantines them
+    pub fn is_past_cutoff(&self, d: &Value) -> bool {
+        let block_number = d.get("block_num").and_then(|v| v.as_u64());
+        let timestamp = get_block_timestamp(d);
+
+        matches!((self.min_block_number, block_number), (Some(min), Some(b)) if b < min)
+            || matches!((self.min_timestamp, timestamp), (Some(min), Some(t)) if t < min)
+    }
+
+    // subscan returns the newest records first, so everything after the first one past
+    // the cutoff is too, returns the kept records and whether to fetch the next page
+    pub fn take_page(&self, page: Vec<Value>) -> (Vec<Value>, bool) {
+        let fetched = page.len();
+        let kept = page
+            .into_iter()
+            .take_while(|d| !self.is_past_cutoff(d))
+            .collect::<Vec<_>>();
+        let has_next = fetched >= self.page_size as usize && kept.len() == fetched;
+
+        (kept, has_next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pagination::Pagination;
+    use bson::DateTime;
+    use serde_json::{json, Value};
+
+    static PAGINATION: Pagination = Pagination {
+        page_size: 3,
+        max_pages: 10,
+        min_block_number: None,
+        min_timestamp: None,
+    };
+
+    fn page(block_numbers: &[u64]) -> Vec<Value> {
+        block_numbers
+            .iter()
+            .map(|b| json!({"block_num": b, "block_timestamp": b * 6}))
+            .collect()
+    }
+
+    #[test]
+    fn walk_goes_on_while_pages_are_full() {
+        assert_eq!(
+            PAGINATION.take_page(page(&[30, 20, 10])),
+            (page(&[30, 20, 10]), true)
+        );
+        assert_eq!(
+            PAGINATION.take_page(page(&[30, 20])),
+            (page(&[30, 20]), false)
+        );
+        assert_eq!(PAGINATION.take_page(Vec::new()), (Vec::new(), false));
+    }
+
+    #[test]
+    fn walk_stops_at_the_cutoff() {
+        let pagination = PAGINATION.with_min_block_number(20);
+        assert_eq!(
+            pagination.take_page(page(&[30, 20, 10])),
+            (page(&[30, 20]), false)
+        );
+
+        // block 20 was produced at 120 seconds
+        let pagination = PAGINATION.with_min_timestamp(DateTime::from_millis(121_000));
+        assert_eq!(
+            pagination.take_page(page(&[30, 20, 10])),
+            (page(&[30]), false)
+        );
+
+        // records without a block are left to the parser
+        let records = vec![json!({"extrinsic_index": "1-1"})];
+        assert_eq!(pagination.take_page(records.clone()), (records, false));
+    }
+}
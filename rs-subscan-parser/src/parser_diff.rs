@@ -0,0 +1,434 @@
+use crate::{
+    pagination::{Pagination, MAX_SUBSCAN_PAGE_SIZE},
+    subscan_api::{MockSubscanApi, RecordingSubscanApi, SubscanApi},
+    subscan_error::SubscanError,
+    subscan_parser::{Network, ParsedOperations, SubscanParser},
+    subscan_scheduler::SubscanEndpoint,
+    ExtrinsicsType, Module, SubscanOperation,
+};
+use rs_utils::clients::http_client::HttpClient;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    str::FromStr,
+};
+use strum_macros::Display;
+
+// set after decoding, by set_hash, the finalization and operation_ids, so they differ between
+// any two runs
+static IGNORED_FIELDS: [&str; 4] = ["hash", "hash_version", "status", "operation_id"];
+
+static BATCH_ALL_CALL: &str = "batch_all";
+static TRANSFERS_CALL: &str = "transfers";
+
+// answer of subscan to one request of a recorded call
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RecordedResponse {
+    pub endpoint: String,
+    pub response: Value,
+}
+
+// a parsed call with the answers subscan gave, an extrinsics type of the module, batch_all
+// or transfers
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RecordedCall {
+    #[serde(default)]
+    pub module: Module,
+    pub call: String,
+
+    // extrinsics were walked back to the block, the latest page only without it
+    #[serde(default)]
+    pub min_block_number: Option<u64>,
+
+    pub responses: Vec<RecordedResponse>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Display, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum DiffKind {
+    Changed,
+
+    // only the baseline decoded it
+    Removed,
+
+    // only the current parser decodes it
+    Added,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct FieldDiff {
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct OperationDiff {
+    pub extrinsic_index: String,
+    pub call_index: u32,
+    pub kind: DiffKind,
+
+    // every field of added and removed operations
+    pub fields: Vec<FieldDiff>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct DiffReport {
+    pub old_operations: usize,
+    pub new_operations: usize,
+    pub unchanged: usize,
+    pub diffs: Vec<OperationDiff>,
+}
+
+// e.g. nominationpools:unbond, calls without a module are staking ones
+pub fn parse_call_spec(spec: &str) -> Option<(Module, String)> {
+    let (module, call) = match spec.split_once(':') {
+        Some((module, call)) => (Module::from_str(module).ok()?, call),
+        None => (Module::Staking, spec),
+    };
+    let is_known = call == BATCH_ALL_CALL
+        || call == TRANSFERS_CALL
+        || ExtrinsicsType::from_str(call).is_ok_and(|e| module.get_extrinsics_types().contains(&e));
+
+    is_known.then(|| (module, call.to_string()))
+}
+
+// the call as subscan answers it now, transfers always have the latest page only
+pub async fn record_call(
+    network: &Network,
+    module: Module,
+    call: &str,
+    min_block_number: Option<u64>,
+) -> Result<RecordedCall, SubscanError> {
+    let api = RecordingSubscanApi::new(HttpClient::new("parser_diff").await);
+    let mut subscan_parser = SubscanParser::with_api(network.clone(), api.clone());
+    parse_call(&mut subscan_parser, &module, call, min_block_number).await?;
+
+    let responses = api
+        .get_responses()
+        .into_iter()
+        .map(|(endpoint, response)| RecordedResponse {
+            endpoint: endpoint.to_string(),
+            response,
+        })
+        .collect();
+
+    Ok(RecordedCall {
+        module,
+        call: call.to_string(),
+        min_block_number,
+        responses,
+    })
+}
+
+// operations the current parser decodes from the recorded answers, nothing is requested,
+// quarantined records and wallet formats are written like in a real run, walks need the
+// SUBSCAN_PAGE_SIZE and SUBSCAN_MAX_PAGES of the recording
+pub async fn decode_call(
+    network: &Network,
+    recorded_call: &RecordedCall,
+) -> Result<Vec<SubscanOperation>, SubscanError> {
+    let mut api = MockSubscanApi::new();
+    for r in recorded_call.responses.iter() {
+        let endpoint = SubscanEndpoint::from_str(&r.endpoint)
+            .map_err(|_| SubscanError::Deserialization(format!("endpoint {}", r.endpoint)))?;
+        api = api.with_response(endpoint, r.response.clone());
+    }
+
+    let mut subscan_parser =
+        SubscanParser::with_api(network.clone(), api).with_api_keys(Some("recorded".to_string()));
+    parse_call(
+        &mut subscan_parser,
+        &recorded_call.module,
+        &recorded_call.call,
+        recorded_call.min_block_number,
+    )
+    .await
+}
+
+async fn parse_call<A: SubscanApi>(
+    subscan_parser: &mut SubscanParser<A>,
+    module: &Module,
+    call: &str,
+    min_block_number: Option<u64>,
+) -> Result<Vec<SubscanOperation>, SubscanError> {
+    let parsed = get_parsed_call(subscan_parser, module, call, min_block_number).await?;
+    let operations = parsed
+        .process(
+            subscan_parser.get_network(),
+            subscan_parser.get_address_format(),
+        )
+        .await;
+
+    Ok(operations)
+}
+
+async fn get_parsed_call<A: SubscanApi>(
+    subscan_parser: &mut SubscanParser<A>,
+    module: &Module,
+    call: &str,
+    min_block_number: Option<u64>,
+) -> Result<ParsedOperations, SubscanError> {
+    let pagination = min_block_number.map(|b| Pagination::from_env().with_min_block_number(b));
+    if call == BATCH_ALL_CALL {
+        return match pagination {
+            Some(pagination) => {
+                subscan_parser
+                    .parse_subscan_batch_all_pages("", &pagination)
+                    .await
+            }
+            None => {
+                subscan_parser
+                    .parse_subscan_batch_all("", 0, MAX_SUBSCAN_PAGE_SIZE)
+                    .await
+            }
+        };
+    }
+
+    if call == TRANSFERS_CALL {
+        return subscan_parser
+            .parse_subscan_transfers("", 0, MAX_SUBSCAN_PAGE_SIZE)
+            .await
+            .map(|(operations, _)| operations)
+            .ok_or_else(|| SubscanError::Deserialization("transfers".to_string()));
+    }
+
+    let extrinsics_type = ExtrinsicsType::from_str(call)
+        .map_err(|_| SubscanError::Deserialization(format!("call {call}")))?;
+    match pagination {
+        Some(pagination) => {
+            subscan_parser
+                .parse_subscan_operations_pages("", module.clone(), extrinsics_type, &pagination)
+                .await
+        }
+        None => {
+            subscan_parser
+                .parse_subscan_operations(
+                    "",
+                    module.clone(),
+                    extrinsics_type,
+                    0,
+                    MAX_SUBSCAN_PAGE_SIZE,
+                )
+                .await
+        }
+    }
+}
+
+// operations are matched by extrinsic and call index, so a changed type or wallet shows up as
+// a field of the same operation
+pub fn diff_operations(old: &[SubscanOperation], new: &[SubscanOperation]) -> DiffReport {
+    let old_values = get_values_by_key(old);
+    let new_values = get_values_by_key(new);
+    let keys = old_values
+        .keys()
+        .chain(new_values.keys())
+        .collect::<BTreeSet<_>>();
+
+    let mut unchanged = 0;
+    let mut diffs = Vec::new();
+    for key in keys {
+        let old_values = old_values.get(key).map(Vec::as_slice).unwrap_or_default();
+        let new_values = new_values.get(key).map(Vec::as_slice).unwrap_or_default();
+
+        // operations sharing a key are paired in their order
+        for i in 0..old_values.len().max(new_values.len()) {
+            let (old_value, new_value) = (old_values.get(i), new_values.get(i));
+            let kind = match (old_value, new_value) {
+                (Some(_), Some(_)) => DiffKind::Changed,
+                (Some(_), None) => DiffKind::Removed,
+                _ => DiffKind::Added,
+            };
+
+            let fields = diff_fields(old_value, new_value);
+            if fields.is_empty() {
+                unchanged += 1;
+                continue;
+            }
+
+            diffs.push(OperationDiff {
+                extrinsic_index: key.1.clone(),
+                call_index: key.2,
+                kind,
+                fields,
+            });
+        }
+    }
+
+    DiffReport {
+        old_operations: old.len(),
+        new_operations: new.len(),
+        unchanged,
+        diffs,
+    }
+}
+
+// blocks in order, the extrinsic index is only compared within a block
+fn get_values_by_key(operations: &[SubscanOperation]) -> BTreeMap<(u64, String, u32), Vec<Value>> {
+    let mut values = BTreeMap::<_, Vec<_>>::new();
+    for s in operations {
+        let key = (s.block_number, s.extrinsic_index.clone(), s.call_index);
+        values
+            .entry(key)
+            .or_default()
+            .push(serde_json::to_value(s).unwrap_or_default());
+    }
+
+    values
+}
+
+fn diff_fields(old: Option<&Value>, new: Option<&Value>) -> Vec<FieldDiff> {
+    let get_field =
+        |v: Option<&Value>, field: &str| v.and_then(|v| v.get(field)).cloned().unwrap_or_default();
+    let fields = [old, new]
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_object())
+        .flat_map(|o| o.keys())
+        .filter(|f| !IGNORED_FIELDS.contains(&f.as_str()))
+        .collect::<BTreeSet<_>>();
+
+    fields
+        .into_iter()
+        .map(|field| FieldDiff {
+            field: field.clone(),
+            old: get_field(old, field),
+            new: get_field(new, field),
+        })
+        .filter(|d| d.old != d.new)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parser_diff::{
+            decode_call, diff_operations, parse_call_spec, DiffKind, RecordedCall, RecordedResponse,
+        },
+        subscan_parser::Network,
+        Module, OperationStatus, OperationType, SubscanOperation,
+    };
+    use bson::DateTime;
+    use serde_json::{json, Value};
+
+    fn operation(extrinsic_index: &str, call_index: u32) -> SubscanOperation {
+        SubscanOperation {
+            hash: String::new(),
+            hash_version: 0,
+            block_number: 42,
+            extrinsic_index: extrinsic_index.to_string(),
+            call_index,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+            operation_quantity: 1.0,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 1.0,
+            operation_type: OperationType::Stake,
+            from_wallet: "from".to_string(),
+            controller_wallet: "from".to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn changed_fields_of_matching_operations_are_reported() {
+        let old = vec![
+            operation("42-1", 0),
+            operation("42-2", 0),
+            operation("42-3", 0),
+        ];
+        let mut changed = operation("42-2", 0);
+        changed.to_wallet = "validator".to_string();
+        changed.status = OperationStatus::Pending;
+        changed.hash = "rehashed".to_string();
+        let mut unchanged = operation("42-1", 0);
+        unchanged.status = OperationStatus::Pending;
+        let new = vec![unchanged, changed, operation("42-3", 5)];
+
+        let report = diff_operations(&old, &new);
+        assert_eq!((report.old_operations, report.new_operations), (3, 3));
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(
+            report
+                .diffs
+                .iter()
+                .map(|d| (d.extrinsic_index.as_str(), d.call_index, d.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                ("42-2", 0, DiffKind::Changed),
+                ("42-3", 0, DiffKind::Removed),
+                ("42-3", 5, DiffKind::Added)
+            ]
+        );
+
+        // hashes and statuses are set after decoding
+        assert_eq!(report.diffs[0].fields.len(), 1);
+        assert_eq!(report.diffs[0].fields[0].field, "to_wallet");
+        assert_eq!(
+            (
+                &report.diffs[0].fields[0].old,
+                &report.diffs[0].fields[0].new
+            ),
+            (&json!("to"), &json!("validator"))
+        );
+        assert_eq!(report.diffs[1].fields[0].new, Value::Null);
+    }
+
+    #[test]
+    fn call_specs_name_a_parsed_call() {
+        assert_eq!(
+            parse_call_spec("unbond"),
+            Some((Module::Staking, "unbond".to_string()))
+        );
+        assert_eq!(
+            parse_call_spec("nominationpools:join"),
+            Some((Module::NominationPools, "join".to_string()))
+        );
+        assert_eq!(
+            parse_call_spec("transfers"),
+            Some((Module::Staking, "transfers".to_string()))
+        );
+        assert_eq!(parse_call_spec("staking:join"), None);
+        assert_eq!(parse_call_spec("unknown:bond"), None);
+    }
+
+    #[tokio::test]
+    async fn recorded_calls_are_decoded_without_requests() {
+        let fixture: Value =
+            serde_json::from_str(include_str!("../fixtures/subscan_unbond_extrinsics.json"))
+                .unwrap();
+        let recorded_call = RecordedCall {
+            module: Module::Staking,
+            call: "unbond".to_string(),
+            min_block_number: None,
+            responses: vec![RecordedResponse {
+                endpoint: "extrinsics".to_string(),
+                response: fixture,
+            }],
+        };
+
+        let operations = decode_call(&Network::Alephzero, &recorded_call)
+            .await
+            .unwrap();
+        assert_eq!(
+            operations
+                .iter()
+                .map(|s| s.extrinsic_index.as_str())
+                .collect::<Vec<_>>(),
+            vec!["58123456-2", "58123460-3"]
+        );
+
+        // recordings are kept as plain json files
+        let recorded_json = serde_json::to_value(&recorded_call).unwrap();
+        assert_eq!(
+            serde_json::from_value::<RecordedCall>(recorded_json).unwrap(),
+            recorded_call
+        );
+    }
+}
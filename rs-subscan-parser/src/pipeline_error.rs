@@ -0,0 +1,138 @@
+use crate::subscan_parser::Network;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+
+// codes are part of the public payload, never rename existing variants
+#[derive(
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    SubscanApiError,
+    SubscanMissingField,
+    SubscanRateLimited,
+    HttpError,
+    DeserializationError,
+    DatabaseError,
+    PriceUnavailable,
+    InvalidRequest,
+    Unauthorized,
+    NotFound,
+    RateLimited,
+    InternalError,
+    TimestampSkew,
+    SubscanUnavailable,
+}
+
+impl ErrorCode {
+    // codes of the rest api error responses
+    pub fn from_http_status(status: u16) -> ErrorCode {
+        match status {
+            401 | 403 => ErrorCode::Unauthorized,
+            404 => ErrorCode::NotFound,
+            429 => ErrorCode::RateLimited,
+            400..=499 => ErrorCode::InvalidRequest,
+            _ => ErrorCode::InternalError,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PipelineError {
+    pub code: ErrorCode,
+    pub message: String,
+
+    // the slug, the variant name is not part of the payload and custom networks keep their url
+    pub network: String,
+    pub endpoint: Option<String>,
+    pub extrinsic_index: Option<String>,
+}
+
+impl PipelineError {
+    pub fn new(code: ErrorCode, message: &str, network: &Network) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+            network: network.get_slug().to_string(),
+            endpoint: None,
+            extrinsic_index: None,
+        }
+    }
+
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = Some(endpoint.to_string());
+        self
+    }
+
+    pub fn with_extrinsic_index(mut self, extrinsic_index: &str) -> Self {
+        self.extrinsic_index = Some(extrinsic_index.to_string());
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        pipeline_error::{ErrorCode, PipelineError},
+        subscan_parser::Network,
+    };
+
+    #[test]
+    fn payload_has_stable_codes_and_context() {
+        let pipeline_error = PipelineError::new(
+            ErrorCode::SubscanRateLimited,
+            "[20008] API rate limit exceeded",
+            &Network::Alephzero,
+        )
+        .with_endpoint("api/scan/extrinsic")
+        .with_extrinsic_index("42-3");
+
+        assert_eq!(
+            pipeline_error.to_json(),
+            r#"{"code":"SUBSCAN_RATE_LIMITED","message":"[20008] API rate limit exceeded","network":"alephzero","endpoint":"api/scan/extrinsic","extrinsic_index":"42-3"}"#
+        );
+    }
+
+    #[test]
+    fn custom_networks_only_show_their_slug() {
+        let network = Network::Custom {
+            slug: "devnet".to_string(),
+            base_url: "http://indexer.internal:4399".to_string(),
+            decimals: 10,
+            ss58_prefix: 7,
+            token_symbol: "DEV".to_string(),
+            explorer_url: None,
+        };
+        let pipeline_error = PipelineError::new(ErrorCode::HttpError, "timed out", &network);
+
+        assert_eq!(pipeline_error.network, "devnet");
+        assert!(!pipeline_error.to_json().contains("indexer.internal"));
+    }
+
+    #[test]
+    fn http_statuses_map_to_codes() {
+        assert_eq!(ErrorCode::from_http_status(400), ErrorCode::InvalidRequest);
+        assert_eq!(ErrorCode::from_http_status(401), ErrorCode::Unauthorized);
+        assert_eq!(ErrorCode::from_http_status(404), ErrorCode::NotFound);
+        assert_eq!(ErrorCode::from_http_status(422), ErrorCode::InvalidRequest);
+        assert_eq!(ErrorCode::from_http_status(429), ErrorCode::RateLimited);
+        assert_eq!(ErrorCode::from_http_status(503), ErrorCode::InternalError);
+    }
+}
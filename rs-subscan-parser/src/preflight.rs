@@ -0,0 +1,142 @@
+use crate::{
+    mongodb_client_identities::MongoDbClientIdentity, mongodb_client_subscan::MongoDbClientSubscan,
+    mongodb_client_validator::MongoDbClientValidator, subscan_parser::Network,
+    subscan_scheduler::SubscanEndpoint,
+};
+use bson::doc;
+use chrono::Utc;
+use mongodb::Collection;
+use reqwest::{header::DATE, Client, Url};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use std::{env, time::Duration};
+use tokio::net::lookup_host;
+use tracing::{info, warn};
+
+static TIMEOUT_MS: u64 = 10_000;
+static MAX_CLOCK_SKEW_SECONDS: i64 = 60;
+
+// validates configuration and external dependencies before the worker starts
+pub async fn preflight(network: Network) -> Result<(), String> {
+    if network == Network::Mock {
+        check_mongodb().await?;
+
+        info!(target: "preflight", "Mock network has no subscan checks, mongodb is reachable.");
+        return Ok(());
+    }
+
+    let base_url = network.get_base_url();
+    check_network(&base_url).await?;
+    check_api_keys(&base_url).await?;
+    check_mongodb().await?;
+
+    info!(target: "preflight", "All preflight checks passed for {network}.");
+    Ok(())
+}
+
+async fn check_network(base_url: &str) -> Result<(), String> {
+    let url = Url::parse(base_url).map_err(|e| format!("base url {base_url} is invalid: {e}"))?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| format!("base url {base_url} has no host"))?;
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let mut addresses = lookup_host(format!("{host}:{port}"))
+        .await
+        .map_err(|e| format!("network host {host} does not resolve: {e}"))?;
+
+    if addresses.next().is_none() {
+        return Err(format!("network host {host} resolved to no addresses"));
+    }
+
+    Ok(())
+}
+
+async fn check_api_keys(base_url: &str) -> Result<(), String> {
+    let api_keys =
+        env::var("SUBSCAN_API_KEY").map_err(|_| "SUBSCAN_API_KEY is not set".to_string())?;
+
+    let client = Client::builder()
+        .timeout(Duration::from_millis(TIMEOUT_MS))
+        .build()
+        .map_err(|e| format!("failed to create http client: {e}"))?;
+
+    // cheapest call we have, a single row of the extrinsics list
+    let url = format!("{base_url}/{}", SubscanEndpoint::Extrinsics.path());
+    for (i, api_key) in api_keys.split(',').enumerate() {
+        let resp = client
+            .post(&url)
+            .header("X-API-Key", api_key)
+            .json(&json!({"row": 1, "page": 0}))
+            .send()
+            .await
+            .map_err(|e| format!("subscan request with api key #{i} failed: {e}"))?;
+
+        let server_date = resp
+            .headers()
+            .get(DATE)
+            .and_then(|d| d.to_str().ok())
+            .and_then(|d| chrono::DateTime::parse_from_rfc2822(d).ok());
+        if let Some(server_date) = server_date {
+            let skew = (Utc::now().timestamp() - server_date.timestamp()).abs();
+            if skew > MAX_CLOCK_SKEW_SECONDS {
+                warn!(target: "preflight", "System clock is {skew} seconds off from subscan server time.");
+            }
+        }
+
+        let body: Value = resp
+            .json()
+            .await
+            .map_err(|e| format!("subscan response with api key #{i} is not json: {e}"))?;
+        let code = body.get("code").and_then(|c| c.as_u64());
+        if code != Some(0) {
+            let message = body
+                .get("message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("unknown error");
+            return Err(format!(
+                "subscan rejected api key #{i} with code {code:?}: {message}"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+async fn check_mongodb() -> Result<(), String> {
+    let mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let client_subscan = mongodb_client_subscan.client_subscan;
+    client_subscan
+        .db
+        .run_command(doc! {"ping": 1}, None)
+        .await
+        .map_err(|e| format!("mongodb is not reachable: {e}"))?;
+
+    check_indexes(&client_subscan.col, &["hash_1", "operation_timestamp_1"]).await;
+
+    let mongodb_client_validator = MongoDbClientValidator::new().await;
+    check_indexes(
+        &mongodb_client_validator.client_validator.col,
+        &["nominator_1_valid_from_1"],
+    )
+    .await;
+
+    let mongodb_client_identity = MongoDbClientIdentity::new().await;
+    check_indexes(&mongodb_client_identity.client_identity.col, &["address_1"]).await;
+
+    Ok(())
+}
+
+async fn check_indexes<T>(col: &Collection<T>, expected: &[&str])
+where
+    T: Serialize,
+    T: DeserializeOwned,
+{
+    // collection might not exist yet on the very first start
+    let names = col.list_index_names().await.unwrap_or_default();
+    for index in expected {
+        if !names.iter().any(|n| n == index) {
+            warn!(target: "preflight", "Index {index} is missing on {}, it will be created on start.", col.name());
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{
+    mongodb_client_price_annotations::MongoDbClientPriceAnnotations,
+    mongodb_client_subscan::MongoDbClientSubscan, PriceAnnotation, SubscanOperation,
+};
+use chrono::Utc;
+use rs_exchanges_parser::{
+    mongodb_client_exchanges::MongoDbClientExchanges, PrimaryToken, SecondaryToken,
+};
+use std::env;
+
+static DEFAULT_PRICE_ANNOTATION_MIN_USD: f64 = 10_000.0;
+static SECONDS_IN_HOUR: i64 = 60 * 60;
+static DEFAULT_MAX_PRICE_AGE_SECONDS: i64 = 15 * 60;
+
+// last offset is +24h, so anything older than that plus a margin is already complete
+static ANNOTATION_LOOKBACK_SECONDS: i64 = 25 * SECONDS_IN_HOUR;
+
+// fills prices at T-1h, T, T+1h and T+24h for recent large operations,
+// later offsets are only filled once they are in the past
+pub async fn annotate_large_operations() {
+    let min_usd = env::var("PRICE_ANNOTATION_MIN_USD")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .unwrap_or(DEFAULT_PRICE_ANNOTATION_MIN_USD);
+
+    let max_price_age = env::var("PRICE_ANNOTATION_MAX_PRICE_AGE_SECONDS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_MAX_PRICE_AGE_SECONDS);
+
+    let now = Utc::now().timestamp();
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let operations = mongodb_client_subscan
+        .get_large_operations(now - ANNOTATION_LOOKBACK_SECONDS, min_usd)
+        .await;
+
+    let mut mongodb_client_price_annotations = MongoDbClientPriceAnnotations::new().await;
+    let mut mongodb_client_exchanges = MongoDbClientExchanges::new().await;
+    for operation in operations {
+        let annotation = mongodb_client_price_annotations
+            .get_annotation(&operation.hash)
+            .await
+            .unwrap_or(empty_annotation(&operation));
+        if annotation.is_complete() {
+            continue;
+        }
+
+        let annotation = fill_annotation(
+            annotation,
+            &mut mongodb_client_exchanges,
+            now,
+            max_price_age,
+        )
+        .await;
+        mongodb_client_price_annotations
+            .import_or_update_annotation(annotation)
+            .await;
+    }
+}
+
+fn empty_annotation(operation: &SubscanOperation) -> PriceAnnotation {
+    PriceAnnotation {
+        hash: operation.hash.clone(),
+        operation_timestamp: operation.operation_timestamp,
+        price_before_1h: None,
+        price_at: None,
+        price_after_1h: None,
+        price_after_24h: None,
+    }
+}
+
+async fn fill_annotation(
+    mut annotation: PriceAnnotation,
+    mongodb_client_exchanges: &mut MongoDbClientExchanges,
+    now: i64,
+    max_price_age: i64,
+) -> PriceAnnotation {
+    for (offset, at) in get_missing_prices(&annotation, now) {
+        let price = mongodb_client_exchanges
+            .get_usd_price_at(PrimaryToken::Azero, SecondaryToken::Usdt, at, max_price_age)
+            .await;
+        match offset {
+            PriceOffset::Before1h => annotation.price_before_1h = price,
+            PriceOffset::At => annotation.price_at = price,
+            PriceOffset::After1h => annotation.price_after_1h = price,
+            PriceOffset::After24h => annotation.price_after_24h = price,
+        }
+    }
+
+    annotation
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PriceOffset {
+    Before1h,
+    At,
+    After1h,
+    After24h,
+}
+
+// prices still missing that can already be known, with their timestamps in seconds
+fn get_missing_prices(annotation: &PriceAnnotation, now: i64) -> Vec<(PriceOffset, i64)> {
+    let timestamp = annotation.operation_timestamp.timestamp_millis() / 1000;
+    let prices = [
+        (
+            PriceOffset::Before1h,
+            annotation.price_before_1h,
+            timestamp - SECONDS_IN_HOUR,
+        ),
+        (PriceOffset::At, annotation.price_at, timestamp),
+        (
+            PriceOffset::After1h,
+            annotation.price_after_1h,
+            timestamp + SECONDS_IN_HOUR,
+        ),
+        (
+            PriceOffset::After24h,
+            annotation.price_after_24h,
+            timestamp + 24 * SECONDS_IN_HOUR,
+        ),
+    ];
+
+    prices
+        .into_iter()
+        .filter(|(_, price, at)| price.is_none() && *at <= now)
+        .map(|(offset, _, at)| (offset, at))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        price_annotations::{get_missing_prices, PriceOffset},
+        PriceAnnotation,
+    };
+    use bson::DateTime;
+
+    #[test]
+    fn only_past_missing_prices_are_filled() {
+        let annotation = PriceAnnotation {
+            hash: "hash".to_string(),
+            operation_timestamp: DateTime::from_millis(100_000 * 1000),
+            price_before_1h: Some(0.5),
+            price_at: None,
+            price_after_1h: None,
+            price_after_24h: None,
+        };
+
+        assert_eq!(
+            get_missing_prices(&annotation, 100_000 + 2 * 60 * 60),
+            vec![
+                (PriceOffset::At, 100_000),
+                (PriceOffset::After1h, 100_000 + 60 * 60),
+            ]
+        );
+    }
+}
@@ -0,0 +1,342 @@
+use crate::{
+    mongodb_client_balance_snapshots::MongoDbClientBalanceSnapshots,
+    mongodb_client_subscan::MongoDbClientSubscan,
+    subscan_parser::{Network, SubscanParser},
+    BalanceSnapshot, OperationType, SubscanOperation,
+};
+use bson::DateTime;
+use serde::{Deserialize, Serialize};
+use std::{env, time::Duration};
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+
+// fees are only known for transfers, staking calls pay a little more than that
+static DEFAULT_RECONCILIATION_TOLERANCE: f64 = 0.1;
+static DEFAULT_BALANCE_SNAPSHOT_INTERVAL_SECONDS: u64 = 60 * 60;
+
+// RECONCILIATION_WALLETS, comma separated wallets whose balances are snapshotted
+pub fn get_reconciliation_wallets() -> Vec<String> {
+    env::var("RECONCILIATION_WALLETS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|w| !w.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+// RECONCILIATION_TOLERANCE in tokens, smaller unexplained deltas count as reconciled
+pub fn get_reconciliation_tolerance() -> f64 {
+    env::var("RECONCILIATION_TOLERANCE")
+        .ok()
+        .and_then(|t| t.parse::<f64>().ok())
+        .filter(|t| *t >= 0.0)
+        .unwrap_or(DEFAULT_RECONCILIATION_TOLERANCE)
+}
+
+// BALANCE_SNAPSHOT_INTERVAL_SECONDS
+pub fn get_balance_snapshot_interval_seconds() -> u64 {
+    env::var("BALANCE_SNAPSHOT_INTERVAL_SECONDS")
+        .ok()
+        .and_then(|i| i.parse::<u64>().ok())
+        .filter(|i| *i > 0)
+        .unwrap_or(DEFAULT_BALANCE_SNAPSHOT_INTERVAL_SECONDS)
+}
+
+// what the feed says moved the total balance of a wallet, in tokens, bonding and unbonding
+// keep the funds within the total
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct BalanceChanges {
+    pub received: f64,
+    pub sent: f64,
+    pub rewards: f64,
+    pub slashes: f64,
+    pub fees: f64,
+}
+
+impl BalanceChanges {
+    pub fn add_operation(&mut self, operation: &SubscanOperation, wallet: &str) {
+        let quantity = operation.operation_quantity;
+        let is_sender = operation.from_wallet == wallet;
+        let is_receiver = operation.to_wallet == wallet;
+        match operation.operation_type {
+            OperationType::Transfer
+            | OperationType::DepositToExchange
+            | OperationType::WithdrawFromExchange => {
+                if is_receiver {
+                    self.received += quantity;
+                }
+                if is_sender {
+                    self.sent += quantity;
+                    self.fees += operation.operation_fee.unwrap_or_default();
+                }
+            }
+            OperationType::Reward if is_receiver => self.rewards += quantity,
+            OperationType::Slash if is_sender => self.slashes += quantity,
+            _ => {}
+        }
+    }
+
+    pub fn get_expected_delta(&self) -> f64 {
+        self.received - self.sent + self.rewards - self.slashes - self.fees
+    }
+}
+
+// two consecutive snapshots of a wallet and the operations between them
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ReconciliationInterval {
+    pub from: DateTime,
+    pub to: DateTime,
+    pub opening_balance: f64,
+    pub closing_balance: f64,
+    pub operations: u64,
+    pub changes: BalanceChanges,
+    pub actual_delta: f64,
+    pub expected_delta: f64,
+
+    // moved by something the feed missed or got wrong
+    pub unexplained_delta: f64,
+    pub is_reconciled: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct WalletReconciliation {
+    pub wallet: String,
+    pub intervals: Vec<ReconciliationInterval>,
+    pub unexplained_delta: f64,
+    pub unreconciled_intervals: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ReconciliationReport {
+    pub from_timestamp: i64,
+    pub to_timestamp: i64,
+    pub tolerance: f64,
+    pub wallets: Vec<WalletReconciliation>,
+}
+
+// operations at the opening snapshot count to the interval, those at the closing one don't
+pub fn reconcile_interval(
+    wallet: &str,
+    opening: &BalanceSnapshot,
+    closing: &BalanceSnapshot,
+    operations: &[SubscanOperation],
+    tolerance: f64,
+) -> ReconciliationInterval {
+    let mut changes = BalanceChanges::default();
+    let mut count = 0;
+    for operation in operations.iter().filter(|o| {
+        o.operation_timestamp >= opening.timestamp && o.operation_timestamp < closing.timestamp
+    }) {
+        changes.add_operation(operation, wallet);
+        count += 1;
+    }
+
+    let actual_delta = closing.total - opening.total;
+    let expected_delta = changes.get_expected_delta();
+    let unexplained_delta = actual_delta - expected_delta;
+
+    ReconciliationInterval {
+        from: opening.timestamp,
+        to: closing.timestamp,
+        opening_balance: opening.total,
+        closing_balance: closing.total,
+        operations: count,
+        changes,
+        actual_delta,
+        expected_delta,
+        unexplained_delta,
+        is_reconciled: unexplained_delta.abs() <= tolerance,
+    }
+}
+
+// compacted operations are gone from the feed, ranges past the retention don't reconcile
+pub async fn reconcile_wallets(
+    wallets: Vec<String>,
+    from_timestamp: i64,
+    to_timestamp: i64,
+    tolerance: f64,
+) -> ReconciliationReport {
+    let mut mongodb_client_balance_snapshots = MongoDbClientBalanceSnapshots::new().await;
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+
+    let mut reconciliations = Vec::new();
+    for wallet in wallets {
+        let snapshots = mongodb_client_balance_snapshots
+            .get_snapshots(
+                &wallet,
+                DateTime::from_millis(from_timestamp * 1_000),
+                DateTime::from_millis(to_timestamp * 1_000),
+            )
+            .await;
+        let operations = match (snapshots.first(), snapshots.last()) {
+            (Some(first), Some(last)) if snapshots.len() > 1 => {
+                mongodb_client_subscan
+                    .get_wallet_range_operations(
+                        &wallet,
+                        first.timestamp.timestamp_millis() / 1_000,
+                        last.timestamp.timestamp_millis() / 1_000 + 1,
+                    )
+                    .await
+            }
+            _ => Vec::new(),
+        };
+
+        let intervals = snapshots
+            .windows(2)
+            .map(|s| reconcile_interval(&wallet, &s[0], &s[1], &operations, tolerance))
+            .collect::<Vec<_>>();
+        for interval in intervals.iter().filter(|i| !i.is_reconciled) {
+            warn!(target: "reconciliation", "{wallet} from {} to {}: {:.4} tokens unexplained.", interval.from, interval.to, interval.unexplained_delta);
+        }
+
+        reconciliations.push(WalletReconciliation {
+            wallet,
+            unexplained_delta: intervals.iter().map(|i| i.unexplained_delta).sum(),
+            unreconciled_intervals: intervals.iter().filter(|i| !i.is_reconciled).count() as u64,
+            intervals,
+        });
+    }
+
+    ReconciliationReport {
+        from_timestamp,
+        to_timestamp,
+        tolerance,
+        wallets: reconciliations,
+    }
+}
+
+// wallets subscan can't answer for are left out of this round
+pub async fn take_balance_snapshots(wallets: &[String]) -> Vec<BalanceSnapshot> {
+    let mut subscan_parser = SubscanParser::new(Network::from_env()).await;
+    let mut snapshots = Vec::new();
+    for wallet in wallets {
+        match subscan_parser.parse_subscan_balance(wallet).await {
+            Ok(snapshot) => snapshots.push(snapshot),
+            Err(e) => {
+                error!(target: "reconciliation", "Failed to get the balance of {wallet}: {e}.")
+            }
+        }
+    }
+
+    let mut mongodb_client_balance_snapshots = MongoDbClientBalanceSnapshots::new().await;
+    mongodb_client_balance_snapshots
+        .import_snapshots(snapshots.clone())
+        .await;
+
+    snapshots
+}
+
+pub async fn take_balance_snapshots_periodically(wallets: Vec<String>) {
+    if wallets.is_empty() {
+        return;
+    }
+
+    let mut mongodb_client_balance_snapshots = MongoDbClientBalanceSnapshots::new().await;
+    mongodb_client_balance_snapshots.create_index().await;
+
+    let interval_seconds = get_balance_snapshot_interval_seconds();
+    loop {
+        let snapshots = take_balance_snapshots(&wallets).await;
+        info!(target: "reconciliation", "Took {} of {} balance snapshots.", snapshots.len(), wallets.len());
+
+        sleep(Duration::from_secs(interval_seconds)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        reconciliation::reconcile_interval, BalanceSnapshot, OperationStatus, OperationType,
+        SubscanOperation,
+    };
+    use bson::DateTime;
+
+    static WALLET: &str = "wallet";
+
+    fn snapshot(timestamp_ms: i64, total: f64) -> BalanceSnapshot {
+        BalanceSnapshot {
+            wallet: WALLET.to_string(),
+            timestamp: DateTime::from_millis(timestamp_ms),
+            total,
+            reserved: 0.0,
+            bonded: 0.0,
+        }
+    }
+
+    fn operation(
+        timestamp_ms: i64,
+        operation_type: OperationType,
+        from_wallet: &str,
+        to_wallet: &str,
+        quantity: f64,
+        fee: Option<f64>,
+    ) -> SubscanOperation {
+        SubscanOperation {
+            hash: format!("{timestamp_ms}"),
+            hash_version: 0,
+            block_number: 1,
+            extrinsic_index: "1-1".to_string(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(timestamp_ms),
+            operation_quantity: quantity,
+            operation_planck: None,
+            operation_fee: fee,
+            operation_era: None,
+            operation_usd: 0.0,
+            operation_type,
+            from_wallet: from_wallet.to_string(),
+            controller_wallet: String::new(),
+            to_wallet: to_wallet.to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn balance_deltas_are_explained_by_operations() {
+        let operations = vec![
+            operation(1_000, OperationType::Transfer, "other", WALLET, 100.0, None),
+            operation(
+                2_000,
+                OperationType::Transfer,
+                WALLET,
+                "other",
+                30.0,
+                Some(0.5),
+            ),
+            operation(3_000, OperationType::Reward, "validator", WALLET, 2.0, None),
+            operation(4_000, OperationType::Slash, WALLET, "", 1.0, None),
+            // bonded funds are part of the total
+            operation(5_000, OperationType::Stake, WALLET, "validator", 50.0, None),
+            // after the closing snapshot
+            operation(10_000, OperationType::Transfer, "other", WALLET, 7.0, None),
+        ];
+
+        let interval = reconcile_interval(
+            WALLET,
+            &snapshot(1_000, 10.0),
+            &snapshot(10_000, 80.5),
+            &operations,
+            0.1,
+        );
+        assert_eq!(interval.operations, 5);
+        assert_eq!(interval.changes.received, 100.0);
+        assert_eq!(interval.changes.fees, 0.5);
+        assert_eq!(interval.expected_delta, 70.5);
+        assert_eq!(interval.unexplained_delta, 0.0);
+        assert!(interval.is_reconciled);
+
+        // a missed transfer of 20 tokens
+        let interval = reconcile_interval(
+            WALLET,
+            &snapshot(1_000, 10.0),
+            &snapshot(10_000, 60.5),
+            &operations,
+            0.1,
+        );
+        assert_eq!(interval.unexplained_delta, -20.0);
+        assert!(!interval.is_reconciled);
+    }
+}
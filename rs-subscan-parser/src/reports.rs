@@ -0,0 +1,312 @@
+use crate::{
+    exports::{compression::ExportCompression, csv::write_query_csv, parts::ExportConfig},
+    mongodb_client_operation_summaries::MongoDbClientOperationSummaries,
+    mongodb_client_subscan::MongoDbClientSubscan,
+    object_storage::{ObjectStorage, ObjectStorageConfig},
+    subscan_parser::Network,
+    OperationTypeVolume,
+};
+use chrono::{Datelike, Days, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, env, str::FromStr, time::Duration};
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+use tokio::time::sleep;
+use tracing::{error, info};
+
+static CHECK_INTERVAL_SECONDS: u64 = 10 * 60;
+
+// operations of a day are still parsed and summarized for a while after it ended
+static REPORT_DELAY_SECONDS: i64 = 60 * 60;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ReportKind {
+    // operations of the previous day, written like EXPORT_CSV_PRESET and EXPORT_CSV_COLUMNS
+    #[default]
+    DailyOperationsCsv,
+
+    // operation type totals of the previous iso week from the hourly summaries
+    WeeklyStatsJson,
+}
+
+impl ReportKind {
+    // REPORT_DAILY_OPERATIONS_CSV_PATH and REPORT_WEEKLY_STATS_JSON_PATH, with {network},
+    // {date}, {year}, {month}, {day} and {week} filled in from the first day of the period
+    pub fn get_path_template(&self) -> String {
+        let key = format!("REPORT_{}_PATH", self.to_string().to_uppercase());
+        env::var(key)
+            .ok()
+            .filter(|t| !t.trim().is_empty())
+            .unwrap_or(self.get_default_path_template().to_string())
+    }
+
+    pub fn get_default_path_template(&self) -> &'static str {
+        match self {
+            ReportKind::DailyOperationsCsv => "{network}/operations/{year}/{month}/{date}.csv",
+            ReportKind::WeeklyStatsJson => "{network}/stats/{week}.json",
+        }
+    }
+
+    // the last complete day or week before today, the end is exclusive
+    pub fn get_period(&self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            ReportKind::DailyOperationsCsv => (today - Days::new(1), today),
+            ReportKind::WeeklyStatsJson => {
+                let monday = today - Days::new(today.weekday().num_days_from_monday() as u64);
+                (monday - Days::new(7), monday)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct WeeklyStats {
+    pub network: String,
+    pub week: String,
+
+    // dates in utc, to is exclusive
+    pub from: String,
+    pub to: String,
+    pub count: u64,
+    pub usd: f64,
+    pub operation_types: Vec<OperationTypeVolume>,
+}
+
+// REPORTS, comma separated kinds delivered once their period is complete
+pub fn get_scheduled_reports() -> Vec<ReportKind> {
+    env::var("REPORTS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+        .filter_map(|k| {
+            ReportKind::from_str(k)
+                .map_err(|_| error!(target: "reports", "Unknown report {k}, skipping it."))
+                .ok()
+        })
+        .collect()
+}
+
+pub fn render_path(template: &str, network: &str, from: NaiveDate) -> String {
+    template
+        .replace("{network}", network)
+        .replace("{date}", &from.format("%Y-%m-%d").to_string())
+        .replace("{year}", &from.format("%Y").to_string())
+        .replace("{month}", &from.format("%m").to_string())
+        .replace("{day}", &from.format("%d").to_string())
+        .replace("{week}", &from.format("%G-W%V").to_string())
+}
+
+// compressed csvs get the extension of EXPORT_COMPRESSION appended
+pub fn get_report_key(
+    kind: ReportKind,
+    network: &str,
+    from: NaiveDate,
+    compression: ExportCompression,
+) -> String {
+    let path = render_path(&kind.get_path_template(), network, from);
+    match kind {
+        ReportKind::DailyOperationsCsv => format!("{path}{}", compression.get_extension()),
+        ReportKind::WeeklyStatsJson => path,
+    }
+}
+
+fn get_timestamp(date: NaiveDate) -> i64 {
+    date.and_hms_opt(0, 0, 0)
+        .unwrap_or_default()
+        .and_utc()
+        .timestamp()
+}
+
+// content type and body of a report
+async fn get_report(
+    kind: ReportKind,
+    network: &Network,
+    from: NaiveDate,
+    to: NaiveDate,
+    config: &ExportConfig,
+) -> Result<(&'static str, Vec<u8>), String> {
+    match kind {
+        ReportKind::DailyOperationsCsv => {
+            let options = config
+                .csv_preset
+                .options()
+                .with_columns(config.csv_columns.clone());
+            let query = MongoDbClientSubscan::get_time_range_query(
+                get_timestamp(from),
+                Some(get_timestamp(to)),
+            );
+
+            let mut writer = config
+                .compression
+                .get_writer(Vec::new())
+                .map_err(|e| e.to_string())?;
+            write_query_csv(&mut writer, query, &options)
+                .await
+                .map_err(|e| e.to_string())?;
+            let body = writer.finish().map_err(|e| e.to_string())?;
+
+            Ok((
+                config.compression.get_content_type().unwrap_or("text/csv"),
+                body,
+            ))
+        }
+        ReportKind::WeeklyStatsJson => {
+            let stats = get_weekly_stats(network, from, to).await;
+            let body = serde_json::to_vec_pretty(&stats).map_err(|e| e.to_string())?;
+
+            Ok(("application/json", body))
+        }
+    }
+}
+
+async fn get_weekly_stats(network: &Network, from: NaiveDate, to: NaiveDate) -> WeeklyStats {
+    let mut mongodb_client_operation_summaries = MongoDbClientOperationSummaries::new().await;
+    let operation_types = mongodb_client_operation_summaries
+        .get_operation_type_volumes(
+            bson::DateTime::from_millis(get_timestamp(from) * 1_000),
+            bson::DateTime::from_millis(get_timestamp(to) * 1_000),
+        )
+        .await;
+
+    WeeklyStats {
+        network: network.get_slug().to_string(),
+        week: from.format("%G-W%V").to_string(),
+        from: from.to_string(),
+        to: to.to_string(),
+        count: operation_types.iter().map(|v| v.count).sum(),
+        usd: operation_types.iter().map(|v| v.usd).sum(),
+        operation_types,
+    }
+}
+
+// skips reports already in the bucket, so restarts and several workers deliver each one once
+async fn deliver_report(
+    storage: &ObjectStorage,
+    kind: ReportKind,
+    network: &Network,
+    today: NaiveDate,
+    key: &str,
+) -> Result<(), String> {
+    if storage.has_object(key).await? {
+        return Ok(());
+    }
+
+    let (from, to) = kind.get_period(today);
+    let config = ExportConfig::from_env();
+    let (content_type, body) = get_report(kind, network, from, to, &config).await?;
+    storage.put_object(key, body, content_type).await?;
+    info!(target: "reports", "Delivered {kind} report {key}.");
+
+    Ok(())
+}
+
+// checks every few minutes whether the last period of each report is in the bucket, failed
+// deliveries are tried again on the next check
+pub async fn deliver_reports_periodically(config: ObjectStorageConfig, kinds: Vec<ReportKind>) {
+    if kinds.is_empty() {
+        return;
+    }
+
+    info!(target: "reports", "Delivering {} reports to bucket {}.", kinds.len(), config.bucket);
+    let storage = ObjectStorage::new(config);
+    let network = Network::from_env();
+    let compression = ExportConfig::from_env().compression;
+    let mut delivered_keys = HashSet::new();
+    loop {
+        let today = (Utc::now() - chrono::Duration::seconds(REPORT_DELAY_SECONDS)).date_naive();
+        for kind in &kinds {
+            let from = kind.get_period(today).0;
+            let key = get_report_key(*kind, network.get_slug(), from, compression);
+            if delivered_keys.contains(&key) {
+                continue;
+            }
+
+            match deliver_report(&storage, *kind, &network, today, &key).await {
+                Ok(()) => {
+                    delivered_keys.insert(key);
+                }
+                Err(e) => error!(target: "reports", "Failed to deliver {kind} report {key}: {e}."),
+            }
+        }
+
+        sleep(Duration::from_secs(CHECK_INTERVAL_SECONDS)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        exports::compression::ExportCompression,
+        reports::{get_report_key, render_path, ReportKind},
+    };
+    use chrono::NaiveDate;
+
+    #[test]
+    fn report_periods_end_before_today() {
+        // a wednesday
+        let today = NaiveDate::from_ymd_opt(2024, 1, 3).unwrap();
+        assert_eq!(
+            ReportKind::DailyOperationsCsv.get_period(today),
+            (NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(), today)
+        );
+        assert_eq!(
+            ReportKind::WeeklyStatsJson.get_period(today),
+            (
+                NaiveDate::from_ymd_opt(2023, 12, 25).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn report_paths_are_rendered_from_the_first_day() {
+        let from = NaiveDate::from_ymd_opt(2024, 12, 30).unwrap();
+        assert_eq!(
+            render_path(
+                ReportKind::DailyOperationsCsv.get_default_path_template(),
+                "polkadot",
+                from
+            ),
+            "polkadot/operations/2024/12/2024-12-30.csv"
+        );
+
+        // iso weeks belong to the year of their thursday
+        assert_eq!(
+            render_path(
+                ReportKind::WeeklyStatsJson.get_default_path_template(),
+                "polkadot",
+                from
+            ),
+            "polkadot/stats/2025-W01.json"
+        );
+        assert_eq!(render_path("{day}/{network}", "kusama", from), "30/kusama");
+
+        assert_eq!(
+            get_report_key(
+                ReportKind::DailyOperationsCsv,
+                "polkadot",
+                from,
+                ExportCompression::Gzip
+            ),
+            "polkadot/operations/2024/12/2024-12-30.csv.gz"
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use rand::Rng;
+use std::{env, time::Duration};
+
+static DEFAULT_SUBSCAN_MAX_ATTEMPTS: u32 = 10;
+static DEFAULT_SUBSCAN_BACKOFF_BASE_MS: u64 = 1_000;
+static DEFAULT_SUBSCAN_BACKOFF_MAX_MS: u64 = 60_000;
+static DEFAULT_SUBSCAN_BACKOFF_JITTER: f64 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+
+    // share of the backoff taken off at random, spreads out workers failing together
+    pub jitter: f64,
+}
+
+impl RetryPolicy {
+    // SUBSCAN_MAX_ATTEMPTS, SUBSCAN_BACKOFF_BASE_MS, SUBSCAN_BACKOFF_MAX_MS and SUBSCAN_BACKOFF_JITTER
+    pub fn from_env() -> RetryPolicy {
+        let max_attempts = env::var("SUBSCAN_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_SUBSCAN_MAX_ATTEMPTS);
+        let base_delay_ms = env::var("SUBSCAN_BACKOFF_BASE_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_SUBSCAN_BACKOFF_BASE_MS);
+        let max_delay_ms = env::var("SUBSCAN_BACKOFF_MAX_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_SUBSCAN_BACKOFF_MAX_MS)
+            .max(base_delay_ms);
+        let jitter = env::var("SUBSCAN_BACKOFF_JITTER")
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|v| (0.0..=1.0).contains(v))
+            .unwrap_or(DEFAULT_SUBSCAN_BACKOFF_JITTER);
+
+        Self {
+            max_attempts,
+            base_delay_ms,
+            max_delay_ms,
+            jitter,
+        }
+    }
+
+    // doubles after every failed attempt, starting with the first one
+    pub fn get_backoff_ms(&self, attempt: u32) -> u64 {
+        let factor = 1u64
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u64::MAX);
+        self.base_delay_ms
+            .saturating_mul(factor)
+            .min(self.max_delay_ms)
+    }
+
+    pub fn get_delay<R: Rng>(&self, attempt: u32, rng: &mut R) -> Duration {
+        let backoff_ms = self.get_backoff_ms(attempt) as f64;
+        let jitter_ms = backoff_ms * self.jitter * rng.gen::<f64>();
+        Duration::from_millis((backoff_ms - jitter_ms) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::retry_policy::RetryPolicy;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::time::Duration;
+
+    static RETRY_POLICY: RetryPolicy = RetryPolicy {
+        max_attempts: 10,
+        base_delay_ms: 1_000,
+        max_delay_ms: 60_000,
+        jitter: 0.5,
+    };
+
+    #[test]
+    fn backoff_doubles_up_to_the_max() {
+        let backoffs = (1..=8)
+            .map(|a| RETRY_POLICY.get_backoff_ms(a))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            backoffs,
+            vec![1_000, 2_000, 4_000, 8_000, 16_000, 32_000, 60_000, 60_000]
+        );
+        assert_eq!(RETRY_POLICY.get_backoff_ms(u32::MAX), 60_000);
+    }
+
+    #[test]
+    fn jitter_only_shortens_the_backoff() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for attempt in 1..=10 {
+            let backoff = Duration::from_millis(RETRY_POLICY.get_backoff_ms(attempt));
+            let delay = RETRY_POLICY.get_delay(attempt, &mut rng);
+            assert!(delay <= backoff);
+            assert!(delay >= backoff / 2);
+        }
+
+        let retry_policy = RetryPolicy {
+            jitter: 0.0,
+            ..RETRY_POLICY
+        };
+        assert_eq!(
+            retry_policy.get_delay(3, &mut rng),
+            Duration::from_millis(4_000)
+        );
+    }
+}
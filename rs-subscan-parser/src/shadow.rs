@@ -0,0 +1,41 @@
+use std::env;
+
+static SHADOW_COLLECTION_SUFFIX: &str = "_shadow";
+
+// SHADOW_MODE=true runs a second pipeline next to production, e.g. a new network or a new
+// build, it only writes to mongodb, into the shadow collections, and leaves the stats, the
+// anomalies and the deliveries which the feed is notified from alone
+pub fn is_shadow_mode() -> bool {
+    env::var("SHADOW_MODE").is_ok_and(|v| v == "true")
+}
+
+// collection of a MONGODB_COLLECTION_* variable, in shadow mode <collection>_shadow
+pub fn get_collection_name(var: &str) -> String {
+    let col = env::var(var).unwrap();
+    get_shadow_collection_name(col, is_shadow_mode())
+}
+
+fn get_shadow_collection_name(col: String, is_shadow_mode: bool) -> String {
+    if is_shadow_mode {
+        format!("{col}{SHADOW_COLLECTION_SUFFIX}")
+    } else {
+        col
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shadow::get_shadow_collection_name;
+
+    #[test]
+    fn shadow_runs_write_next_to_production() {
+        assert_eq!(
+            get_shadow_collection_name("subscan".to_string(), true),
+            "subscan_shadow"
+        );
+        assert_eq!(
+            get_shadow_collection_name("subscan".to_string(), false),
+            "subscan"
+        );
+    }
+}
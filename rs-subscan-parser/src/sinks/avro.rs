@@ -0,0 +1,179 @@
+use crate::sinks::protobuf::{ProtoField, ProtoType};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+static AVRO_NAMESPACE: &str = "nymtradefeed";
+
+// confluent wire format, magic byte and the registry id of the writer schema
+static MAGIC_BYTE: u8 = 0;
+
+// subjects follow the record name strategy so every topic shares one schema history
+pub fn get_subject() -> String {
+    format!("{AVRO_NAMESPACE}.Operation")
+}
+
+// record schema generated from the same field table as the .proto, every field has a default
+// so adding fields keeps old and new readers compatible, fields must never be removed
+pub fn get_schema(fields: &[ProtoField]) -> Value {
+    get_record_schema("Operation", fields)
+}
+
+fn get_record_schema(name: &str, fields: &[ProtoField]) -> Value {
+    let fields = fields
+        .iter()
+        .map(|field| {
+            let (avro_type, default) = match field.proto_type {
+                ProtoType::String => (json!("string"), json!("")),
+                ProtoType::Uint64 => (json!("long"), json!(0)),
+                ProtoType::Double => (json!("double"), json!(0.0)),
+                ProtoType::OptionalDouble => (json!(["null", "double"]), Value::Null),
+                ProtoType::RepeatedString => {
+                    (json!({"type": "array", "items": "string"}), json!([]))
+                }
+                ProtoType::Message(message, message_fields) => (
+                    json!(["null", get_record_schema(message, message_fields)]),
+                    Value::Null,
+                ),
+            };
+
+            json!({"name": field.name, "type": avro_type, "default": default})
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "type": "record",
+        "name": name,
+        "namespace": AVRO_NAMESPACE,
+        "fields": fields,
+    })
+}
+
+// avro binary datum framed for schema registry aware consumers
+pub fn encode<T: Serialize>(item: &T, fields: &[ProtoField], schema_id: u32) -> Vec<u8> {
+    let value = serde_json::to_value(item).unwrap_or_default();
+    let mut buffer = vec![MAGIC_BYTE];
+    buffer.extend_from_slice(&schema_id.to_be_bytes());
+    encode_record(&value, fields, &mut buffer);
+
+    buffer
+}
+
+// avro has no optional record fields, missing values are written as their defaults
+fn encode_record(value: &Value, fields: &[ProtoField], buffer: &mut Vec<u8>) {
+    for field in fields {
+        let value = value.get(field.name).unwrap_or(&Value::Null);
+
+        match field.proto_type {
+            ProtoType::String => encode_string(value.as_str().unwrap_or_default(), buffer),
+            ProtoType::Uint64 => encode_long(value.as_u64().unwrap_or_default() as i64, buffer),
+            ProtoType::Double => {
+                buffer.extend_from_slice(&value.as_f64().unwrap_or_default().to_le_bytes())
+            }
+            ProtoType::OptionalDouble => match value.as_f64() {
+                Some(n) => {
+                    encode_long(1, buffer);
+                    buffer.extend_from_slice(&n.to_le_bytes());
+                }
+                None => encode_long(0, buffer),
+            },
+            ProtoType::RepeatedString => {
+                let items = value
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>();
+                if !items.is_empty() {
+                    encode_long(items.len() as i64, buffer);
+                    for item in items {
+                        encode_string(item, buffer);
+                    }
+                }
+                encode_long(0, buffer);
+            }
+            ProtoType::Message(_, message_fields) => {
+                if value.is_object() {
+                    encode_long(1, buffer);
+                    encode_record(value, message_fields, buffer);
+                } else {
+                    encode_long(0, buffer);
+                }
+            }
+        }
+    }
+}
+
+// zigzag varint
+fn encode_long(n: i64, buffer: &mut Vec<u8>) {
+    let mut n = ((n << 1) ^ (n >> 63)) as u64;
+    while n >= 0x80 {
+        buffer.push((n as u8 & 0x7f) | 0x80);
+        n >>= 7;
+    }
+    buffer.push(n as u8);
+}
+
+fn encode_string(s: &str, buffer: &mut Vec<u8>) {
+    encode_long(s.len() as i64, buffer);
+    buffer.extend_from_slice(s.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sinks::{
+        avro::{encode, encode_long, get_schema},
+        protobuf::OPERATION_FIELDS,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn longs_are_zigzag_encoded() {
+        for (n, expected) in [
+            (0, vec![0x00]),
+            (-1, vec![0x01]),
+            (1, vec![0x02]),
+            (64, vec![0x80, 0x01]),
+        ] {
+            let mut buffer = Vec::new();
+            encode_long(n, &mut buffer);
+            assert_eq!(buffer, expected);
+        }
+    }
+
+    #[test]
+    fn schema_fields_have_defaults() {
+        let schema = get_schema(OPERATION_FIELDS);
+        let fields = schema["fields"].as_array().unwrap();
+
+        assert_eq!(fields.len(), OPERATION_FIELDS.len());
+        assert!(fields.iter().all(|f| f.get("default").is_some()));
+        assert_eq!(
+            fields[1],
+            json!({"name": "block_number", "type": "long", "default": 0})
+        );
+        assert_eq!(fields[12]["type"][1]["name"], "PriceAnnotation");
+    }
+
+    #[test]
+    fn records_are_framed_with_the_schema_id() {
+        let operation = json!({
+            "hash": "h",
+            "block_number": 1,
+            "annotations": ["a"],
+            "price_annotation": {"price_at": 0.5},
+        });
+
+        let encoded = encode(&operation, OPERATION_FIELDS, 7);
+        let mut expected = vec![0x00, 0x00, 0x00, 0x00, 0x07, 0x02, b'h', 0x02];
+        // 6 empty strings up to to_wallet and 2 for the amounts
+        expected.extend_from_slice(&[0x00; 8]);
+        expected.extend_from_slice(&0.0f64.to_le_bytes());
+        expected.extend_from_slice(&[0x02, 0x02, b'a', 0x00]);
+        expected.extend_from_slice(&[0x02, 0x00, 0x02]);
+        expected.extend_from_slice(&0.5f64.to_le_bytes());
+        // the missing operation_id is an empty string
+        expected.extend_from_slice(&[0x00, 0x00, 0x00]);
+
+        assert_eq!(encoded, expected);
+    }
+}
@@ -0,0 +1,86 @@
+use crate::sinks::Sink;
+use std::{env, slice::Chunks, time::Duration};
+
+// how a sink takes its operations, one by one unless {SINK}_BATCH_SIZE or
+// {SINK}_BATCH_WINDOW_MS are set, e.g. KAFKA_BATCH_SIZE=500 and KAFKA_BATCH_WINDOW_MS=5000
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SinkBatching {
+    // operations per delivery, 1 delivers every operation on its own
+    pub max_size: usize,
+
+    // how long the outbox dispatcher holds pending operations back for more to come, runs
+    // written without the outbox are batches of their own
+    pub window: Duration,
+}
+
+impl Default for SinkBatching {
+    fn default() -> Self {
+        Self {
+            max_size: 1,
+            window: Duration::ZERO,
+        }
+    }
+}
+
+impl SinkBatching {
+    pub fn from_env(sink: &Sink) -> SinkBatching {
+        let prefix = sink.to_string().to_uppercase();
+        let max_size = env::var(format!("{prefix}_BATCH_SIZE"))
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(1);
+        let window_ms = env::var(format!("{prefix}_BATCH_WINDOW_MS"))
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or_default();
+
+        Self {
+            max_size,
+            window: Duration::from_millis(window_ms),
+        }
+    }
+
+    pub fn is_per_operation(&self) -> bool {
+        self.max_size <= 1
+    }
+
+    // pending operations go out once a batch is full or the oldest of them waited the window
+    pub fn is_due(&self, pending: usize, oldest_age: Duration) -> bool {
+        pending >= self.max_size || oldest_age >= self.window
+    }
+
+    pub fn get_batches<'a, T>(&self, items: &'a [T]) -> Chunks<'a, T> {
+        items.chunks(self.max_size.max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sinks::batching::SinkBatching;
+    use std::time::Duration;
+
+    #[test]
+    fn batches_go_out_when_full_or_old_enough() {
+        let batching = SinkBatching {
+            max_size: 500,
+            window: Duration::from_secs(5),
+        };
+        assert!(!batching.is_per_operation());
+        assert!(!batching.is_due(499, Duration::from_secs(4)));
+        assert!(batching.is_due(500, Duration::ZERO));
+        assert!(batching.is_due(1, Duration::from_secs(5)));
+
+        let sizes = batching
+            .get_batches(&[0; 1_200])
+            .map(|b| b.len())
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, vec![500, 500, 200]);
+
+        // one by one, every pending operation is due at once
+        let batching = SinkBatching::default();
+        assert!(batching.is_per_operation());
+        assert!(batching.is_due(1, Duration::ZERO));
+        assert_eq!(batching.get_batches(&[0; 3]).count(), 3);
+    }
+}
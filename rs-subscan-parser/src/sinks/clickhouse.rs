@@ -0,0 +1,140 @@
+use crate::{subscan_parser::Network, SubscanOperation};
+use chrono::{TimeZone, Utc};
+use rs_utils::clients::clickhouse_client::ClickHouseClient;
+use serde::{Deserialize, Serialize};
+use std::env;
+
+static DEFAULT_CLICKHOUSE_TABLE: &str = "operations";
+static DEFAULT_CLICKHOUSE_BATCH_SIZE: usize = 10_000;
+
+// replacing merge tree drops operations written twice on merges, queries needing exact counts use FINAL
+static CREATE_TABLE_QUERY: &str = r#"CREATE TABLE IF NOT EXISTS {table} (
+    network LowCardinality(String),
+    hash String,
+    block_number UInt64,
+    extrinsic_index String,
+    operation_timestamp DateTime64(3, 'UTC'),
+    operation_quantity Float64,
+    operation_usd Float64,
+    operation_type LowCardinality(String),
+    from_wallet String,
+    controller_wallet String,
+    to_wallet String
+)
+ENGINE = ReplacingMergeTree
+PARTITION BY toYYYYMM(operation_timestamp)
+ORDER BY (network, operation_type, operation_timestamp, hash)"#;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct ClickHouseOperation {
+    pub network: String,
+    pub hash: String,
+    pub block_number: u64,
+    pub extrinsic_index: String,
+    pub operation_timestamp: String,
+    pub operation_quantity: f64,
+    pub operation_usd: f64,
+    pub operation_type: String,
+    pub from_wallet: String,
+    pub controller_wallet: String,
+    pub to_wallet: String,
+}
+
+impl ClickHouseOperation {
+    pub fn new(network: &Network, s: &SubscanOperation) -> Self {
+        let operation_timestamp = Utc
+            .timestamp_millis_opt(s.operation_timestamp.timestamp_millis())
+            .single()
+            .unwrap_or_default()
+            .format("%Y-%m-%d %H:%M:%S%.3f")
+            .to_string();
+
+        Self {
+            network: network.to_string(),
+            hash: s.hash.clone(),
+            block_number: s.block_number,
+            extrinsic_index: s.extrinsic_index.clone(),
+            operation_timestamp,
+            operation_quantity: s.operation_quantity,
+            operation_usd: s.operation_usd,
+            operation_type: s.operation_type.to_string(),
+            from_wallet: s.from_wallet.clone(),
+            controller_wallet: s.controller_wallet.clone(),
+            to_wallet: s.to_wallet.clone(),
+        }
+    }
+}
+
+pub async fn write_operations(operations: &[SubscanOperation]) {
+    if operations.is_empty() {
+        return;
+    }
+
+    let url = &env::var("CLICKHOUSE_URL").unwrap();
+    let user = env::var("CLICKHOUSE_USER")
+        .ok()
+        .filter(|u| !u.is_empty())
+        .unwrap_or("default".to_string());
+    let password = env::var("CLICKHOUSE_PASSWORD").unwrap_or_default();
+    let table = env::var("CLICKHOUSE_TABLE")
+        .ok()
+        .filter(|t| !t.is_empty())
+        .unwrap_or(DEFAULT_CLICKHOUSE_TABLE.to_string());
+    let batch_size = env::var("CLICKHOUSE_BATCH_SIZE")
+        .ok()
+        .and_then(|b| b.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_CLICKHOUSE_BATCH_SIZE)
+        .max(1);
+
+    let mut clickhouse_client = ClickHouseClient::new(url, &user, &password, "clickhouse").await;
+    clickhouse_client
+        .execute(&CREATE_TABLE_QUERY.replace("{table}", &table))
+        .await;
+
+    let network = Network::from_env();
+    for chunk in operations.chunks(batch_size) {
+        let rows = chunk
+            .iter()
+            .map(|s| ClickHouseOperation::new(&network, s))
+            .collect::<Vec<_>>();
+        clickhouse_client.insert_json_each_row(&table, &rows).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        sinks::clickhouse::ClickHouseOperation, subscan_parser::Network, OperationStatus,
+        OperationType, SubscanOperation,
+    };
+    use bson::DateTime;
+
+    #[test]
+    fn rows_use_clickhouse_datetime_format() {
+        let operation = SubscanOperation {
+            hash: "hash".to_string(),
+            hash_version: 0,
+            block_number: 1,
+            extrinsic_index: "1-1".to_string(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_123),
+            operation_quantity: 1.5,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 3.0,
+            operation_type: OperationType::RequestUnstake,
+            from_wallet: "from".to_string(),
+            controller_wallet: "controller".to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        };
+
+        let row = ClickHouseOperation::new(&Network::Alephzero, &operation);
+        assert_eq!(row.network, "alephzero");
+        assert_eq!(row.operation_timestamp, "2023-11-14 22:13:20.123");
+        assert_eq!(row.operation_type, "RequestUnstake");
+    }
+}
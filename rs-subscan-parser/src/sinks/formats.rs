@@ -0,0 +1,199 @@
+use crate::{
+    exports::ExportOperation,
+    sinks::{avro, protobuf, Sink},
+};
+use rs_utils::clients::schema_registry_client::SchemaRegistryClient;
+use serde::{Deserialize, Serialize};
+use std::{env, str::FromStr};
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+use tracing::{error, info};
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum SinkFormat {
+    #[default]
+    Json,
+    Cbor,
+
+    // Operation message of proto/operation.proto
+    Protobuf,
+
+    // schema registered at SCHEMA_REGISTRY_URL, messages carry its id
+    Avro,
+}
+
+// format of a sink with the registry id of the avro schema
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SinkEncoder {
+    pub format: SinkFormat,
+    pub schema_id: Option<u32>,
+}
+
+impl SinkFormat {
+    // e.g. NATS_FORMAT=cbor, json if not set
+    pub fn from_env(sink: &Sink) -> SinkFormat {
+        let name = format!("{}_FORMAT", sink.to_string().to_uppercase());
+        let Ok(format) = env::var(&name) else {
+            return SinkFormat::default();
+        };
+
+        SinkFormat::from_str(format.trim()).unwrap_or_else(|_| {
+            error!(target: "sinks", "Unknown {name} {format}, using json.");
+            SinkFormat::default()
+        })
+    }
+
+    pub fn get_content_type(&self) -> &'static str {
+        match self {
+            SinkFormat::Json => "application/json",
+            SinkFormat::Cbor => "application/cbor",
+            SinkFormat::Protobuf => "application/x-protobuf",
+            SinkFormat::Avro => "application/vnd.confluent.avro",
+        }
+    }
+}
+
+impl SinkEncoder {
+    // avro sinks register the schema before publishing, json is used if that fails
+    pub async fn from_env(sink: &Sink) -> SinkEncoder {
+        let format = SinkFormat::from_env(sink);
+        if format != SinkFormat::Avro {
+            return SinkEncoder {
+                format,
+                schema_id: None,
+            };
+        }
+
+        let url = &env::var("SCHEMA_REGISTRY_URL").unwrap();
+        let mut schema_registry_client = SchemaRegistryClient::new(url, "schema_registry").await;
+        let schema = avro::get_schema(protobuf::OPERATION_FIELDS).to_string();
+        let schema_id = schema_registry_client
+            .register_avro_schema(&avro::get_subject(), &schema)
+            .await;
+
+        match schema_id {
+            Some(schema_id) => {
+                info!(target: "sinks", "Publishing {sink} as avro with schema id {schema_id}.");
+                SinkEncoder {
+                    format,
+                    schema_id: Some(schema_id),
+                }
+            }
+            None => {
+                error!(target: "sinks", "Unexpected schema registry response, publishing {sink} as json.");
+                SinkEncoder {
+                    format: SinkFormat::Json,
+                    schema_id: None,
+                }
+            }
+        }
+    }
+
+    // every format carries the same fields, the ones of ExportOperation
+    pub fn encode(&self, operation: &ExportOperation) -> Vec<u8> {
+        match self.format {
+            SinkFormat::Json => serde_json::to_vec(operation).unwrap_or_default(),
+            SinkFormat::Cbor => {
+                let mut buffer = Vec::new();
+                if let Err(e) = ciborium::into_writer(operation, &mut buffer) {
+                    error!(target: "sinks", "cbor encoding error: {e}");
+                }
+                buffer
+            }
+            SinkFormat::Protobuf => protobuf::encode(operation, protobuf::OPERATION_FIELDS),
+            SinkFormat::Avro => avro::encode(
+                operation,
+                protobuf::OPERATION_FIELDS,
+                self.schema_id.unwrap_or_default(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        amount::Balance,
+        exports::{precision::ExportPrecision, ExportOperation},
+        sinks::formats::{SinkEncoder, SinkFormat},
+        OperationStatus, OperationType, SubscanOperation,
+    };
+    use bson::DateTime;
+
+    #[test]
+    fn formats_carry_the_same_operation() {
+        let operation = SubscanOperation {
+            hash: "hash".to_string(),
+            hash_version: 0,
+            block_number: 1,
+            extrinsic_index: "1-1".to_string(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+            operation_quantity: 1.5,
+            operation_planck: Some(Balance(1_500_000_000_000)),
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 3.0,
+            operation_type: OperationType::Stake,
+            from_wallet: "from".to_string(),
+            controller_wallet: "controller".to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        };
+        let operation = ExportOperation::new(operation, &ExportPrecision::full());
+
+        let json = SinkEncoder {
+            format: SinkFormat::Json,
+            schema_id: None,
+        }
+        .encode(&operation);
+        assert_eq!(
+            serde_json::from_slice::<ExportOperation>(&json).unwrap(),
+            operation
+        );
+
+        let cbor = SinkEncoder {
+            format: SinkFormat::Cbor,
+            schema_id: None,
+        }
+        .encode(&operation);
+        assert_eq!(
+            ciborium::from_reader::<ExportOperation, _>(cbor.as_slice()).unwrap(),
+            operation
+        );
+        assert!(cbor.len() < json.len());
+
+        let protobuf = SinkEncoder {
+            format: SinkFormat::Protobuf,
+            schema_id: None,
+        }
+        .encode(&operation);
+        assert!(protobuf.starts_with(&[0x0a, 0x04, b'h', b'a', b's', b'h', 0x10, 0x01]));
+        assert!(protobuf.len() < cbor.len());
+
+        let avro = SinkEncoder {
+            format: SinkFormat::Avro,
+            schema_id: Some(3),
+        }
+        .encode(&operation);
+        assert_eq!(avro[..5], [0, 0, 0, 0, 3]);
+    }
+}
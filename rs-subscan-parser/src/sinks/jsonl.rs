@@ -0,0 +1,121 @@
+use crate::{
+    exports::{precision::ExportPrecision, ExportOperation},
+    SubscanOperation,
+};
+use std::{
+    env,
+    fs::OpenOptions,
+    io::{self, BufWriter, Write},
+    sync::Mutex,
+    time::Duration,
+};
+use tokio::time::sleep;
+use tracing::error;
+
+static DELAY_MS: u64 = 100;
+
+// batches of concurrent writers never interleave their lines
+static JSONL_LOCK: Mutex<()> = Mutex::new(());
+
+// TO_JSONL_PATH, the file operations are appended to, stdout if not set or -
+pub fn get_jsonl_path() -> Option<String> {
+    env::var("TO_JSONL_PATH")
+        .ok()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty() && p != "-")
+}
+
+// one ExportOperation per line, flushed after every batch so that the other end of a pipe
+// gets the operations of a page as soon as it is parsed, a failed batch is written again in
+// full, readers dedupe by hash
+pub async fn write_operations(operations: &[SubscanOperation]) {
+    if operations.is_empty() {
+        return;
+    }
+
+    let path = get_jsonl_path();
+    loop {
+        let res = {
+            let _lock = JSONL_LOCK.lock().unwrap();
+            match &path {
+                Some(path) => OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .and_then(|file| write_lines(BufWriter::new(file), operations)),
+                None => write_lines(io::stdout().lock(), operations),
+            }
+        };
+
+        match res {
+            Ok(()) => return,
+
+            // nobody reads anymore, e.g. the tool at the end of the pipe exited
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+                error!(target: "jsonl", "Output closed, dropping {} operations.", operations.len());
+                return;
+            }
+            Err(e) => {
+                error!(target: "jsonl", "Write error: {e}; Sleeping {DELAY_MS} ms.");
+                sleep(Duration::from_millis(DELAY_MS)).await;
+            }
+        }
+    }
+}
+
+pub fn write_lines<W: Write>(mut writer: W, operations: &[SubscanOperation]) -> io::Result<()> {
+    for operation in operations {
+        let e = ExportOperation::new(operation.clone(), &ExportPrecision::full());
+        serde_json::to_writer(&mut writer, &e)?;
+        writer.write_all(b"\n")?;
+    }
+
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        exports::ExportOperation, sinks::jsonl::write_lines, OperationStatus, OperationType,
+        SubscanOperation,
+    };
+    use bson::DateTime;
+
+    #[test]
+    fn operations_are_written_one_per_line() {
+        let operations = (0..2)
+            .map(|i| SubscanOperation {
+                hash: format!("hash{i}"),
+                hash_version: 0,
+                block_number: 100 + i,
+                extrinsic_index: format!("{}-1", 100 + i),
+                call_index: 0,
+                operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+                operation_quantity: 1.5,
+                operation_planck: None,
+                operation_fee: None,
+                operation_era: None,
+                operation_usd: 9.0,
+                operation_type: OperationType::Transfer,
+                from_wallet: "from".to_string(),
+                controller_wallet: "controller".to_string(),
+                to_wallet: "to".to_string(),
+                status: OperationStatus::Finalized,
+                operation_id: None,
+                nomination_targets: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut buffer = Vec::new();
+        write_lines(&mut buffer, &operations).unwrap();
+        write_lines(&mut buffer, &operations[..1]).unwrap();
+
+        // appended batches read back as a single stream
+        let lines = String::from_utf8(buffer).unwrap();
+        let hashes = lines
+            .lines()
+            .map(|l| serde_json::from_str::<ExportOperation>(l).unwrap().hash)
+            .collect::<Vec<_>>();
+        assert_eq!(hashes, vec!["hash0", "hash1", "hash0"]);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{
+    exports::{precision::ExportPrecision, ExportOperation},
+    sinks::{batching::SinkBatching, formats::SinkEncoder, Sink},
+    subscan_parser::Network,
+    SubscanOperation,
+};
+use futures::future::join_all;
+use rdkafka::{
+    message::{Header, OwnedHeaders},
+    producer::{FutureProducer, FutureRecord},
+    ClientConfig,
+};
+use std::{env, time::Duration};
+use tokio::time::sleep;
+use tracing::error;
+
+static DELAY_MS: u64 = 100;
+static SEND_TIMEOUT_SECONDS: u64 = 30;
+static DEFAULT_KAFKA_TOPIC: &str = "feed.{network}";
+
+// KAFKA_TOPIC may use the {network} placeholder
+pub fn get_topic(template: &str, network: &Network) -> String {
+    template.replace("{network}", &network.to_string())
+}
+
+// messages are keyed by the operation hash, so they keep their order per operation and a
+// compacted topic holds each operation once, the idempotent producer drops its own retries,
+// batched operations are handed to the producer together and leave in its batches
+pub async fn write_operations(operations: &[SubscanOperation]) {
+    if operations.is_empty() {
+        return;
+    }
+
+    let batching = SinkBatching::from_env(&Sink::Kafka);
+    let producer = connect(&batching).await;
+    let template = env::var("KAFKA_TOPIC")
+        .ok()
+        .filter(|t| !t.is_empty())
+        .unwrap_or(DEFAULT_KAFKA_TOPIC.to_string());
+    let topic = get_topic(&template, &Network::from_env());
+    let encoder = SinkEncoder::from_env(&Sink::Kafka).await;
+    for batch in batching.get_batches(operations) {
+        let sends = batch.iter().map(|operation| {
+            let payload = encoder.encode(&ExportOperation::new(
+                operation.clone(),
+                &ExportPrecision::full(),
+            ));
+            send(&producer, &topic, &encoder, operation, payload)
+        });
+        join_all(sends).await;
+    }
+}
+
+async fn send(
+    producer: &FutureProducer,
+    topic: &str,
+    encoder: &SinkEncoder,
+    operation: &SubscanOperation,
+    payload: Vec<u8>,
+) {
+    loop {
+        let headers = OwnedHeaders::new().insert(Header {
+            key: "content-type",
+            value: Some(encoder.format.get_content_type()),
+        });
+        let record = FutureRecord::to(topic)
+            .key(&operation.hash)
+            .payload(&payload)
+            .headers(headers);
+        let send = producer
+            .send(record, Duration::from_secs(SEND_TIMEOUT_SECONDS))
+            .await;
+        if let Err((e, _)) = send {
+            error!(target: "kafka", "send error: {e}; Sleeping {DELAY_MS} ms.");
+
+            sleep(Duration::from_millis(DELAY_MS)).await;
+            continue;
+        }
+
+        break;
+    }
+}
+
+// the producer waits up to the batch window for a batch to fill
+async fn connect(batching: &SinkBatching) -> FutureProducer {
+    let brokers = &env::var("KAFKA_BROKERS").unwrap();
+
+    loop {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("enable.idempotence", "true")
+            .set("linger.ms", batching.window.as_millis().to_string())
+            .set("batch.num.messages", batching.max_size.to_string())
+            .create::<FutureProducer>();
+        let Ok(producer) = producer else {
+            error!(target: "kafka", "connect error: {}; Sleeping {DELAY_MS} ms.", producer.err().unwrap());
+
+            sleep(Duration::from_millis(DELAY_MS)).await;
+            continue;
+        };
+
+        return producer;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{sinks::kafka::get_topic, subscan_parser::Network};
+
+    #[test]
+    fn topics_are_per_network() {
+        assert_eq!(
+            get_topic("feed.{network}", &Network::Alephzero),
+            format!("feed.{}", Network::Alephzero)
+        );
+        assert_eq!(get_topic("operations", &Network::Alephzero), "operations");
+    }
+}
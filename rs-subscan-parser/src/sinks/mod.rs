@@ -0,0 +1,206 @@
+pub mod avro;
+pub mod batching;
+pub mod clickhouse;
+pub mod formats;
+pub mod jsonl;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "nats")]
+pub mod nats;
+pub mod protobuf;
+#[cfg(feature = "redis")]
+pub mod redis;
+pub mod webhook;
+
+use crate::{
+    latency::record_deliveries, mongodb_client_subscan::MongoDbClientSubscan,
+    shadow::is_shadow_mode, OperationType, SubscanOperation,
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::{env, str::FromStr};
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+use tracing::{error, info_span, Instrument};
+
+#[derive(
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum Sink {
+    #[default]
+    Mongodb,
+
+    // write-only copy for analytical queries
+    Clickhouse,
+
+    // jetstream subjects feed.<network>.<operation_type>, needs the nats feature,
+    // NATS_FORMAT picks json, cbor, protobuf or avro
+    Nats,
+
+    // compact json messages for edge dashboards, needs the mqtt feature,
+    // MQTT_FORMAT=cbor, protobuf or avro sends the full operation instead
+    Mqtt,
+
+    // KAFKA_TOPIC of the network for analytics consumers, needs the kafka feature,
+    // KAFKA_FORMAT picks json, cbor, protobuf or avro
+    Kafka,
+
+    // REDIS_KEY channel or stream for low latency notifications, needs the redis feature,
+    // REDIS_FORMAT picks json, cbor, protobuf or avro
+    Redis,
+
+    // signed json POSTs to every https endpoint of WEBHOOK_URLS
+    Webhook,
+
+    // newline delimited json appended to TO_JSONL_PATH or stdout, for piping into other tools
+    ToJsonl,
+}
+
+impl Sink {
+    // SINKS=mongodb,... written in the given order, mongodb only if not set or in shadow mode
+    pub fn from_env() -> Vec<Sink> {
+        if is_shadow_mode() {
+            return vec![Sink::Mongodb];
+        }
+
+        let sinks = env::var("SINKS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .filter_map(|s| {
+                let sink = Sink::from_str(s);
+                if sink.is_err() {
+                    error!(target: "sinks", "Unknown sink {s}, skipping it.");
+                }
+                sink.ok()
+            })
+            .unique()
+            .collect::<Vec<_>>();
+
+        if sinks.is_empty() {
+            return vec![Sink::default()];
+        }
+        sinks
+    }
+
+    // returns operations stored for the first time, the ones stats are built from,
+    // delivery latencies are recorded once the sink accepted the operations
+    pub async fn write_operations(
+        &self,
+        operations: Vec<SubscanOperation>,
+    ) -> Vec<SubscanOperation> {
+        let span = info_span!("sink", sink = %self, operations = operations.len());
+        self.deliver_operations(operations).instrument(span).await
+    }
+
+    async fn deliver_operations(&self, operations: Vec<SubscanOperation>) -> Vec<SubscanOperation> {
+        match self {
+            Sink::Mongodb => {
+                let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+                let stored = mongodb_client_subscan
+                    .import_subscan_operations(operations)
+                    .await;
+                record_deliveries(self, &stored).await;
+                stored
+            }
+            Sink::Clickhouse => {
+                clickhouse::write_operations(&operations).await;
+                record_deliveries(self, &operations).await;
+                Vec::new()
+            }
+            Sink::Nats => {
+                #[cfg(feature = "nats")]
+                {
+                    nats::write_operations(&operations).await;
+                    record_deliveries(self, &operations).await;
+                }
+                #[cfg(not(feature = "nats"))]
+                error!(target: "sinks", "Built without the nats feature, skipping {} operations.", operations.len());
+                Vec::new()
+            }
+            Sink::Mqtt => {
+                #[cfg(feature = "mqtt")]
+                {
+                    mqtt::write_operations(&operations).await;
+                    record_deliveries(self, &operations).await;
+                }
+                #[cfg(not(feature = "mqtt"))]
+                error!(target: "sinks", "Built without the mqtt feature, skipping {} operations.", operations.len());
+                Vec::new()
+            }
+            Sink::Kafka => {
+                #[cfg(feature = "kafka")]
+                {
+                    kafka::write_operations(&operations).await;
+                    record_deliveries(self, &operations).await;
+                }
+                #[cfg(not(feature = "kafka"))]
+                error!(target: "sinks", "Built without the kafka feature, skipping {} operations.", operations.len());
+                Vec::new()
+            }
+            Sink::Redis => {
+                #[cfg(feature = "redis")]
+                {
+                    redis::write_operations(&operations).await;
+                    record_deliveries(self, &operations).await;
+                }
+                #[cfg(not(feature = "redis"))]
+                error!(target: "sinks", "Built without the redis feature, skipping {} operations.", operations.len());
+                Vec::new()
+            }
+            Sink::Webhook => {
+                webhook::write_operations(&operations).await;
+                record_deliveries(self, &operations).await;
+                Vec::new()
+            }
+            Sink::ToJsonl => {
+                jsonl::write_operations(&operations).await;
+                record_deliveries(self, &operations).await;
+                Vec::new()
+            }
+        }
+    }
+}
+
+// RequestUnstake -> request_unstake, used in subjects and topics
+pub fn get_operation_type_token(operation_type: &OperationType) -> String {
+    let mut token = String::new();
+    for (i, c) in operation_type.to_string().chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            token.push('_');
+        }
+        token.push(c.to_ascii_lowercase());
+    }
+
+    token
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{sinks::get_operation_type_token, OperationType};
+
+    #[test]
+    fn operation_type_tokens_are_snake_case() {
+        assert_eq!(get_operation_type_token(&OperationType::Stake), "stake");
+        assert_eq!(
+            get_operation_type_token(&OperationType::WithdrawFromExchange),
+            "withdraw_from_exchange"
+        );
+    }
+}
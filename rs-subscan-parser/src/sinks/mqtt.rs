@@ -0,0 +1,214 @@
+use crate::{
+    exports::{precision::ExportPrecision, ExportOperation},
+    sinks::{
+        formats::{SinkEncoder, SinkFormat},
+        get_operation_type_token, Sink,
+    },
+    subscan_parser::Network,
+    SubscanOperation,
+};
+use rumqttc::{AsyncClient, Event, MqttOptions, Outgoing, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use std::{env, time::Duration};
+use tokio::time::sleep;
+use tracing::error;
+
+static DELAY_MS: u64 = 100;
+static DEFAULT_MQTT_PORT: u16 = 1883;
+static DEFAULT_MQTT_CLIENT_ID: &str = "nym-tradefeed";
+static DEFAULT_MQTT_TOPIC: &str = "feed/{network}/{operation_type}";
+
+// short keys keep messages small for edge devices
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct MqttOperation {
+    #[serde(rename = "h")]
+    pub hash: String,
+    #[serde(rename = "x")]
+    pub extrinsic_index: String,
+    #[serde(rename = "ts")]
+    pub timestamp: i64,
+    #[serde(rename = "t")]
+    pub operation_type: String,
+    #[serde(rename = "q")]
+    pub quantity: f64,
+    #[serde(rename = "u")]
+    pub usd: f64,
+    #[serde(rename = "f")]
+    pub from_wallet: String,
+    #[serde(rename = "to")]
+    pub to_wallet: String,
+}
+
+impl From<&SubscanOperation> for MqttOperation {
+    fn from(s: &SubscanOperation) -> Self {
+        Self {
+            hash: s.hash.clone(),
+            extrinsic_index: s.extrinsic_index.clone(),
+            timestamp: s.operation_timestamp.timestamp_millis() / 1000,
+            operation_type: get_operation_type_token(&s.operation_type),
+            quantity: s.operation_quantity,
+            usd: s.operation_usd,
+            from_wallet: s.from_wallet.clone(),
+            to_wallet: s.to_wallet.clone(),
+        }
+    }
+}
+
+// MQTT_TOPIC may use {network} and {operation_type} placeholders
+pub fn get_topic(template: &str, network: &Network, operation: &SubscanOperation) -> String {
+    template.replace("{network}", &network.to_string()).replace(
+        "{operation_type}",
+        &get_operation_type_token(&operation.operation_type),
+    )
+}
+
+fn get_qos() -> QoS {
+    match env::var("MQTT_QOS").as_deref() {
+        Ok("0") => QoS::AtMostOnce,
+        Ok("2") => QoS::ExactlyOnce,
+        _ => QoS::AtLeastOnce,
+    }
+}
+
+fn get_options() -> MqttOptions {
+    let host = env::var("MQTT_HOST").unwrap();
+    let port = env::var("MQTT_PORT")
+        .ok()
+        .and_then(|p| p.parse::<u16>().ok())
+        .unwrap_or(DEFAULT_MQTT_PORT);
+    let client_id = env::var("MQTT_CLIENT_ID")
+        .ok()
+        .filter(|c| !c.is_empty())
+        .unwrap_or(DEFAULT_MQTT_CLIENT_ID.to_string());
+
+    let mut options = MqttOptions::new(client_id, host, port);
+    options.set_keep_alive(Duration::from_secs(30));
+    if let Some(username) = env::var("MQTT_USERNAME").ok().filter(|u| !u.is_empty()) {
+        options.set_credentials(username, env::var("MQTT_PASSWORD").unwrap_or_default());
+    }
+
+    options
+}
+
+// json keeps the short keys, binary formats are already compact and use the shared schema
+pub fn get_payload(encoder: &SinkEncoder, operation: &SubscanOperation) -> Vec<u8> {
+    match encoder.format {
+        SinkFormat::Json => serde_json::to_vec(&MqttOperation::from(operation)).unwrap_or_default(),
+        _ => encoder.encode(&ExportOperation::new(
+            operation.clone(),
+            &ExportPrecision::full(),
+        )),
+    }
+}
+
+pub async fn write_operations(operations: &[SubscanOperation]) {
+    if operations.is_empty() {
+        return;
+    }
+
+    let template = env::var("MQTT_TOPIC")
+        .ok()
+        .filter(|t| !t.is_empty())
+        .unwrap_or(DEFAULT_MQTT_TOPIC.to_string());
+    let network = Network::from_env();
+    let encoder = SinkEncoder::from_env(&Sink::Mqtt).await;
+    let messages = operations
+        .iter()
+        .map(|s| (get_topic(&template, &network, s), get_payload(&encoder, s)))
+        .collect::<Vec<_>>();
+
+    // a broken connection republishes the whole batch, consumers get at-least-once delivery
+    loop {
+        if let Err(e) = publish_messages(messages.clone(), get_qos()).await {
+            error!(target: "mqtt", "publish error: {e}; Sleeping {DELAY_MS} ms.");
+
+            sleep(Duration::from_millis(DELAY_MS)).await;
+            continue;
+        }
+
+        return;
+    }
+}
+
+// returns once the broker confirmed every message as required by the qos
+async fn publish_messages(messages: Vec<(String, Vec<u8>)>, qos: QoS) -> Result<(), String> {
+    let total = messages.len();
+    let (client, mut eventloop) = AsyncClient::new(get_options(), total.clamp(10, 1_000));
+
+    let publisher = tokio::spawn(async move {
+        for (topic, payload) in messages {
+            client
+                .publish(topic, qos, false, payload)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        Ok::<_, String>(client)
+    });
+
+    let mut confirmed = 0;
+    while confirmed < total {
+        let event = eventloop.poll().await.map_err(|e| e.to_string())?;
+        let is_confirmation = matches!(
+            (qos, event),
+            (QoS::AtMostOnce, Event::Outgoing(Outgoing::Publish(_)))
+                | (QoS::AtLeastOnce, Event::Incoming(Packet::PubAck(_)))
+                | (QoS::ExactlyOnce, Event::Incoming(Packet::PubComp(_)))
+        );
+        if is_confirmation {
+            confirmed += 1;
+        }
+    }
+
+    let client = publisher.await.map_err(|e| e.to_string())??;
+    let _ = client.disconnect().await;
+    let _ = eventloop.poll().await;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        sinks::mqtt::{get_topic, MqttOperation},
+        subscan_parser::Network,
+        OperationStatus, OperationType, SubscanOperation,
+    };
+    use bson::DateTime;
+
+    #[test]
+    fn messages_are_compact() {
+        let operation = SubscanOperation {
+            hash: "hash".to_string(),
+            hash_version: 0,
+            block_number: 1,
+            extrinsic_index: "1-1".to_string(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_123),
+            operation_quantity: 1.5,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 3.0,
+            operation_type: OperationType::ReStake,
+            from_wallet: "from".to_string(),
+            controller_wallet: "controller".to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        };
+
+        assert_eq!(
+            get_topic(
+                "azero/{network}/{operation_type}",
+                &Network::Mock,
+                &operation
+            ),
+            "azero/mock/re_stake"
+        );
+        assert_eq!(
+            serde_json::to_string(&MqttOperation::from(&operation)).unwrap(),
+            r#"{"h":"hash","x":"1-1","ts":1700000000,"t":"re_stake","q":1.5,"u":3.0,"f":"from","to":"to"}"#
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{
+    exports::{precision::ExportPrecision, ExportOperation},
+    sinks::{formats::SinkEncoder, get_operation_type_token, Sink},
+    subscan_parser::Network,
+    SubscanOperation,
+};
+use async_nats::jetstream::{self, message::PublishMessage, stream::Config};
+use std::{env, time::Duration};
+use tokio::time::sleep;
+use tracing::error;
+
+static DELAY_MS: u64 = 100;
+static DEFAULT_NATS_STREAM: &str = "FEED";
+
+// messages with an extrinsic_index already published within the window are dropped by jetstream
+static DEFAULT_NATS_DUPLICATE_WINDOW_SECONDS: u64 = 24 * 60 * 60;
+
+pub fn get_subject(network: &Network, operation: &SubscanOperation) -> String {
+    format!(
+        "feed.{network}.{}",
+        get_operation_type_token(&operation.operation_type)
+    )
+}
+
+pub async fn write_operations(operations: &[SubscanOperation]) {
+    if operations.is_empty() {
+        return;
+    }
+
+    let context = connect().await;
+    let network = Network::from_env();
+    let encoder = SinkEncoder::from_env(&Sink::Nats).await;
+    for operation in operations {
+        let subject = get_subject(&network, operation);
+        let payload = encoder.encode(&ExportOperation::new(
+            operation.clone(),
+            &ExportPrecision::full(),
+        ));
+
+        loop {
+            let publish = PublishMessage::build()
+                .payload(payload.clone().into())
+                .header("Content-Type", encoder.format.get_content_type())
+                .message_id(&operation.extrinsic_index);
+            let ack = match context.send_publish(subject.clone(), publish).await {
+                Ok(ack) => ack.await.map(|_| ()).map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            if let Err(e) = ack {
+                error!(target: "nats", "publish error: {e}; Sleeping {DELAY_MS} ms.");
+
+                sleep(Duration::from_millis(DELAY_MS)).await;
+                continue;
+            }
+
+            break;
+        }
+    }
+}
+
+async fn connect() -> jetstream::Context {
+    let url = &env::var("NATS_URL").unwrap();
+    let stream = env::var("NATS_STREAM")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .unwrap_or(DEFAULT_NATS_STREAM.to_string());
+    let duplicate_window = env::var("NATS_DUPLICATE_WINDOW_SECONDS")
+        .ok()
+        .and_then(|d| d.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_NATS_DUPLICATE_WINDOW_SECONDS);
+
+    loop {
+        let client = async_nats::connect(url).await;
+        let Ok(client) = client else {
+            error!(target: "nats", "connect error: {}; Sleeping {DELAY_MS} ms.", client.err().unwrap());
+
+            sleep(Duration::from_millis(DELAY_MS)).await;
+            continue;
+        };
+
+        let context = jetstream::new(client);
+        let config = Config {
+            name: stream.clone(),
+            subjects: vec!["feed.>".to_string()],
+            duplicate_window: Duration::from_secs(duplicate_window),
+            ..Default::default()
+        };
+        if let Err(e) = context.get_or_create_stream(config).await {
+            error!(target: "nats", "get_or_create_stream error: {e}; Sleeping {DELAY_MS} ms.");
+
+            sleep(Duration::from_millis(DELAY_MS)).await;
+            continue;
+        }
+
+        return context;
+    }
+}
@@ -0,0 +1,266 @@
+use serde::Serialize;
+use serde_json::Value;
+
+static PROTO_PACKAGE: &str = "nymtradefeed";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProtoType {
+    String,
+    Uint64,
+    Double,
+    OptionalDouble,
+    RepeatedString,
+    Message(&'static str, &'static [ProtoField]),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProtoField {
+    pub tag: u32,
+    pub name: &'static str,
+    pub proto_type: ProtoType,
+}
+
+static PRICE_ANNOTATION_FIELDS: &[ProtoField] = &[
+    ProtoField {
+        tag: 1,
+        name: "price_before_1h",
+        proto_type: ProtoType::OptionalDouble,
+    },
+    ProtoField {
+        tag: 2,
+        name: "price_at",
+        proto_type: ProtoType::OptionalDouble,
+    },
+    ProtoField {
+        tag: 3,
+        name: "price_after_1h",
+        proto_type: ProtoType::OptionalDouble,
+    },
+    ProtoField {
+        tag: 4,
+        name: "price_after_24h",
+        proto_type: ProtoType::OptionalDouble,
+    },
+];
+
+// wire schema of ExportOperation, names are its serialized keys and tags must never be reused
+pub static OPERATION_FIELDS: &[ProtoField] = &[
+    ProtoField {
+        tag: 1,
+        name: "hash",
+        proto_type: ProtoType::String,
+    },
+    ProtoField {
+        tag: 2,
+        name: "block_number",
+        proto_type: ProtoType::Uint64,
+    },
+    ProtoField {
+        tag: 3,
+        name: "extrinsic_index",
+        proto_type: ProtoType::String,
+    },
+    ProtoField {
+        tag: 4,
+        name: "operation_timestamp",
+        proto_type: ProtoType::String,
+    },
+    ProtoField {
+        tag: 5,
+        name: "operation_type",
+        proto_type: ProtoType::String,
+    },
+    ProtoField {
+        tag: 6,
+        name: "from_wallet",
+        proto_type: ProtoType::String,
+    },
+    ProtoField {
+        tag: 7,
+        name: "controller_wallet",
+        proto_type: ProtoType::String,
+    },
+    ProtoField {
+        tag: 8,
+        name: "to_wallet",
+        proto_type: ProtoType::String,
+    },
+    ProtoField {
+        tag: 9,
+        name: "amount",
+        proto_type: ProtoType::String,
+    },
+    ProtoField {
+        tag: 10,
+        name: "amount_planck",
+        proto_type: ProtoType::String,
+    },
+    ProtoField {
+        tag: 11,
+        name: "amount_usd",
+        proto_type: ProtoType::Double,
+    },
+    ProtoField {
+        tag: 12,
+        name: "annotations",
+        proto_type: ProtoType::RepeatedString,
+    },
+    ProtoField {
+        tag: 13,
+        name: "price_annotation",
+        proto_type: ProtoType::Message("PriceAnnotation", PRICE_ANNOTATION_FIELDS),
+    },
+    ProtoField {
+        tag: 14,
+        name: "operation_id",
+        proto_type: ProtoType::String,
+    },
+];
+
+// the .proto consumers generate their code from, see the proto_schema binary
+pub fn get_proto() -> String {
+    let mut proto = format!("syntax = \"proto3\";\n\npackage {PROTO_PACKAGE};\n");
+    write_message(&mut proto, "Operation", OPERATION_FIELDS);
+
+    proto
+}
+
+fn write_message(proto: &mut String, name: &str, fields: &[ProtoField]) {
+    proto.push_str(&format!("\nmessage {name} {{\n"));
+
+    let mut nested = Vec::new();
+    for field in fields {
+        let proto_type = match field.proto_type {
+            ProtoType::String => "string",
+            ProtoType::Uint64 => "uint64",
+            ProtoType::Double => "double",
+            ProtoType::OptionalDouble => "optional double",
+            ProtoType::RepeatedString => "repeated string",
+            ProtoType::Message(message, message_fields) => {
+                nested.push((message, message_fields));
+                message
+            }
+        };
+        proto.push_str(&format!("  {proto_type} {} = {};\n", field.name, field.tag));
+    }
+    proto.push_str("}\n");
+
+    for (message, message_fields) in nested {
+        write_message(proto, message, message_fields);
+    }
+}
+
+// values are taken from the serialized item so the field table is the only schema to maintain
+pub fn encode<T: Serialize>(item: &T, fields: &[ProtoField]) -> Vec<u8> {
+    let value = serde_json::to_value(item).unwrap_or_default();
+    let mut buffer = Vec::new();
+    encode_message(&value, fields, &mut buffer);
+
+    buffer
+}
+
+// proto3 defaults are left out, optional fields are written whenever they are set
+fn encode_message(value: &Value, fields: &[ProtoField], buffer: &mut Vec<u8>) {
+    for field in fields {
+        let Some(value) = value.get(field.name) else {
+            continue;
+        };
+
+        match field.proto_type {
+            ProtoType::String => {
+                if let Some(s) = value.as_str().filter(|s| !s.is_empty()) {
+                    encode_bytes(field.tag, s.as_bytes(), buffer);
+                }
+            }
+            ProtoType::Uint64 => {
+                if let Some(n) = value.as_u64().filter(|n| *n != 0) {
+                    encode_varint((field.tag as u64) << 3, buffer);
+                    encode_varint(n, buffer);
+                }
+            }
+            ProtoType::Double => {
+                if let Some(n) = value.as_f64().filter(|n| *n != 0.0) {
+                    encode_double(field.tag, n, buffer);
+                }
+            }
+            ProtoType::OptionalDouble => {
+                if let Some(n) = value.as_f64() {
+                    encode_double(field.tag, n, buffer);
+                }
+            }
+            ProtoType::RepeatedString => {
+                for s in value
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                {
+                    encode_bytes(field.tag, s.as_bytes(), buffer);
+                }
+            }
+            ProtoType::Message(_, message_fields) => {
+                if value.is_object() {
+                    let mut message = Vec::new();
+                    encode_message(value, message_fields, &mut message);
+                    encode_bytes(field.tag, &message, buffer);
+                }
+            }
+        }
+    }
+}
+
+fn encode_varint(mut n: u64, buffer: &mut Vec<u8>) {
+    while n >= 0x80 {
+        buffer.push((n as u8 & 0x7f) | 0x80);
+        n >>= 7;
+    }
+    buffer.push(n as u8);
+}
+
+fn encode_bytes(tag: u32, bytes: &[u8], buffer: &mut Vec<u8>) {
+    encode_varint(((tag as u64) << 3) | 2, buffer);
+    encode_varint(bytes.len() as u64, buffer);
+    buffer.extend_from_slice(bytes);
+}
+
+fn encode_double(tag: u32, n: f64, buffer: &mut Vec<u8>) {
+    encode_varint(((tag as u64) << 3) | 1, buffer);
+    buffer.extend_from_slice(&n.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sinks::protobuf::{encode, encode_varint, get_proto, OPERATION_FIELDS};
+    use serde_json::json;
+
+    #[test]
+    fn checked_in_proto_is_up_to_date() {
+        assert_eq!(get_proto(), include_str!("../../proto/operation.proto"));
+    }
+
+    #[test]
+    fn varints_use_seven_bit_groups() {
+        let mut buffer = Vec::new();
+        encode_varint(300, &mut buffer);
+        assert_eq!(buffer, vec![0xac, 0x02]);
+    }
+
+    #[test]
+    fn operations_encode_by_field_table() {
+        let operation = json!({
+            "hash": "h",
+            "block_number": 150,
+            "extrinsic_index": "",
+            "amount_usd": 1.0,
+            "annotations": ["a", "b"],
+            "price_annotation": {"price_at": 0.5, "price_after_1h": null},
+        });
+
+        let mut expected = vec![0x0a, 0x01, b'h', 0x10, 0x96, 0x01, 0x59];
+        expected.extend_from_slice(&1.0f64.to_le_bytes());
+        expected.extend_from_slice(&[0x62, 0x01, b'a', 0x62, 0x01, b'b', 0x6a, 0x09, 0x11]);
+        expected.extend_from_slice(&0.5f64.to_le_bytes());
+
+        assert_eq!(encode(&operation, OPERATION_FIELDS), expected);
+    }
+}
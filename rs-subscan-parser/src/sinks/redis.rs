@@ -0,0 +1,229 @@
+use crate::{
+    exports::{precision::ExportPrecision, ExportOperation},
+    sinks::{formats::SinkEncoder, Sink},
+    subscan_parser::Network,
+    SubscanOperation,
+};
+use redis::{aio::MultiplexedConnection, Cmd, RedisResult};
+use serde::{Deserialize, Serialize};
+use std::{env, str::FromStr, time::Duration};
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+use tokio::time::sleep;
+use tracing::error;
+
+static DELAY_MS: u64 = 100;
+static DEFAULT_REDIS_KEY: &str = "feed.{network}";
+
+// streams are trimmed to about this many entries, consumers lagging further lose the oldest
+static DEFAULT_REDIS_STREAM_MAXLEN: u64 = 100_000;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum RedisTarget {
+    // PUBLISH, only subscribers connected at the time get the operation
+    #[default]
+    Channel,
+
+    // XADD, consumer groups can catch up on what they missed
+    Stream,
+}
+
+impl RedisTarget {
+    // REDIS_TARGET=channel or stream, channel if not set
+    pub fn from_env() -> RedisTarget {
+        let Ok(target) = env::var("REDIS_TARGET") else {
+            return RedisTarget::default();
+        };
+
+        RedisTarget::from_str(target.trim()).unwrap_or_else(|_| {
+            error!(target: "redis", "Unknown REDIS_TARGET {target}, using channel.");
+            RedisTarget::default()
+        })
+    }
+}
+
+// REDIS_KEY may use the {network} placeholder
+pub fn get_key(template: &str, network: &Network) -> String {
+    template.replace("{network}", &network.to_string())
+}
+
+fn get_command(
+    target: RedisTarget,
+    key: &str,
+    stream_maxlen: u64,
+    operation: &SubscanOperation,
+    content_type: &str,
+    payload: &[u8],
+) -> Cmd {
+    match target {
+        RedisTarget::Channel => redis::cmd("PUBLISH").arg(key).arg(payload).clone(),
+        RedisTarget::Stream => redis::cmd("XADD")
+            .arg(key)
+            .arg("MAXLEN")
+            .arg("~")
+            .arg(stream_maxlen)
+            .arg("*")
+            .arg("hash")
+            .arg(&operation.hash)
+            .arg("content_type")
+            .arg(content_type)
+            .arg("payload")
+            .arg(payload)
+            .clone(),
+    }
+}
+
+// operations are sent one by one in their order, a retried one may reach consumers twice,
+// which tell them apart by the hash
+pub async fn write_operations(operations: &[SubscanOperation]) {
+    if operations.is_empty() {
+        return;
+    }
+
+    let mut connection = connect().await;
+    let target = RedisTarget::from_env();
+    let template = env::var("REDIS_KEY")
+        .ok()
+        .filter(|k| !k.is_empty())
+        .unwrap_or(DEFAULT_REDIS_KEY.to_string());
+    let key = get_key(&template, &Network::from_env());
+    let stream_maxlen = env::var("REDIS_STREAM_MAXLEN")
+        .ok()
+        .and_then(|m| m.parse::<u64>().ok())
+        .filter(|m| *m > 0)
+        .unwrap_or(DEFAULT_REDIS_STREAM_MAXLEN);
+    let encoder = SinkEncoder::from_env(&Sink::Redis).await;
+    for operation in operations {
+        let payload = encoder.encode(&ExportOperation::new(
+            operation.clone(),
+            &ExportPrecision::full(),
+        ));
+        let command = get_command(
+            target,
+            &key,
+            stream_maxlen,
+            operation,
+            encoder.format.get_content_type(),
+            &payload,
+        );
+
+        loop {
+            let result: RedisResult<()> = command.query_async(&mut connection).await;
+            if let Err(e) = result {
+                error!(target: "redis", "{target} error: {e}; Sleeping {DELAY_MS} ms.");
+
+                sleep(Duration::from_millis(DELAY_MS)).await;
+                continue;
+            }
+
+            break;
+        }
+    }
+}
+
+async fn connect() -> MultiplexedConnection {
+    let url = &env::var("REDIS_URL").unwrap();
+
+    loop {
+        let connection = match redis::Client::open(url.as_str()) {
+            Ok(client) => client.get_multiplexed_async_connection().await,
+            Err(e) => Err(e),
+        };
+        let Ok(connection) = connection else {
+            error!(target: "redis", "connect error: {}; Sleeping {DELAY_MS} ms.", connection.err().unwrap());
+
+            sleep(Duration::from_millis(DELAY_MS)).await;
+            continue;
+        };
+
+        return connection;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        sinks::redis::{get_command, get_key, RedisTarget},
+        subscan_parser::Network,
+        OperationStatus, OperationType, SubscanOperation,
+    };
+    use bson::DateTime;
+
+    #[test]
+    fn keys_are_per_network() {
+        assert_eq!(
+            get_key("feed.{network}", &Network::Alephzero),
+            format!("feed.{}", Network::Alephzero)
+        );
+        assert_eq!(get_key("operations", &Network::Alephzero), "operations");
+    }
+
+    #[test]
+    fn channels_publish_and_streams_add_trimmed_entries() {
+        let operation = SubscanOperation {
+            hash: "hash".to_string(),
+            hash_version: 2,
+            block_number: 1,
+            extrinsic_index: "1-1".to_string(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+            operation_quantity: 1.0,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 1.0,
+            operation_type: OperationType::Stake,
+            from_wallet: "from".to_string(),
+            controller_wallet: "from".to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        };
+
+        let payload = "{}".as_bytes();
+        let command = get_command(
+            RedisTarget::Channel,
+            "feed",
+            10,
+            &operation,
+            "json",
+            payload,
+        );
+        let expected = redis::cmd("PUBLISH").arg("feed").arg(payload).clone();
+        assert_eq!(command.get_packed_command(), expected.get_packed_command());
+
+        let command = get_command(RedisTarget::Stream, "feed", 10, &operation, "json", payload);
+        let expected = redis::cmd("XADD")
+            .arg("feed")
+            .arg("MAXLEN")
+            .arg("~")
+            .arg("10")
+            .arg("*")
+            .arg("hash")
+            .arg("hash")
+            .arg("content_type")
+            .arg("json")
+            .arg("payload")
+            .arg(payload)
+            .clone();
+        assert_eq!(command.get_packed_command(), expected.get_packed_command());
+    }
+}
@@ -0,0 +1,314 @@
+use crate::{
+    exports::{precision::ExportPrecision, ExportOperation},
+    hmac::hmac_sha256,
+    pipeline_error::{ErrorCode, PipelineError},
+    retry_policy::RetryPolicy,
+    sinks::{batching::SinkBatching, Sink},
+    subscan_parser::Network,
+    SubscanOperation,
+};
+use chrono::Utc;
+use futures::future::join_all;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::{env, time::Duration};
+use tokio::time::sleep;
+use tracing::error;
+
+static DEFAULT_WEBHOOK_MAX_ATTEMPTS: u32 = 5;
+static DEFAULT_WEBHOOK_TIMEOUT_SECONDS: u64 = 10;
+static WEBHOOK_BACKOFF_BASE_MS: u64 = 1_000;
+static WEBHOOK_BACKOFF_MAX_MS: u64 = 60_000;
+static WEBHOOK_BACKOFF_JITTER: f64 = 0.5;
+static WEBHOOK_EVENT: &str = "operation.created";
+static WEBHOOK_BATCH_EVENT: &str = "operations.created";
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct WebhookPayload {
+    pub event: String,
+    pub network: String,
+    pub operation: ExportOperation,
+}
+
+// sent instead of WebhookPayload when WEBHOOK_BATCH_SIZE is above 1
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct WebhookBatchPayload {
+    pub event: String,
+    pub network: String,
+    pub operations: Vec<ExportOperation>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WebhookConfig {
+    pub urls: Vec<String>,
+
+    // signs every payload if set, see get_signature
+    pub secret: Option<String>,
+    pub retry_policy: RetryPolicy,
+    pub timeout: Duration,
+}
+
+impl WebhookConfig {
+    // WEBHOOK_URLS, WEBHOOK_SECRET, WEBHOOK_MAX_ATTEMPTS and WEBHOOK_TIMEOUT_SECONDS
+    pub fn from_env() -> WebhookConfig {
+        let urls = get_webhook_urls(&env::var("WEBHOOK_URLS").unwrap_or_default());
+        let secret = env::var("WEBHOOK_SECRET").ok().filter(|s| !s.is_empty());
+        let max_attempts = env::var("WEBHOOK_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_WEBHOOK_MAX_ATTEMPTS);
+        let timeout_seconds = env::var("WEBHOOK_TIMEOUT_SECONDS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_WEBHOOK_TIMEOUT_SECONDS);
+
+        Self {
+            urls,
+            secret,
+            retry_policy: RetryPolicy {
+                max_attempts,
+                base_delay_ms: WEBHOOK_BACKOFF_BASE_MS,
+                max_delay_ms: WEBHOOK_BACKOFF_MAX_MS,
+                jitter: WEBHOOK_BACKOFF_JITTER,
+            },
+            timeout: Duration::from_secs(timeout_seconds),
+        }
+    }
+}
+
+// comma separated, payloads are only sent over https
+pub fn get_webhook_urls(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+        .filter(|u| {
+            let is_https = u.starts_with("https://");
+            if !is_https {
+                error!(target: "webhook", "Webhook url {u} is not https, skipping it.");
+            }
+            is_https
+        })
+        .map(String::from)
+        .collect()
+}
+
+// X-Webhook-Signature, the timestamp is signed along so receivers can reject replays
+pub fn get_signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut message = format!("{timestamp}.").into_bytes();
+    message.extend_from_slice(body);
+
+    format!(
+        "sha256={}",
+        hex::encode(hmac_sha256(secret.as_bytes(), &message))
+    )
+}
+
+// client errors but timeouts and rate limits won't go away by sending the payload again
+fn is_retryable(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+// X-Webhook-Id of a batch, the same operations make the same id again when they are retried
+pub fn get_batch_id(operations: &[SubscanOperation]) -> String {
+    let hashes = operations
+        .iter()
+        .map(|o| o.hash.as_str())
+        .collect::<Vec<_>>();
+    sha256::digest(hashes.join(","))
+}
+
+// one payload per operation, or per batch of WEBHOOK_BATCH_SIZE operations
+fn get_payloads(
+    operations: &[SubscanOperation],
+    batching: &SinkBatching,
+    network: &Network,
+) -> Vec<(String, Vec<u8>)> {
+    let network = network.get_slug().to_string();
+    if batching.is_per_operation() {
+        return operations
+            .iter()
+            .map(|s| {
+                let payload = WebhookPayload {
+                    event: WEBHOOK_EVENT.to_string(),
+                    network: network.clone(),
+                    operation: ExportOperation::new(s.clone(), &ExportPrecision::full()),
+                };
+                (
+                    s.hash.clone(),
+                    serde_json::to_vec(&payload).unwrap_or_default(),
+                )
+            })
+            .collect();
+    }
+
+    batching
+        .get_batches(operations)
+        .map(|batch| {
+            let payload = WebhookBatchPayload {
+                event: WEBHOOK_BATCH_EVENT.to_string(),
+                network: network.clone(),
+                operations: batch
+                    .iter()
+                    .map(|s| ExportOperation::new(s.clone(), &ExportPrecision::full()))
+                    .collect(),
+            };
+            (
+                get_batch_id(batch),
+                serde_json::to_vec(&payload).unwrap_or_default(),
+            )
+        })
+        .collect()
+}
+
+// every endpoint gets the operations in their order, one payload each, an endpoint down
+// only delays its own deliveries, a payload failing every attempt is dropped
+pub async fn write_operations(operations: &[SubscanOperation]) {
+    let config = WebhookConfig::from_env();
+    if operations.is_empty() || config.urls.is_empty() {
+        return;
+    }
+
+    let batching = SinkBatching::from_env(&Sink::Webhook);
+    let network = Network::from_env();
+    let payloads = get_payloads(operations, &batching, &network);
+
+    let client = Client::builder()
+        .timeout(config.timeout)
+        .build()
+        .unwrap_or_default();
+    let deliveries = config.urls.iter().map(|url| {
+        let (client, config, payloads, network) = (&client, &config, &payloads, &network);
+        async move {
+            for (hash, body) in payloads {
+                deliver(client, config, network, url, hash, body).await;
+            }
+        }
+    });
+    join_all(deliveries).await;
+}
+
+// reported with the webhook id, the endpoint is the url the payload was dropped for
+fn get_pipeline_error(network: &Network, url: &str, hash: &str, reason: &str) -> PipelineError {
+    PipelineError::new(
+        ErrorCode::HttpError,
+        &format!("webhook {hash}: {reason}"),
+        network,
+    )
+    .with_endpoint(url)
+}
+
+async fn deliver(
+    client: &Client,
+    config: &WebhookConfig,
+    network: &Network,
+    url: &str,
+    hash: &str,
+    body: &[u8],
+) {
+    let max_attempts = config.retry_policy.max_attempts;
+    for attempt in 1..=max_attempts {
+        let timestamp = Utc::now().timestamp();
+        let mut request = client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .header("X-Webhook-Id", hash)
+            .header("X-Webhook-Timestamp", timestamp.to_string())
+            .body(body.to_vec());
+        if let Some(secret) = &config.secret {
+            request = request.header(
+                "X-Webhook-Signature",
+                get_signature(secret, timestamp, body),
+            );
+        }
+
+        let reason = match request.send().await {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) if !is_retryable(response.status()) => {
+                let reason = format!("rejected with {}", response.status());
+                let pipeline_error = get_pipeline_error(network, url, hash, &reason);
+                error!(target: "webhook", "Webhook error: {}, dropping it.", pipeline_error.to_json());
+                return;
+            }
+            Ok(response) => response.status().to_string(),
+            Err(e) => e.to_string(),
+        };
+
+        if attempt == max_attempts {
+            let reason = format!("failed {max_attempts} times, {reason}");
+            let pipeline_error = get_pipeline_error(network, url, hash, &reason);
+            error!(target: "webhook", "Webhook error: {}, dropping it.", pipeline_error.to_json());
+            return;
+        }
+
+        let delay = config
+            .retry_policy
+            .get_delay(attempt, &mut rand::thread_rng());
+        error!(target: "webhook", "Webhook {url} error for {hash}: {reason}; Sleeping {} ms.", delay.as_millis());
+        sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        hmac::hmac_sha256,
+        pipeline_error::ErrorCode,
+        sinks::webhook::{get_pipeline_error, get_signature, get_webhook_urls, is_retryable},
+        subscan_parser::Network,
+    };
+    use reqwest::StatusCode;
+
+    #[test]
+    fn only_https_urls_are_used() {
+        assert_eq!(
+            get_webhook_urls(" https://a.example/hook, http://b.example/hook,,https://c.example "),
+            vec!["https://a.example/hook", "https://c.example"]
+        );
+        assert!(get_webhook_urls("").is_empty());
+    }
+
+    #[test]
+    fn signatures_are_hmac_sha256_of_timestamp_and_body() {
+        assert_eq!(
+            get_signature("Jefe", 1_700_000_000, b"{}"),
+            format!(
+                "sha256={}",
+                hex::encode(hmac_sha256(b"Jefe", b"1700000000.{}"))
+            )
+        );
+    }
+
+    #[test]
+    fn only_transient_failures_are_retried() {
+        assert!(is_retryable(StatusCode::BAD_GATEWAY));
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_retryable(StatusCode::NOT_FOUND));
+        assert!(!is_retryable(StatusCode::UNAUTHORIZED));
+    }
+
+    #[test]
+    fn dropped_payloads_are_reported_with_codes() {
+        let pipeline_error = get_pipeline_error(
+            &Network::Polkadot,
+            "https://a.example/hook",
+            "0xabc",
+            "rejected with 410 Gone",
+        );
+
+        assert_eq!(pipeline_error.code, ErrorCode::HttpError);
+        assert_eq!(pipeline_error.network, "polkadot");
+        assert_eq!(
+            pipeline_error.endpoint.as_deref(),
+            Some("https://a.example/hook")
+        );
+        assert_eq!(
+            pipeline_error.message,
+            "webhook 0xabc: rejected with 410 Gone"
+        );
+    }
+}
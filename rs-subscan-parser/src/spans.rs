@@ -0,0 +1,30 @@
+use crate::subscan_parser::Network;
+use tracing::{info_span, Span};
+
+// random id shared by every log line of one pass through the pipeline
+pub fn new_correlation_id() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+// one pass of fetch, enrich and store, the subscan requests, staking operations and sink
+// writes of the pass are spans within it
+pub fn get_run_span(network: &Network) -> Span {
+    info_span!(
+        "run",
+        correlation_id = %new_correlation_id(),
+        network = %network,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spans::new_correlation_id;
+
+    #[test]
+    fn correlation_ids_are_16_hex_digits() {
+        let id = new_correlation_id();
+        assert_eq!(id.len(), 16);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(id, new_correlation_id());
+    }
+}
@@ -0,0 +1,248 @@
+use crate::{
+    mongodb_client_operation_summaries::MongoDbClientOperationSummaries,
+    mongodb_client_staking_flow::MongoDbClientStakingFlow,
+    mongodb_client_subscan::MongoDbClientSubscan, subscan_parser::SubscanParser, OperationSummary,
+    OperationType, StakingFlowCandle, SubscanOperation, SummaryDirection,
+};
+use bson::DateTime;
+use itertools::Itertools;
+
+static MILLIS_IN_DAY: i64 = 24 * 60 * 60 * 1_000;
+
+// stake and unstake amounts moved at a point in time
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct Flow {
+    pub timestamp: DateTime,
+    pub inflow: f64,
+    pub outflow: f64,
+}
+
+// rebuilds the candles of the affected validators starting from the earliest day the
+// operations touched, older candles stay as stored and seed the cumulative flow
+pub async fn update_staking_flow(operations: &[SubscanOperation]) {
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let mut mongodb_client_operation_summaries = MongoDbClientOperationSummaries::new().await;
+    let mut mongodb_client_staking_flow = MongoDbClientStakingFlow::new().await;
+
+    let from_days = operations
+        .iter()
+        .filter(|s| !SubscanParser::is_address_empty(&s.to_wallet))
+        .into_grouping_map_by(|s| s.to_wallet.clone())
+        .fold(i64::MAX, |from_day, _, s| {
+            from_day.min(get_day(s.operation_timestamp).timestamp_millis())
+        });
+
+    for (validator, from_day) in from_days {
+        let from_day = DateTime::from_millis(from_day);
+        let previous = mongodb_client_staking_flow
+            .get_last_candle_before(&validator, from_day)
+            .await;
+
+        // compacted hours only survive in summaries
+        let operations = mongodb_client_subscan
+            .get_validator_operations_from(&validator, from_day)
+            .await;
+        let summaries = mongodb_client_operation_summaries
+            .get_wallet_summaries(
+                &validator,
+                from_day.timestamp_millis() / 1000,
+                i64::MAX / 1000,
+            )
+            .await;
+
+        let flows = get_flows(&operations, &summaries);
+        let candles = build_candles(&validator, previous.as_ref(), &flows);
+        mongodb_client_staking_flow
+            .import_or_update_candles(candles)
+            .await;
+    }
+}
+
+fn get_day(timestamp: DateTime) -> DateTime {
+    let millis = timestamp.timestamp_millis();
+    DateTime::from_millis(millis - millis.rem_euclid(MILLIS_IN_DAY))
+}
+
+fn get_flow(operation_type: &OperationType, timestamp: DateTime, quantity: f64) -> Option<Flow> {
+    let (inflow, outflow) = match operation_type {
+        OperationType::Stake => (quantity, 0.0),
+        OperationType::RequestUnstake => (0.0, quantity),
+        _ => return None,
+    };
+
+    Some(Flow {
+        timestamp,
+        inflow,
+        outflow,
+    })
+}
+
+// sorted by timestamp, a summary counts as a single flow at the start of its hour
+pub fn get_flows(operations: &[SubscanOperation], summaries: &[OperationSummary]) -> Vec<Flow> {
+    let operation_flows = operations.iter().filter_map(|s| {
+        get_flow(
+            &s.operation_type,
+            s.operation_timestamp,
+            s.operation_quantity,
+        )
+    });
+    let summary_flows = summaries
+        .iter()
+        .filter(|s| s.direction == SummaryDirection::In)
+        .filter_map(|s| get_flow(&s.operation_type, s.hour, s.quantity));
+
+    operation_flows
+        .chain(summary_flows)
+        .sorted_by_key(|f| f.timestamp)
+        .collect()
+}
+
+// days without flows between two candles carry the previous close
+pub fn build_candles(
+    validator: &str,
+    previous: Option<&StakingFlowCandle>,
+    flows: &[Flow],
+) -> Vec<StakingFlowCandle> {
+    let mut candles: Vec<StakingFlowCandle> = Vec::new();
+    let mut cumulative = previous.map(|c| c.close).unwrap_or_default();
+    let mut last_day = previous.map(|c| c.day);
+
+    for flow in flows {
+        let day = get_day(flow.timestamp);
+        if last_day != Some(day) {
+            let mut gap_day = last_day.map(|d| d.timestamp_millis() + MILLIS_IN_DAY);
+            while let Some(millis) = gap_day.filter(|m| *m < day.timestamp_millis()) {
+                candles.push(get_flat_candle(
+                    validator,
+                    DateTime::from_millis(millis),
+                    cumulative,
+                ));
+                gap_day = Some(millis + MILLIS_IN_DAY);
+            }
+            candles.push(get_flat_candle(validator, day, cumulative));
+            last_day = Some(day);
+        }
+
+        let candle = candles.last_mut().unwrap();
+        cumulative += flow.inflow - flow.outflow;
+        candle.inflow += flow.inflow;
+        candle.outflow += flow.outflow;
+        candle.high = candle.high.max(cumulative);
+        candle.low = candle.low.min(cumulative);
+        candle.close = cumulative;
+    }
+
+    candles
+}
+
+fn get_flat_candle(validator: &str, day: DateTime, close: f64) -> StakingFlowCandle {
+    StakingFlowCandle {
+        validator: validator.to_string(),
+        day,
+        open: close,
+        high: close,
+        low: close,
+        close,
+        inflow: 0.0,
+        outflow: 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        staking_flow::{build_candles, get_flows},
+        OperationStatus, OperationSummary, OperationType, StakingFlowCandle, SubscanOperation,
+        SummaryDirection,
+    };
+    use bson::DateTime;
+
+    static MILLIS_IN_DAY: i64 = 24 * 60 * 60 * 1_000;
+
+    fn operation(day: i64, operation_type: OperationType, quantity: f64) -> SubscanOperation {
+        SubscanOperation {
+            hash: String::new(),
+            hash_version: 0,
+            block_number: 0,
+            extrinsic_index: String::new(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(day * MILLIS_IN_DAY + 1_000),
+            operation_quantity: quantity,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 0.0,
+            operation_type,
+            from_wallet: String::new(),
+            controller_wallet: String::new(),
+            to_wallet: "validator".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn candles_follow_cumulative_flow() {
+        let operations = vec![
+            operation(0, OperationType::Stake, 1_000.0),
+            operation(0, OperationType::RequestUnstake, 1_500.0),
+            operation(0, OperationType::Stake, 700.0),
+            operation(0, OperationType::ReStake, 10_000.0),
+            operation(2, OperationType::Stake, 300.0),
+        ];
+
+        let candles = build_candles("validator", None, &get_flows(&operations, &[]));
+        assert_eq!(candles.len(), 3);
+
+        let first = &candles[0];
+        assert_eq!(first.open, 0.0);
+        assert_eq!(first.high, 1_000.0);
+        assert_eq!(first.low, -500.0);
+        assert_eq!(first.close, 200.0);
+        assert_eq!(first.inflow, 1_700.0);
+        assert_eq!(first.outflow, 1_500.0);
+
+        // nothing happened on the second day
+        let gap = &candles[1];
+        assert_eq!(gap.day, DateTime::from_millis(MILLIS_IN_DAY));
+        assert_eq!((gap.open, gap.close, gap.inflow), (200.0, 200.0, 0.0));
+
+        let third = &candles[2];
+        assert_eq!(third.open, 200.0);
+        assert_eq!(third.close, 500.0);
+        assert_eq!(third.low, 200.0);
+    }
+
+    #[test]
+    fn candles_continue_from_stored_candle_and_summaries() {
+        let previous = StakingFlowCandle {
+            validator: "validator".to_string(),
+            day: DateTime::from_millis(0),
+            open: 0.0,
+            high: 5_000.0,
+            low: 0.0,
+            close: 4_000.0,
+            inflow: 5_000.0,
+            outflow: 1_000.0,
+        };
+        let summaries = vec![OperationSummary {
+            wallet: "validator".to_string(),
+            hour: DateTime::from_millis(2 * MILLIS_IN_DAY),
+            operation_type: OperationType::RequestUnstake,
+            direction: SummaryDirection::In,
+            count: 2,
+            quantity: 1_000.0,
+            usd: 0.0,
+        }];
+        let operations = vec![operation(2, OperationType::Stake, 500.0)];
+
+        let flows = get_flows(&operations, &summaries);
+        let candles = build_candles("validator", Some(&previous), &flows);
+        assert_eq!(candles.len(), 2);
+        assert_eq!(candles[0].close, 4_000.0);
+        assert_eq!(candles[1].open, 4_000.0);
+        assert_eq!(candles[1].low, 3_000.0);
+        assert_eq!(candles[1].close, 3_500.0);
+    }
+}
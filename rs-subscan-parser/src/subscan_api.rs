@@ -0,0 +1,139 @@
+use crate::{subscan_error::SubscanError, subscan_scheduler::SubscanEndpoint};
+use reqwest::header::HeaderMap;
+use rs_utils::clients::http_client::HttpClient;
+use serde_json::Value;
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    sync::{Arc, Mutex},
+};
+use strum::IntoEnumIterator;
+
+// transport of the subscan requests, retries and response codes are handled by the parser
+pub trait SubscanApi: Clone + Send + Sync {
+    fn post_json(
+        &mut self,
+        url: &str,
+        headers: HeaderMap,
+        payload: &Value,
+    ) -> impl Future<Output = Result<Value, SubscanError>> + Send;
+}
+
+impl SubscanApi for HttpClient {
+    async fn post_json(
+        &mut self,
+        url: &str,
+        headers: HeaderMap,
+        payload: &Value,
+    ) -> Result<Value, SubscanError> {
+        self.try_post_request::<Value, Value>(url, headers, payload)
+            .await
+            .map_err(SubscanError::from)
+    }
+}
+
+// answers from fixtures instead of subscan, clones share the fixtures and the recorded requests
+#[derive(Clone, Debug, Default)]
+pub struct MockSubscanApi {
+    responses: Arc<Mutex<HashMap<SubscanEndpoint, VecDeque<Value>>>>,
+    requests: Arc<Mutex<Vec<(SubscanEndpoint, Value)>>>,
+}
+
+impl MockSubscanApi {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // answers of an endpoint are given in order, the last one is repeated
+    pub fn with_response(self, endpoint: SubscanEndpoint, response: Value) -> Self {
+        self.responses
+            .lock()
+            .unwrap()
+            .entry(endpoint)
+            .or_default()
+            .push_back(response);
+        self
+    }
+
+    // payloads posted so far, oldest first
+    pub fn get_requests(&self, endpoint: SubscanEndpoint) -> Vec<Value> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(e, _)| *e == endpoint)
+            .map(|(_, payload)| payload.clone())
+            .collect()
+    }
+
+    fn respond(&self, url: &str, payload: &Value) -> Result<Value, SubscanError> {
+        let mut responses = self.responses.lock().unwrap();
+        let (endpoint, answers) = responses
+            .iter_mut()
+            .find(|(e, _)| url.ends_with(e.path()))
+            .ok_or_else(|| SubscanError::Http(format!("no fixture for {url}")))?;
+
+        self.requests
+            .lock()
+            .unwrap()
+            .push((*endpoint, payload.clone()));
+
+        match answers.len() {
+            0 => Err(SubscanError::Http(format!("no fixture for {url}"))),
+            1 => Ok(answers[0].clone()),
+            _ => Ok(answers.pop_front().unwrap()),
+        }
+    }
+}
+
+// passes requests on and keeps every answer, e.g. to replay them later with MockSubscanApi,
+// clones share the recorded answers
+#[derive(Clone, Debug)]
+pub struct RecordingSubscanApi<A> {
+    api: A,
+    responses: Arc<Mutex<Vec<(SubscanEndpoint, Value)>>>,
+}
+
+impl<A: SubscanApi> RecordingSubscanApi<A> {
+    pub fn new(api: A) -> Self {
+        Self {
+            api,
+            responses: Arc::default(),
+        }
+    }
+
+    // answers so far, oldest first
+    pub fn get_responses(&self) -> Vec<(SubscanEndpoint, Value)> {
+        self.responses.lock().unwrap().clone()
+    }
+}
+
+impl<A: SubscanApi> SubscanApi for RecordingSubscanApi<A> {
+    async fn post_json(
+        &mut self,
+        url: &str,
+        headers: HeaderMap,
+        payload: &Value,
+    ) -> Result<Value, SubscanError> {
+        let response = self.api.post_json(url, headers, payload).await?;
+        if let Some(endpoint) = SubscanEndpoint::iter().find(|e| url.ends_with(e.path())) {
+            self.responses
+                .lock()
+                .unwrap()
+                .push((endpoint, response.clone()));
+        }
+
+        Ok(response)
+    }
+}
+
+impl SubscanApi for MockSubscanApi {
+    async fn post_json(
+        &mut self,
+        url: &str,
+        _headers: HeaderMap,
+        payload: &Value,
+    ) -> Result<Value, SubscanError> {
+        self.respond(url, payload)
+    }
+}
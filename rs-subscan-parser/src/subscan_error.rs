@@ -0,0 +1,161 @@
+use crate::{circuit_breaker::RequestOutcome, pipeline_error::ErrorCode};
+use rs_utils::clients::http_client::RequestError;
+use serde_json::Value;
+
+pub static SUBSCAN_RATE_LIMITED_CODE: u64 = 20008;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubscanError {
+    Http(String),
+    Api {
+        code: u64,
+        message: String,
+    },
+    Deserialization(String),
+    RateLimited(String),
+
+    // dotted path of the field in the response, e.g. data.extrinsics
+    MissingField(String),
+
+    // retries ran out, the error of the last attempt
+    AttemptsExhausted {
+        attempts: u32,
+        last_error: Box<SubscanError>,
+    },
+
+    // failed fast without a request, subscan kept failing lately
+    CircuitOpen {
+        retry_in_ms: u64,
+    },
+}
+
+impl SubscanError {
+    // non zero code of a subscan response
+    pub fn from_code(code: u64, message: &str) -> SubscanError {
+        if code == SUBSCAN_RATE_LIMITED_CODE {
+            SubscanError::RateLimited(message.to_string())
+        } else {
+            SubscanError::Api {
+                code,
+                message: message.to_string(),
+            }
+        }
+    }
+
+    // subscan's own code when it answered with one, e.g. 20008, the error code otherwise
+    pub fn get_code_label(&self) -> String {
+        match self {
+            SubscanError::Api { code, .. } => code.to_string(),
+            SubscanError::RateLimited(_) => SUBSCAN_RATE_LIMITED_CODE.to_string(),
+            SubscanError::AttemptsExhausted { last_error, .. } => last_error.get_code_label(),
+            _ => self.get_error_code().to_string(),
+        }
+    }
+
+    pub fn get_error_code(&self) -> ErrorCode {
+        match self {
+            SubscanError::Http(_) => ErrorCode::HttpError,
+            SubscanError::Api { .. } => ErrorCode::SubscanApiError,
+            SubscanError::Deserialization(_) => ErrorCode::DeserializationError,
+            SubscanError::RateLimited(_) => ErrorCode::SubscanRateLimited,
+            SubscanError::MissingField(_) => ErrorCode::SubscanMissingField,
+            SubscanError::AttemptsExhausted { last_error, .. } => last_error.get_error_code(),
+            SubscanError::CircuitOpen { .. } => ErrorCode::SubscanUnavailable,
+        }
+    }
+
+    // subscan failing to answer or answering with an error code counts against it, a
+    // malformed response doesn't tell either way
+    pub fn get_request_outcome(&self) -> RequestOutcome {
+        match self {
+            SubscanError::Http(_)
+            | SubscanError::Api { .. }
+            | SubscanError::RateLimited(_)
+            | SubscanError::AttemptsExhausted { .. } => RequestOutcome::Failure,
+            SubscanError::Deserialization(_)
+            | SubscanError::MissingField(_)
+            | SubscanError::CircuitOpen { .. } => RequestOutcome::Neutral,
+        }
+    }
+}
+
+impl std::fmt::Display for SubscanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubscanError::Http(e) => write!(f, "http error: {e}"),
+            SubscanError::Api { code, message } => write!(f, "[{code}] {message}"),
+            SubscanError::Deserialization(e) => write!(f, "deserialization error: {e}"),
+            SubscanError::RateLimited(message) => {
+                write!(f, "[{SUBSCAN_RATE_LIMITED_CODE}] {message}")
+            }
+            SubscanError::MissingField(path) => write!(f, "response has no {path}"),
+            SubscanError::AttemptsExhausted {
+                attempts,
+                last_error,
+            } => write!(f, "gave up after {attempts} attempts, {last_error}"),
+            SubscanError::CircuitOpen { retry_in_ms } => {
+                write!(f, "subscan circuit is open, retrying in {retry_in_ms} ms")
+            }
+        }
+    }
+}
+
+impl From<RequestError> for SubscanError {
+    fn from(e: RequestError) -> Self {
+        match e {
+            RequestError::Parse(_) => SubscanError::Deserialization(e.to_string()),
+            RequestError::Send(_) | RequestError::Response(_) => SubscanError::Http(e.to_string()),
+        }
+    }
+}
+
+// value at a dotted path of a response, the error names the whole path
+pub fn get_field<'a>(value: &'a Value, path: &str) -> Result<&'a Value, SubscanError> {
+    path.split('.').try_fold(value, |v, key| {
+        v.get(key)
+            .ok_or_else(|| SubscanError::MissingField(path.to_string()))
+    })
+}
+
+pub fn get_array<'a>(value: &'a Value, path: &str) -> Result<&'a Vec<Value>, SubscanError> {
+    get_field(value, path)?
+        .as_array()
+        .ok_or_else(|| SubscanError::Deserialization(format!("{path} is not an array")))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        pipeline_error::ErrorCode,
+        subscan_error::{get_array, SubscanError},
+    };
+    use serde_json::json;
+
+    #[test]
+    fn missing_fields_name_their_path() {
+        let resp = json!({"code": 0, "data": {"extrinsics": [], "count": 0}});
+
+        assert_eq!(get_array(&resp, "data.extrinsics"), Ok(&Vec::new()));
+        assert_eq!(
+            get_array(&resp, "data.transfers"),
+            Err(SubscanError::MissingField("data.transfers".to_string()))
+        );
+        assert_eq!(
+            get_array(&resp, "data.count"),
+            Err(SubscanError::Deserialization(
+                "data.count is not an array".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn rate_limits_are_told_apart_from_api_errors() {
+        let rate_limited = SubscanError::from_code(20008, "API rate limit exceeded");
+        assert_eq!(rate_limited.get_error_code(), ErrorCode::SubscanRateLimited);
+        assert_eq!(rate_limited.to_string(), "[20008] API rate limit exceeded");
+
+        let api_error = SubscanError::from_code(10004, "Record Not Found");
+        assert_eq!(api_error.get_error_code(), ErrorCode::SubscanApiError);
+        assert_eq!(api_error.to_string(), "[10004] Record Not Found");
+    }
+}
@@ -1,200 +1,399 @@
 use crate::{
-    ExtrinsicsType, Identity, Module, OperationType, SubscanEvent, SubscanEventParam,
-    SubscanOperation,
+    address::{self, AddressFormat},
+    amount::{from_planck_str, Balance},
+    circuit_breaker::CircuitBreaker,
+    data_quality::{quarantine_records, QuarantineSource, QuarantinedRecord},
+    exports::precision::parse_decimal_planck,
+    metrics::{Metric, Metrics},
+    mock_network::{self, MOCK_SLOT_SECONDS},
+    pagination::Pagination,
+    pipeline_error::PipelineError,
+    retry_policy::RetryPolicy,
+    subscan_api::SubscanApi,
+    subscan_error::{get_array, get_field, SubscanError},
+    subscan_scheduler::{RequestPriority, SubscanEndpoint, SubscanScheduler},
+    timestamp_validation::get_block_timestamp,
+    wallet_formats::{normalize_identities, normalize_operations},
+    BalanceSnapshot, ExtrinsicsType, Identity, Module, OperationStatus, OperationType,
+    SubscanEvent, SubscanEventParam, SubscanExtrinsic, SubscanOperation,
 };
 use bson::DateTime;
-use log::error;
+use futures::{stream, Stream};
 use rand::seq::IteratorRandom;
 use reqwest::header::{HeaderMap, HeaderValue};
 use rs_utils::clients::http_client::HttpClient;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
-use std::{env, time::Duration};
-use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+use std::{env, fmt, str::FromStr, time::Instant};
+use strum_macros::EnumString;
 use tokio::time::sleep;
+use tracing::{error, field, info, info_span, Instrument, Span};
 
 pub static EMPTY_ADDRESS: &str = "0x0";
-pub static AZERO_DENOMINATOR: f64 = 1e12;
+static DEFAULT_CUSTOM_DECIMALS: u32 = 12;
+static DEFAULT_CUSTOM_SS58_PREFIX: u16 = 42;
+
+// scale limit of rust_decimal
+static MAX_DECIMALS: u32 = 28;
+
+// event params naming the account an extrinsic was made for, when its row has no signer
+static SIGNER_EVENT_PARAMS: [&str; 3] = ["stash", "who", "member"];
 
 #[derive(
-    Clone,
-    Debug,
-    Serialize,
-    Deserialize,
-    EnumString,
-    Default,
-    IntoStaticStr,
-    EnumIter,
-    Display,
-    PartialEq,
-    Eq,
-    PartialOrd,
-    Ord,
-    Hash,
+    Clone, Debug, Serialize, Deserialize, EnumString, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
 )]
 #[strum(serialize_all = "snake_case")]
 pub enum Network {
     #[default]
     Alephzero,
+    Polkadot,
+    Kusama,
+    Astar,
+    Moonbeam,
+    Westend,
+
+    // generated data for demos, needs no api keys
+    Mock,
+
+    // self-hosted subscan compatible indexer, configured by SUBSCAN_BASE_URL
+    #[strum(disabled)]
+    Custom {
+        slug: String,
+        base_url: String,
+        decimals: u32,
+        ss58_prefix: u16,
+        token_symbol: String,
+        explorer_url: Option<String>,
+    },
 }
 
-#[derive(Clone, Debug)]
-pub struct SubscanParser {
-    http_client: HttpClient,
-    network: String,
-}
+impl Network {
+    // SUBSCAN_NETWORK names the network, with SUBSCAN_BASE_URL set it is a custom one
+    pub fn from_env() -> Network {
+        let slug = env::var("SUBSCAN_NETWORK").unwrap_or_default();
+        match env::var("SUBSCAN_BASE_URL") {
+            Ok(base_url) if !base_url.is_empty() => Network::custom_from_env(slug, base_url),
+            _ => Network::from_str(&slug).unwrap_or_default(),
+        }
+    }
 
-impl SubscanParser {
-    pub async fn new(network: Network) -> Self {
-        let http_client = HttpClient::new("subscan_parser").await;
-        SubscanParser {
-            network: network.to_string(),
-            http_client,
+    // SUBSCAN_DECIMALS, SUBSCAN_SS58_PREFIX and SUBSCAN_TOKEN_SYMBOL of the indexed chain,
+    // a generic substrate chain if they are not set, SUBSCAN_EXPLORER_URL if it has a web explorer
+    fn custom_from_env(slug: String, base_url: String) -> Network {
+        let decimals = env::var("SUBSCAN_DECIMALS")
+            .ok()
+            .and_then(|d| d.parse::<u32>().ok())
+            .unwrap_or(DEFAULT_CUSTOM_DECIMALS);
+        let ss58_prefix = env::var("SUBSCAN_SS58_PREFIX")
+            .ok()
+            .and_then(|p| p.parse::<u16>().ok())
+            .unwrap_or(DEFAULT_CUSTOM_SS58_PREFIX);
+        let token_symbol = env::var("SUBSCAN_TOKEN_SYMBOL")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| slug.to_uppercase());
+        let explorer_url = env::var("SUBSCAN_EXPLORER_URL")
+            .ok()
+            .filter(|u| !u.is_empty())
+            .map(|u| u.trim_end_matches('/').to_string());
+
+        Network::Custom {
+            slug,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            decimals,
+            ss58_prefix,
+            token_symbol,
+            explorer_url,
         }
     }
 
-    pub async fn parse_subscan_events(
-        &mut self,
-        event_indexes: Vec<String>,
-    ) -> Option<Vec<SubscanEvent>> {
-        let mut resp;
+    pub fn get_slug(&self) -> &str {
+        match self {
+            Network::Alephzero => "alephzero",
+            Network::Polkadot => "polkadot",
+            Network::Kusama => "kusama",
+            Network::Astar => "astar",
+            Network::Moonbeam => "moonbeam",
+            Network::Westend => "westend",
+            Network::Mock => "mock",
+            Network::Custom { slug, .. } => slug,
+        }
+    }
 
-        loop {
-            let url = format!(
-                "https://{}.api.subscan.io/api/scan/event/params",
-                self.network
-            );
+    // api endpoints are appended to it
+    pub fn get_base_url(&self) -> String {
+        match self {
+            Network::Custom { base_url, .. } => base_url.clone(),
+            _ => format!("https://{}.api.subscan.io", self.get_slug()),
+        }
+    }
 
-            let subscan_api_key = SubscanParser::get_random_api_key();
+    // subscan web explorer, none for networks without one
+    pub fn get_explorer_url(&self) -> Option<String> {
+        match self {
+            Network::Mock => None,
+            Network::Custom { explorer_url, .. } => explorer_url.clone(),
+            _ => Some(format!("https://{}.subscan.io", self.get_slug())),
+        }
+    }
 
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                "X-API-Key",
-                HeaderValue::from_str(&subscan_api_key).unwrap(),
-            );
+    // average time between blocks, timestamps are validated against it
+    pub fn get_block_time_ms(&self) -> i64 {
+        match self {
+            Network::Alephzero => 1_000,
+            Network::Polkadot
+            | Network::Kusama
+            | Network::Astar
+            | Network::Moonbeam
+            | Network::Westend
+            | Network::Custom { .. } => 6_000,
+            Network::Mock => MOCK_SLOT_SECONDS * 1_000,
+        }
+    }
 
-            let payload = json!({"event_index": event_indexes});
+    // addresses are encoded with it, moonbeam accounts are ethereum style and only shown as hex
+    pub fn get_ss58_prefix(&self) -> u16 {
+        match self {
+            Network::Alephzero | Network::Westend | Network::Mock => 42,
+            Network::Polkadot => 0,
+            Network::Kusama => 2,
+            Network::Astar => 5,
+            Network::Moonbeam => 1284,
+            Network::Custom { ss58_prefix, .. } => *ss58_prefix,
+        }
+    }
 
-            resp = self
-                .http_client
-                .post_request::<Value, Value>(&url, headers, payload)
-                .await;
+    // 20 byte accounts without an ss58 form
+    pub fn has_ethereum_accounts(&self) -> bool {
+        *self == Network::Moonbeam
+    }
 
-            let code = resp.get("code")?.as_u64()?;
-            if code != 0 {
-                let message = resp.get("message")?.as_str()?;
-                error!(target: "subscan_parser", "Parse error[{code}]: {message}. Sleeping 1 seconds.");
-                sleep(Duration::from_millis(1_000)).await;
-                continue;
-            }
+    // SUBSCAN_DECIMALS_OVERRIDES wins over the registry, for runtimes which redenominated
+    pub fn get_decimals(&self) -> u32 {
+        env::var("SUBSCAN_DECIMALS_OVERRIDES")
+            .ok()
+            .and_then(|o| get_decimals_override(&o, self.get_slug()))
+            .unwrap_or_else(|| self.get_default_decimals())
+    }
 
-            break;
+    pub fn get_default_decimals(&self) -> u32 {
+        match self {
+            Network::Alephzero | Network::Kusama | Network::Westend | Network::Mock => 12,
+            Network::Polkadot => 10,
+            Network::Astar | Network::Moonbeam => 18,
+            Network::Custom { decimals, .. } => *decimals,
         }
+    }
 
-        let data = resp.get("data")?.as_array()?;
-        let subscan_events = data
-            .iter()
-            .filter_map(|d| -> Option<_> {
-                let module_id = d.get("module_id")?.as_str()?.to_string();
-                let event_index = d.get("event_index")?.as_str()?.to_string();
-                let event_params = d
-                    .get("params")?
-                    .as_array()?
-                    .iter()
-                    .filter_map(|p| {
-                        let type_name = p.get("type_name")?.as_str()?.to_string();
-                        let value = p.get("value")?.as_str()?.to_string();
-                        let name = p.get("name")?.as_str()?.to_string();
-
-                        Some(SubscanEventParam {
-                            type_name,
-                            value,
-                            name,
-                        })
-                    })
-                    .collect();
+    pub fn get_token_symbol(&self) -> &str {
+        match self {
+            Network::Alephzero | Network::Mock => "AZERO",
+            Network::Polkadot => "DOT",
+            Network::Kusama => "KSM",
+            Network::Astar => "ASTR",
+            Network::Moonbeam => "GLMR",
+            Network::Westend => "WND",
+            Network::Custom { token_symbol, .. } => token_symbol,
+        }
+    }
+}
 
-                Some(SubscanEvent {
-                    module_id,
-                    event_index,
-                    event_params,
-                })
-            })
-            .collect::<Vec<SubscanEvent>>();
-        Some(subscan_events)
+// decimals of the network in a list like "polkadot:10,astar:18", more than a decimal holds are ignored
+fn get_decimals_override(overrides: &str, slug: &str) -> Option<u32> {
+    overrides
+        .split(',')
+        .filter_map(|o| o.split_once(':'))
+        .find(|(s, _)| s.trim() == slug)
+        .and_then(|(_, d)| d.trim().parse::<u32>().ok())
+        .filter(|d| *d <= MAX_DECIMALS)
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.get_slug())
     }
+}
 
-    pub async fn parse_subscan_extrinsic_details(
-        &mut self,
-        extrinsic_index: String,
-    ) -> Option<Vec<SubscanEvent>> {
-        let mut resp;
+// operations of a response as subscan wrote them and the rows which couldn't be parsed,
+// the pipeline quarantines and normalizes them, parsing itself never writes to the db
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParsedOperations {
+    pub operations: Vec<SubscanOperation>,
+    pub quarantined: Vec<QuarantinedRecord>,
+}
 
-        loop {
-            let url = format!("https://{}.api.subscan.io/api/scan/extrinsic", self.network);
+impl ParsedOperations {
+    pub fn append(&mut self, other: ParsedOperations) {
+        self.operations.extend(other.operations);
+        self.quarantined.extend(other.quarantined);
+    }
+
+    // the operations with their wallets in the address format
+    pub async fn process(
+        self,
+        network: &Network,
+        address_format: AddressFormat,
+    ) -> Vec<SubscanOperation> {
+        let mut operations = self.operations;
+        quarantine_records(self.quarantined).await;
+        normalize_operations(&mut operations, network, address_format).await;
+
+        operations
+    }
+}
 
-            let subscan_api_key = SubscanParser::get_random_api_key();
+// posts through the real subscan api unless built with another one, e.g. fixtures in tests,
+// cheap to clone as the reqwest client keeps its connection pool behind an Arc, so tasks share
+// clones of one parser instead of building their own
+#[derive(Clone, Debug)]
+pub struct SubscanParser<A = HttpClient> {
+    api: A,
+    network: Network,
+    retry_policy: RetryPolicy,
 
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                "X-API-Key",
-                HeaderValue::from_str(&subscan_api_key).unwrap(),
-            );
+    // encoding of the parsed wallets, SUBSCAN_ADDRESS_FORMAT unless set otherwise
+    address_format: AddressFormat,
 
-            let payload = json!({
-                "extrinsic_index": extrinsic_index,
-                "only_extrinsic_event" : true
-            });
-
-            resp = self
-                .http_client
-                .post_request::<Value, Value>(&url, headers, payload)
-                .await;
-
-            let code = resp.get("code")?.as_u64()?;
-            if code != 0 {
-                let message = resp.get("message")?.as_str()?;
-                error!(target: "subscan_parser", "Parse error[{code}]: {message}. Sleeping 1 seconds.");
-                sleep(Duration::from_millis(1_000)).await;
-                continue;
-            }
+    // comma separated like SUBSCAN_API_KEY, which is used unless set
+    api_keys: Option<String>,
+}
+
+impl SubscanParser {
+    pub async fn new(network: Network) -> Self {
+        let http_client = HttpClient::new("subscan_parser").await;
+        SubscanParser::with_api(network, http_client)
+    }
+}
 
-            break;
+impl<A: SubscanApi> SubscanParser<A> {
+    pub fn with_api(network: Network, api: A) -> Self {
+        SubscanParser {
+            network,
+            api,
+            retry_policy: RetryPolicy::from_env(),
+            address_format: AddressFormat::from_env(),
+            api_keys: None,
         }
+    }
+
+    pub fn with_address_format(self, address_format: AddressFormat) -> Self {
+        Self {
+            address_format,
+            ..self
+        }
+    }
+
+    pub fn with_api_keys(self, api_keys: Option<String>) -> Self {
+        Self { api_keys, ..self }
+    }
+
+    pub fn get_address_format(&self) -> AddressFormat {
+        self.address_format
+    }
 
-        let data = resp.get("data")?.get("event")?.as_array()?;
+    pub fn get_network(&self) -> &Network {
+        &self.network
+    }
 
+    pub async fn parse_subscan_events(
+        &mut self,
+        event_indexes: Vec<String>,
+    ) -> Result<Vec<SubscanEvent>, SubscanError> {
+        let payload = json!({"event_index": event_indexes});
+        let resp = self
+            .post_subscan(
+                SubscanEndpoint::Events,
+                RequestPriority::Enrichment,
+                payload,
+            )
+            .await?;
+
+        let data = get_array(&resp, "data")?;
         let subscan_events = data
             .iter()
             .filter_map(|d| -> Option<_> {
                 let module_id = d.get("module_id")?.as_str()?.to_string();
+                let event_id = d
+                    .get("event_id")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string();
                 let event_index = d.get("event_index")?.as_str()?.to_string();
-                let params: Value = serde_json::from_str(d.get("params")?.as_str()?).ok()?;
-                let event_params = params
+                let event_params = d
+                    .get("params")?
                     .as_array()?
                     .iter()
-                    .filter_map(|p| {
-                        let type_name = p.get("type_name")?.as_str()?.to_string();
-                        let value = p.get("value")?.as_str()?.to_string();
-                        let name = p.get("name")?.as_str()?.to_string();
-
-                        Some(SubscanEventParam {
-                            type_name,
-                            value,
-                            name,
-                        })
-                    })
+                    .filter_map(parse_event_param)
                     .collect();
 
                 Some(SubscanEvent {
                     module_id,
+                    event_id,
                     event_index,
                     event_params,
                 })
             })
             .collect::<Vec<SubscanEvent>>();
-        Some(subscan_events)
+        Ok(subscan_events)
+    }
+
+    pub async fn parse_subscan_extrinsic_details(
+        &mut self,
+        extrinsic_index: String,
+    ) -> Result<Vec<SubscanEvent>, SubscanError> {
+        let payload = json!({
+            "extrinsic_index": extrinsic_index,
+            "only_extrinsic_event" : true
+        });
+        let resp = self
+            .post_subscan(
+                SubscanEndpoint::ExtrinsicDetail,
+                RequestPriority::Enrichment,
+                payload,
+            )
+            .await?;
+
+        let data = get_array(&resp, "data.event")?;
+
+        Ok(SubscanParser::parse_detail_events(data))
+    }
+
+    // none if subscan doesn't know the extrinsic
+    pub async fn parse_subscan_extrinsic(
+        &mut self,
+        extrinsic_index: &str,
+    ) -> Result<Option<SubscanExtrinsic>, SubscanError> {
+        let payload = json!({
+            "extrinsic_index": extrinsic_index,
+            "only_extrinsic_event" : true
+        });
+        let resp = self
+            .post_subscan(
+                SubscanEndpoint::ExtrinsicDetail,
+                RequestPriority::Enrichment,
+                payload,
+            )
+            .await?;
+
+        let Some(data) = resp.get("data").filter(|d| !d.is_null()) else {
+            return Ok(None);
+        };
+        let block_number = get_field(data, "block_num")?
+            .as_u64()
+            .ok_or_else(|| SubscanError::Deserialization("block_num is not a number".into()))?;
+        let events = data
+            .get("event")
+            .and_then(|e| e.as_array())
+            .map(|e| SubscanParser::parse_detail_events(e))
+            .unwrap_or_default();
+
+        Ok(Some(SubscanExtrinsic {
+            extrinsic_index: extrinsic_index.to_string(),
+            block_number,
+            is_success: !SubscanParser::is_failed(data),
+            events,
+        }))
     }
 
     pub async fn parse_subscan_operations(
@@ -202,125 +401,76 @@ impl SubscanParser {
         address: &str,
         module: Module,
         extrinsics_type: ExtrinsicsType,
+        page: u32,
         num_items: u32,
-    ) -> Option<Vec<SubscanOperation>> {
-        let mut resp;
+    ) -> Result<ParsedOperations, SubscanError> {
+        let payload = json!(
+            {"address": address, "row": num_items, "page": page, "module": module, "call": extrinsics_type.to_string(), "success": true}
+        );
+        let data = self.get_extrinsics(address, payload).await?;
 
-        loop {
-            let url = format!(
-                "https://{}.api.subscan.io/api/scan/extrinsics",
-                self.network
-            );
-
-            let subscan_api_key = SubscanParser::get_random_api_key();
-
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                "X-API-Key",
-                HeaderValue::from_str(&subscan_api_key).unwrap(),
-            );
-
-            let payload = json!(
-                {"address": address, "row": num_items, "page": 0, "module": module, "call": extrinsics_type.to_string(), "success": true}
-            );
-            resp = self
-                .http_client
-                .post_request::<Value, Value>(&url, headers, payload)
-                .await;
-
-            let code = resp.get("code")?.as_u64()?;
-            if code != 0 {
-                let message = resp.get("message")?.as_str()?;
-                error!(target: "subscan_parser", "Parse error[{code}]: {message}. Sleeping 1 seconds.");
-                sleep(Duration::from_millis(1_000)).await;
-                continue;
-            }
-
-            break;
-        }
-
-        let data = resp.get("data")?.get("extrinsics")?.as_array()?;
-        let subscan_operations = data
-            .iter()
-            .filter_map(|d| {
-                if !d.get("success")?.as_bool()? {
-                    return None;
-                };
-
-                let operation_timestamp =
-                    DateTime::from_millis(d.get("block_timestamp")?.as_i64()? * 1_000);
-                let from_wallet = d.get("account_id")?.as_str()?.to_string();
-                let block_number = d.get("block_num")?.as_u64()?;
-                let extrinsic_index = d.get("extrinsic_index")?.as_str()?.to_string();
-
-                let operation_type = match extrinsics_type {
-                    ExtrinsicsType::Bond | ExtrinsicsType::BondExtra | ExtrinsicsType::Rebond => {
-                        OperationType::Stake
-                    }
-                    ExtrinsicsType::Nominate => OperationType::ReStake,
-                    ExtrinsicsType::Unbond => OperationType::RequestUnstake,
-                    ExtrinsicsType::WithdrawUnbonded => OperationType::WithdrawUnstaked,
-                };
-
-                let to_wallet = if extrinsics_type == ExtrinsicsType::Nominate {
-                    let params: Value = serde_json::from_str(d.get("params")?.as_str()?).ok()?;
-
-                    let addr = params
-                        .as_array()?
-                        .first()?
-                        .get("value")?
-                        .as_array()?
-                        .first()?
-                        .get("Id")?
-                        .as_str()?;
-
-                    let addr = addr[2..].to_string();
-                    let decoded = hex::decode(addr).ok()?;
-                    let byte_arr: [u8; 32] = decoded.try_into().ok()?;
-                    AccountId32::from(byte_arr)
-                        .to_ss58check_with_version(Ss58AddressFormat::custom(42))
-                } else {
-                    EMPTY_ADDRESS.to_string()
-                };
-
-                let controller_wallet = if extrinsics_type == ExtrinsicsType::Bond {
-                    let params: Value = serde_json::from_str(d.get("params")?.as_str()?).ok()?;
-
-                    let addr = params
-                        .as_array()?
-                        .iter()
-                        .find(|p| p.get("name").unwrap().as_str().unwrap() == "controller")?
-                        .get("value")?
-                        .get("Id")?
-                        .as_str()?;
-
-                    let addr = addr[2..].to_string();
-                    let decoded = hex::decode(addr).ok()?;
-                    let byte_arr: [u8; 32] = decoded.try_into().ok()?;
-                    AccountId32::from(byte_arr)
-                        .to_ss58check_with_version(Ss58AddressFormat::custom(42))
-                } else {
-                    EMPTY_ADDRESS.to_string()
-                };
+        Ok(self.parse_extrinsics(&data, &extrinsics_type).await)
+    }
 
-                let subscan_operation = SubscanOperation {
-                    hash: String::new(),
-                    block_number,
-                    operation_timestamp,
-                    operation_quantity: 0.321,
-                    operation_usd: 0.123,
-                    operation_type,
-                    from_wallet,
-                    to_wallet,
-                    controller_wallet,
-                    extrinsic_index,
-                };
+    // every page up to the cutoff of the pagination, oldest operation first
+    pub async fn parse_subscan_operations_pages(
+        &mut self,
+        address: &str,
+        module: Module,
+        extrinsics_type: ExtrinsicsType,
+        pagination: &Pagination,
+    ) -> Result<ParsedOperations, SubscanError> {
+        let data = self
+            .get_extrinsics_pages(
+                address,
+                json!(module),
+                &extrinsics_type.to_string(),
+                pagination,
+            )
+            .await?;
+
+        Ok(self.parse_extrinsics(&data, &extrinsics_type).await)
+    }
 
-                Some(subscan_operation)
-            })
-            .rev()
-            .collect();
-        Some(subscan_operations)
+    // same walk as parse_subscan_operations_pages, one item per page with the newest operation
+    // first as subscan pages them, a page is only requested once the one before it is consumed,
+    // an error ends it
+    pub fn stream_operations(
+        &self,
+        address: &str,
+        module: Module,
+        extrinsics_type: ExtrinsicsType,
+        pagination: Pagination,
+    ) -> impl Stream<Item = Result<ParsedOperations, SubscanError>> {
+        let address = address.to_string();
+        stream::unfold(
+            (self.clone(), Some(0)),
+            move |(mut subscan_parser, page)| {
+                let address = address.clone();
+                let module = module.clone();
+                let extrinsics_type = extrinsics_type.clone();
+                async move {
+                    let page = page.filter(|p| *p < pagination.max_pages)?;
+                    let payload = json!(
+                        {"address": address, "row": pagination.page_size, "page": page, "module": module, "call": extrinsics_type.to_string(), "success": true}
+                    );
+                    let (parsed, next_page) =
+                        match subscan_parser.get_extrinsics(&address, payload).await {
+                            Ok(data) => {
+                                let (kept, has_next) = pagination.take_page(data);
+                                let mut parsed = subscan_parser
+                                    .parse_extrinsics(&kept, &extrinsics_type)
+                                    .await;
+                                parsed.operations.reverse();
+                                (Ok(parsed), has_next.then_some(page + 1))
+                            }
+                            Err(e) => (Err(e), None),
+                        };
+
+                    Some((parsed, (subscan_parser, next_page)))
+                }
+            },
+        )
     }
 
     pub async fn parse_subscan_batch_all(
@@ -328,189 +478,190 @@ impl SubscanParser {
         address: &str,
         page: u32,
         num_items: u32,
-    ) -> Option<Vec<SubscanOperation>> {
-        let mut resp;
+    ) -> Result<ParsedOperations, SubscanError> {
+        let payload = json!(
+            {"address": address, "row": num_items, "page": page, "module": "utility", "call": "batch_all", "success": true}
+        );
+        let data = self.get_extrinsics(address, payload).await?;
 
-        loop {
-            let url = format!(
-                "https://{}.api.subscan.io/api/scan/extrinsics",
-                self.network
-            );
+        Ok(self.parse_batch_all_extrinsics(&data).await)
+    }
 
-            let subscan_api_key = SubscanParser::get_random_api_key();
+    // every page up to the cutoff of the pagination, oldest operation first
+    pub async fn parse_subscan_batch_all_pages(
+        &mut self,
+        address: &str,
+        pagination: &Pagination,
+    ) -> Result<ParsedOperations, SubscanError> {
+        let data = self
+            .get_extrinsics_pages(address, json!("utility"), "batch_all", pagination)
+            .await?;
 
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                "X-API-Key",
-                HeaderValue::from_str(&subscan_api_key).unwrap(),
-            );
+        Ok(self.parse_batch_all_extrinsics(&data).await)
+    }
+
+    // successful extrinsics of the call on the whole network, a single row is requested
+    pub async fn get_extrinsics_count(
+        &mut self,
+        module: Value,
+        call: &str,
+    ) -> Result<u64, SubscanError> {
+        let payload = json!(
+            {"row": 1, "page": 0, "module": module, "call": call, "success": true}
+        );
+        let resp = self
+            .post_subscan(
+                SubscanEndpoint::Extrinsics,
+                RequestPriority::Enrichment,
+                payload,
+            )
+            .await?;
+
+        get_field(&resp, "data.count")?
+            .as_u64()
+            .ok_or_else(|| SubscanError::Deserialization("count is not a number".to_string()))
+    }
+
+    async fn get_extrinsics(
+        &mut self,
+        address: &str,
+        payload: Value,
+    ) -> Result<Vec<Value>, SubscanError> {
+        let resp = self
+            .post_subscan(
+                SubscanEndpoint::Extrinsics,
+                SubscanParser::get_priority(address),
+                payload,
+            )
+            .await?;
+
+        Ok(get_array(&resp, "data.extrinsics")?.clone())
+    }
 
+    // newest first as subscan returns them, an error on any page fails the whole walk
+    async fn get_extrinsics_pages(
+        &mut self,
+        address: &str,
+        module: Value,
+        call: &str,
+        pagination: &Pagination,
+    ) -> Result<Vec<Value>, SubscanError> {
+        let mut extrinsics = Vec::new();
+        for page in 0..pagination.max_pages {
             let payload = json!(
-                {"address": address, "row": num_items, "page": page, "module": "utility", "call": "batch_all", "success": true}
+                {"address": address, "row": pagination.page_size, "page": page, "module": module, "call": call, "success": true}
             );
-            resp = self
-                .http_client
-                .post_request::<Value, Value>(&url, headers, payload)
-                .await;
-
-            let code = resp.get("code")?.as_u64()?;
-            if code != 0 {
-                let message = resp.get("message")?.as_str()?;
-                error!(target: "subscan_parser", "Parse error[{code}]: {message}. Sleeping 1 seconds.");
-                sleep(Duration::from_millis(1_000)).await;
-                continue;
-            }
+            let data = self.get_extrinsics(address, payload).await?;
 
-            break;
+            let (mut kept, has_next) = pagination.take_page(data);
+            extrinsics.append(&mut kept);
+            if !has_next {
+                break;
+            }
         }
 
-        let data = resp.get("data")?.get("extrinsics")?.as_array()?;
-        let subscan_operations = data
+        Ok(extrinsics)
+    }
+
+    async fn parse_extrinsics(
+        &mut self,
+        data: &[Value],
+        extrinsics_type: &ExtrinsicsType,
+    ) -> ParsedOperations {
+        let data = self.fill_missing_signers(data).await;
+        let mut quarantined = Vec::new();
+        let operations = data
             .iter()
+            .filter(|d| !SubscanParser::is_failed(d))
             .filter_map(|d| {
-                if !d.get("success")?.as_bool()? {
-                    return None;
-                };
-
-                let operation_timestamp =
-                    DateTime::from_millis(d.get("block_timestamp")?.as_i64()? * 1_000);
-                let from_wallet = d.get("account_id")?.as_str()?.to_string();
-                let block_number = d.get("block_num")?.as_u64()?;
-                let extrinsic_index = d.get("extrinsic_index")?.as_str()?.to_string();
-
-                let params: Value = serde_json::from_str(d.get("params")?.as_str()?).ok()?;
-                let value = params.as_array()?.first()?.get("value")?.as_array()?;
-                let bond_extra = value
-                    .iter()
-                    .find(|p| p.get("call_name").unwrap() == "bond_extra");
-                let bond = value.iter().find(|p| p.get("call_name").unwrap() == "bond");
-                let unbond = value
-                    .iter()
-                    .find(|p| p.get("call_name").unwrap() == "unbond");
-                let nominate = value
-                    .iter()
-                    .find(|p| p.get("call_name").unwrap() == "nominate");
-
-                let bond_amount = if bond.is_some() {
-                    str::parse::<f64>(
-                        bond.unwrap()
-                            .get("params")?
-                            .as_array()?
-                            .iter()
-                            .find(|p| p.get("name").unwrap() == "value")?
-                            .get("value")?
-                            .as_str()?,
-                    )
-                    .ok()?
-                        / AZERO_DENOMINATOR
-                } else {
-                    0.0
-                };
-
-                let bond_extra_amount = if bond_extra.is_some() {
-                    str::parse::<f64>(
-                        bond_extra
-                            .unwrap()
-                            .get("params")?
-                            .as_array()?
-                            .iter()
-                            .find(|p| p.get("name").unwrap() == "max_additional")?
-                            .get("value")?
-                            .as_str()?,
-                    )
-                    .ok()?
-                        / AZERO_DENOMINATOR
-                } else {
-                    0.0
-                };
+                let operation = SubscanParser::parse_extrinsic(d, extrinsics_type, &self.network);
+                if operation.is_none() {
+                    quarantined.push(QuarantinedRecord::malformed(
+                        QuarantineSource::Extrinsics,
+                        d,
+                    ));
+                }
+                operation
+            })
+            .rev()
+            .collect::<Vec<_>>();
 
-                let unbond_amount = if unbond.is_some() {
-                    str::parse::<f64>(
-                        unbond
-                            .unwrap()
-                            .get("params")?
-                            .as_array()?
-                            .iter()
-                            .find(|p| p.get("name").unwrap() == "value")?
-                            .get("value")?
-                            .as_str()?,
-                    )
-                    .ok()?
-                        / AZERO_DENOMINATOR
-                } else {
-                    0.0
-                };
+        ParsedOperations {
+            operations,
+            quarantined,
+        }
+    }
 
-                let operation_quantity = bond_amount + bond_extra_amount + unbond_amount;
-
-                let to_wallet = if nominate.is_some() {
-                    let addr = nominate
-                        .unwrap()
-                        .get("params")?
-                        .as_array()?
-                        .first()?
-                        .get("value")?
-                        .as_array()?
-                        .first()?
-                        .get("Id")?
-                        .as_str()?;
-
-                    let addr = addr[2..].to_string();
-                    let decoded = hex::decode(addr).ok()?;
-                    let byte_arr: [u8; 32] = decoded.try_into().ok()?;
-                    AccountId32::from(byte_arr)
-                        .to_ss58check_with_version(Ss58AddressFormat::custom(42))
-                } else {
-                    EMPTY_ADDRESS.to_string()
-                };
+    async fn parse_batch_all_extrinsics(&mut self, data: &[Value]) -> ParsedOperations {
+        let data = self.fill_missing_signers(data).await;
+        let mut quarantined = Vec::new();
+        let operations = data
+            .iter()
+            .filter(|d| !SubscanParser::is_failed(d))
+            .filter_map(|d| {
+                let operation = SubscanParser::parse_batch_all(d, &self.network);
+                if operation.is_none() {
+                    quarantined.push(QuarantinedRecord::malformed(QuarantineSource::BatchAll, d));
+                }
+                operation
+            })
+            .rev()
+            .collect::<Vec<_>>();
 
-                let controller_wallet = if bond.is_some() {
-                    let params = bond.unwrap().get("params")?;
-
-                    let addr = params
-                        .as_array()?
-                        .iter()
-                        .find(|p| p.get("name").unwrap().as_str().unwrap() == "controller")?
-                        .get("value")?
-                        .get("Id")?
-                        .as_str()?;
-
-                    let addr = addr[2..].to_string();
-                    let decoded = hex::decode(addr).ok()?;
-                    let byte_arr: [u8; 32] = decoded.try_into().ok()?;
-                    AccountId32::from(byte_arr)
-                        .to_ss58check_with_version(Ss58AddressFormat::custom(42))
-                } else {
-                    EMPTY_ADDRESS.to_string()
-                };
+        ParsedOperations {
+            operations,
+            quarantined,
+        }
+    }
 
-                let operation_type = if unbond_amount > 1e-12 {
-                    OperationType::RequestUnstake
-                } else if to_wallet != EMPTY_ADDRESS {
-                    OperationType::ReStake
-                } else {
-                    OperationType::Stake
-                };
+    // subscan leaves account_id out of some rows, e.g. of unsigned or batched calls, the
+    // signer is taken from the extrinsic detail then so the operation is kept
+    async fn fill_missing_signers(&mut self, data: &[Value]) -> Vec<Value> {
+        let mut data = data.to_vec();
+        for d in data.iter_mut() {
+            if SubscanParser::is_failed(d) || SubscanParser::get_account_id(d).is_some() {
+                continue;
+            }
+            let Some(extrinsic_index) = d.get("extrinsic_index").and_then(|e| e.as_str()) else {
+                continue;
+            };
 
-                let subscan_operation = SubscanOperation {
-                    hash: String::new(),
-                    block_number,
-                    operation_timestamp,
-                    operation_quantity,
-                    operation_usd: 0.123,
-                    operation_type,
-                    from_wallet,
-                    to_wallet,
-                    controller_wallet,
-                    extrinsic_index,
-                };
+            let extrinsic_index = extrinsic_index.to_string();
+            match self.get_extrinsic_signer(&extrinsic_index).await {
+                Ok(Some(signer)) => {
+                    info!(target: "subscan_parser", "Took the signer of {extrinsic_index} from its detail, the row had no account_id.");
+                    d["account_id"] = json!(signer);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    error!(target: "subscan_parser", "Extrinsic signer error of {extrinsic_index}: {e}.")
+                }
+            }
+        }
 
-                Some(subscan_operation)
-            })
-            .rev()
-            .collect();
+        data
+    }
 
-        Some(subscan_operations)
+    async fn get_extrinsic_signer(
+        &mut self,
+        extrinsic_index: &str,
+    ) -> Result<Option<String>, SubscanError> {
+        let payload = json!({
+            "extrinsic_index": extrinsic_index,
+            "only_extrinsic_event" : true
+        });
+        let resp = self
+            .post_subscan(
+                SubscanEndpoint::ExtrinsicDetail,
+                RequestPriority::Enrichment,
+                payload,
+            )
+            .await?;
+
+        Ok(SubscanParser::get_signer(
+            get_field(&resp, "data")?,
+            &self.network,
+        ))
     }
 
     pub async fn parse_subscan_identity(
@@ -523,43 +674,20 @@ impl SubscanParser {
             return None;
         }
 
-        let mut resp;
-
-        loop {
-            let url = format!(
-                "https://{}.api.subscan.io/api/scan/extrinsics",
-                self.network
-            );
-
-            let subscan_api_key = SubscanParser::get_random_api_key();
-
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                "X-API-Key",
-                HeaderValue::from_str(&subscan_api_key).unwrap(),
-            );
-
-            let payload = json!(
-                {"address": address, "row": num_items, "page": page, "module": "identity", "call": "set_identity", "success": true}
-            );
-            resp = self
-                .http_client
-                .post_request::<Value, Value>(&url, headers, payload)
-                .await;
-
-            let code = resp.get("code")?.as_u64()?;
-            if code != 0 {
-                let message = resp.get("message")?.as_str()?;
-                error!(target: "subscan_parser", "Parse error[{code}]: {message}. Sleeping 1 seconds.");
-                sleep(Duration::from_millis(1_000)).await;
-                continue;
-            }
-
-            break;
-        }
+        let payload = json!(
+            {"address": address, "row": num_items, "page": page, "module": "identity", "call": "set_identity", "success": true}
+        );
+        let resp = self
+            .post_subscan(
+                SubscanEndpoint::Extrinsics,
+                RequestPriority::Enrichment,
+                payload,
+            )
+            .await
+            .ok()?;
 
         let data = resp.get("data")?.get("extrinsics")?.as_array()?;
-        let identities = data
+        let mut identities = data
             .iter()
             .filter_map(|d| {
                 if !d.get("success")?.as_bool()? {
@@ -585,91 +713,58 @@ impl SubscanParser {
             })
             .rev()
             .collect::<Vec<_>>();
+        normalize_identities(&mut identities, &self.network, self.address_format).await;
 
         Some(identities)
     }
 
+    // transfers of the address, of the whole network if it is empty
     pub async fn parse_subscan_transfers(
         &mut self,
+        address: &str,
         page: u32,
         num_items: u32,
-    ) -> Option<(Vec<SubscanOperation>, Vec<Identity>)> {
-        let mut resp;
-
-        loop {
-            let url = format!("https://{}.api.subscan.io/api/scan/transfers", self.network);
-
-            let subscan_api_key = SubscanParser::get_random_api_key();
-
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                "X-API-Key",
-                HeaderValue::from_str(&subscan_api_key).unwrap(),
-            );
-
-            let payload = json!(
-                {
-                    "row": num_items,
-                    "page": page,
-                    "success": true,
-                    "asset_symbol": "AZERO",
-                }
-            );
-            resp = self
-                .http_client
-                .post_request::<Value, Value>(&url, headers, payload)
-                .await;
-
-            let code = resp.get("code")?.as_u64()?;
-            if code != 0 {
-                let message = resp.get("message")?.as_str()?;
-                error!(target: "subscan_parser", "Parse error[{code}]: {message}. Sleeping 1 seconds.");
-                sleep(Duration::from_millis(1_000)).await;
-                continue;
+    ) -> Option<(ParsedOperations, Vec<Identity>)> {
+        let mut payload = json!(
+            {
+                "row": num_items,
+                "page": page,
+                "success": true,
+                "asset_symbol": self.network.get_token_symbol(),
             }
-
-            break;
+        );
+        if !address.is_empty() {
+            payload["address"] = json!(address);
         }
+        let resp = self
+            .post_subscan(
+                SubscanEndpoint::Transfers,
+                SubscanParser::get_priority(address),
+                payload,
+            )
+            .await
+            .ok()?;
 
         let data = resp.get("data")?.get("transfers")?.as_array()?;
-        let subscan_operations = data
+        let mut quarantined = Vec::new();
+        let operations = data
             .iter()
+            .filter(|d| !SubscanParser::is_failed(d))
             .filter_map(|d| {
-                if !d.get("success")?.as_bool()? {
-                    return None;
-                };
-
-                let operation_timestamp =
-                    DateTime::from_millis(d.get("block_timestamp")?.as_i64()? * 1_000);
-                let from_wallet = d.get("from")?.as_str()?.to_string();
-                let to_wallet = d.get("to")?.as_str()?.to_string();
-                let block_number = d.get("block_num")?.as_u64()?;
-                let extrinsic_index = d.get("extrinsic_index")?.as_str()?.to_string();
-                let operation_quantity = str::parse::<f64>(d.get("amount")?.as_str()?).ok()?;
-
-                let operation_type = OperationType::Transfer;
-
-                let controller_wallet = EMPTY_ADDRESS.to_string();
-
-                let subscan_operation = SubscanOperation {
-                    hash: String::new(),
-                    block_number,
-                    operation_timestamp,
-                    operation_quantity,
-                    operation_usd: 0.123,
-                    operation_type,
-                    from_wallet,
-                    to_wallet,
-                    controller_wallet,
-                    extrinsic_index,
-                };
-
-                Some(subscan_operation)
+                let operation = SubscanParser::parse_transfer(d, &self.network);
+                if operation.is_none() {
+                    quarantined.push(QuarantinedRecord::malformed(QuarantineSource::Transfers, d));
+                }
+                operation
             })
             .rev()
-            .collect();
+            .collect::<Vec<_>>();
+        let parsed = ParsedOperations {
+            operations,
+            quarantined,
+        };
 
-        let identities = data
+        let mut identities = data
             .iter()
             .filter_map(|d| {
                 if !d.get("success")?.as_bool()? {
@@ -709,20 +804,1104 @@ impl SubscanParser {
             .rev()
             .flatten()
             .collect::<Vec<_>>();
+        normalize_identities(&mut identities, &self.network, self.address_format).await;
 
-        Some((subscan_operations, identities))
+        Some((parsed, identities))
     }
 
-    fn get_random_api_key() -> String {
-        env::var("SUBSCAN_API_KEY")
-            .unwrap()
+    // staking rewards paid out to the address and slashes it took
+    pub async fn parse_subscan_reward_slash(
+        &mut self,
+        address: &str,
+        page: u32,
+        num_items: u32,
+    ) -> Result<ParsedOperations, SubscanError> {
+        let payload = json!({"address": address, "row": num_items, "page": page});
+        let resp = self
+            .post_subscan(
+                SubscanEndpoint::RewardSlash,
+                SubscanParser::get_priority(address),
+                payload,
+            )
+            .await?;
+
+        // accounts without rewards have a null list
+        let data = match get_field(&resp, "data.list")? {
+            Value::Null => Vec::new(),
+            _ => get_array(&resp, "data.list")?.clone(),
+        };
+
+        let mut quarantined = Vec::new();
+        let operations = data
+            .iter()
+            .filter_map(|d| {
+                let operation = SubscanParser::parse_reward_slash(d, &self.network);
+                if operation.is_none() {
+                    quarantined.push(QuarantinedRecord::malformed(
+                        QuarantineSource::RewardSlash,
+                        d,
+                    ));
+                }
+                operation
+            })
+            .rev()
+            .collect::<Vec<_>>();
+
+        Ok(ParsedOperations {
+            operations,
+            quarantined,
+        })
+    }
+
+    // current balance of the address, subscan writes the amounts in tokens as strings
+    pub async fn parse_subscan_balance(
+        &mut self,
+        address: &str,
+    ) -> Result<BalanceSnapshot, SubscanError> {
+        let payload = json!({"key": address});
+        let resp = self
+            .post_subscan(
+                SubscanEndpoint::Account,
+                RequestPriority::Enrichment,
+                payload,
+            )
+            .await?;
+
+        let get_amount = |path: &str| {
+            let amount = get_field(&resp, path)?;
+            amount
+                .as_str()
+                .and_then(|a| a.parse::<f64>().ok())
+                .or_else(|| amount.as_f64())
+                .ok_or_else(|| SubscanError::Deserialization(format!("{path} is not an amount")))
+        };
+
+        Ok(BalanceSnapshot {
+            wallet: address.to_string(),
+            timestamp: DateTime::now(),
+            total: get_amount("data.account.balance")?,
+            reserved: get_amount("data.account.reserved")?,
+            bonded: get_amount("data.account.bonded")?,
+        })
+    }
+
+    // latest block indexed by subscan, subscan writes it as a string
+    pub async fn get_head_block_number(&mut self) -> Result<u64, SubscanError> {
+        let resp = self
+            .post_subscan(SubscanEndpoint::Metadata, RequestPriority::Head, json!({}))
+            .await?;
+
+        SubscanParser::get_block_number_field(&resp, "blockNum")
+    }
+
+    // latest block which can't be reverted anymore
+    pub async fn get_finalized_block_number(&mut self) -> Result<u64, SubscanError> {
+        let resp = self
+            .post_subscan(SubscanEndpoint::Metadata, RequestPriority::Head, json!({}))
+            .await?;
+
+        SubscanParser::get_block_number_field(&resp, "finalized_blockNum")
+    }
+
+    // every attempt logs within the span of the request
+    async fn post_subscan(
+        &mut self,
+        endpoint: SubscanEndpoint,
+        priority: RequestPriority,
+        payload: Value,
+    ) -> Result<Value, SubscanError> {
+        if self.network == Network::Mock {
+            return Ok(mock_network::respond(endpoint, &payload));
+        }
+
+        let span = info_span!(
+            "subscan_request",
+            network = %self.network,
+            endpoint = endpoint.path(),
+            extrinsic_index = payload.get("extrinsic_index").and_then(|e| e.as_str()),
+            attempt = field::Empty,
+        );
+        self.send_subscan_request(endpoint, priority, payload)
+            .instrument(span)
+            .await
+    }
+
+    async fn send_subscan_request(
+        &mut self,
+        endpoint: SubscanEndpoint,
+        priority: RequestPriority,
+        payload: Value,
+    ) -> Result<Value, SubscanError> {
+        let url = format!("{}/{}", self.network.get_base_url(), endpoint.path());
+
+        let max_attempts = self.retry_policy.max_attempts;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            Span::current().record("attempt", attempt);
+
+            // while subscan is down the request fails fast instead of being retried
+            if let Err(retry_in) = CircuitBreaker::global().try_acquire() {
+                return Err(SubscanError::CircuitOpen {
+                    retry_in_ms: retry_in.as_millis() as u64,
+                });
+            }
+            SubscanScheduler::global().acquire(endpoint, priority).await;
+
+            let subscan_api_key = self.get_random_api_key();
+
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                "X-API-Key",
+                HeaderValue::from_str(&subscan_api_key).unwrap(),
+            );
+
+            let endpoint_label = endpoint.to_string();
+            let labels = [("endpoint", endpoint_label.as_str())];
+            Metrics::global().increment(Metric::SubscanRequestsTotal, &labels);
+            let started_at = Instant::now();
+            let resp = self.api.post_json(&url, headers, &payload).await;
+            Metrics::global().observe(
+                Metric::SubscanRequestDurationSeconds,
+                &labels,
+                started_at.elapsed(),
+            );
+            // request and api errors are retried, a malformed response is not
+            let e = match resp {
+                Ok(resp) => {
+                    let code = get_field(&resp, "code").and_then(|c| {
+                        c.as_u64().ok_or_else(|| {
+                            SubscanError::Deserialization("code is not a number".to_string())
+                        })
+                    });
+                    match code {
+                        Ok(0) => {
+                            CircuitBreaker::global().record_success();
+                            return Ok(resp);
+                        }
+                        Ok(code) => {
+                            let message = resp
+                                .get("message")
+                                .and_then(|m| m.as_str())
+                                .unwrap_or_default();
+                            SubscanError::from_code(code, message)
+                        }
+                        Err(e) => {
+                            CircuitBreaker::global().record(e.get_request_outcome());
+                            let pipeline_error = self.get_pipeline_error(&e, endpoint, &payload);
+                            error!(target: "subscan_parser", "Parse error: {}.", pipeline_error.to_json());
+                            return Err(e);
+                        }
+                    }
+                }
+                Err(e) => e,
+            };
+            CircuitBreaker::global().record(e.get_request_outcome());
+
+            let code = e.get_code_label();
+            Metrics::global().increment(
+                Metric::SubscanErrorsTotal,
+                &[("endpoint", &endpoint_label), ("code", &code)],
+            );
+
+            let pipeline_error = self.get_pipeline_error(&e, endpoint, &payload);
+            if attempt >= max_attempts {
+                error!(target: "subscan_parser", "Request error ({attempt}/{max_attempts}): {}. Giving up.", pipeline_error.to_json());
+                return Err(SubscanError::AttemptsExhausted {
+                    attempts: attempt,
+                    last_error: Box::new(e),
+                });
+            }
+
+            let delay = self
+                .retry_policy
+                .get_delay(attempt, &mut rand::thread_rng());
+            error!(target: "subscan_parser", "Request error ({attempt}/{max_attempts}): {}. Sleeping {} ms.", pipeline_error.to_json(), delay.as_millis());
+            Metrics::global().increment(Metric::SubscanRetriesTotal, &labels);
+            sleep(delay).await;
+        }
+    }
+
+    fn get_pipeline_error(
+        &self,
+        e: &SubscanError,
+        endpoint: SubscanEndpoint,
+        payload: &Value,
+    ) -> PipelineError {
+        let pipeline_error = PipelineError::new(e.get_error_code(), &e.to_string(), &self.network)
+            .with_endpoint(endpoint.path());
+        match payload.get("extrinsic_index").and_then(|e| e.as_str()) {
+            Some(extrinsic_index) => pipeline_error.with_extrinsic_index(extrinsic_index),
+            None => pipeline_error,
+        }
+    }
+
+    fn get_random_api_key(&self) -> String {
+        self.api_keys
+            .clone()
+            .unwrap_or_else(|| env::var("SUBSCAN_API_KEY").unwrap())
             .split(',')
             .choose(&mut rand::thread_rng())
             .unwrap()
             .to_string()
     }
+}
+
+impl SubscanParser {
+    // none if a field is missing or malformed, the record is quarantined then
+    fn parse_extrinsic(
+        d: &Value,
+        extrinsics_type: &ExtrinsicsType,
+        network: &Network,
+    ) -> Option<SubscanOperation> {
+        d.get("success")?.as_bool().filter(|s| *s)?;
+
+        let operation_timestamp = get_block_timestamp(d)?;
+        let from_wallet = d.get("account_id")?.as_str()?.to_string();
+        let block_number = d.get("block_num")?.as_u64()?;
+        let extrinsic_index = d.get("extrinsic_index")?.as_str()?.to_string();
+
+        let operation_type = match extrinsics_type {
+            ExtrinsicsType::Bond
+            | ExtrinsicsType::BondExtra
+            | ExtrinsicsType::Rebond
+            | ExtrinsicsType::Join => OperationType::Stake,
+            ExtrinsicsType::Nominate => OperationType::ReStake,
+            ExtrinsicsType::Unbond => OperationType::RequestUnstake,
+            ExtrinsicsType::WithdrawUnbonded => OperationType::WithdrawUnstaked,
+            ExtrinsicsType::ClaimPayout => OperationType::Reward,
+        };
+
+        let nomination_targets = if *extrinsics_type == ExtrinsicsType::Nominate {
+            let params: Value = serde_json::from_str(d.get("params")?.as_str()?).ok()?;
+
+            SubscanParser::get_nomination_targets(&params, network)?
+        } else {
+            Vec::new()
+        };
+        let to_wallet = nomination_targets
+            .first()
+            .cloned()
+            .unwrap_or(EMPTY_ADDRESS.to_string());
+
+        let controller_wallet = if *extrinsics_type == ExtrinsicsType::Bond {
+            let params: Value = serde_json::from_str(d.get("params")?.as_str()?).ok()?;
+
+            let addr = params
+                .as_array()?
+                .iter()
+                .find(|p| p.get("name").unwrap().as_str().unwrap() == "controller")?
+                .get("value")?
+                .get("Id")?
+                .as_str()?;
+
+            address::to_ss58(network, addr)?
+        } else {
+            EMPTY_ADDRESS.to_string()
+        };
+
+        let subscan_operation = SubscanOperation {
+            hash: String::new(),
+            hash_version: 0,
+            block_number,
+            operation_timestamp,
+            operation_quantity: 0.321,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 0.123,
+            operation_type,
+            from_wallet,
+            to_wallet,
+            controller_wallet,
+            extrinsic_index,
+            call_index: 0,
+            status: OperationStatus::Pending,
+            operation_id: None,
+            nomination_targets,
+        };
+
+        Some(subscan_operation)
+    }
+
+    // none if a field is missing or malformed, the record is quarantined then
+    fn parse_batch_all(d: &Value, network: &Network) -> Option<SubscanOperation> {
+        d.get("success")?.as_bool().filter(|s| *s)?;
+
+        let operation_timestamp = get_block_timestamp(d)?;
+        let from_wallet = d.get("account_id")?.as_str()?.to_string();
+        let block_number = d.get("block_num")?.as_u64()?;
+        let extrinsic_index = d.get("extrinsic_index")?.as_str()?.to_string();
+
+        let params: Value = serde_json::from_str(d.get("params")?.as_str()?).ok()?;
+        let value = params.as_array()?.first()?.get("value")?.as_array()?;
+        let bond_extra = value
+            .iter()
+            .find(|p| p.get("call_name").unwrap() == "bond_extra");
+        let bond = value.iter().find(|p| p.get("call_name").unwrap() == "bond");
+        let unbond = value
+            .iter()
+            .find(|p| p.get("call_name").unwrap() == "unbond");
+        let nominate = value
+            .iter()
+            .find(|p| p.get("call_name").unwrap() == "nominate");
+
+        // amounts are in planck, a call without its amount param quarantines the extrinsic
+        let mut amounts = Vec::new();
+        for (call, name) in [
+            (bond, "value"),
+            (bond_extra, "max_additional"),
+            (unbond, "value"),
+        ] {
+            if let Some(call) = call {
+                let amount = SubscanParser::get_call_param(call, name)?;
+                amounts.push(Balance::from_planck_str(amount).ok()?);
+            }
+        }
+        let decimals = network.get_decimals();
+        let planck = amounts
+            .iter()
+            .try_fold(Balance::default(), |sum, a| sum.checked_add(*a))?;
+        let operation_quantity = planck.to_f64(decimals)?;
+        let operation_planck = Some(planck);
+        let unbond_amount = match unbond {
+            Some(_) => amounts.last()?.to_decimal(decimals).ok()?,
+            None => Decimal::ZERO,
+        };
+
+        let nomination_targets = if let Some(nominate) = nominate {
+            SubscanParser::get_nomination_targets(nominate.get("params")?, network)?
+        } else {
+            Vec::new()
+        };
+        let to_wallet = nomination_targets
+            .first()
+            .cloned()
+            .unwrap_or(EMPTY_ADDRESS.to_string());
+
+        let controller_wallet = if let Some(bond) = bond {
+            let params = bond.get("params")?;
+
+            let addr = params
+                .as_array()?
+                .iter()
+                .find(|p| p.get("name").unwrap().as_str().unwrap() == "controller")?
+                .get("value")?
+                .get("Id")?
+                .as_str()?;
+
+            address::to_ss58(network, addr)?
+        } else {
+            EMPTY_ADDRESS.to_string()
+        };
+
+        let operation_type = if unbond_amount > Decimal::ZERO {
+            OperationType::RequestUnstake
+        } else if to_wallet != EMPTY_ADDRESS {
+            OperationType::ReStake
+        } else {
+            OperationType::Stake
+        };
+
+        let subscan_operation = SubscanOperation {
+            hash: String::new(),
+            hash_version: 0,
+            block_number,
+            operation_timestamp,
+            operation_quantity,
+            operation_planck,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 0.123,
+            operation_type,
+            from_wallet,
+            to_wallet,
+            controller_wallet,
+            extrinsic_index,
+            call_index: 0,
+            status: OperationStatus::Pending,
+            operation_id: None,
+            nomination_targets,
+        };
+
+        Some(subscan_operation)
+    }
+
+    // none if a field is missing or malformed, the record is quarantined then
+    fn parse_transfer(d: &Value, network: &Network) -> Option<SubscanOperation> {
+        d.get("success")?.as_bool().filter(|s| *s)?;
+
+        let operation_timestamp = get_block_timestamp(d)?;
+        let from_wallet = d.get("from")?.as_str()?.to_string();
+        let to_wallet = d.get("to")?.as_str()?.to_string();
+        let block_number = d.get("block_num")?.as_u64()?;
+        let extrinsic_index = d.get("extrinsic_index")?.as_str()?.to_string();
+        let amount = d.get("amount")?.as_str()?;
+        let operation_quantity = str::parse::<f64>(amount).ok()?;
+        let operation_planck = parse_decimal_planck(amount, network.get_decimals()).map(Balance);
+
+        // fee is in planck, unlike the amount
+        let operation_fee = d
+            .get("fee")
+            .and_then(|f| f.as_str())
+            .and_then(|f| from_planck_str(f, network.get_decimals()).ok())
+            .and_then(|f| f.to_f64());
+
+        // tells apart transfers of the same batch
+        let call_index = d
+            .get("event_idx")
+            .and_then(|e| e.as_u64())
+            .and_then(|e| u32::try_from(e).ok())
+            .unwrap_or(0);
+
+        let operation_type = OperationType::Transfer;
+
+        let controller_wallet = EMPTY_ADDRESS.to_string();
+
+        let subscan_operation = SubscanOperation {
+            hash: String::new(),
+            hash_version: 0,
+            block_number,
+            operation_timestamp,
+            operation_quantity,
+            operation_planck,
+            operation_fee,
+            operation_era: None,
+            operation_usd: 0.123,
+            operation_type,
+            from_wallet,
+            to_wallet,
+            controller_wallet,
+            extrinsic_index,
+            call_index,
+            status: OperationStatus::Pending,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        };
+
+        Some(subscan_operation)
+    }
+
+    // rewards go from the validator to the account, slashed amounts are burned
+    fn parse_reward_slash(d: &Value, network: &Network) -> Option<SubscanOperation> {
+        let operation_type = match d.get("event_id")?.as_str()? {
+            "Reward" | "Rewarded" => OperationType::Reward,
+            "Slash" | "Slashed" => OperationType::Slash,
+            _ => return None,
+        };
+
+        let operation_timestamp = get_block_timestamp(d)?;
+        let block_number = d.get("block_num")?.as_u64()?;
+        let account = d.get("account")?.as_str()?.to_string();
+        let operation_planck = Balance::from_planck_str(d.get("amount")?.as_str()?).ok()?;
+        let operation_quantity = operation_planck.to_f64(network.get_decimals())?;
+        let operation_era = u32::try_from(d.get("era")?.as_u64()?).ok()?;
+
+        // payouts made by another account have no extrinsic of their own on the account
+        let extrinsic_index = d
+            .get("extrinsic_index")
+            .and_then(|e| e.as_str())
+            .filter(|e| !e.is_empty())
+            .or_else(|| d.get("event_index")?.as_str())?
+            .to_string();
+
+        // one payout extrinsic rewards every nominator of the validator, e.g. "42-7" is event 7
+        let call_index = d
+            .get("event_index")
+            .and_then(|e| e.as_str())
+            .and_then(|e| e.rsplit_once('-'))
+            .and_then(|(_, i)| i.parse::<u32>().ok())
+            .unwrap_or(0);
+
+        let validator = d
+            .get("validator_stash")
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.is_empty())
+            .unwrap_or(EMPTY_ADDRESS)
+            .to_string();
+        let (from_wallet, to_wallet) = match operation_type {
+            OperationType::Reward => (validator, account),
+            _ => (account, EMPTY_ADDRESS.to_string()),
+        };
+
+        let subscan_operation = SubscanOperation {
+            hash: String::new(),
+            hash_version: 0,
+            block_number,
+            operation_timestamp,
+            operation_quantity,
+            operation_planck: Some(operation_planck),
+            operation_fee: None,
+            operation_era: Some(operation_era),
+            operation_usd: 0.123,
+            operation_type,
+            from_wallet,
+            to_wallet,
+            controller_wallet: EMPTY_ADDRESS.to_string(),
+            extrinsic_index,
+            call_index,
+            status: OperationStatus::Pending,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        };
+
+        Some(subscan_operation)
+    }
+
+    fn get_account_id(d: &Value) -> Option<&str> {
+        d.get("account_id")
+            .and_then(|a| a.as_str())
+            .filter(|a| !a.is_empty())
+    }
+
+    // signer of an extrinsic detail, else the first account one of its events was emitted for
+    fn get_signer(detail: &Value, network: &Network) -> Option<String> {
+        let signer = SubscanParser::get_account_id(detail).or_else(|| {
+            detail
+                .pointer("/account_display/address")
+                .and_then(|a| a.as_str())
+                .filter(|a| !a.is_empty())
+        });
+        if let Some(signer) = signer {
+            return Some(signer.to_string());
+        }
+
+        detail
+            .get("event")?
+            .as_array()?
+            .iter()
+            .filter_map(|e| serde_json::from_str::<Value>(e.get("params")?.as_str()?).ok())
+            .flat_map(|params| params.as_array().cloned().unwrap_or_default())
+            .filter(|p| {
+                p.get("name")
+                    .and_then(|n| n.as_str())
+                    .is_some_and(|n| SIGNER_EVENT_PARAMS.contains(&n))
+            })
+            .find_map(|p| address::to_ss58(network, p.get("value")?.as_str()?))
+    }
+
+    fn parse_detail_events(data: &[Value]) -> Vec<SubscanEvent> {
+        data.iter()
+            .filter_map(|d| -> Option<_> {
+                let module_id = d.get("module_id")?.as_str()?.to_string();
+                let event_id = d
+                    .get("event_id")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+                let event_index = d.get("event_index")?.as_str()?.to_string();
+                let params: Value = serde_json::from_str(d.get("params")?.as_str()?).ok()?;
+                let event_params = params
+                    .as_array()?
+                    .iter()
+                    .filter_map(parse_event_param)
+                    .collect();
+
+                Some(SubscanEvent {
+                    module_id,
+                    event_id,
+                    event_index,
+                    event_params,
+                })
+            })
+            .collect()
+    }
+
+    // failed extrinsics are skipped, records without a success flag are malformed
+    fn is_failed(d: &Value) -> bool {
+        d.get("success").and_then(|s| s.as_bool()) == Some(false)
+    }
+
+    // metadata fields are numbers or numeric strings
+    fn get_block_number_field(resp: &Value, field: &str) -> Result<u64, SubscanError> {
+        let block_num = get_field(resp, &format!("data.{field}"))?;
+        block_num
+            .as_u64()
+            .or_else(|| block_num.as_str().and_then(|b| b.parse::<u64>().ok()))
+            .ok_or_else(|| SubscanError::Deserialization(format!("{field} is not a number")))
+    }
+
+    // validators of the targets param in the order the call lists them, none if one of them
+    // is unreadable or there are none
+    fn get_nomination_targets(params: &Value, network: &Network) -> Option<Vec<String>> {
+        let targets = params.as_array()?.first()?.get("value")?.as_array()?;
+        if targets.is_empty() {
+            return None;
+        }
+
+        targets
+            .iter()
+            .map(|t| address::to_ss58(network, t.get("Id")?.as_str()?))
+            .collect()
+    }
+
+    fn get_call_param<'a>(call: &'a Value, name: &str) -> Option<&'a str> {
+        call.get("params")?
+            .as_array()?
+            .iter()
+            .find(|p| p.get("name").is_some_and(|n| n == name))?
+            .get("value")?
+            .as_str()
+    }
+
+    // empty address means we are polling the latest extrinsics of the whole network
+    fn get_priority(address: &str) -> RequestPriority {
+        if address.is_empty() {
+            RequestPriority::Head
+        } else {
+            RequestPriority::Enrichment
+        }
+    }
 
     pub fn is_address_empty(addr: &str) -> bool {
         addr == EMPTY_ADDRESS || addr.is_empty()
     }
 }
+
+// params without a value are kept with a null one, values are decoded by their type later
+fn parse_event_param(p: &Value) -> Option<SubscanEventParam> {
+    let type_name = p.get("type_name")?.as_str()?.to_string();
+    let value = p.get("value").cloned().unwrap_or_default();
+    let name = p.get("name")?.as_str()?.to_string();
+
+    Some(SubscanEventParam {
+        type_name,
+        value,
+        name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        address::AddressFormat,
+        amount::Balance,
+        data_quality::QuarantineSource,
+        pagination::Pagination,
+        subscan_api::MockSubscanApi,
+        subscan_parser::{
+            get_decimals_override, parse_event_param, Network, SubscanParser, EMPTY_ADDRESS,
+        },
+        subscan_scheduler::SubscanEndpoint,
+        ExtrinsicsType, Identity, Module, OperationStatus, OperationType,
+    };
+    use bson::DateTime;
+    use futures::StreamExt;
+    use serde_json::{json, Value};
+    use std::str::FromStr;
+
+    static ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+    static BOB: &str = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+    static ALICE_HEX: &str = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+
+    #[test]
+    fn custom_networks_use_their_own_indexer() {
+        let network = Network::Custom {
+            slug: "devnet".to_string(),
+            base_url: "http://indexer.local:4399".to_string(),
+            decimals: 10,
+            ss58_prefix: 7,
+            token_symbol: "DEV".to_string(),
+            explorer_url: None,
+        };
+
+        assert_eq!(network.to_string(), "devnet");
+        assert_eq!(network.get_base_url(), "http://indexer.local:4399");
+        assert_eq!(network.get_ss58_prefix(), 7);
+        assert_eq!(network.get_token_symbol(), "DEV");
+        assert_eq!(
+            Network::Kusama.get_base_url(),
+            "https://kusama.api.subscan.io"
+        );
+    }
+
+    #[test]
+    fn decimals_can_be_overridden_per_network() {
+        assert_eq!(Network::Polkadot.get_default_decimals(), 10);
+        assert_eq!(Network::Moonbeam.get_default_decimals(), 18);
+
+        let overrides = "polkadot:12, astar : 6,kusama:29,westend:x";
+        assert_eq!(get_decimals_override(overrides, "polkadot"), Some(12));
+        assert_eq!(get_decimals_override(overrides, "astar"), Some(6));
+        assert_eq!(get_decimals_override(overrides, "kusama"), None);
+        assert_eq!(get_decimals_override(overrides, "westend"), None);
+        assert_eq!(get_decimals_override(overrides, "alephzero"), None);
+        assert_eq!(get_decimals_override("", "alephzero"), None);
+    }
+
+    #[test]
+    fn custom_is_not_a_network_name() {
+        assert_eq!(Network::from_str("alephzero"), Ok(Network::Alephzero));
+        assert!(Network::from_str("custom").is_err());
+    }
+
+    #[test]
+    fn rewards_and_slashes_keep_their_era() {
+        let record = |event_id: &str| {
+            json!({
+                "account": "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY",
+                "amount": "1500000000000",
+                "block_num": 42,
+                "block_timestamp": 1_700_000_000,
+                "era": 123,
+                "event_id": event_id,
+                "event_index": "42-7",
+                "extrinsic_index": "",
+                "validator_stash": "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty",
+            })
+        };
+
+        let reward =
+            SubscanParser::parse_reward_slash(&record("Rewarded"), &Network::Alephzero).unwrap();
+        assert_eq!(reward.operation_type, OperationType::Reward);
+        assert_eq!(reward.operation_quantity, 1.5);
+        assert_eq!(reward.operation_era, Some(123));
+        assert_eq!(reward.extrinsic_index, "42-7");
+        assert_eq!(reward.call_index, 7);
+        assert_eq!(
+            reward.from_wallet,
+            "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty"
+        );
+
+        let slash =
+            SubscanParser::parse_reward_slash(&record("Slashed"), &Network::Alephzero).unwrap();
+        assert_eq!(slash.operation_type, OperationType::Slash);
+        assert_eq!(
+            slash.from_wallet,
+            "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+        );
+        assert_eq!(slash.to_wallet, EMPTY_ADDRESS);
+
+        assert_eq!(
+            SubscanParser::parse_reward_slash(&record("Bonded"), &Network::Alephzero),
+            None
+        );
+    }
+
+    #[test]
+    fn event_params_keep_values_of_any_shape() {
+        let era = parse_event_param(&json!({"type_name": "EraIndex", "name": "era", "value": 943}));
+        assert_eq!(era.unwrap().as_u64(), Some(943));
+
+        let destination = parse_event_param(
+            &json!({"type_name": "RewardDestination", "name": "dest", "value": {"Staked": null}}),
+        );
+        assert_eq!(destination.unwrap().value, json!({"Staked": null}));
+
+        assert_eq!(
+            parse_event_param(&json!({"type_name": "bool", "name": "unused"}))
+                .unwrap()
+                .value,
+            json!(null)
+        );
+        assert_eq!(
+            parse_event_param(&json!({"name": "era", "value": 943})),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn extrinsics_are_parsed_from_fixtures() {
+        let fixture: Value =
+            serde_json::from_str(include_str!("../fixtures/subscan_unbond_extrinsics.json"))
+                .unwrap();
+        let api = MockSubscanApi::new().with_response(SubscanEndpoint::Extrinsics, fixture);
+        let mut subscan_parser = SubscanParser::with_api(Network::Alephzero, api.clone())
+            .with_address_format(AddressFormat::Native)
+            .with_api_keys(Some("fixture".to_string()));
+
+        let operations = subscan_parser
+            .parse_subscan_operations("", Module::Staking, ExtrinsicsType::Unbond, 0, 3)
+            .await
+            .unwrap()
+            .operations;
+
+        // failed extrinsics are skipped, the oldest operation comes first
+        assert_eq!(operations.len(), 2);
+        assert_eq!(operations[0].extrinsic_index, "58123456-2");
+        assert_eq!(operations[1].block_number, 58123460);
+        assert!(operations
+            .iter()
+            .all(|s| s.operation_type == OperationType::RequestUnstake));
+
+        // a single call for the page asked for
+        let requests = api.get_requests(SubscanEndpoint::Extrinsics);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            (
+                &requests[0]["call"],
+                &requests[0]["page"],
+                &requests[0]["row"]
+            ),
+            (&json!("unbond"), &json!(0), &json!(3))
+        );
+    }
+
+    #[tokio::test]
+    async fn malformed_rows_are_returned_for_quarantine() {
+        let fixture: Value = serde_json::from_str(include_str!(
+            "../fixtures/subscan_malformed_extrinsics.json"
+        ))
+        .unwrap();
+        let api = MockSubscanApi::new().with_response(SubscanEndpoint::Extrinsics, fixture);
+        let mut subscan_parser = SubscanParser::with_api(Network::Alephzero, api)
+            .with_address_format(AddressFormat::Native)
+            .with_api_keys(Some("fixture".to_string()));
+
+        // nothing is written while parsing, so no db is needed
+        let parsed = subscan_parser
+            .parse_subscan_operations("", Module::Staking, ExtrinsicsType::Unbond, 0, 2)
+            .await
+            .unwrap();
+
+        assert_eq!(parsed.operations.len(), 1);
+        assert_eq!(parsed.operations[0].extrinsic_index, "58123456-2");
+        assert_eq!(parsed.quarantined.len(), 1);
+        assert_eq!(parsed.quarantined[0].source, QuarantineSource::Extrinsics);
+        assert_eq!(
+            parsed.quarantined[0].record["extrinsic_index"],
+            "58123460-3"
+        );
+    }
+
+    #[tokio::test]
+    async fn transfers_of_an_address_are_parsed_from_fixtures() {
+        let fixture: Value =
+            serde_json::from_str(include_str!("../fixtures/subscan_address_transfers.json"))
+                .unwrap();
+        let api = MockSubscanApi::new().with_response(SubscanEndpoint::Transfers, fixture);
+        let mut subscan_parser = SubscanParser::with_api(Network::Alephzero, api.clone())
+            .with_address_format(AddressFormat::Native)
+            .with_api_keys(Some("fixture".to_string()));
+
+        let (parsed, identities) = subscan_parser
+            .parse_subscan_transfers(ALICE, 0, 3)
+            .await
+            .unwrap();
+
+        // the failed transfer is skipped, the oldest one comes first
+        let operations = parsed.operations;
+        assert_eq!(operations.len(), 2);
+        assert!(parsed.quarantined.is_empty());
+
+        let sent = &operations[0];
+        assert_eq!(sent.operation_type, OperationType::Transfer);
+        assert_eq!(
+            (sent.from_wallet.as_str(), sent.to_wallet.as_str()),
+            (ALICE, BOB)
+        );
+        assert_eq!(sent.controller_wallet, EMPTY_ADDRESS);
+        assert_eq!(
+            sent.operation_timestamp,
+            DateTime::from_millis(1_700_000_000_000)
+        );
+        assert_eq!((sent.block_number, sent.call_index), (58123456, 3));
+        assert_eq!(sent.extrinsic_index, "58123456-1");
+        assert_eq!(sent.operation_quantity, 12.5);
+        assert_eq!(sent.operation_planck, Some(Balance(12_500_000_000_000)));
+        // the amount is in tokens and the fee in planck
+        assert_eq!(sent.operation_fee, Some(0.0154));
+        assert_eq!(sent.status, OperationStatus::Pending);
+
+        let received = &operations[1];
+        assert_eq!(
+            (received.from_wallet.as_str(), received.to_wallet.as_str()),
+            (BOB, ALICE)
+        );
+        assert_eq!(
+            received.operation_timestamp,
+            DateTime::from_millis(1_700_000_240_000)
+        );
+        assert_eq!(received.operation_planck, Some(Balance(500_000_000_000)));
+        assert_eq!(received.operation_fee, Some(0.001));
+
+        assert_eq!(
+            identities,
+            vec![Identity {
+                address: BOB.to_string(),
+                identity: "bob".to_string(),
+            }]
+        );
+
+        let requests = api.get_requests(SubscanEndpoint::Transfers);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            (
+                &requests[0]["address"],
+                &requests[0]["asset_symbol"],
+                &requests[0]["row"]
+            ),
+            (&json!(ALICE), &json!("AZERO"), &json!(3))
+        );
+    }
+
+    #[tokio::test]
+    async fn operations_are_streamed_page_by_page() {
+        let fixture: Value =
+            serde_json::from_str(include_str!("../fixtures/subscan_unbond_extrinsics.json"))
+                .unwrap();
+        let api = MockSubscanApi::new()
+            .with_response(SubscanEndpoint::Extrinsics, fixture)
+            .with_response(
+                SubscanEndpoint::Extrinsics,
+                json!({"code": 0, "data": {"extrinsics": []}}),
+            );
+        let subscan_parser = SubscanParser::with_api(Network::Alephzero, api.clone())
+            .with_address_format(AddressFormat::Native)
+            .with_api_keys(Some("fixture".to_string()));
+        let pagination = Pagination {
+            page_size: 3,
+            max_pages: 5,
+            min_block_number: None,
+            min_timestamp: None,
+        };
+
+        // nothing is requested before the first page is polled
+        let mut stream = Box::pin(subscan_parser.stream_operations(
+            "",
+            Module::Staking,
+            ExtrinsicsType::Unbond,
+            pagination,
+        ));
+        assert!(api.get_requests(SubscanEndpoint::Extrinsics).is_empty());
+
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!(
+            first
+                .operations
+                .iter()
+                .map(|s| s.extrinsic_index.as_str())
+                .collect::<Vec<_>>(),
+            vec!["58123460-3", "58123456-2"]
+        );
+        assert_eq!(api.get_requests(SubscanEndpoint::Extrinsics).len(), 1);
+
+        // the full first page asks for the next one, which ends the walk
+        let second = stream.next().await.unwrap().unwrap();
+        assert!(second.operations.is_empty());
+        assert!(stream.next().await.is_none());
+        let requests = api.get_requests(SubscanEndpoint::Extrinsics);
+        assert_eq!(
+            requests.iter().map(|r| &r["page"]).collect::<Vec<_>>(),
+            vec![&json!(0), &json!(1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn head_block_number_is_read_from_fixtures() {
+        let api = MockSubscanApi::new().with_response(
+            SubscanEndpoint::Metadata,
+            json!({
+                "code": 0,
+                "message": "Success",
+                "data": {"blockNum": "58123460", "finalized_blockNum": 58123458},
+            }),
+        );
+        let mut subscan_parser = SubscanParser::with_api(Network::Alephzero, api)
+            .with_api_keys(Some("fixture".to_string()));
+
+        assert_eq!(
+            subscan_parser.get_head_block_number().await.unwrap(),
+            58123460
+        );
+        assert_eq!(
+            subscan_parser.get_finalized_block_number().await.unwrap(),
+            58123458
+        );
+    }
+
+    #[tokio::test]
+    async fn balances_are_read_in_tokens() {
+        let api = MockSubscanApi::new().with_response(
+            SubscanEndpoint::Account,
+            json!({
+                "code": 0,
+                "message": "Success",
+                "data": {"account": {
+                    "address": ALICE,
+                    "balance": "1250.5",
+                    "reserved": "0",
+                    "bonded": "1000",
+                }},
+            }),
+        );
+        let mut subscan_parser = SubscanParser::with_api(Network::Alephzero, api.clone())
+            .with_api_keys(Some("fixture".to_string()));
+
+        let snapshot = subscan_parser.parse_subscan_balance(ALICE).await.unwrap();
+        assert_eq!(snapshot.wallet, ALICE);
+        assert_eq!(snapshot.total, 1_250.5);
+        assert_eq!(snapshot.bonded, 1_000.0);
+        assert_eq!(
+            api.get_requests(SubscanEndpoint::Account),
+            vec![json!({"key": ALICE})]
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_signers_are_taken_from_the_detail() {
+        let row = json!({
+            "block_num": 58123460,
+            "block_timestamp": 1_700_000_240,
+            "extrinsic_index": "58123460-3",
+            "success": true,
+        });
+        let api = MockSubscanApi::new()
+            .with_response(
+                SubscanEndpoint::Extrinsics,
+                json!({"code": 0, "data": {"extrinsics": [row]}}),
+            )
+            .with_response(
+                SubscanEndpoint::ExtrinsicDetail,
+                json!({
+                    "code": 0,
+                    "data": {"account_id": "", "account_display": {"address": ALICE}, "event": []},
+                }),
+            );
+        let mut subscan_parser = SubscanParser::with_api(Network::Alephzero, api.clone())
+            .with_address_format(AddressFormat::Native)
+            .with_api_keys(Some("fixture".to_string()));
+
+        let operations = subscan_parser
+            .parse_subscan_operations("", Module::Staking, ExtrinsicsType::Unbond, 0, 1)
+            .await
+            .unwrap()
+            .operations;
+        assert_eq!(operations.len(), 1);
+        assert_eq!(operations[0].from_wallet, ALICE);
+        assert_eq!(
+            api.get_requests(SubscanEndpoint::ExtrinsicDetail)[0]["extrinsic_index"],
+            "58123460-3"
+        );
+    }
+
+    #[test]
+    fn signers_fall_back_to_the_accounts_of_the_events() {
+        let params = json!([
+            {"type_name": "Balance", "name": "amount", "value": "1000"},
+            {"type_name": "AccountId", "name": "stash", "value": ALICE_HEX},
+        ]);
+        let detail = json!({
+            "account_id": "",
+            "event": [{
+                "module_id": "staking",
+                "event_id": "Unbonded",
+                "params": params.to_string(),
+            }],
+        });
+        assert_eq!(
+            SubscanParser::get_signer(&detail, &Network::Alephzero),
+            Some(ALICE.to_string())
+        );
+
+        let signed = json!({"account_id": ALICE, "event": []});
+        assert_eq!(
+            SubscanParser::get_signer(&signed, &Network::Alephzero),
+            Some(ALICE.to_string())
+        );
+        assert_eq!(
+            SubscanParser::get_signer(&json!({"event": []}), &Network::Alephzero),
+            None
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use std::{
+    env,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+use tokio::time::sleep;
+
+static DEFAULT_UNITS_PER_SECOND: f64 = 10.0;
+static DEFAULT_HEAD_RESERVED_UNITS: f64 = 2.0;
+static POLL_INTERVAL_MS: u64 = 20;
+
+static SCHEDULER: OnceLock<SubscanScheduler> = OnceLock::new();
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    EnumString,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum SubscanEndpoint {
+    Extrinsics,
+    Transfers,
+    Events,
+    ExtrinsicDetail,
+    RewardSlash,
+    Metadata,
+    Account,
+}
+
+impl SubscanEndpoint {
+    pub fn path(&self) -> &'static str {
+        match self {
+            SubscanEndpoint::Extrinsics => "api/scan/extrinsics",
+            SubscanEndpoint::Transfers => "api/v2/scan/transfers",
+            SubscanEndpoint::Events => "api/scan/event/params",
+            SubscanEndpoint::ExtrinsicDetail => "api/scan/extrinsic",
+            SubscanEndpoint::RewardSlash => "api/scan/account/reward_slash",
+            SubscanEndpoint::Metadata => "api/scan/metadata",
+            SubscanEndpoint::Account => "api/v2/scan/search",
+        }
+    }
+
+    // relative cost of a single call, list endpoints are the cheapest ones
+    pub fn weight(&self) -> f64 {
+        match self {
+            SubscanEndpoint::Extrinsics
+            | SubscanEndpoint::Transfers
+            | SubscanEndpoint::RewardSlash
+            | SubscanEndpoint::Metadata
+            | SubscanEndpoint::Account => 1.0,
+            SubscanEndpoint::Events => 2.0,
+            SubscanEndpoint::ExtrinsicDetail => 3.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RequestPriority {
+    // polling the latest extrinsics/transfers, keeps the feed live
+    Head,
+    // per extrinsic or per address lookups
+    Enrichment,
+}
+
+// keeps waiting_head right even when the waiting future is dropped
+struct HeadWaiter<'a> {
+    waiting_head: &'a AtomicUsize,
+}
+
+impl<'a> HeadWaiter<'a> {
+    fn new(waiting_head: &'a AtomicUsize) -> Self {
+        waiting_head.fetch_add(1, Ordering::SeqCst);
+        Self { waiting_head }
+    }
+}
+
+impl Drop for HeadWaiter<'_> {
+    fn drop(&mut self) {
+        self.waiting_head.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// plain request count limit of the api key on top of the weighted units, whatever the endpoint
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct RequestLimit {
+    requests_per_second: f64,
+    burst: f64,
+}
+
+impl RequestLimit {
+    // SUBSCAN_REQUESTS_PER_SECOND of the quota tier, SUBSCAN_REQUESTS_BURST defaults to one second of it
+    fn from_env() -> Option<RequestLimit> {
+        let requests_per_second = env::var("SUBSCAN_REQUESTS_PER_SECOND")
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|v| *v > 0.0)?;
+        let burst = env::var("SUBSCAN_REQUESTS_BURST")
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|v| *v >= 1.0)
+            .unwrap_or(requests_per_second.max(1.0));
+
+        Some(Self {
+            requests_per_second,
+            burst,
+        })
+    }
+}
+
+struct SchedulerState {
+    units: f64,
+    requests: f64,
+    last_refill: Instant,
+}
+
+pub struct SubscanScheduler {
+    units_per_second: f64,
+    head_reserved_units: f64,
+    request_limit: Option<RequestLimit>,
+    waiting_head: AtomicUsize,
+    state: Mutex<SchedulerState>,
+}
+
+impl SubscanScheduler {
+    fn new() -> Self {
+        let units_per_second = env::var("SUBSCAN_SCHEDULER_UNITS_PER_SECOND")
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|v| *v > 0.0)
+            .unwrap_or(DEFAULT_UNITS_PER_SECOND);
+        let head_reserved_units = env::var("SUBSCAN_SCHEDULER_HEAD_RESERVED_UNITS")
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|v| *v >= 0.0)
+            .unwrap_or(DEFAULT_HEAD_RESERVED_UNITS);
+        let request_limit = RequestLimit::from_env();
+
+        Self {
+            units_per_second,
+            head_reserved_units,
+            request_limit,
+            waiting_head: AtomicUsize::new(0),
+            state: Mutex::new(SchedulerState {
+                units: units_per_second,
+                requests: request_limit.map(|l| l.burst).unwrap_or_default(),
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn global() -> &'static SubscanScheduler {
+        SCHEDULER.get_or_init(SubscanScheduler::new)
+    }
+
+    // waits until the call fits into the shared budget
+    // head polling always goes first, enrichment can't eat into the reserved units
+    pub async fn acquire(&self, endpoint: SubscanEndpoint, priority: RequestPriority) {
+        let cost = endpoint.weight();
+        let _head_waiter =
+            (priority == RequestPriority::Head).then(|| HeadWaiter::new(&self.waiting_head));
+
+        while !self.try_take(cost, priority) {
+            sleep(Duration::from_millis(POLL_INTERVAL_MS)).await;
+        }
+    }
+
+    fn try_take(&self, cost: f64, priority: RequestPriority) -> bool {
+        let mut state = self.state.lock().unwrap();
+
+        // budget never exceeds one second worth of units
+        let capacity = self.units_per_second.max(cost + self.head_reserved_units);
+        let elapsed = state.last_refill.elapsed().as_secs_f64();
+        state.units = (state.units + elapsed * self.units_per_second).min(capacity);
+        state.last_refill = Instant::now();
+
+        if let Some(request_limit) = self.request_limit {
+            state.requests = (state.requests + elapsed * request_limit.requests_per_second)
+                .min(request_limit.burst);
+            if state.requests < 1.0 {
+                return false;
+            }
+        }
+
+        let required = match priority {
+            RequestPriority::Head => cost,
+            RequestPriority::Enrichment => {
+                if self.waiting_head.load(Ordering::SeqCst) > 0 {
+                    return false;
+                }
+
+                cost + self.head_reserved_units
+            }
+        };
+
+        if state.units < required {
+            return false;
+        }
+
+        state.units -= cost;
+        if self.request_limit.is_some() {
+            state.requests -= 1.0;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::subscan_scheduler::{
+        RequestLimit, RequestPriority, SchedulerState, SubscanEndpoint, SubscanScheduler,
+    };
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+        time::{Duration, Instant},
+    };
+
+    #[tokio::test]
+    async fn cancelled_head_acquire_releases_waiter() {
+        let scheduler = SubscanScheduler {
+            units_per_second: 0.001,
+            head_reserved_units: 0.0,
+            request_limit: None,
+            waiting_head: AtomicUsize::new(0),
+            state: Mutex::new(SchedulerState {
+                units: 0.0,
+                requests: 0.0,
+                last_refill: Instant::now(),
+            }),
+        };
+
+        let acquire = scheduler.acquire(SubscanEndpoint::Events, RequestPriority::Head);
+        let res = tokio::time::timeout(Duration::from_millis(50), acquire).await;
+
+        assert!(res.is_err());
+        assert_eq!(scheduler.waiting_head.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn request_limit_caps_requests_whatever_their_weight() {
+        let scheduler = SubscanScheduler {
+            units_per_second: 1_000.0,
+            head_reserved_units: 0.0,
+            request_limit: Some(RequestLimit {
+                requests_per_second: 0.001,
+                burst: 2.0,
+            }),
+            waiting_head: AtomicUsize::new(0),
+            state: Mutex::new(SchedulerState {
+                units: 1_000.0,
+                requests: 2.0,
+                last_refill: Instant::now(),
+            }),
+        };
+
+        assert!(scheduler.try_take(1.0, RequestPriority::Head));
+        assert!(scheduler.try_take(1.0, RequestPriority::Enrichment));
+        assert!(!scheduler.try_take(1.0, RequestPriority::Head));
+    }
+}
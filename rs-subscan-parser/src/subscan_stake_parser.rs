@@ -1,48 +1,264 @@
 use crate::{
+    address::AddressFormat,
+    amount::Balance,
+    data_quality::{quarantine_records, QuarantineSource, QuarantinedRecord},
+    event_matcher::{EventFields, EventMatcher, FromEvents},
+    feature_flags::FeatureFlags,
+    mock_network::MOCK_AZERO_USD_PRICE,
     mongodb_client_identities::MongoDbClientIdentity,
     mongodb_client_subscan::MongoDbClientSubscan,
     mongodb_client_validator::MongoDbClientValidator,
-    subscan_parser::{Network, SubscanParser, AZERO_DENOMINATOR},
-    ExtrinsicsType, Module, SubscanOperation, Validator, MINIMUM_AZERO_TO_SAVE_TO_DB,
+    operation_prices::{set_current_price, set_operation_prices},
+    pagination::{Pagination, MAX_SUBSCAN_PAGE_SIZE},
+    pipeline_error::{ErrorCode, PipelineError},
+    subscan_api::SubscanApi,
+    subscan_parser::{Network, ParsedOperations, SubscanParser},
+    sync_checkpoint::{get_latest_checkpoint, load_checkpoint},
+    task_limit::TaskLimit,
+    ExtrinsicsType, Module, SubscanEvent, SubscanOperation, SyncCheckpoint, Validator,
+    MINIMUM_AZERO_TO_SAVE_TO_DB,
 };
+use bson::DateTime;
 use futures::{stream::FuturesUnordered, StreamExt};
 use itertools::Itertools;
 use rs_exchanges_parser::{
     mongodb_client_exchanges::MongoDbClientExchanges, PrimaryToken, SecondaryToken,
 };
-use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
-use std::collections::HashSet;
-use strum::IntoEnumIterator;
-
-pub async fn parse_staking() -> Option<Vec<SubscanOperation>> {
-    let price_task = tokio::spawn(async move {
-        let mut mongodb_client_exchanges = MongoDbClientExchanges::new().await;
-        mongodb_client_exchanges
-            .get_usd_price(PrimaryToken::Azero, SecondaryToken::Usdt)
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use std::{collections::HashSet, env, ops::Range};
+use tracing::{error, info_span, Instrument};
+
+static DEFAULT_NOMINATIONS_LOOKBACK_HOURS: i64 = 24;
+static DEFAULT_STAKING_ROWS_PER_PAGE: u32 = 100;
+static DEFAULT_STAKING_PAGES_TO_SCAN: u32 = 1;
+static DEFAULT_STAKING_CONCURRENCY: usize = 32;
+
+// current price of the operations, the fallback for the ones without a historical price
+#[derive(Clone, Debug, PartialEq)]
+pub enum PriceSource {
+    // latest AZERO/USDT price of the exchanges parser, historical prices are looked up too
+    Exchanges,
+
+    // every operation is valued at it, for networks without exchanges data
+    Fixed(f64),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StakingParserConfig {
+    pub network: Network,
+
+    // comma separated like SUBSCAN_API_KEY, which is used if none
+    pub api_keys: Option<String>,
+
+    // latest extrinsics of every staking call and of batch_all
+    pub rows_per_page: u32,
+    pub pages_to_scan: u32,
+
+    // subscan and mongo lookups running at once, the scheduler still limits the request rate,
+    // shared with whatever else the caller hands the same limit to
+    pub task_limit: TaskLimit,
+    pub price_source: PriceSource,
+
+    // only this page of every call is walked and the checkpoint is left alone, see backfill
+    pub backfill_page: Option<u32>,
+
+    // only extrinsics of these wallets are walked, the whole network if empty
+    pub addresses: Vec<String>,
+}
+
+impl StakingParserConfig {
+    // STAKING_ROWS_PER_PAGE, STAKING_PAGES_TO_SCAN and STAKING_CONCURRENCY, the mock network
+    // is valued at its fixed price
+    pub fn from_env() -> StakingParserConfig {
+        let network = Network::from_env();
+        let rows_per_page = env::var("STAKING_ROWS_PER_PAGE")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_STAKING_ROWS_PER_PAGE)
+            .min(MAX_SUBSCAN_PAGE_SIZE);
+        let pages_to_scan = env::var("STAKING_PAGES_TO_SCAN")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_STAKING_PAGES_TO_SCAN);
+        let concurrency = env::var("STAKING_CONCURRENCY")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_STAKING_CONCURRENCY);
+        let price_source = if network == Network::Mock {
+            PriceSource::Fixed(MOCK_AZERO_USD_PRICE)
+        } else {
+            PriceSource::Exchanges
+        };
+
+        Self {
+            network,
+            api_keys: None,
+            rows_per_page,
+            pages_to_scan,
+            task_limit: TaskLimit::new(concurrency),
+            price_source,
+            backfill_page: None,
+            addresses: Vec::new(),
+        }
+    }
+
+    pub fn with_backfill_page(self, backfill_page: u32) -> Self {
+        Self {
+            backfill_page: Some(backfill_page),
+            ..self
+        }
+    }
+
+    pub fn with_addresses(self, addresses: Vec<String>) -> Self {
+        Self { addresses, ..self }
+    }
+
+    // the empty address walks the extrinsics of the whole network
+    fn get_addresses(&self) -> Vec<String> {
+        if self.addresses.is_empty() {
+            vec![String::new()]
+        } else {
+            self.addresses.clone()
+        }
+    }
+
+    // the checkpoint is the newest block of the network, runs over a part of it leave it alone
+    fn is_checkpointed(&self) -> bool {
+        self.backfill_page.is_none() && self.addresses.is_empty()
+    }
+
+    async fn get_subscan_parser(&self) -> SubscanParser {
+        SubscanParser::new(self.network.clone())
             .await
-    });
+            .with_api_keys(self.api_keys.clone())
+    }
 
-    let mut tasks = FuturesUnordered::new();
-    for e in ExtrinsicsType::iter() {
-        tasks.push(tokio::spawn(async move {
-            let mut subscan_parser = SubscanParser::new(Network::Alephzero).await;
-            subscan_parser
-                .parse_subscan_operations("", Module::Staking, e, 100)
-                .await
-        }));
+    async fn get_current_price(&self) -> Option<f64> {
+        match self.price_source {
+            PriceSource::Exchanges => {
+                let mut mongodb_client_exchanges = MongoDbClientExchanges::new().await;
+                mongodb_client_exchanges
+                    .get_usd_price(PrimaryToken::Azero, SecondaryToken::Usdt)
+                    .await
+            }
+            PriceSource::Fixed(price) => Some(price),
+        }
     }
 
-    let mut subscan_operations = Vec::new();
+    async fn set_prices(&self, subscan_operations: &mut [SubscanOperation], price: f64) {
+        match self.price_source {
+            PriceSource::Exchanges => {
+                set_operation_prices(subscan_operations, price, &self.network).await
+            }
+            PriceSource::Fixed(_) => set_current_price(subscan_operations, price),
+        }
+    }
+}
+
+// staking operations of the wallets only, every wallet is walked on its own
+pub async fn parse_staking_for_addresses(
+    config: &StakingParserConfig,
+    addresses: &[String],
+) -> Option<Vec<SubscanOperation>> {
+    if addresses.is_empty() {
+        return Some(Vec::new());
+    }
+
+    parse_staking(&config.clone().with_addresses(addresses.to_vec()))
+        .await
+        .map(|(subscan_operations, _)| subscan_operations)
+}
+
+// operations of the run with the checkpoint to save once they are stored, none if an extrinsic
+// of the run could not be fetched so the next run walks the same range again
+pub async fn parse_staking(
+    config: &StakingParserConfig,
+) -> Option<(Vec<SubscanOperation>, Option<SyncCheckpoint>)> {
+    let task_limit = &config.task_limit;
+
+    // clones share the connection pool, every task of the run goes through this one
+    let subscan_parser = config.get_subscan_parser().await;
+
+    // after the first run only extrinsics since the checkpoint are walked, its block included
+    let previous_checkpoint = if config.is_checkpointed() {
+        load_checkpoint(&config.network).await
+    } else {
+        None
+    };
+    let pagination = previous_checkpoint
+        .as_ref()
+        .map(|c| Pagination::from_env().with_min_block_number(c.block_number));
+    let pages = match config.backfill_page {
+        Some(page) => page..page + 1,
+        None if pagination.is_some() => 0..1,
+        None => 0..config.pages_to_scan,
+    };
+    let price_config = config.clone();
+    let price_task = tokio::spawn(async move { price_config.get_current_price().await });
+
+    let addresses = config.get_addresses();
+    let mut requests = Vec::new();
+    for address in addresses.iter() {
+        for module in FeatureFlags::global().get_enabled_modules() {
+            for e in module.get_extrinsics_types() {
+                for page in pages.clone() {
+                    requests.push((address.clone(), module.clone(), e.clone(), page));
+                }
+            }
+        }
+    }
+    let mut tasks = requests
+        .into_iter()
+        .map(|(address, module, e, page)| {
+            let mut subscan_parser = subscan_parser.clone();
+            let rows_per_page = config.rows_per_page;
+            task_limit.spawn(async move {
+                match pagination {
+                    Some(pagination) => {
+                        subscan_parser
+                            .parse_subscan_operations_pages(&address, module, e, &pagination)
+                            .await
+                    }
+                    None => {
+                        subscan_parser
+                            .parse_subscan_operations(&address, module, e, page, rows_per_page)
+                            .await
+                    }
+                }
+            })
+        })
+        .collect::<FuturesUnordered<_>>();
+
+    let mut is_complete = true;
+    let mut parsed = ParsedOperations::default();
     while let Some(res) = tasks.next().await {
         let Ok(s) = res else {
+            is_complete = false;
             continue;
         };
 
-        let Some(mut s) = s else {
-            continue;
-        };
-        subscan_operations.append(&mut s);
+        match s {
+            Ok(s) => parsed.append(s),
+            Err(e) => {
+                error!(target: "subscan_parser", "Staking extrinsics error: {e}.");
+                is_complete = false;
+            }
+        }
     }
+    let address_format = subscan_parser.get_address_format();
+    let subscan_operations = parsed.process(&config.network, address_format).await;
+    let checkpoint =
+        get_latest_checkpoint(&config.network, &subscan_operations, previous_checkpoint);
+
+    // later pages can overlap the first ones when new blocks come in meanwhile, and wallets of
+    // the same extrinsic find it each
+    let subscan_operations = subscan_operations
+        .into_iter()
+        .unique_by(|s| s.extrinsic_index.clone())
+        .collect();
 
     // skipping already existing records
     let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
@@ -51,48 +267,51 @@ pub async fn parse_staking() -> Option<Vec<SubscanOperation>> {
         .await;
 
     // adding from_wallet and operation_quantity
-    let mut tasks = FuturesUnordered::new();
-    for s in subscan_operations {
-        let mut s_clone = s.clone();
-        tasks.push(tokio::spawn(async move {
-            let mut subscan_parser = SubscanParser::new(Network::Alephzero).await;
-            let events = subscan_parser
-                .parse_subscan_extrinsic_details(s.extrinsic_index)
-                .await?;
-
-            let stake_event = events.iter().find(|p| p.module_id == "staking")?;
-
-            // event must have at least 2 parameters
-            if stake_event.event_params.len() < 2 {
-                return None;
-            }
-
-            let stash_param = stake_event.event_params.first()?;
-            if stash_param.name != "stash" && stash_param.name != "who" {
-                return None;
-            }
-
-            let amount_param = stake_event.event_params.last()?;
-            if amount_param.name != "amount" {
-                return None;
-            }
+    let mut tasks = subscan_operations
+        .into_iter()
+        .map(|s| {
+            let config = config.clone();
+            let mut subscan_parser = subscan_parser.clone();
+            let span = info_span!("staking_operation", extrinsic_index = %s.extrinsic_index);
+            let task = async move {
+                let events = match subscan_parser
+                    .parse_subscan_extrinsic_details(s.extrinsic_index.clone())
+                    .await
+                {
+                    Ok(events) => events,
+                    Err(e) => {
+                        error!(target: "subscan_parser", "Extrinsic details error of {}: {e}.", s.extrinsic_index);
+                        return Err(e);
+                    }
+                };
 
-            let stash_wallet = stash_param.value.clone()[2..].to_string();
-            let decoded = hex::decode(stash_wallet).ok()?;
-            let byte_arr: [u8; 32] = decoded.try_into().ok()?;
-            let address = AccountId32::from(byte_arr)
-                .to_ss58check_with_version(Ss58AddressFormat::custom(42));
-            s_clone.from_wallet = address;
-            s_clone.operation_quantity =
-                amount_param.value.parse::<f64>().ok()? / AZERO_DENOMINATOR;
+                let operation = enrich_with_staking_event(
+                    s.clone(),
+                    &events,
+                    &config.network,
+                    subscan_parser.get_address_format(),
+                );
+                if operation.is_none() {
+                    let record = serde_json::to_value(&s).unwrap_or_default();
+                    let reason = "no staking event with stash and amount".to_string();
+                    quarantine_records(vec![QuarantinedRecord::new(
+                        QuarantineSource::StakingEvents,
+                        record,
+                        vec![reason],
+                    )])
+                    .await;
+                }
 
-            Some(s_clone)
-        }));
-    }
+                Ok(operation)
+            };
+            task_limit.spawn(task.instrument(span))
+        })
+        .collect::<FuturesUnordered<_>>();
 
     let mut subscan_operations = Vec::new();
     while let Some(res) = tasks.next().await {
-        let Ok(s) = res else {
+        let Ok(Ok(s)) = res else {
+            is_complete = false;
             continue;
         };
 
@@ -102,13 +321,51 @@ pub async fn parse_staking() -> Option<Vec<SubscanOperation>> {
         subscan_operations.push(s);
     }
 
-    // parsing batch all operations
-    let batch_all_operations = tokio::spawn(async move {
-        let mut subscan_parser = SubscanParser::new(Network::Alephzero).await;
-        subscan_parser.parse_subscan_batch_all("", 0, 20).await
-    })
-    .await
-    .ok()??;
+    // parsing batch all operations, a failed wallet doesn't cost the others theirs
+    let mut tasks = addresses
+        .iter()
+        .flat_map(|address| pages.clone().map(move |page| (address.clone(), page)))
+        .map(|(address, page)| {
+            let mut subscan_parser = subscan_parser.clone();
+            let rows_per_page = config.rows_per_page;
+            task_limit.spawn(async move {
+                match pagination {
+                    Some(pagination) => {
+                        subscan_parser
+                            .parse_subscan_batch_all_pages(&address, &pagination)
+                            .await
+                    }
+                    None => {
+                        subscan_parser
+                            .parse_subscan_batch_all(&address, page, rows_per_page)
+                            .await
+                    }
+                }
+            })
+        })
+        .collect::<FuturesUnordered<_>>();
+
+    let mut parsed = ParsedOperations::default();
+    while let Some(res) = tasks.next().await {
+        let Ok(b) = res else {
+            is_complete = false;
+            continue;
+        };
+
+        match b {
+            Ok(b) => parsed.append(b),
+            Err(e) => {
+                error!(target: "subscan_parser", "Batch all error: {e}.");
+                is_complete = false;
+            }
+        }
+    }
+    let batch_all_operations = parsed.process(&config.network, address_format).await;
+    let checkpoint = get_latest_checkpoint(&config.network, &batch_all_operations, checkpoint);
+    let batch_all_operations = batch_all_operations
+        .into_iter()
+        .unique_by(|s| s.extrinsic_index.clone())
+        .collect();
 
     // skipping already existing records
     let mut batch_all_operations = mongodb_client_subscan
@@ -117,6 +374,19 @@ pub async fn parse_staking() -> Option<Vec<SubscanOperation>> {
 
     subscan_operations.append(&mut batch_all_operations);
 
+    // payouts and slashes aren't extrinsics of the wallets, they are looked up on their own
+    let reward_wallets = get_reward_wallets(config, &subscan_operations);
+    let (parsed, is_reward_complete) = get_reward_slash_operations(
+        &subscan_parser,
+        task_limit,
+        &reward_wallets,
+        pages.clone(),
+        config.rows_per_page,
+    )
+    .await;
+    is_complete &= is_reward_complete;
+    let mut reward_operations = parsed.process(&config.network, address_format).await;
+
     // saving validators to db
     let validators = convert_operations_to_validators(subscan_operations.clone());
     let validators_task = tokio::spawn(async move {
@@ -132,11 +402,21 @@ pub async fn parse_staking() -> Option<Vec<SubscanOperation>> {
         .filter(|p| p.operation_quantity > MINIMUM_AZERO_TO_SAVE_TO_DB)
         .collect::<Vec<_>>();
 
-    // updating to current price
-    let price = price_task.await.ok()??;
-    for s in subscan_operations.iter_mut() {
-        s.operation_usd = s.operation_quantity * price;
-    }
+    // current price is the fallback for operations without a historical one
+    let price = match price_task.await.ok()? {
+        Some(price) => price,
+        None => {
+            let pipeline_error = PipelineError::new(
+                ErrorCode::PriceUnavailable,
+                "no current AZERO price",
+                &config.network,
+            );
+            error!(target: "subscan_parser", "Parse error: {}.", pipeline_error.to_json());
+            return None;
+        }
+    };
+    config.set_prices(&mut subscan_operations, price).await;
+    config.set_prices(&mut reward_operations, price).await;
 
     validators_task.await.ok()?;
 
@@ -151,98 +431,61 @@ pub async fn parse_staking() -> Option<Vec<SubscanOperation>> {
         .get_not_existing_nominators(nominators)
         .await;
 
-    // parsing validators for given non existing nominators
-    let mut tasks = FuturesUnordered::new();
-    for nominator in not_existing_nominators.into_iter() {
-        let nominator_clone = nominator.clone();
-        tasks.push(tokio::spawn(async move {
-            let mut subscan_parser = SubscanParser::new(Network::Alephzero).await;
-            subscan_parser
-                .parse_subscan_batch_all(&nominator_clone, 0, 100)
-                .await
-        }));
-
-        tasks.push(tokio::spawn(async move {
-            let mut subscan_parser = SubscanParser::new(Network::Alephzero).await;
-            subscan_parser
-                .parse_subscan_operations(&nominator, Module::Staking, ExtrinsicsType::Nominate, 1)
-                .await
-        }));
-    }
-
-    let mut validators = Vec::new();
-    while let Some(res) = tasks.next().await {
-        let Ok(s) = res else {
-            continue;
-        };
-
-        let Some(s) = s else {
-            continue;
-        };
-
-        let mut v = convert_operations_to_validators(s);
-        validators.append(&mut v);
-    }
+    // most of them nominated lately, so one walk over the recent nominations of the network
+    // finds them, only the rest is looked up with two requests per nominator
+    let mut recent_nominations = RecentNominations::default();
+    let validators = get_nominations_of(
+        config,
+        &subscan_parser,
+        &mut recent_nominations,
+        not_existing_nominators,
+        true,
+    )
+    .await;
 
     // updating validators
     mongodb_client_validator
         .import_or_update_validators(validators)
         .await;
 
-    for s in subscan_operations.iter_mut() {
-        let to_wallet = mongodb_client_validator
-            .get_validator_by_nominator(&s.from_wallet)
-            .await;
-        let Some(to_wallet) = to_wallet else {
-            continue;
-        };
-        s.to_wallet = to_wallet.validator;
-    }
-
-    // for wallets with separate controller wallet, we should find out to which validator they staked from controller wallet
-    for s in subscan_operations.iter_mut() {
-        if !SubscanParser::is_address_empty(&s.to_wallet) {
-            continue;
-        }
-
-        if SubscanParser::is_address_empty(&s.controller_wallet) {
-            continue;
-        }
+    set_validators(&mut mongodb_client_validator, &mut subscan_operations).await;
 
-        let mut subscan_parser = SubscanParser::new(Network::Alephzero).await;
-        let controller_operations = subscan_parser
-            .parse_subscan_operations(
-                &s.controller_wallet,
-                Module::Staking,
-                ExtrinsicsType::Nominate,
-                1,
-            )
-            .await;
-
-        let Some(mut controller_operations) = controller_operations else {
-            continue;
-        };
-
-        for c in controller_operations.iter_mut() {
-            c.from_wallet = s.from_wallet.clone();
-        }
+    // for wallets with separate controller wallet, we should find out to which validator they
+    // staked from controller wallet, each controller is looked up once for all of its stashes
+    let controlled_stashes = subscan_operations
+        .iter()
+        .filter(|s| SubscanParser::is_address_empty(&s.to_wallet))
+        .filter(|s| !SubscanParser::is_address_empty(&s.controller_wallet))
+        .map(|s| (s.from_wallet.clone(), s.controller_wallet.clone()))
+        .unique()
+        .collect::<Vec<_>>();
+    let controllers = controlled_stashes
+        .iter()
+        .map(|(_, controller)| controller.clone())
+        .unique()
+        .collect();
+    let controller_validators = get_nominations_of(
+        config,
+        &subscan_parser,
+        &mut recent_nominations,
+        controllers,
+        false,
+    )
+    .await;
 
-        // updating validators
-        mongodb_client_validator
-            .import_or_update_validators(convert_operations_to_validators(controller_operations))
-            .await;
-    }
+    // updating validators
+    mongodb_client_validator
+        .import_or_update_validators(assign_to_stashes(
+            &controlled_stashes,
+            &controller_validators,
+        ))
+        .await;
 
+    // controller nominations could have changed them, rewards keep the validator which paid them
+    set_validators(&mut mongodb_client_validator, &mut subscan_operations).await;
+    subscan_operations.append(&mut reward_operations);
     for s in subscan_operations.iter_mut() {
-        s.set_hash();
-
-        let to_wallet = mongodb_client_validator
-            .get_validator_by_nominator(&s.from_wallet)
-            .await;
-        let Some(to_wallet) = to_wallet else {
-            continue;
-        };
-        s.to_wallet = to_wallet.validator;
+        s.set_hash(&config.network);
     }
 
     // removing operations with less than MINIMUM_AZERO_TO_SAVE_TO_DB AZERO amount
@@ -261,7 +504,7 @@ pub async fn parse_staking() -> Option<Vec<SubscanOperation>> {
         .map(|m| m.to_wallet.to_string())
         .collect::<Vec<_>>();
     let new_addresses: HashSet<String> =
-        HashSet::from_iter(from_wallets.into_iter().chain(to_wallets.into_iter()));
+        HashSet::from_iter(from_wallets.into_iter().chain(to_wallets));
     let new_addresses = new_addresses.into_iter().collect::<Vec<_>>();
 
     // skipping already existing records
@@ -271,13 +514,13 @@ pub async fn parse_staking() -> Option<Vec<SubscanOperation>> {
         .await;
 
     // parsing non existing identities
-    let mut tasks = FuturesUnordered::new();
-    for a in new_addresses {
-        tasks.push(tokio::spawn(async move {
-            let mut subscan_parser = SubscanParser::new(Network::Alephzero).await;
-            subscan_parser.parse_subscan_identity(&a, 0, 1).await
-        }));
-    }
+    let mut tasks = new_addresses
+        .into_iter()
+        .map(|a| {
+            let mut subscan_parser = subscan_parser.clone();
+            task_limit.spawn(async move { subscan_parser.parse_subscan_identity(&a, 0, 1).await })
+        })
+        .collect::<FuturesUnordered<_>>();
 
     let mut identities = Vec::new();
     while let Some(res) = tasks.next().await {
@@ -297,23 +540,675 @@ pub async fn parse_staking() -> Option<Vec<SubscanOperation>> {
         .import_or_update_identities(identities)
         .await;
 
-    Some(subscan_operations)
+    // a page of the history says nothing about the newest extrinsics, an incomplete one is
+    // walked again as a whole
+    if config.backfill_page.is_some() {
+        return is_complete.then_some((subscan_operations, None));
+    }
+
+    // a few wallets say nothing about the newest extrinsics of the network
+    if !config.is_checkpointed() {
+        return Some((subscan_operations, None));
+    }
+
+    if !is_complete {
+        error!(target: "subscan_parser", "Staking run incomplete, keeping the previous checkpoint.");
+        return Some((subscan_operations, None));
+    }
+
+    Some((subscan_operations, checkpoint))
 }
 
+// first validator nominated at the time of the operations, loaded for all nominators at once
+async fn set_validators(
+    mongodb_client_validator: &mut MongoDbClientValidator,
+    subscan_operations: &mut [SubscanOperation],
+) {
+    let nominators = subscan_operations
+        .iter()
+        .map(|s| s.from_wallet.clone())
+        .unique()
+        .collect::<Vec<_>>();
+    let histories = mongodb_client_validator
+        .get_nominations_histories(nominators)
+        .await;
+
+    for s in subscan_operations.iter_mut() {
+        let Some(history) = histories.get(&s.from_wallet) else {
+            continue;
+        };
+        let Some(version) = get_validator_at(history, s.operation_timestamp) else {
+            continue;
+        };
+        let Some(to_wallet) = version.validators.first() else {
+            continue;
+        };
+        s.to_wallet = to_wallet.clone();
+    }
+}
+
+// version valid at the timestamp of a history sorted by valid_from, freshly bonded funds are
+// nominated right after the bond, so when nothing was nominated yet the first later one is used
+fn get_validator_at(history: &[Validator], timestamp: DateTime) -> Option<&Validator> {
+    history
+        .iter()
+        .rev()
+        .find(|v| v.valid_from <= timestamp && !matches!(v.valid_to, Some(t) if t <= timestamp))
+        .or_else(|| history.iter().find(|v| v.valid_from > timestamp))
+}
+
+// validators nominated by the addresses, the recent nominations of the network are searched
+// first and only the addresses missing there are queried one by one, with their batch_all calls
+// too if asked for
+async fn get_nominations_of(
+    config: &StakingParserConfig,
+    subscan_parser: &SubscanParser,
+    recent_nominations: &mut RecentNominations,
+    addresses: Vec<String>,
+    with_batch_all: bool,
+) -> Vec<Validator> {
+    let (mut validators, not_found) = recent_nominations.split(subscan_parser, addresses).await;
+    let task_limit = &config.task_limit;
+
+    let calls = if with_batch_all {
+        vec![true, false]
+    } else {
+        vec![false]
+    };
+    let mut tasks = not_found
+        .into_iter()
+        .flat_map(|address| calls.iter().map(move |c| (address.clone(), *c)))
+        .map(|(address, is_batch_all)| {
+            let mut subscan_parser = subscan_parser.clone();
+            task_limit.spawn(async move {
+                if is_batch_all {
+                    subscan_parser
+                        .parse_subscan_batch_all_pages(&address, &Pagination::from_env())
+                        .await
+                } else {
+                    subscan_parser
+                        .parse_subscan_operations(
+                            &address,
+                            Module::Staking,
+                            ExtrinsicsType::Nominate,
+                            0,
+                            1,
+                        )
+                        .await
+                }
+            })
+        })
+        .collect::<FuturesUnordered<_>>();
+
+    while let Some(res) = tasks.next().await {
+        let Ok(s) = res else {
+            continue;
+        };
+
+        let s = match s {
+            Ok(s) => s,
+            Err(e) => {
+                error!(target: "subscan_parser", "Nominations error: {e}.");
+                continue;
+            }
+        };
+
+        let s = s
+            .process(&config.network, subscan_parser.get_address_format())
+            .await;
+        let mut v = convert_operations_to_validators(s);
+        validators.append(&mut v);
+    }
+
+    validators
+}
+
+// nominate and batch_all extrinsics of the whole network from the last NOMINATIONS_LOOKBACK_HOURS,
+// walked once when first needed and shared by every lookup of the run
+#[derive(Default)]
+struct RecentNominations {
+    nominations: Option<Vec<SubscanOperation>>,
+}
+
+impl RecentNominations {
+    // validators of the addresses and the addresses which aren't among them
+    async fn split(
+        &mut self,
+        subscan_parser: &SubscanParser,
+        addresses: Vec<String>,
+    ) -> (Vec<Validator>, Vec<String>) {
+        if addresses.is_empty() {
+            return (Vec::new(), addresses);
+        }
+
+        if self.nominations.is_none() {
+            self.nominations = Some(get_recent_nominations(subscan_parser.clone()).await);
+        }
+
+        split_nominations(addresses, self.nominations.as_deref().unwrap_or_default())
+    }
+}
+
+async fn get_recent_nominations(mut subscan_parser: SubscanParser) -> Vec<SubscanOperation> {
+    let lookback_hours = env::var("NOMINATIONS_LOOKBACK_HOURS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_NOMINATIONS_LOOKBACK_HOURS);
+    let min_timestamp =
+        DateTime::from_millis(DateTime::now().timestamp_millis() - lookback_hours * 3_600_000);
+    let pagination = Pagination::from_env().with_min_timestamp(min_timestamp);
+
+    let mut parsed = ParsedOperations::default();
+    match subscan_parser
+        .parse_subscan_operations_pages("", Module::Staking, ExtrinsicsType::Nominate, &pagination)
+        .await
+    {
+        Ok(n) => parsed.append(n),
+        Err(e) => error!(target: "subscan_parser", "Recent nominations error: {e}."),
+    }
+    match subscan_parser
+        .parse_subscan_batch_all_pages("", &pagination)
+        .await
+    {
+        Ok(n) => parsed.append(n),
+        Err(e) => error!(target: "subscan_parser", "Recent batch all error: {e}."),
+    }
+
+    parsed
+        .process(
+            subscan_parser.get_network(),
+            subscan_parser.get_address_format(),
+        )
+        .await
+}
+
+// wallets whose payouts and slashes are looked up, the ones staking in the run when the whole
+// network is walked
+fn get_reward_wallets(
+    config: &StakingParserConfig,
+    subscan_operations: &[SubscanOperation],
+) -> Vec<String> {
+    if !config.addresses.is_empty() {
+        return config.addresses.clone();
+    }
+
+    subscan_operations
+        .iter()
+        .map(|s| s.from_wallet.clone())
+        .filter(|w| !SubscanParser::is_address_empty(w))
+        .unique()
+        .collect()
+}
+
+// the same pages of every wallet as of its extrinsics, false if one of them failed
+async fn get_reward_slash_operations<A: SubscanApi + 'static>(
+    subscan_parser: &SubscanParser<A>,
+    task_limit: &TaskLimit,
+    wallets: &[String],
+    pages: Range<u32>,
+    rows_per_page: u32,
+) -> (ParsedOperations, bool) {
+    let mut tasks = wallets
+        .iter()
+        .flat_map(|wallet| pages.clone().map(move |page| (wallet.clone(), page)))
+        .map(|(wallet, page)| {
+            let mut subscan_parser = subscan_parser.clone();
+            task_limit.spawn(async move {
+                subscan_parser
+                    .parse_subscan_reward_slash(&wallet, page, rows_per_page)
+                    .await
+            })
+        })
+        .collect::<FuturesUnordered<_>>();
+
+    let mut is_complete = true;
+    let mut parsed = ParsedOperations::default();
+    while let Some(res) = tasks.next().await {
+        let Ok(r) = res else {
+            is_complete = false;
+            continue;
+        };
+
+        match r {
+            Ok(r) => parsed.append(r),
+            Err(e) => {
+                error!(target: "subscan_parser", "Reward slash error: {e}.");
+                is_complete = false;
+            }
+        }
+    }
+
+    (parsed, is_complete)
+}
+
+// nominations of the controllers count for the stashes they control
+fn assign_to_stashes(
+    controlled_stashes: &[(String, String)],
+    controller_validators: &[Validator],
+) -> Vec<Validator> {
+    controlled_stashes
+        .iter()
+        .flat_map(|(stash, controller)| {
+            controller_validators
+                .iter()
+                .filter(move |v| v.nominator == *controller)
+                .map(|v| Validator {
+                    nominator: stash.clone(),
+                    ..v.clone()
+                })
+        })
+        .collect()
+}
+
+fn split_nominations(
+    nominators: Vec<String>,
+    nominations: &[SubscanOperation],
+) -> (Vec<Validator>, Vec<String>) {
+    let wanted = nominators.iter().collect::<HashSet<_>>();
+    let validators = convert_operations_to_validators(
+        nominations
+            .iter()
+            .filter(|n| wanted.contains(&n.from_wallet))
+            .cloned()
+            .collect(),
+    );
+
+    let found = validators
+        .iter()
+        .map(|v| v.nominator.clone())
+        .collect::<HashSet<_>>();
+    let not_found = nominators
+        .into_iter()
+        .filter(|n| !found.contains(n))
+        .collect();
+
+    (validators, not_found)
+}
+
+// the event every staking extrinsic but nominate emits
+#[derive(Clone, Debug, PartialEq)]
+struct StakingEvent {
+    stash: String,
+    amount: Decimal,
+    planck: Balance,
+}
+
+impl FromEvents for StakingEvent {
+    fn get_matcher() -> EventMatcher {
+        EventMatcher::new("staking", "Bonded")
+            .event("Unbonded")
+            .event("Withdrawn")
+            .field("stash")
+            .alias("who")
+            .field("amount")
+    }
+
+    fn from_fields(fields: &EventFields, network: &Network) -> Option<Self> {
+        let amount_param = fields.get("amount")?;
+
+        Some(Self {
+            stash: fields.get("stash")?.as_account(network)?,
+            amount: amount_param.as_balance(network.get_decimals())?,
+            planck: amount_param.as_planck()?,
+        })
+    }
+}
+
+// pool extrinsics emit staking events of the pool account too, the member comes from the pool event
+#[derive(Clone, Debug, PartialEq)]
+struct PoolEvent(StakingEvent);
+
+impl FromEvents for PoolEvent {
+    fn get_matcher() -> EventMatcher {
+        EventMatcher::new("nominationpools", "Bonded")
+            .event("Unbonded")
+            .event("Withdrawn")
+            .event("PaidOut")
+            .field("member")
+            .field("amount")
+            .alias("bonded")
+            .alias("balance")
+            .alias("payout")
+    }
+
+    fn from_fields(fields: &EventFields, network: &Network) -> Option<Self> {
+        let amount_param = fields.get("amount")?;
+
+        Some(Self(StakingEvent {
+            stash: fields.get("member")?.as_account(network)?,
+            amount: amount_param.as_balance(network.get_decimals())?,
+            planck: amount_param.as_planck()?,
+        }))
+    }
+}
+
+fn get_staking_event(events: &[SubscanEvent], network: &Network) -> Option<StakingEvent> {
+    PoolEvent::from_events(events, network)
+        .map(|p| p.0)
+        .or_else(|| StakingEvent::from_events(events, network))
+}
+
+// stash and planck the operation of a staking extrinsic is built from
+pub fn get_staking_amount(events: &[SubscanEvent], network: &Network) -> Option<(String, Balance)> {
+    get_staking_event(events, network).map(|e| (e.stash, e.planck))
+}
+
+// stash and amount come from the staking or pool event of the extrinsic, the stash is
+// written in the format of the other wallets of the operation
+fn enrich_with_staking_event(
+    mut s: SubscanOperation,
+    events: &[SubscanEvent],
+    network: &Network,
+    address_format: AddressFormat,
+) -> Option<SubscanOperation> {
+    let staking_event = get_staking_event(events, network)?;
+
+    s.from_wallet = address_format.format(network, &staking_event.stash)?;
+    s.operation_quantity = staking_event.amount.to_f64()?;
+    s.operation_planck = Some(staking_event.planck);
+
+    Some(s)
+}
+
+// every target of a nominate call, operations parsed before targets were kept only have to_wallet
 fn convert_operations_to_validators(source: Vec<SubscanOperation>) -> Vec<Validator> {
     source
         .into_iter()
         .filter_map(|p| {
-            if SubscanParser::is_address_empty(&p.to_wallet)
+            if SubscanParser::is_address_empty(&p.from_wallet)
                 || SubscanParser::is_address_empty(&p.to_wallet)
             {
                 return None;
             }
 
+            let validators = if p.nomination_targets.is_empty() {
+                vec![p.to_wallet]
+            } else {
+                p.nomination_targets
+            };
             Some(Validator {
                 nominator: p.from_wallet,
-                validator: p.to_wallet,
+                validators,
+                valid_from: p.operation_timestamp,
+                valid_to: None,
+                extrinsic_index: Some(p.extrinsic_index),
             })
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        address::{to_hex, AddressFormat},
+        subscan_api::MockSubscanApi,
+        subscan_parser::{Network, SubscanParser, EMPTY_ADDRESS},
+        subscan_scheduler::SubscanEndpoint,
+        subscan_stake_parser::{
+            assign_to_stashes, enrich_with_staking_event, get_reward_slash_operations,
+            get_reward_wallets, get_validator_at, split_nominations, PriceSource,
+            StakingParserConfig,
+        },
+        task_limit::TaskLimit,
+        OperationStatus, OperationType, SubscanEvent, SubscanEventParam, SubscanOperation,
+        Validator,
+    };
+    use bson::DateTime;
+    use serde_json::{json, Value};
+
+    fn nomination(from_wallet: &str, to_wallet: &str) -> SubscanOperation {
+        SubscanOperation {
+            hash: String::new(),
+            hash_version: 0,
+            block_number: 1,
+            extrinsic_index: "1-1".to_string(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+            operation_quantity: 0.0,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 0.0,
+            operation_type: OperationType::ReStake,
+            from_wallet: from_wallet.to_string(),
+            controller_wallet: EMPTY_ADDRESS.to_string(),
+            to_wallet: to_wallet.to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn nominators_without_recent_nominations_are_left_over() {
+        let nominators = ["alice", "bob", "carol", "erin"].map(String::from).to_vec();
+        let mut multi_target = nomination("erin", "first");
+        multi_target.nomination_targets = vec!["first".to_string(), "second".to_string()];
+        let nominations = vec![
+            nomination("alice", "validator"),
+            nomination("dave", "validator"),
+            // bonded without nominating
+            nomination("bob", EMPTY_ADDRESS),
+            multi_target,
+        ];
+
+        let (validators, not_found) = split_nominations(nominators, &nominations);
+        assert_eq!(
+            validators
+                .iter()
+                .map(|v| (v.nominator.as_str(), v.validators.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("alice", vec!["validator".to_string()]),
+                ("erin", vec!["first".to_string(), "second".to_string()])
+            ]
+        );
+        assert_eq!(not_found, vec!["bob".to_string(), "carol".to_string()]);
+    }
+
+    #[test]
+    fn controller_nominations_count_for_every_stash() {
+        let (controller_validators, _) = split_nominations(
+            vec!["controller".to_string()],
+            &[nomination("controller", "v1")],
+        );
+        let controlled_stashes = [
+            ("stash".to_string(), "controller".to_string()),
+            ("other_stash".to_string(), "controller".to_string()),
+            ("lonely_stash".to_string(), "unknown".to_string()),
+        ];
+
+        let validators = assign_to_stashes(&controlled_stashes, &controller_validators);
+        assert_eq!(
+            validators
+                .iter()
+                .map(|v| (v.nominator.as_str(), v.validators.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("stash", vec!["v1".to_string()]),
+                ("other_stash", vec!["v1".to_string()])
+            ]
+        );
+    }
+
+    #[test]
+    fn validators_are_looked_up_in_the_history() {
+        let version = |validator: &str, valid_from: i64, valid_to: Option<i64>| Validator {
+            nominator: "alice".to_string(),
+            validators: vec![validator.to_string(), "other".to_string()],
+            valid_from: DateTime::from_millis(valid_from),
+            valid_to: valid_to.map(DateTime::from_millis),
+            extrinsic_index: None,
+        };
+        let history = vec![version("first", 10, Some(20)), version("second", 20, None)];
+        let get_validator = |timestamp: i64| {
+            get_validator_at(&history, DateTime::from_millis(timestamp))
+                .and_then(|v| v.validators.first())
+                .map(String::as_str)
+        };
+
+        assert_eq!(get_validator(10), Some("first"));
+        assert_eq!(get_validator(19), Some("first"));
+        assert_eq!(get_validator(20), Some("second"));
+        assert_eq!(get_validator(1_000), Some("second"));
+
+        // bonded before the first nomination
+        assert_eq!(get_validator(5), Some("first"));
+        assert_eq!(get_validator_at(&[], DateTime::from_millis(5)), None);
+    }
+
+    #[test]
+    fn pool_members_are_taken_from_the_pool_event() {
+        static MEMBER: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+        static POOL: &str = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+        let event = |module_id: &str, params: [(&str, &str, &str); 2]| SubscanEvent {
+            module_id: module_id.to_string(),
+            event_id: "Bonded".to_string(),
+            event_index: "1-1".to_string(),
+            event_params: params
+                .iter()
+                .map(|(type_name, name, value)| SubscanEventParam {
+                    type_name: type_name.to_string(),
+                    value: Value::from(*value),
+                    name: name.to_string(),
+                })
+                .collect(),
+        };
+
+        let staking_event = event(
+            "staking",
+            [
+                ("AccountId", "stash", POOL),
+                ("BalanceOf", "amount", "1500000000000"),
+            ],
+        );
+        let pool_event = event(
+            "nominationpools",
+            [
+                ("AccountId", "member", MEMBER),
+                ("BalanceOf", "bonded", "1500000000000"),
+            ],
+        );
+
+        let operation = nomination(EMPTY_ADDRESS, EMPTY_ADDRESS);
+        let joined = enrich_with_staking_event(
+            operation.clone(),
+            &[staking_event.clone(), pool_event],
+            &Network::Alephzero,
+            AddressFormat::Native,
+        )
+        .unwrap();
+        assert_eq!(joined.from_wallet, MEMBER);
+        assert_eq!(joined.operation_quantity, 1.5);
+
+        let bonded = enrich_with_staking_event(
+            operation,
+            &[staking_event],
+            &Network::Alephzero,
+            AddressFormat::Hex,
+        )
+        .unwrap();
+        assert_eq!(bonded.from_wallet, to_hex(POOL).unwrap());
+    }
+
+    #[tokio::test]
+    async fn fixed_prices_skip_the_exchanges() {
+        let config = StakingParserConfig {
+            network: Network::Polkadot,
+            api_keys: Some("key".to_string()),
+            rows_per_page: 10,
+            pages_to_scan: 2,
+            task_limit: TaskLimit::new(4),
+            price_source: PriceSource::Fixed(2.5),
+            backfill_page: None,
+            addresses: Vec::new(),
+        };
+        let mut operations = vec![nomination("alice", "validator")];
+        operations[0].operation_quantity = 100.0;
+
+        let price = config.get_current_price().await.unwrap();
+        config.set_prices(&mut operations, price).await;
+        assert_eq!(operations[0].operation_usd, 250.0);
+    }
+
+    #[test]
+    fn address_runs_leave_the_checkpoint_alone() {
+        let config = StakingParserConfig {
+            network: Network::Polkadot,
+            api_keys: None,
+            rows_per_page: 10,
+            pages_to_scan: 1,
+            task_limit: TaskLimit::new(4),
+            price_source: PriceSource::Fixed(2.5),
+            backfill_page: None,
+            addresses: Vec::new(),
+        };
+        assert!(config.is_checkpointed());
+        assert_eq!(config.get_addresses(), vec![String::new()]);
+
+        let wallets = vec!["alice".to_string(), "bob".to_string()];
+        let config = config.with_addresses(wallets.clone());
+        assert!(!config.is_checkpointed());
+        assert_eq!(config.get_addresses(), wallets);
+    }
+
+    #[tokio::test]
+    async fn rewards_and_slashes_of_the_run_wallets_are_parsed() {
+        let config = StakingParserConfig {
+            network: Network::Alephzero,
+            api_keys: Some("fixture".to_string()),
+            rows_per_page: 10,
+            pages_to_scan: 1,
+            task_limit: TaskLimit::new(4),
+            price_source: PriceSource::Fixed(2.5),
+            backfill_page: None,
+            addresses: Vec::new(),
+        };
+        let operations = vec![
+            nomination("alice", "validator"),
+            nomination("alice", "validator"),
+            nomination(EMPTY_ADDRESS, "validator"),
+        ];
+        let wallets = get_reward_wallets(&config, &operations);
+        assert_eq!(wallets, vec!["alice".to_string()]);
+
+        let rows = json!({"code": 0, "message": "Success", "data": {"list": [
+            {
+                "event_id": "Rewarded",
+                "block_num": 58123460,
+                "block_timestamp": 1_700_000_240,
+                "account": "alice",
+                "amount": "600000000000000",
+                "era": 943,
+                "extrinsic_index": "58123460-1",
+                "event_index": "58123460-7",
+                "validator_stash": "validator",
+            },
+            {"event_id": "Slashed", "account": "alice"},
+        ]}});
+        let api = MockSubscanApi::new().with_response(SubscanEndpoint::RewardSlash, rows);
+        let subscan_parser = SubscanParser::with_api(Network::Alephzero, api.clone())
+            .with_address_format(AddressFormat::Native)
+            .with_api_keys(config.api_keys.clone());
+
+        let (parsed, is_complete) = get_reward_slash_operations(
+            &subscan_parser,
+            &config.task_limit,
+            &wallets,
+            0..1,
+            config.rows_per_page,
+        )
+        .await;
+        assert!(is_complete);
+        assert_eq!(parsed.operations.len(), 1);
+        assert_eq!(parsed.operations[0].operation_type, OperationType::Reward);
+        assert_eq!(parsed.operations[0].to_wallet, "alice");
+        assert_eq!(parsed.operations[0].call_index, 7);
+        assert_eq!(parsed.quarantined.len(), 1);
+        assert_eq!(
+            api.get_requests(SubscanEndpoint::RewardSlash),
+            vec![json!({"address": "alice", "row": 10, "page": 0})]
+        );
+    }
+}
@@ -1,6 +1,10 @@
 use crate::{
+    mock_network::MOCK_AZERO_USD_PRICE,
     mongodb_client_identities::MongoDbClientIdentity,
-    subscan_parser::{Network, SubscanParser},
+    operation_prices::set_operation_prices,
+    pipeline_error::{ErrorCode, PipelineError},
+    subscan_parser::{Network, ParsedOperations, SubscanParser},
+    task_limit::TaskLimit,
     SubscanOperation, MINIMUM_AZERO_TO_SAVE_TO_DB,
 };
 use futures::{stream::FuturesUnordered, StreamExt};
@@ -9,8 +13,10 @@ use rs_exchanges_parser::{
     mongodb_client_exchanges::MongoDbClientExchanges, PrimaryToken, SecondaryToken,
 };
 use std::collections::HashSet;
+use tracing::error;
 
-pub async fn parse_transfers() -> Option<Vec<SubscanOperation>> {
+// the pages are fetched within the task limit, pass the one of the staking parser to share it
+pub async fn parse_transfers(task_limit: &TaskLimit) -> Option<Vec<SubscanOperation>> {
     let price_task = tokio::spawn(async move {
         let mut mongodb_client_exchanges = MongoDbClientExchanges::new().await;
         mongodb_client_exchanges
@@ -18,29 +24,38 @@ pub async fn parse_transfers() -> Option<Vec<SubscanOperation>> {
             .await
     });
 
+    // clones share the connection pool
+    let subscan_parser = SubscanParser::new(Network::from_env()).await;
     let mut tasks = FuturesUnordered::new();
     for page in 0..10 {
-        tasks.push(tokio::spawn(async move {
-            let mut subscan_parser = SubscanParser::new(Network::Alephzero).await;
-            subscan_parser.parse_subscan_transfers(page, 100).await
-        }));
+        let mut subscan_parser = subscan_parser.clone();
+        tasks.push(
+            task_limit
+                .spawn(async move { subscan_parser.parse_subscan_transfers("", page, 100).await }),
+        );
     }
 
-    let mut subscan_operations = Vec::new();
+    let mut parsed = ParsedOperations::default();
     let mut identities = HashSet::new();
     while let Some(res) = tasks.next().await {
         let Ok(s) = res else {
             continue;
         };
 
-        let Some((mut s, d)) = s else {
+        let Some((s, d)) = s else {
             continue;
         };
-        subscan_operations.append(&mut s);
+        parsed.append(s);
         for dd in d {
             identities.insert(dd);
         }
     }
+    let subscan_operations = parsed
+        .process(
+            subscan_parser.get_network(),
+            subscan_parser.get_address_format(),
+        )
+        .await;
 
     let identities = identities.into_iter().collect_vec();
 
@@ -50,12 +65,25 @@ pub async fn parse_transfers() -> Option<Vec<SubscanOperation>> {
         .filter(|p| p.operation_quantity > MINIMUM_AZERO_TO_SAVE_TO_DB)
         .collect::<Vec<_>>();
 
-    // updating to current price
-    let price = price_task.await.ok()??;
+    // current price is the fallback for operations without a historical one, mock network
+    // runs without exchanges data
+    let price = match price_task.await.ok()? {
+        Some(price) => price,
+        None if Network::from_env() == Network::Mock => MOCK_AZERO_USD_PRICE,
+        None => {
+            let pipeline_error = PipelineError::new(
+                ErrorCode::PriceUnavailable,
+                "no current AZERO price",
+                &Network::from_env(),
+            );
+            error!(target: "subscan_parser", "Parse error: {}.", pipeline_error.to_json());
+            return None;
+        }
+    };
+    let network = Network::from_env();
+    set_operation_prices(&mut subscan_operations, price, &network).await;
     for s in subscan_operations.iter_mut() {
-        s.operation_usd = s.operation_quantity * price;
-
-        s.set_hash();
+        s.set_hash(&network);
     }
 
     // saving newly parsed identities
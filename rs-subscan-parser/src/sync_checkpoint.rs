@@ -0,0 +1,111 @@
+use crate::{
+    mongodb_client_checkpoints::MongoDbClientCheckpoints, subscan_parser::Network,
+    SubscanOperation, SyncCheckpoint,
+};
+use bson::DateTime;
+
+// extrinsics are ordered by block and by their index within it, "42-10" comes after "42-3"
+fn get_position(block_number: u64, extrinsic_index: &str) -> (u64, u64) {
+    let index = extrinsic_index
+        .rsplit_once('-')
+        .and_then(|(_, i)| i.parse::<u64>().ok())
+        .unwrap_or_default();
+
+    (block_number, index)
+}
+
+// checkpoint of the newest operation, never older than the previous checkpoint
+pub fn get_latest_checkpoint(
+    network: &Network,
+    operations: &[SubscanOperation],
+    previous: Option<SyncCheckpoint>,
+) -> Option<SyncCheckpoint> {
+    let latest = operations
+        .iter()
+        .max_by_key(|s| get_position(s.block_number, &s.extrinsic_index))
+        .map(|s| SyncCheckpoint {
+            network: network.get_slug().to_string(),
+            block_number: s.block_number,
+            extrinsic_index: s.extrinsic_index.clone(),
+            updated_at: DateTime::now(),
+        });
+
+    [previous, latest]
+        .into_iter()
+        .flatten()
+        .max_by_key(|c| get_position(c.block_number, &c.extrinsic_index))
+}
+
+pub async fn load_checkpoint(network: &Network) -> Option<SyncCheckpoint> {
+    let mut mongodb_client_checkpoints = MongoDbClientCheckpoints::new().await;
+    mongodb_client_checkpoints
+        .get_checkpoint(network.get_slug())
+        .await
+}
+
+// called once the operations up to it are stored, none keeps the previous one
+pub async fn save_checkpoint(checkpoint: Option<&SyncCheckpoint>) {
+    let Some(checkpoint) = checkpoint else {
+        return;
+    };
+
+    let mut mongodb_client_checkpoints = MongoDbClientCheckpoints::new().await;
+    mongodb_client_checkpoints.save_checkpoint(checkpoint).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        subscan_parser::Network, sync_checkpoint::get_latest_checkpoint, OperationStatus,
+        OperationType, SubscanOperation, SyncCheckpoint,
+    };
+    use bson::DateTime;
+
+    fn operation(extrinsic_index: &str) -> SubscanOperation {
+        let block_number = extrinsic_index.split('-').next().unwrap().parse().unwrap();
+        SubscanOperation {
+            hash: extrinsic_index.to_string(),
+            hash_version: 0,
+            block_number,
+            extrinsic_index: extrinsic_index.to_string(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_000),
+            operation_quantity: 1.0,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 1.0,
+            operation_type: OperationType::Stake,
+            from_wallet: "from".to_string(),
+            controller_wallet: "from".to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn checkpoint_moves_to_the_newest_extrinsic_only() {
+        let operations = vec![operation("42-3"), operation("42-10"), operation("41-20")];
+        let checkpoint = get_latest_checkpoint(&Network::Alephzero, &operations, None).unwrap();
+        assert_eq!(
+            (checkpoint.network.as_str(), checkpoint.block_number),
+            ("alephzero", 42)
+        );
+        assert_eq!(checkpoint.extrinsic_index, "42-10");
+
+        // a run which only saw older extrinsics keeps the previous checkpoint
+        let previous = SyncCheckpoint {
+            network: "alephzero".to_string(),
+            block_number: 50,
+            extrinsic_index: "50-1".to_string(),
+            updated_at: DateTime::from_millis(0),
+        };
+        assert_eq!(
+            get_latest_checkpoint(&Network::Alephzero, &operations, Some(previous.clone())),
+            Some(previous)
+        );
+        assert_eq!(get_latest_checkpoint(&Network::Alephzero, &[], None), None);
+    }
+}
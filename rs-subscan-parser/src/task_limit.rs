@@ -0,0 +1,102 @@
+use std::{future::Future, sync::Arc};
+use tokio::{sync::Semaphore, task::JoinHandle};
+use tracing::Instrument;
+
+// caps the spawned tasks doing work at once, clones share the permits so parsers running side
+// by side stay within one budget
+#[derive(Clone, Debug)]
+pub struct TaskLimit {
+    semaphore: Arc<Semaphore>,
+    limit: usize,
+}
+
+impl TaskLimit {
+    pub fn new(limit: usize) -> Self {
+        let limit = limit.max(1);
+        Self {
+            semaphore: Arc::new(Semaphore::new(limit)),
+            limit,
+        }
+    }
+
+    pub fn get_limit(&self) -> usize {
+        self.limit
+    }
+
+    // the task waits for a permit before the future is polled and holds it until it is done,
+    // so nothing of the future runs while the limit is reached, it logs within the span of
+    // its caller
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let semaphore = self.semaphore.clone();
+        tokio::spawn(
+            async move {
+                let _permit = semaphore.acquire_owned().await;
+                future.await
+            }
+            .in_current_span(),
+        )
+    }
+}
+
+// limits are the same when they share their permits
+impl PartialEq for TaskLimit {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.semaphore, &other.semaphore)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::task_limit::TaskLimit;
+    use futures::{stream::FuturesUnordered, StreamExt};
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+    use tokio::time::sleep;
+
+    #[tokio::test]
+    async fn shared_limits_cap_running_tasks() {
+        let task_limit = TaskLimit::new(3);
+        let other_parser_limit = task_limit.clone();
+        assert_eq!(task_limit, other_parser_limit);
+        assert_ne!(task_limit, TaskLimit::new(3));
+
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let mut tasks = (0..12)
+            .map(|i| {
+                let running = running.clone();
+                let max_running = max_running.clone();
+                let limit = if i % 2 == 0 {
+                    &task_limit
+                } else {
+                    &other_parser_limit
+                };
+                limit.spawn(async move {
+                    let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(now_running, Ordering::SeqCst);
+                    sleep(Duration::from_millis(10)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    i
+                })
+            })
+            .collect::<FuturesUnordered<_>>();
+
+        let mut finished = 0;
+        while let Some(res) = tasks.next().await {
+            assert!(res.is_ok());
+            finished += 1;
+        }
+        assert_eq!(finished, 12);
+        assert_eq!(max_running.load(Ordering::SeqCst), 3);
+        assert_eq!(TaskLimit::new(0).get_limit(), 1);
+    }
+}
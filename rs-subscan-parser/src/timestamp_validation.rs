@@ -0,0 +1,300 @@
+use crate::{subscan_parser::Network, SubscanOperation};
+use bson::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{env, str::FromStr, sync::OnceLock};
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+
+static DEFAULT_TIMESTAMP_SKEW_TOLERANCE_SECONDS: i64 = 300;
+
+// 1e11 seconds is the year 5138 and 1e11 milliseconds is 1973, chain timestamps are between
+static MIN_MILLIS_TIMESTAMP: i64 = 100_000_000_000;
+
+static TIMESTAMP_UNIT: OnceLock<TimestampUnit> = OnceLock::new();
+
+// unit of the block_timestamp of subscan records, seconds on most endpoints
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum TimestampUnit {
+    // told apart by magnitude, record by record
+    #[default]
+    Auto,
+    Seconds,
+    Milliseconds,
+}
+
+impl TimestampUnit {
+    // SUBSCAN_TIMESTAMP_UNIT=auto|seconds|milliseconds
+    pub fn from_env() -> TimestampUnit {
+        env::var("SUBSCAN_TIMESTAMP_UNIT")
+            .ok()
+            .and_then(|u| TimestampUnit::from_str(u.trim()).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn global() -> TimestampUnit {
+        *TIMESTAMP_UNIT.get_or_init(TimestampUnit::from_env)
+    }
+
+    // none for timestamps before the epoch, no block has one
+    pub fn to_millis(&self, timestamp: i64) -> Option<i64> {
+        if timestamp < 0 {
+            return None;
+        }
+
+        match self {
+            TimestampUnit::Seconds => timestamp.checked_mul(1_000),
+            TimestampUnit::Milliseconds => Some(timestamp),
+            TimestampUnit::Auto if timestamp >= MIN_MILLIS_TIMESTAMP => Some(timestamp),
+            TimestampUnit::Auto => Some(timestamp * 1_000),
+        }
+    }
+}
+
+// block_timestamp of a subscan record in SUBSCAN_TIMESTAMP_UNIT
+pub fn get_block_timestamp(d: &Value) -> Option<DateTime> {
+    let timestamp = d.get("block_timestamp")?.as_i64()?;
+    let millis = TimestampUnit::global().to_millis(timestamp)?;
+
+    Some(DateTime::from_millis(millis))
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum TimestampSkewAction {
+    // logged and kept
+    #[default]
+    Flag,
+
+    // quarantined before any sink
+    Reject,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimestampValidation {
+    pub tolerance_ms: i64,
+    pub action: TimestampSkewAction,
+}
+
+impl TimestampValidation {
+    // TIMESTAMP_SKEW_TOLERANCE_SECONDS and TIMESTAMP_SKEW_ACTION=flag|reject
+    pub fn from_env() -> TimestampValidation {
+        let tolerance_seconds = env::var("TIMESTAMP_SKEW_TOLERANCE_SECONDS")
+            .ok()
+            .and_then(|t| t.parse::<i64>().ok())
+            .filter(|t| *t > 0)
+            .unwrap_or(DEFAULT_TIMESTAMP_SKEW_TOLERANCE_SECONDS);
+        let action = env::var("TIMESTAMP_SKEW_ACTION")
+            .ok()
+            .and_then(|a| TimestampSkewAction::from_str(a.trim()).ok())
+            .unwrap_or_default();
+
+        Self {
+            tolerance_ms: tolerance_seconds * 1_000,
+            action,
+        }
+    }
+
+    // none if the timestamp is in the future or off the time the block number implies,
+    // the expectation is the median of the batch so a single glitch cannot shift it
+    pub fn get_skew_reason(
+        &self,
+        operation: &SubscanOperation,
+        expected_offset_ms: i64,
+        block_time_ms: i64,
+        now: DateTime,
+    ) -> Option<String> {
+        let timestamp_ms = operation.operation_timestamp.timestamp_millis();
+        if timestamp_ms > now.timestamp_millis() + self.tolerance_ms {
+            return Some(format!(
+                "timestamp {} is in the future",
+                operation.operation_timestamp
+            ));
+        }
+
+        let skew_ms = get_offset_ms(operation, block_time_ms) - expected_offset_ms;
+        if skew_ms.abs() > self.tolerance_ms {
+            return Some(format!(
+                "timestamp {} is {} s off block {}",
+                operation.operation_timestamp,
+                skew_ms / 1_000,
+                operation.block_number
+            ));
+        }
+
+        None
+    }
+
+    // one reason per operation, in order
+    pub fn get_skew_reasons(
+        &self,
+        operations: &[SubscanOperation],
+        now: DateTime,
+    ) -> Vec<Option<String>> {
+        let block_time_ms = Network::from_env().get_block_time_ms();
+        let Some(expected_offset_ms) = get_expected_offset_ms(operations, block_time_ms) else {
+            return Vec::new();
+        };
+
+        operations
+            .iter()
+            .map(|o| self.get_skew_reason(o, expected_offset_ms, block_time_ms, now))
+            .collect()
+    }
+}
+
+// time of block zero as implied by an operation
+fn get_offset_ms(operation: &SubscanOperation, block_time_ms: i64) -> i64 {
+    operation.operation_timestamp.timestamp_millis() - operation.block_number as i64 * block_time_ms
+}
+
+fn get_expected_offset_ms(operations: &[SubscanOperation], block_time_ms: i64) -> Option<i64> {
+    let mut offsets = operations
+        .iter()
+        .map(|o| get_offset_ms(o, block_time_ms))
+        .collect::<Vec<_>>();
+    offsets.sort_unstable();
+
+    offsets.get(offsets.len() / 2).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        timestamp_validation::{
+            get_block_timestamp, get_expected_offset_ms, TimestampSkewAction, TimestampUnit,
+            TimestampValidation,
+        },
+        OperationStatus, OperationType, SubscanOperation,
+    };
+    use bson::DateTime;
+    use serde_json::json;
+
+    fn get_operation(block_number: u64, timestamp_seconds: i64) -> SubscanOperation {
+        SubscanOperation {
+            hash: block_number.to_string(),
+            hash_version: 0,
+            block_number,
+            extrinsic_index: format!("{block_number}-1"),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(timestamp_seconds * 1_000),
+            operation_quantity: 1.0,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 1.0,
+            operation_type: OperationType::Transfer,
+            from_wallet: "from".to_string(),
+            controller_wallet: "from".to_string(),
+            to_wallet: "to".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn timestamps_off_their_block_are_skewed() {
+        let validation = TimestampValidation {
+            tolerance_ms: 60_000,
+            action: TimestampSkewAction::Reject,
+        };
+        let now = DateTime::from_millis(2_000_000 * 1_000);
+        let operations = vec![
+            get_operation(100, 1_000_100),
+            get_operation(200, 1_000_230),
+            get_operation(300, 1_000_300),
+            // indexer glitch, an hour late
+            get_operation(400, 1_004_000),
+            // block and timestamp far ahead of now
+            get_operation(1_500_000, 2_500_000),
+        ];
+
+        let expected_offset_ms = get_expected_offset_ms(&operations, 1_000).unwrap();
+        assert_eq!(expected_offset_ms, 1_000_000 * 1_000);
+
+        let skewed = operations
+            .iter()
+            .map(|o| {
+                validation
+                    .get_skew_reason(o, expected_offset_ms, 1_000, now)
+                    .is_some()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(skewed, vec![false, false, false, true, true]);
+    }
+
+    #[test]
+    fn timestamp_units_are_told_apart() {
+        let seconds = 1_700_000_000;
+        let millis = 1_700_000_000_123;
+
+        assert_eq!(
+            TimestampUnit::Auto.to_millis(seconds),
+            Some(seconds * 1_000)
+        );
+        assert_eq!(TimestampUnit::Auto.to_millis(millis), Some(millis));
+        assert_eq!(TimestampUnit::Auto.to_millis(0), Some(0));
+        assert_eq!(TimestampUnit::Auto.to_millis(-1), None);
+
+        // a configured unit wins over the magnitude
+        assert_eq!(
+            TimestampUnit::Milliseconds.to_millis(seconds),
+            Some(seconds)
+        );
+        assert_eq!(
+            TimestampUnit::Seconds.to_millis(millis),
+            Some(millis * 1_000)
+        );
+        assert_eq!(TimestampUnit::Seconds.to_millis(i64::MAX), None);
+    }
+
+    #[test]
+    fn block_timestamps_in_milliseconds_stay_in_this_century() {
+        let from_seconds = get_block_timestamp(&json!({"block_timestamp": 1_700_000_000}));
+        let from_millis = get_block_timestamp(&json!({"block_timestamp": 1_700_000_000_000i64}));
+        assert_eq!(from_seconds, from_millis);
+        assert_eq!(
+            from_millis.unwrap().try_to_rfc3339_string().unwrap(),
+            "2023-11-14T22:13:20Z"
+        );
+
+        assert_eq!(
+            get_block_timestamp(&json!({"block_timestamp": "1700000000"})),
+            None
+        );
+        assert_eq!(get_block_timestamp(&json!({})), None);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{
+    mongodb_client_operation_totals::MongoDbClientOperationTotals,
+    mongodb_client_validator::MongoDbClientValidator, OperationTotals, OperationType, TotalsView,
+    Validator,
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, env};
+
+static DEFAULT_VALIDATOR_CONCENTRATION_TOP_NOMINATORS: usize = 3;
+static DEFAULT_VALIDATOR_CONCENTRATION_MAX_SHARE: f64 = 0.5;
+
+static BONDING_OPERATION_TYPES: [OperationType; 2] =
+    [OperationType::Stake, OperationType::RequestUnstake];
+
+// VALIDATOR_CONCENTRATION_TOP_NOMINATORS, how many of the biggest nominators are summed up
+pub fn get_top_nominators() -> usize {
+    env::var("VALIDATOR_CONCENTRATION_TOP_NOMINATORS")
+        .ok()
+        .and_then(|n| n.parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_VALIDATOR_CONCENTRATION_TOP_NOMINATORS)
+}
+
+// VALIDATOR_CONCENTRATION_MAX_SHARE, validators whose top nominators bond more are flagged
+pub fn get_max_share() -> f64 {
+    env::var("VALIDATOR_CONCENTRATION_MAX_SHARE")
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|s| *s > 0.0 && *s <= 1.0)
+        .unwrap_or(DEFAULT_VALIDATOR_CONCENTRATION_MAX_SHARE)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct NominatorStake {
+    pub nominator: String,
+    pub bonded: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ValidatorConcentration {
+    pub validator: String,
+    pub nominators: u64,
+    pub bonded: f64,
+
+    // biggest first
+    pub top_nominators: Vec<NominatorStake>,
+
+    // bonded by the top nominators out of bonded by all
+    pub top_share: f64,
+    pub concentrated: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ConcentrationReport {
+    pub top_nominators: usize,
+    pub max_share: f64,
+
+    // most concentrated first
+    pub validators: Vec<ValidatorConcentration>,
+}
+
+pub async fn get_concentration_report(
+    top_nominators: usize,
+    max_share: f64,
+) -> ConcentrationReport {
+    let mut mongodb_client_validator = MongoDbClientValidator::new().await;
+    let nominations = mongodb_client_validator.get_current_nominations().await;
+
+    let nominators = nominations
+        .iter()
+        .map(|v| v.nominator.clone())
+        .unique()
+        .collect();
+    let mut mongodb_client_operation_totals = MongoDbClientOperationTotals::new().await;
+    let totals = mongodb_client_operation_totals
+        .get_totals_of_keys(&TotalsView::Wallet, nominators, &BONDING_OPERATION_TYPES)
+        .await;
+
+    build_report(
+        &nominations,
+        &get_bonded_amounts(&totals),
+        top_nominators,
+        max_share,
+    )
+}
+
+// staked minus requested to unstake by every wallet, never below zero
+pub fn get_bonded_amounts(totals: &[OperationTotals]) -> HashMap<String, f64> {
+    let mut bonded: HashMap<String, f64> = HashMap::new();
+    for t in totals.iter().filter(|t| t.view == TotalsView::Wallet) {
+        let quantity = match t.operation_type {
+            OperationType::Stake => t.quantity,
+            OperationType::RequestUnstake => -t.quantity,
+            _ => continue,
+        };
+        *bonded.entry(t.key.clone()).or_default() += quantity;
+    }
+
+    bonded.values_mut().for_each(|b| *b = b.max(0.0));
+    bonded
+}
+
+// the bond of a nominator is split evenly between its targets, the chain elects how it
+// is actually spread and that is not stored
+pub fn build_report(
+    nominations: &[Validator],
+    bonded: &HashMap<String, f64>,
+    top_nominators: usize,
+    max_share: f64,
+) -> ConcentrationReport {
+    let mut stakes: HashMap<&str, Vec<NominatorStake>> = HashMap::new();
+    for n in nominations.iter().filter(|n| n.valid_to.is_none()) {
+        let targets = n.validators.iter().unique().collect::<Vec<_>>();
+        let share = bonded.get(&n.nominator).copied().unwrap_or_default() / targets.len() as f64;
+        for validator in targets {
+            stakes
+                .entry(validator.as_str())
+                .or_default()
+                .push(NominatorStake {
+                    nominator: n.nominator.clone(),
+                    bonded: share,
+                });
+        }
+    }
+
+    let validators = stakes
+        .into_iter()
+        .map(|(validator, stakes)| {
+            let nominators = stakes.len() as u64;
+            let bonded = stakes.iter().map(|s| s.bonded).sum::<f64>();
+            let top_nominators = stakes
+                .into_iter()
+                .sorted_by(|a, b| {
+                    b.bonded
+                        .total_cmp(&a.bonded)
+                        .then(a.nominator.cmp(&b.nominator))
+                })
+                .take(top_nominators)
+                .collect::<Vec<_>>();
+            let top_bonded = top_nominators.iter().map(|s| s.bonded).sum::<f64>();
+            let top_share = if bonded > 0.0 {
+                top_bonded / bonded
+            } else {
+                0.0
+            };
+
+            ValidatorConcentration {
+                validator: validator.to_string(),
+                nominators,
+                bonded,
+                top_nominators,
+                top_share,
+                concentrated: top_share > max_share,
+            }
+        })
+        .sorted_by(|a, b| {
+            b.top_share
+                .total_cmp(&a.top_share)
+                .then(a.validator.cmp(&b.validator))
+        })
+        .collect();
+
+    ConcentrationReport {
+        top_nominators,
+        max_share,
+        validators,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        validator_concentration::{build_report, get_bonded_amounts},
+        OperationTotals, OperationType, TotalsView, Validator,
+    };
+    use bson::DateTime;
+    use std::collections::HashMap;
+
+    fn totals(wallet: &str, operation_type: OperationType, quantity: f64) -> OperationTotals {
+        OperationTotals {
+            view: TotalsView::Wallet,
+            key: wallet.to_string(),
+            operation_type,
+            count: 1,
+            quantity,
+            usd: 0.0,
+            checked_at: None,
+        }
+    }
+
+    fn nomination(nominator: &str, validators: &[&str]) -> Validator {
+        Validator {
+            nominator: nominator.to_string(),
+            validators: validators.iter().map(|v| v.to_string()).collect(),
+            valid_from: DateTime::MIN,
+            valid_to: None,
+            extrinsic_index: None,
+        }
+    }
+
+    #[test]
+    fn bonded_amounts_subtract_unstaking() {
+        let bonded = get_bonded_amounts(&[
+            totals("alice", OperationType::Stake, 1_000.0),
+            totals("alice", OperationType::RequestUnstake, 400.0),
+            totals("bob", OperationType::RequestUnstake, 100.0),
+            totals("carol", OperationType::Transfer, 100.0),
+        ]);
+
+        assert_eq!(
+            bonded,
+            HashMap::from([("alice".to_string(), 600.0), ("bob".to_string(), 0.0)])
+        );
+    }
+
+    #[test]
+    fn top_nominators_are_flagged_over_the_max_share() {
+        let nominations = [
+            nomination("alice", &["v1", "v2"]),
+            nomination("bob", &["v1"]),
+            nomination("carol", &["v2"]),
+            nomination("dave", &["v2"]),
+            Validator {
+                valid_to: Some(DateTime::MAX),
+                ..nomination("erin", &["v1"])
+            },
+        ];
+        let bonded = HashMap::from([
+            ("alice".to_string(), 1_000.0),
+            ("bob".to_string(), 100.0),
+            ("carol".to_string(), 500.0),
+            ("dave".to_string(), 500.0),
+            ("erin".to_string(), 10_000.0),
+        ]);
+
+        let report = build_report(&nominations, &bonded, 1, 0.5);
+        assert_eq!(report.validators.len(), 2);
+
+        // alice bonds 500 to each of her validators
+        let v1 = &report.validators[0];
+        assert_eq!(v1.validator, "v1");
+        assert_eq!((v1.nominators, v1.bonded), (2, 600.0));
+        assert_eq!(v1.top_nominators[0].nominator, "alice");
+        assert_eq!(v1.top_share, 500.0 / 600.0);
+        assert!(v1.concentrated);
+
+        let v2 = &report.validators[1];
+        assert_eq!((v2.nominators, v2.bonded), (3, 1_500.0));
+        assert_eq!(v2.top_nominators[0].nominator, "alice");
+        assert_eq!(v2.top_share, 1.0 / 3.0);
+        assert!(!v2.concentrated);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{
+    mongodb_client_operation_summaries::MongoDbClientOperationSummaries,
+    mongodb_client_volume_anomalies::MongoDbClientVolumeAnomalies, OperationType, VolumeAnomaly,
+    VolumeAnomalyKind,
+};
+use bson::DateTime;
+use std::{collections::HashMap, env, time::Duration};
+use strum::IntoEnumIterator;
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+static MILLIS_IN_HOUR: i64 = 60 * 60 * 1_000;
+static DEFAULT_VOLUME_BASELINE_HOURS: i64 = 7 * 24;
+static DEFAULT_VOLUME_ANOMALY_Z_SCORE: f64 = 3.0;
+static CHECK_INTERVAL_SECONDS: u64 = 60;
+
+// operations of an hour are still parsed for a few minutes after it ended
+static DETECTION_DELAY_MS: i64 = 5 * 60 * 1_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VolumeBaseline {
+    pub hours: i64,
+    pub z_score: f64,
+}
+
+impl VolumeBaseline {
+    // VOLUME_BASELINE_HOURS of history, anomalies beyond VOLUME_ANOMALY_Z_SCORE deviations
+    pub fn from_env() -> VolumeBaseline {
+        let hours = env::var("VOLUME_BASELINE_HOURS")
+            .ok()
+            .and_then(|h| h.parse::<i64>().ok())
+            .filter(|h| *h > 0)
+            .unwrap_or(DEFAULT_VOLUME_BASELINE_HOURS);
+        let z_score = env::var("VOLUME_ANOMALY_Z_SCORE")
+            .ok()
+            .and_then(|z| z.parse::<f64>().ok())
+            .filter(|z| *z > 0.0)
+            .unwrap_or(DEFAULT_VOLUME_ANOMALY_Z_SCORE);
+
+        Self { hours, z_score }
+    }
+
+    // history holds the counts of the baseline hours before the checked one
+    pub fn detect_anomaly(
+        &self,
+        operation_type: &OperationType,
+        hour: DateTime,
+        count: u64,
+        history: &[u64],
+    ) -> Option<VolumeAnomaly> {
+        if history.is_empty() {
+            return None;
+        }
+
+        let mean = history.iter().sum::<u64>() as f64 / history.len() as f64;
+        let variance = history
+            .iter()
+            .map(|c| (*c as f64 - mean).powi(2))
+            .sum::<f64>()
+            / history.len() as f64;
+        let stddev = variance.sqrt();
+
+        // counts are roughly poisson, a flat baseline must not make every change anomalous
+        let scale = stddev.max(mean.sqrt()).max(1.0);
+        let z_score = (count as f64 - mean) / scale;
+        let kind = if z_score > self.z_score {
+            VolumeAnomalyKind::Spike
+        } else if z_score < -self.z_score {
+            VolumeAnomalyKind::Drought
+        } else {
+            return None;
+        };
+
+        Some(VolumeAnomaly {
+            operation_type: operation_type.clone(),
+            hour,
+            kind,
+            count,
+            baseline_mean: mean,
+            baseline_stddev: stddev,
+            z_score,
+            detected_at: DateTime::now(),
+        })
+    }
+}
+
+fn get_hour(timestamp_ms: i64) -> i64 {
+    timestamp_ms - timestamp_ms.rem_euclid(MILLIS_IN_HOUR)
+}
+
+// hours without operations count as zero, that is what a drought looks like
+pub async fn detect_volume_anomalies(
+    hour: DateTime,
+    baseline: &VolumeBaseline,
+) -> Vec<VolumeAnomaly> {
+    let hour_ms = hour.timestamp_millis();
+    let from = DateTime::from_millis(hour_ms - baseline.hours * MILLIS_IN_HOUR);
+    let to = DateTime::from_millis(hour_ms + MILLIS_IN_HOUR);
+
+    let mut mongodb_client_operation_summaries = MongoDbClientOperationSummaries::new().await;
+    let counts = mongodb_client_operation_summaries
+        .get_hourly_volumes(from, to)
+        .await
+        .into_iter()
+        .map(|v| ((v.operation_type, v.hour.timestamp_millis()), v.count))
+        .collect::<HashMap<_, _>>();
+
+    OperationType::iter()
+        .filter_map(|operation_type| {
+            let get_count = |ms: i64| {
+                counts
+                    .get(&(operation_type.clone(), ms))
+                    .copied()
+                    .unwrap_or(0)
+            };
+            let history = (1..=baseline.hours)
+                .map(|i| get_count(hour_ms - i * MILLIS_IN_HOUR))
+                .collect::<Vec<_>>();
+
+            baseline.detect_anomaly(&operation_type, hour, get_count(hour_ms), &history)
+        })
+        .collect()
+}
+
+// checks every hour once it is complete
+pub async fn detect_volume_anomalies_periodically() {
+    let mut mongodb_client_volume_anomalies = MongoDbClientVolumeAnomalies::new().await;
+    mongodb_client_volume_anomalies.create_index().await;
+
+    let mut last_checked_hour = None;
+    loop {
+        let now = DateTime::now().timestamp_millis();
+        let hour = DateTime::from_millis(get_hour(now - DETECTION_DELAY_MS) - MILLIS_IN_HOUR);
+        if last_checked_hour != Some(hour) {
+            let baseline = VolumeBaseline::from_env();
+            let anomalies = detect_volume_anomalies(hour, &baseline).await;
+            info!(target: "volume_anomalies", "Checked volumes of {hour}, {} anomalies.", anomalies.len());
+
+            for anomaly in anomalies {
+                warn!(target: "volume_anomalies", "{} of {}: {} operations, baseline {:.1} ± {:.1} (z {:.1}).", anomaly.kind, anomaly.operation_type, anomaly.count, anomaly.baseline_mean, anomaly.baseline_stddev, anomaly.z_score);
+                mongodb_client_volume_anomalies
+                    .import_or_update_anomaly(anomaly)
+                    .await;
+            }
+            last_checked_hour = Some(hour);
+        }
+
+        sleep(Duration::from_secs(CHECK_INTERVAL_SECONDS)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        volume_anomalies::{get_hour, VolumeBaseline},
+        OperationType, VolumeAnomalyKind,
+    };
+    use bson::DateTime;
+
+    #[test]
+    fn spikes_and_droughts_leave_the_baseline() {
+        let baseline = VolumeBaseline {
+            hours: 6,
+            z_score: 3.0,
+        };
+        let history = [40, 44, 36, 42, 38, 40];
+        let hour = DateTime::from_millis(0);
+        let detect = |count| {
+            baseline
+                .detect_anomaly(&OperationType::Transfer, hour, count, &history)
+                .map(|a| a.kind)
+        };
+
+        assert_eq!(detect(45), None);
+        assert_eq!(detect(120), Some(VolumeAnomalyKind::Spike));
+        assert_eq!(detect(0), Some(VolumeAnomalyKind::Drought));
+    }
+
+    #[test]
+    fn quiet_operation_types_are_not_anomalous() {
+        let baseline = VolumeBaseline {
+            hours: 6,
+            z_score: 3.0,
+        };
+        let hour = DateTime::from_millis(0);
+
+        // a single operation after a silent week is no spike, a silent hour no drought
+        for (history, count) in [([0; 6], 1), ([0, 1, 0, 2, 0, 1], 0)] {
+            assert!(baseline
+                .detect_anomaly(&OperationType::Stake, hour, count, &history)
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn hours_are_floored() {
+        assert_eq!(get_hour(7_199_999), 3_600_000);
+        assert_eq!(get_hour(7_200_000), 7_200_000);
+    }
+}
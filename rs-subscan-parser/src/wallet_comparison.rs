@@ -0,0 +1,302 @@
+use crate::{
+    mongodb_client_subscan::MongoDbClientSubscan, mongodb_client_validator::MongoDbClientValidator,
+    OperationType, SubscanOperation, Validator,
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, env};
+
+static DEFAULT_WALLET_COMPARISON_WINDOW_SECONDS: i64 = 60 * 60;
+
+// every pair is compared, so the report grows with the square of the wallets
+pub static MAX_COMPARED_WALLETS: usize = 50;
+
+static STAKING_OPERATION_TYPES: [OperationType; 4] = [
+    OperationType::Stake,
+    OperationType::ReStake,
+    OperationType::RequestUnstake,
+    OperationType::WithdrawUnstaked,
+];
+
+// WALLET_COMPARISON_WINDOW_SECONDS, operations of two wallets this close count as coinciding
+pub fn get_comparison_window_seconds() -> i64 {
+    env::var("WALLET_COMPARISON_WINDOW_SECONDS")
+        .ok()
+        .and_then(|w| w.parse::<i64>().ok())
+        .filter(|w| *w > 0)
+        .unwrap_or(DEFAULT_WALLET_COMPARISON_WINDOW_SECONDS)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct WalletProfile {
+    pub wallet: String,
+    pub staked: f64,
+    pub unstaked: f64,
+    pub staking_operations: u64,
+
+    // every validator the wallet ever nominated, sorted
+    pub validators: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct WalletPair {
+    pub wallet: String,
+    pub other_wallet: String,
+    pub shared_validators: Vec<String>,
+
+    // shared validators out of the validators of either wallet
+    pub validator_overlap: f64,
+
+    // staking operations of either wallet with one of the other within the window,
+    // out of the staking operations of both
+    pub timing_correlation: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct WalletComparison {
+    pub window_seconds: i64,
+    pub wallets: Vec<WalletProfile>,
+
+    // most correlated first
+    pub pairs: Vec<WalletPair>,
+}
+
+pub async fn compare_wallets(wallets: Vec<String>, window_seconds: i64) -> WalletComparison {
+    let wallets = wallets.into_iter().unique().collect::<Vec<_>>();
+
+    let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
+    let mut operations = HashMap::new();
+    for wallet in wallets.iter() {
+        let wallet_operations = mongodb_client_subscan
+            .get_wallet_operations(wallet, None)
+            .await;
+        operations.insert(wallet.clone(), wallet_operations);
+    }
+
+    let mut mongodb_client_validator = MongoDbClientValidator::new().await;
+    let nominations = mongodb_client_validator
+        .get_nominations_histories(wallets.clone())
+        .await;
+
+    build_comparison(&wallets, &operations, &nominations, window_seconds)
+}
+
+pub fn build_comparison(
+    wallets: &[String],
+    operations: &HashMap<String, Vec<SubscanOperation>>,
+    nominations: &HashMap<String, Vec<Validator>>,
+    window_seconds: i64,
+) -> WalletComparison {
+    let staking_operations = wallets
+        .iter()
+        .map(|w| {
+            let wallet_operations = operations
+                .get(w)
+                .into_iter()
+                .flatten()
+                .filter(|s| STAKING_OPERATION_TYPES.contains(&s.operation_type))
+                .collect::<Vec<_>>();
+            (w, wallet_operations)
+        })
+        .collect::<HashMap<_, _>>();
+
+    let profiles = wallets
+        .iter()
+        .map(|w| {
+            let wallet_operations = &staking_operations[w];
+            let get_quantity = |operation_type: OperationType| {
+                wallet_operations
+                    .iter()
+                    .filter(|s| s.operation_type == operation_type)
+                    .map(|s| s.operation_quantity)
+                    .sum()
+            };
+            let validators = nominations
+                .get(w)
+                .into_iter()
+                .flatten()
+                .flat_map(|v| v.validators.iter().cloned())
+                .sorted()
+                .dedup()
+                .collect();
+
+            WalletProfile {
+                wallet: w.clone(),
+                staked: get_quantity(OperationType::Stake),
+                unstaked: get_quantity(OperationType::RequestUnstake),
+                staking_operations: wallet_operations.len() as u64,
+                validators,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let window_ms = window_seconds * 1_000;
+    let pairs = profiles
+        .iter()
+        .tuple_combinations()
+        .map(|(a, b)| {
+            let shared_validators = a
+                .validators
+                .iter()
+                .filter(|v| b.validators.contains(v))
+                .cloned()
+                .collect::<Vec<_>>();
+            let all_validators = a.validators.len() + b.validators.len() - shared_validators.len();
+
+            let timestamps_a = get_timestamps(&staking_operations[&a.wallet]);
+            let timestamps_b = get_timestamps(&staking_operations[&b.wallet]);
+            let coinciding = count_coinciding(&timestamps_a, &timestamps_b, window_ms)
+                + count_coinciding(&timestamps_b, &timestamps_a, window_ms);
+
+            WalletPair {
+                wallet: a.wallet.clone(),
+                other_wallet: b.wallet.clone(),
+                validator_overlap: get_share(shared_validators.len(), all_validators),
+                shared_validators,
+                timing_correlation: get_share(coinciding, timestamps_a.len() + timestamps_b.len()),
+            }
+        })
+        .sorted_by(|a, b| {
+            b.timing_correlation
+                .total_cmp(&a.timing_correlation)
+                .then(b.validator_overlap.total_cmp(&a.validator_overlap))
+        })
+        .collect();
+
+    WalletComparison {
+        window_seconds,
+        wallets: profiles,
+        pairs,
+    }
+}
+
+fn get_timestamps(operations: &[&SubscanOperation]) -> Vec<i64> {
+    operations
+        .iter()
+        .map(|s| s.operation_timestamp.timestamp_millis())
+        .sorted()
+        .collect()
+}
+
+// timestamps with one of the others at most window_ms away, both sorted
+fn count_coinciding(timestamps: &[i64], others: &[i64], window_ms: i64) -> usize {
+    timestamps
+        .iter()
+        .filter(|t| {
+            let i = others.partition_point(|o| *o < **t - window_ms);
+            others.get(i).is_some_and(|o| *o <= **t + window_ms)
+        })
+        .count()
+}
+
+fn get_share(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+
+    count as f64 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        wallet_comparison::{build_comparison, count_coinciding},
+        OperationStatus, OperationType, SubscanOperation, Validator,
+    };
+    use bson::DateTime;
+    use std::collections::HashMap;
+
+    fn operation(wallet: &str, operation_type: OperationType, minute: i64) -> SubscanOperation {
+        SubscanOperation {
+            hash: format!("{wallet}_{minute}"),
+            hash_version: 0,
+            block_number: minute as u64,
+            extrinsic_index: format!("{minute}-1"),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(1_700_000_000_000 + minute * 60_000),
+            operation_quantity: 100.0,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 150.0,
+            operation_type,
+            from_wallet: wallet.to_string(),
+            controller_wallet: wallet.to_string(),
+            to_wallet: "validator".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        }
+    }
+
+    fn nominations(wallet: &str, validators: &[&str]) -> Vec<Validator> {
+        validators
+            .iter()
+            .map(|v| Validator {
+                nominator: wallet.to_string(),
+                validators: vec![v.to_string()],
+                valid_from: DateTime::MIN,
+                valid_to: None,
+                extrinsic_index: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn coinciding_operations_are_within_the_window() {
+        assert_eq!(count_coinciding(&[0, 100, 500], &[40, 620], 60), 2);
+        assert_eq!(count_coinciding(&[0, 100, 500], &[], 60), 0);
+        assert_eq!(count_coinciding(&[], &[40], 60), 0);
+    }
+
+    #[test]
+    fn pairs_compare_totals_validators_and_timing() {
+        let wallets = ["alice", "bob", "carol"].map(String::from).to_vec();
+        let operations = HashMap::from([
+            (
+                "alice".to_string(),
+                vec![
+                    operation("alice", OperationType::Stake, 0),
+                    operation("alice", OperationType::RequestUnstake, 600),
+                    operation("alice", OperationType::Transfer, 1_000),
+                ],
+            ),
+            (
+                "bob".to_string(),
+                vec![
+                    operation("bob", OperationType::Stake, 30),
+                    operation("bob", OperationType::Stake, 5_000),
+                ],
+            ),
+        ]);
+        let nominations = HashMap::from([
+            (
+                "alice".to_string(),
+                nominations("alice", &["v1", "v2", "v1"]),
+            ),
+            ("bob".to_string(), nominations("bob", &["v2", "v3"])),
+        ]);
+
+        let comparison = build_comparison(&wallets, &operations, &nominations, 60 * 60);
+        let alice = &comparison.wallets[0];
+        assert_eq!((alice.staked, alice.unstaked), (100.0, 100.0));
+        assert_eq!(alice.staking_operations, 2);
+        assert_eq!(alice.validators, vec!["v1", "v2"]);
+        assert_eq!(comparison.wallets[2].staking_operations, 0);
+
+        // only the first stakes of alice and bob are half an hour apart
+        let pair = &comparison.pairs[0];
+        assert_eq!(
+            (pair.wallet.as_str(), pair.other_wallet.as_str()),
+            ("alice", "bob")
+        );
+        assert_eq!(pair.shared_validators, vec!["v2"]);
+        assert_eq!(pair.validator_overlap, 1.0 / 3.0);
+        assert_eq!(pair.timing_correlation, 0.5);
+
+        assert_eq!(comparison.pairs.len(), 3);
+        assert!(comparison.pairs[1..]
+            .iter()
+            .all(|p| p.timing_correlation == 0.0 && p.validator_overlap == 0.0));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{
+    address::{to_hex, to_ss58, AddressFormat},
+    mongodb_client_wallet_formats::MongoDbClientWalletFormats,
+    subscan_parser::{Network, EMPTY_ADDRESS},
+    Identity, SubscanOperation, WalletFormats,
+};
+use itertools::Itertools;
+use std::mem;
+use tracing::info;
+
+// SUBSCAN_ADDRESS_FORMAT encoding of the network the parser runs against
+pub fn get_canonical_address(address: &str) -> Option<String> {
+    AddressFormat::from_env().format(&Network::from_env(), address)
+}
+
+// ss58 with the prefix of the network, lowercase hex on networks with ethereum style accounts,
+// none if the address is neither the hex public key nor an ss58 of an account
+pub fn get_network_address(network: &Network, address: &str) -> Option<String> {
+    if network.has_ethereum_accounts() {
+        // 0x and 20 bytes
+        return to_hex(address).filter(|a| a.len() == 42);
+    }
+
+    to_ss58(network, address)
+}
+
+// replaces the address by its canonical encoding, returns the replaced one as alternate,
+// unreadable addresses are kept for the data quality validation to reject
+pub fn normalize_address(
+    address: &mut String,
+    network: &Network,
+    address_format: AddressFormat,
+) -> Option<WalletFormats> {
+    if address == EMPTY_ADDRESS {
+        return None;
+    }
+
+    let canonical = address_format.format(network, address)?;
+    if *address == canonical {
+        return None;
+    }
+
+    let alternate = mem::replace(address, canonical.clone());
+    Some(WalletFormats {
+        canonical,
+        alternates: vec![alternate],
+    })
+}
+
+pub fn normalize_operation(
+    operation: &mut SubscanOperation,
+    network: &Network,
+    address_format: AddressFormat,
+) -> Vec<WalletFormats> {
+    [
+        &mut operation.from_wallet,
+        &mut operation.controller_wallet,
+        &mut operation.to_wallet,
+    ]
+    .into_iter()
+    .chain(operation.nomination_targets.iter_mut())
+    .filter_map(|a| normalize_address(a, network, address_format))
+    .collect()
+}
+
+// one entry per wallet with every alternate seen for it
+pub fn merge_wallet_formats(wallet_formats: Vec<WalletFormats>) -> Vec<WalletFormats> {
+    wallet_formats
+        .into_iter()
+        .into_group_map_by(|w| w.canonical.clone())
+        .into_iter()
+        .map(|(canonical, formats)| WalletFormats {
+            canonical,
+            alternates: formats
+                .into_iter()
+                .flat_map(|f| f.alternates)
+                .unique()
+                .sorted()
+                .collect(),
+        })
+        .sorted()
+        .collect()
+}
+
+pub async fn import_wallet_formats(wallet_formats: Vec<WalletFormats>) {
+    if wallet_formats.is_empty() {
+        return;
+    }
+
+    let wallet_formats = merge_wallet_formats(wallet_formats);
+    info!(target: "wallet_formats", "Normalized {} wallets seen in other encodings.", wallet_formats.len());
+
+    let mut mongodb_client_wallet_formats = MongoDbClientWalletFormats::new().await;
+    mongodb_client_wallet_formats
+        .import_or_update_wallet_formats(wallet_formats)
+        .await;
+}
+
+// runs on parsed operations before they are hashed, enriched or stored
+pub async fn normalize_operations(
+    operations: &mut [SubscanOperation],
+    network: &Network,
+    address_format: AddressFormat,
+) {
+    let wallet_formats = operations
+        .iter_mut()
+        .flat_map(|o| normalize_operation(o, network, address_format))
+        .collect();
+    import_wallet_formats(wallet_formats).await;
+}
+
+pub async fn normalize_identities(
+    identities: &mut [Identity],
+    network: &Network,
+    address_format: AddressFormat,
+) {
+    let wallet_formats = identities
+        .iter_mut()
+        .filter_map(|i| normalize_address(&mut i.address, network, address_format))
+        .collect();
+    import_wallet_formats(wallet_formats).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        address::AddressFormat,
+        subscan_parser::{Network, EMPTY_ADDRESS},
+        wallet_formats::{
+            get_canonical_address, get_network_address, merge_wallet_formats, normalize_address,
+        },
+        WalletFormats,
+    };
+
+    static CANONICAL: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+    static HEX: &str = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+    static POLKADOT: &str = "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5";
+
+    #[test]
+    fn every_encoding_has_the_same_canonical_address() {
+        for address in [CANONICAL, HEX, POLKADOT] {
+            assert_eq!(get_canonical_address(address).as_deref(), Some(CANONICAL));
+            assert_eq!(
+                get_network_address(&Network::Polkadot, address).as_deref(),
+                Some(POLKADOT)
+            );
+        }
+        assert_eq!(get_canonical_address("0x1234"), None);
+        assert_eq!(get_canonical_address("5Grwva"), None);
+    }
+
+    #[test]
+    fn ethereum_style_accounts_stay_hex() {
+        let address = "0xF24FF3a9CF04c71Dbc94D0b566f7A27B94566cac";
+        assert_eq!(
+            get_network_address(&Network::Moonbeam, address).as_deref(),
+            Some("0xf24ff3a9cf04c71dbc94d0b566f7a27b94566cac")
+        );
+        assert_eq!(get_network_address(&Network::Moonbeam, HEX), None);
+    }
+
+    #[test]
+    fn replaced_addresses_are_kept_as_alternates() {
+        let mut address = HEX.to_string();
+        let formats = normalize_address(&mut address, &Network::Alephzero, AddressFormat::Native);
+        assert_eq!(address, CANONICAL);
+        assert_eq!(
+            formats,
+            Some(WalletFormats {
+                canonical: CANONICAL.to_string(),
+                alternates: vec![HEX.to_string()],
+            })
+        );
+
+        for address in [CANONICAL, EMPTY_ADDRESS, "5Grwva"] {
+            let mut normalized = address.to_string();
+            assert_eq!(
+                normalize_address(&mut normalized, &Network::Alephzero, AddressFormat::Native),
+                None
+            );
+            assert_eq!(normalized, address);
+        }
+
+        // the alternate of a hex setup is the ss58
+        let mut address = CANONICAL.to_string();
+        let formats = normalize_address(&mut address, &Network::Alephzero, AddressFormat::Hex);
+        assert_eq!(address, HEX);
+        assert_eq!(formats.unwrap().alternates, vec![CANONICAL.to_string()]);
+    }
+
+    #[test]
+    fn alternates_are_merged_per_wallet() {
+        let formats = |alternate: &str| WalletFormats {
+            canonical: CANONICAL.to_string(),
+            alternates: vec![alternate.to_string()],
+        };
+
+        assert_eq!(
+            merge_wallet_formats(vec![formats(POLKADOT), formats(HEX), formats(POLKADOT)]),
+            vec![WalletFormats {
+                canonical: CANONICAL.to_string(),
+                alternates: vec![HEX.to_string(), POLKADOT.to_string()],
+            }]
+        );
+    }
+}
@@ -1,9 +1,339 @@
+use bson::{oid::ObjectId, DateTime};
+use rand::{distributions::Alphanumeric, Rng};
+use rs_subscan_parser::{OperationType, SubscanOperation};
 use serde::{Deserialize, Serialize};
+use std::env;
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
 
+pub mod mongodb_client_alerts;
+pub mod mongodb_client_mute_rules;
 pub mod mongodb_client_telegram;
 pub mod telegram_posting;
 
+static ACK_TOKEN_LEN: usize = 32;
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
 pub struct Telegram {
     pub already_posted_hash: String,
 }
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum AlertRule {
+    #[default]
+    Staking,
+    Transfer,
+    DepositWithdraw,
+
+    // not about an operation, the operation type is kept as the wallet to dedup by
+    VolumeAnomaly,
+}
+
+impl AlertRule {
+    pub fn from_operation_type(operation_type: &OperationType) -> AlertRule {
+        match operation_type {
+            OperationType::Stake
+            | OperationType::ReStake
+            | OperationType::RequestUnstake
+            | OperationType::WithdrawUnstaked
+            | OperationType::Reward
+            | OperationType::Slash => AlertRule::Staking,
+            OperationType::Transfer => AlertRule::Transfer,
+            OperationType::DepositToExchange | OperationType::WithdrawFromExchange => {
+                AlertRule::DepositWithdraw
+            }
+        }
+    }
+
+    // e.g. ALERT_DEDUP_MINUTES_STAKING, no dedup if not set
+    pub fn get_dedup_window_seconds(&self) -> i64 {
+        env::var(format!("ALERT_DEDUP_MINUTES_{self}"))
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(0)
+            * 60
+    }
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Default,
+    IntoStaticStr,
+    EnumIter,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum AlertStatus {
+    #[default]
+    Open,
+    Acked,
+    Resolved,
+}
+
+// alert posted to the channel, kept to dedup, escalate and acknowledge it later
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct Alert {
+    pub id: String,
+    pub rule: AlertRule,
+    pub wallet: String,
+    pub operation_hash: String,
+    pub message: String,
+    pub status: AlertStatus,
+
+    // secret of the ack links posted along with the alert
+    pub ack_token: String,
+    pub fired_at: DateTime,
+    pub acked_at: Option<DateTime>,
+    pub resolved_at: Option<DateTime>,
+    pub escalated_at: Option<DateTime>,
+}
+
+impl Alert {
+    pub fn new(rule: AlertRule, operation: &SubscanOperation, message: String) -> Alert {
+        Self::new_for_wallet(
+            rule,
+            operation.from_wallet.clone(),
+            operation.hash.clone(),
+            message,
+        )
+    }
+
+    pub fn new_for_wallet(
+        rule: AlertRule,
+        wallet: String,
+        operation_hash: String,
+        message: String,
+    ) -> Alert {
+        let ack_token = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(ACK_TOKEN_LEN)
+            .map(char::from)
+            .collect();
+
+        Alert {
+            id: ObjectId::new().to_hex(),
+            rule,
+            wallet,
+            operation_hash,
+            message,
+            status: AlertStatus::Open,
+            ack_token,
+            fired_at: DateTime::now(),
+            acked_at: None,
+            resolved_at: None,
+            escalated_at: None,
+        }
+    }
+
+    // alerts of the same rule and wallet fired after it make this one a duplicate, none if the
+    // rule has no window
+    pub fn get_dedup_since(&self, window_seconds: i64) -> Option<DateTime> {
+        if window_seconds <= 0 {
+            return None;
+        }
+
+        Some(DateTime::from_millis(
+            self.fired_at.timestamp_millis() - window_seconds * 1000,
+        ))
+    }
+
+    // resolved alerts are final, acking twice keeps the first ack time, false if nothing changed
+    pub fn set_status(&mut self, status: AlertStatus, now: DateTime) -> bool {
+        match (self.status, status) {
+            (AlertStatus::Resolved, _) | (AlertStatus::Acked, AlertStatus::Acked) => return false,
+            _ => {}
+        }
+
+        self.status = status;
+        match status {
+            AlertStatus::Acked => self.acked_at = Some(now),
+            AlertStatus::Resolved => self.resolved_at = Some(now),
+            AlertStatus::Open => {}
+        }
+        true
+    }
+
+    // empty without API_PUBLIC_URL, as there is nowhere to point the links to
+    pub fn get_ack_links(&self) -> String {
+        let Ok(api_public_url) = env::var("API_PUBLIC_URL") else {
+            return String::new();
+        };
+        if api_public_url.is_empty() {
+            return String::new();
+        }
+
+        let url = format!(
+            "{}/alerts/{}",
+            api_public_url.trim_end_matches('/'),
+            self.id
+        );
+        format!(
+            r#"<a href="{url}/ack?token={token}">✅ Ack</a> | <a href="{url}/resolve?token={token}">☑️ Resolve</a> | "#,
+            token = self.ack_token
+        )
+    }
+}
+
+// every criterion which is set has to match for the operation to be muted
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct MuteRule {
+    pub id: String,
+    pub wallet: Option<String>,
+    pub label: Option<String>,
+    pub operation_type: Option<OperationType>,
+    pub starts_at: Option<DateTime>,
+    pub ends_at: Option<DateTime>,
+    pub comment: Option<String>,
+}
+
+impl MuteRule {
+    pub fn has_criteria(&self) -> bool {
+        self.wallet.is_some() || self.label.is_some() || self.operation_type.is_some()
+    }
+
+    pub fn is_active(&self, now: DateTime) -> bool {
+        self.starts_at.is_none_or(|s| s <= now) && self.ends_at.is_none_or(|e| now < e)
+    }
+
+    // labels are identities or exchange names of both sides of the operation
+    pub fn matches(&self, operation: &SubscanOperation, labels: &[&str], now: DateTime) -> bool {
+        if !self.has_criteria() || !self.is_active(now) {
+            return false;
+        }
+
+        let wallet_matches = self
+            .wallet
+            .as_ref()
+            .is_none_or(|w| &operation.from_wallet == w || &operation.to_wallet == w);
+        let label_matches = self
+            .label
+            .as_ref()
+            .is_none_or(|l| labels.iter().any(|p| p.eq_ignore_ascii_case(l)));
+        let operation_type_matches = self
+            .operation_type
+            .as_ref()
+            .is_none_or(|t| &operation.operation_type == t);
+
+        wallet_matches && label_matches && operation_type_matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Alert, AlertRule, AlertStatus, MuteRule};
+    use bson::DateTime;
+    use rs_subscan_parser::{OperationStatus, OperationType, SubscanOperation};
+
+    #[test]
+    fn resolved_alerts_are_final_and_the_first_ack_counts() {
+        let mut alert = Alert::new_for_wallet(
+            AlertRule::Staking,
+            "nominator".to_string(),
+            "hash".to_string(),
+            String::new(),
+        );
+        let acked_at = DateTime::from_millis(1_000);
+
+        assert!(alert.set_status(AlertStatus::Acked, acked_at));
+        assert!(!alert.set_status(AlertStatus::Acked, DateTime::from_millis(2_000)));
+        assert_eq!(alert.status, AlertStatus::Acked);
+        assert_eq!(alert.acked_at, Some(acked_at));
+
+        let resolved_at = DateTime::from_millis(3_000);
+        assert!(alert.set_status(AlertStatus::Resolved, resolved_at));
+        assert!(!alert.set_status(AlertStatus::Acked, DateTime::from_millis(4_000)));
+        assert!(!alert.set_status(AlertStatus::Open, DateTime::from_millis(4_000)));
+        assert!(!alert.set_status(AlertStatus::Resolved, DateTime::from_millis(4_000)));
+        assert_eq!(alert.status, AlertStatus::Resolved);
+        assert_eq!(alert.acked_at, Some(acked_at));
+        assert_eq!(alert.resolved_at, Some(resolved_at));
+
+        // open alerts can be resolved without an ack
+        let mut alert = Alert::new_for_wallet(
+            AlertRule::Transfer,
+            "treasury".to_string(),
+            "hash".to_string(),
+            String::new(),
+        );
+        assert!(alert.set_status(AlertStatus::Resolved, resolved_at));
+        assert_eq!(alert.acked_at, None);
+    }
+
+    #[test]
+    fn mute_rule_matches_all_set_criteria() {
+        let operation = SubscanOperation {
+            hash: String::new(),
+            hash_version: 0,
+            block_number: 0,
+            extrinsic_index: String::new(),
+            call_index: 0,
+            operation_timestamp: DateTime::from_millis(0),
+            operation_quantity: 0.0,
+            operation_planck: None,
+            operation_fee: None,
+            operation_era: None,
+            operation_usd: 0.0,
+            operation_type: OperationType::Transfer,
+            from_wallet: "treasury".to_string(),
+            controller_wallet: String::new(),
+            to_wallet: "cold".to_string(),
+            status: OperationStatus::Finalized,
+            operation_id: None,
+            nomination_targets: Vec::new(),
+        };
+        let rule = MuteRule {
+            id: String::new(),
+            wallet: Some("cold".to_string()),
+            label: None,
+            operation_type: Some(OperationType::Transfer),
+            starts_at: None,
+            ends_at: Some(DateTime::from_millis(1_000)),
+            comment: None,
+        };
+
+        assert!(rule.matches(&operation, &[], DateTime::from_millis(500)));
+        assert!(!rule.matches(&operation, &[], DateTime::from_millis(1_000)));
+
+        let rule = MuteRule {
+            operation_type: Some(OperationType::Stake),
+            ..rule
+        };
+        assert!(!rule.matches(&operation, &[], DateTime::from_millis(500)));
+
+        let rule = MuteRule {
+            wallet: None,
+            label: Some("Foundation".to_string()),
+            operation_type: None,
+            ..rule
+        };
+        assert!(rule.matches(&operation, &["foundation"], DateTime::from_millis(500)));
+    }
+}
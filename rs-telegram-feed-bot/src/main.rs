@@ -1,16 +1,27 @@
+use bson::DateTime;
 use chrono::Utc;
-use log::info;
+use futures::StreamExt;
+use log::{error, info};
+use mongodb::change_stream::{event::ChangeStreamEvent, ChangeStream};
 use num_format::{Locale, ToFormattedString};
 use rs_exchanges_parser::{
     mongodb_client_exchanges::MongoDbClientExchanges, ExchangeTrade, ExchangesWallets,
     PrimaryToken, TradeType,
 };
 use rs_subscan_parser::{
-    mongodb_client_identities::MongoDbClientIdentity, mongodb_client_subscan::MongoDbClientSubscan,
-    subscan_parser::EMPTY_ADDRESS, OperationType,
+    explorer::{get_account_url, get_operation_url},
+    mongodb_client_identities::MongoDbClientIdentity,
+    mongodb_client_operation_annotations::MongoDbClientOperationAnnotations,
+    mongodb_client_subscan::MongoDbClientSubscan,
+    mongodb_client_volume_anomalies::MongoDbClientVolumeAnomalies,
+    operations_watcher::is_change_streams_enabled,
+    subscan_parser::{Network, EMPTY_ADDRESS},
+    OperationType, VolumeAnomalyKind,
 };
 use rs_telegram_feed_bot::{
-    mongodb_client_telegram::MongoDbClientTelegram, telegram_posting::TelegramPosting, Telegram,
+    mongodb_client_alerts::MongoDbClientAlerts, mongodb_client_mute_rules::MongoDbClientMuteRules,
+    mongodb_client_telegram::MongoDbClientTelegram, telegram_posting::TelegramPosting, Alert,
+    AlertRule, Telegram,
 };
 use rs_utils::utils::logger::initialize_logger;
 use std::{cmp, env, str::FromStr, time::Duration};
@@ -22,6 +33,9 @@ static FILTER_MIN_USD_DEPOSIT_WITHDRAW: f64 = 25_000.0;
 static FILTER_MIN_USD_TRADE: f64 = 2_500.0;
 static FROM_SECONDS_AGO: i64 = 60 * 60 * 24;
 
+// escalations are still checked this often when nothing new is stored
+static CHANGE_STREAM_IDLE_SECONDS: u64 = 60;
+
 #[tokio::main(worker_threads = 100)]
 async fn main() {
     initialize_logger().expect("failed to initialize logging.");
@@ -35,8 +49,28 @@ async fn start_worker() {
     let mut mongodb_client_telegram = MongoDbClientTelegram::new().await;
     mongodb_client_telegram.create_index().await;
 
+    let mut mongodb_client_mute_rules = MongoDbClientMuteRules::new().await;
+    mongodb_client_mute_rules.create_index().await;
+
+    let mut mongodb_client_alerts = MongoDbClientAlerts::new().await;
+    mongodb_client_alerts.create_index().await;
+
     let bot_father_key = &env::var("TELEGRAM_BOT_FATHER_KEY").unwrap();
     let channel_id = &env::var("TELEGRAM_CHANNEL_ID").unwrap();
+    let network = Network::from_env();
+
+    // new operations and trades from any writer wake the loop up instead of polling every second
+    let (mut operations_stream, mut trades_stream) = if is_change_streams_enabled() {
+        (
+            MongoDbClientSubscan::new()
+                .await
+                .watch_operations(None)
+                .await,
+            MongoDbClientExchanges::new().await.watch_trades().await,
+        )
+    } else {
+        (None, None)
+    };
 
     loop {
         let mut mongodb_client_subscan = MongoDbClientSubscan::new().await;
@@ -47,6 +81,17 @@ async fn start_worker() {
             .get_filtered_operations(from_timestamp, None)
             .await;
 
+        // operations annotated by analysts as expected are not posted
+        let mut mongodb_client_operation_annotations =
+            MongoDbClientOperationAnnotations::new().await;
+        let suppressed_hashes = mongodb_client_operation_annotations
+            .get_suppressed_hashes(subscan_operations.iter().map(|s| s.hash.clone()).collect())
+            .await;
+        subscan_operations.retain(|s| !suppressed_hashes.contains(&s.hash));
+
+        let mute_rules = mongodb_client_mute_rules.get_not_expired_mute_rules().await;
+        let now = DateTime::now();
+
         let advertisement = r#"<a href="https://mixnet.explorers.guru/mixnode/A87Dg2wJ9zjd9tQ568oCUcRYSi6EcxMPSgDb1gdFWdKn">💘 Our validator</a>"#;
 
         let mut subscan_counter = 0;
@@ -109,6 +154,8 @@ async fn start_worker() {
                 | OperationType::ReStake
                 | OperationType::RequestUnstake
                 | OperationType::WithdrawUnstaked
+                | OperationType::Reward
+                | OperationType::Slash
                     if subscan_operation.operation_usd < FILTER_MIN_USD_STAKING =>
                 {
                     continue
@@ -116,6 +163,20 @@ async fn start_worker() {
                 _ => {}
             }
 
+            let exchange_labels = [&subscan_operation.from_wallet, &subscan_operation.to_wallet]
+                .into_iter()
+                .filter_map(|w| ExchangesWallets::from_str(w).ok())
+                .map(|e| e.get_exchange().to_string())
+                .collect::<Vec<_>>();
+            let mut labels = vec![from_identity.as_str(), to_identity.as_str()];
+            labels.extend(exchange_labels.iter().map(|l| l.as_str()));
+            if mute_rules
+                .iter()
+                .any(|r| r.matches(subscan_operation, &labels, now))
+            {
+                continue;
+            }
+
             let circle = match subscan_operation.operation_type {
                 OperationType::Stake => "🔵",
                 OperationType::ReStake => "🟡",
@@ -124,44 +185,51 @@ async fn start_worker() {
                 OperationType::Transfer => "🟤",
                 OperationType::DepositToExchange => "⚪",
                 OperationType::WithdrawFromExchange => "⚫",
+                OperationType::Reward => "🟢",
+                OperationType::Slash => "🔴",
             };
 
             let circles = get_circles(circle, subscan_operation.operation_usd);
 
+            let from_url = get_account_url(&network, &subscan_operation.from_wallet);
+            let to_url = get_account_url(&network, &subscan_operation.to_wallet);
+            let from_link = get_link(from_url.as_deref(), &from_identity);
+            let to_link = get_link(to_url.as_deref(), &to_identity);
+            let from_exchange_link = get_link(from_url.as_deref(), &from_exchange);
+            let to_exchange_link = get_link(to_url.as_deref(), &to_exchange);
+            let tx_link = get_link(
+                get_operation_url(&network, subscan_operation).as_deref(),
+                "📶 Tx Hash",
+            );
+
             let message = match subscan_operation.operation_type {
                 OperationType::Stake => format!(
                     r#"📘 Started stake of <b>{}</b> AZERO (<b>${}</b>)
 
 {circles}
 
-From address: <a href="https://alephzero.subscan.io/account/{}">{from_identity}</a>
-To validator: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a>
+From address: {from_link}
+To validator: {to_link}
 
-<a href="https://alephzero.subscan.io/extrinsic/{}">📶 Tx Hash</a> | "#,
+{tx_link} | "#,
                     (subscan_operation.operation_quantity.floor() as u64)
                         .to_formatted_string(&Locale::en),
                     (subscan_operation.operation_usd.floor() as u64)
                         .to_formatted_string(&Locale::en),
-                    subscan_operation.from_wallet,
-                    subscan_operation.to_wallet,
-                    subscan_operation.extrinsic_index,
                 ),
                 OperationType::ReStake => format!(
                     r#"📒 Re-staked stake of <b>{}</b> AZERO (<b>${}</b>)
 
 {circles}
 
-From address: <a href="https://alephzero.subscan.io/account/{}">{from_identity}</a>
-To validator: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a>
+From address: {from_link}
+To validator: {to_link}
 
-<a href="https://alephzero.subscan.io/extrinsic/{}">📶 Tx Hash</a> | "#,
+{tx_link} | "#,
                     (subscan_operation.operation_quantity.floor() as u64)
                         .to_formatted_string(&Locale::en),
                     (subscan_operation.operation_usd.floor() as u64)
                         .to_formatted_string(&Locale::en),
-                    subscan_operation.from_wallet,
-                    subscan_operation.to_wallet,
-                    subscan_operation.extrinsic_index,
                 ),
                 OperationType::RequestUnstake => {
                     format!(
@@ -169,17 +237,14 @@ To validator: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a
 
 {circles}
 
-From address: <a href="https://alephzero.subscan.io/account/{}">{from_identity}</a>
-From validator: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a>
+From address: {from_link}
+From validator: {to_link}
 
-<a href="https://alephzero.subscan.io/extrinsic/{}">📶 Tx Hash</a> | "#,
+{tx_link} | "#,
                         (subscan_operation.operation_quantity.floor() as u64)
                             .to_formatted_string(&Locale::en),
                         (subscan_operation.operation_usd.floor() as u64)
                             .to_formatted_string(&Locale::en),
-                        subscan_operation.from_wallet,
-                        subscan_operation.to_wallet,
-                        subscan_operation.extrinsic_index,
                     )
                 }
                 OperationType::WithdrawUnstaked => {
@@ -188,17 +253,14 @@ From validator: <a href="https://alephzero.subscan.io/account/{}">{to_identity}<
 
 {circles}
 
-From address: <a href="https://alephzero.subscan.io/account/{}">{from_identity}</a>
-From validator: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a>
+From address: {from_link}
+From validator: {to_link}
 
-<a href="https://alephzero.subscan.io/extrinsic/{}">📶 Tx Hash</a> | "#,
+{tx_link} | "#,
                         (subscan_operation.operation_quantity.floor() as u64)
                             .to_formatted_string(&Locale::en),
                         (subscan_operation.operation_usd.floor() as u64)
                             .to_formatted_string(&Locale::en),
-                        subscan_operation.from_wallet,
-                        subscan_operation.to_wallet,
-                        subscan_operation.extrinsic_index,
                     )
                 }
                 OperationType::Transfer => {
@@ -207,17 +269,14 @@ From validator: <a href="https://alephzero.subscan.io/account/{}">{to_identity}<
                     
 {circles}
 
-From address: <a href="https://alephzero.subscan.io/account/{}">{from_identity}</a>
-To address: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a>
+From address: {from_link}
+To address: {to_link}
 
-<a href="https://alephzero.subscan.io/extrinsic/{}">📶 Tx Hash</a> | "#,
+{tx_link} | "#,
                         (subscan_operation.operation_quantity.floor() as u64)
                             .to_formatted_string(&Locale::en),
                         (subscan_operation.operation_usd.floor() as u64)
                             .to_formatted_string(&Locale::en),
-                        subscan_operation.from_wallet,
-                        subscan_operation.to_wallet,
-                        subscan_operation.extrinsic_index
                     )
                 }
                 OperationType::DepositToExchange => {
@@ -226,17 +285,14 @@ To address: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a>
                     
 {circles}
 
-From address: <a href="https://alephzero.subscan.io/account/{}">{from_identity}</a>
-To exchange: <a href="https://alephzero.subscan.io/account/{}">{to_exchange}</a>
+From address: {from_link}
+To exchange: {to_exchange_link}
 
-<a href="https://alephzero.subscan.io/extrinsic/{}">📶 Tx Hash</a> | "#,
+{tx_link} | "#,
                         (subscan_operation.operation_quantity.floor() as u64)
                             .to_formatted_string(&Locale::en),
                         (subscan_operation.operation_usd.floor() as u64)
                             .to_formatted_string(&Locale::en),
-                        subscan_operation.from_wallet,
-                        subscan_operation.to_wallet,
-                        subscan_operation.extrinsic_index
                     )
                 }
                 OperationType::WithdrawFromExchange => {
@@ -245,28 +301,106 @@ To exchange: <a href="https://alephzero.subscan.io/account/{}">{to_exchange}</a>
                     
 {circles}
 
-From exchange: <a href="https://alephzero.subscan.io/account/{}">{from_exchange}</a>
-To address: <a href="https://alephzero.subscan.io/account/{}">{to_identity}</a>
+From exchange: {from_exchange_link}
+To address: {to_link}
+
+{tx_link} | "#,
+                        (subscan_operation.operation_quantity.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        (subscan_operation.operation_usd.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                    )
+                }
+                OperationType::Reward => {
+                    format!(
+                        r#"💰 Received staking reward of <b>{}</b> AZERO (<b>${}</b>) for era {}
+
+{circles}
+
+From validator: {from_link}
+To address: {to_link}
+
+{tx_link} | "#,
+                        (subscan_operation.operation_quantity.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        (subscan_operation.operation_usd.floor() as u64)
+                            .to_formatted_string(&Locale::en),
+                        get_era(subscan_operation.operation_era),
+                    )
+                }
+                OperationType::Slash => {
+                    format!(
+                        r#"🔪 Slashed <b>{}</b> AZERO (<b>${}</b>) in era {}
+
+{circles}
+
+From address: {from_link}
 
-<a href="https://alephzero.subscan.io/extrinsic/{}">📶 Tx Hash</a> | "#,
+{tx_link} | "#,
                         (subscan_operation.operation_quantity.floor() as u64)
                             .to_formatted_string(&Locale::en),
                         (subscan_operation.operation_usd.floor() as u64)
                             .to_formatted_string(&Locale::en),
-                        subscan_operation.from_wallet,
-                        subscan_operation.to_wallet,
-                        subscan_operation.extrinsic_index
+                        get_era(subscan_operation.operation_era),
                     )
                 }
             };
 
-            messages.push(message);
+            let alert = Alert::new(
+                AlertRule::from_operation_type(&subscan_operation.operation_type),
+                subscan_operation,
+                message.clone(),
+            );
+            messages.push((
+                message,
+                Some(alert),
+                Some(subscan_operation.summary(&network)),
+            ));
 
             subscan_counter += 1;
         }
 
         // ------------------------------------------------------- //
 
+        // messages only depend on the anomaly, so reposts are caught by the telegram hashes
+        let mut mongodb_client_volume_anomalies = MongoDbClientVolumeAnomalies::new().await;
+        let anomalies = mongodb_client_volume_anomalies
+            .get_detected_anomalies(DateTime::from_millis(from_timestamp * 1000))
+            .await;
+        let mut anomaly_counter = 0;
+        for anomaly in anomalies {
+            let headline = match anomaly.kind {
+                VolumeAnomalyKind::Spike => "📈 Volume spike",
+                VolumeAnomalyKind::Drought => "📉 Volume drought",
+            };
+            let message = format!(
+                r#"{headline} of <b>{}</b> operations
+
+<b>{}</b> operations in the hour from {}
+Baseline: {:.1} ± {:.1} per hour (z {:.1})
+
+"#,
+                anomaly.operation_type,
+                anomaly.count.to_formatted_string(&Locale::en),
+                anomaly.hour.try_to_rfc3339_string().unwrap_or_default(),
+                anomaly.baseline_mean,
+                anomaly.baseline_stddev,
+                anomaly.z_score,
+            );
+
+            let alert = Alert::new_for_wallet(
+                AlertRule::VolumeAnomaly,
+                anomaly.operation_type.to_string(),
+                String::new(),
+                message.clone(),
+            );
+            messages.push((message, Some(alert), None));
+
+            anomaly_counter += 1;
+        }
+
+        // ------------------------------------------------------- //
+
         let mut mongodb_client_exchanges = MongoDbClientExchanges::new().await;
         let non_grouped_exchanges_operations = mongodb_client_exchanges
             .get_filtered_trades(PrimaryToken::Azero, from_timestamp, None)
@@ -344,13 +478,13 @@ Bought <b>{}</b> AZERO for <b>{}</b> {} on {exchange_name}
                 ),
             };
 
-            messages.push(message);
+            messages.push((message, None, None));
 
             exchange_counter += 1;
         }
 
         let mut mongodb_client_telegram = MongoDbClientTelegram::new().await;
-        let telegram_hashes = messages.iter().map(sha256::digest).collect();
+        let telegram_hashes = messages.iter().map(|(m, _, _)| sha256::digest(m)).collect();
         let non_existing_hashes = mongodb_client_telegram
             .get_not_existing_telegrams(telegram_hashes)
             .await;
@@ -358,18 +492,49 @@ Bought <b>{}</b> AZERO for <b>{}</b> {} on {exchange_name}
 
         let messages = messages
             .into_iter()
-            .filter(|p| non_existing_hashes.contains(&sha256::digest(p)))
-            .collect::<Vec<String>>();
-        let skipped_counter = messages_len - messages.len();
+            .filter(|(m, _, _)| non_existing_hashes.contains(&sha256::digest(m)))
+            .collect::<Vec<_>>();
+        let mut skipped_counter = messages_len - messages.len();
 
+        let mut mongodb_client_alerts = MongoDbClientAlerts::new().await;
         let mut telegram_posting = TelegramPosting::new(bot_father_key, channel_id).await;
-        for message in messages {
-            let message_with_advertisement = format!("{message}{advertisement}");
-            telegram_posting
-                .post_message(&message_with_advertisement)
-                .await;
-
+        // summaries of operations only, trades and anomalies are counted in the totals
+        for (message, alert, summary) in messages {
             let already_posted_hash = sha256::digest(&message);
+
+            // deduplicated messages are marked as posted so they are not retried once the window ends
+            let dedup_since = alert
+                .as_ref()
+                .and_then(|a| a.get_dedup_since(a.rule.get_dedup_window_seconds()));
+            let is_duplicate = match (&alert, dedup_since) {
+                (Some(alert), Some(since)) => {
+                    mongodb_client_alerts
+                        .is_duplicate(alert.rule, &alert.wallet, since)
+                        .await
+                }
+                _ => false,
+            };
+
+            if is_duplicate {
+                skipped_counter += 1;
+            } else {
+                let ack_links = alert
+                    .as_ref()
+                    .map(|a| a.get_ack_links())
+                    .unwrap_or_default();
+                let message_with_advertisement = format!("{message}{ack_links}{advertisement}");
+                telegram_posting
+                    .post_message(&message_with_advertisement)
+                    .await;
+                if let Some(summary) = summary {
+                    info!(target: "telegram_posting", "Posted {summary}.");
+                }
+
+                if let Some(alert) = alert {
+                    mongodb_client_alerts.import_alert(alert).await;
+                }
+            }
+
             mongodb_client_telegram
                 .import_telegrams(vec![Telegram {
                     already_posted_hash,
@@ -379,9 +544,70 @@ Bought <b>{}</b> AZERO for <b>{}</b> {} on {exchange_name}
             sleep(Duration::from_millis(250)).await;
         }
 
-        info!(target: "telegram_posting", "Skipped {skipped_counter}. Posted {exchange_counter} trades and {subscan_counter} subscan operations. Sleeping 1 sec.");
+        escalate_alerts(bot_father_key, channel_id).await;
+
+        info!(target: "telegram_posting", "Skipped {skipped_counter}. Posted {exchange_counter} trades, {subscan_counter} subscan operations and {anomaly_counter} volume anomalies. Waiting for changes.");
 
+        tokio::select! {
+            _ = wait_for_change(&mut operations_stream) => {}
+            _ = wait_for_change(&mut trades_stream) => {}
+            _ = sleep(Duration::from_secs(CHANGE_STREAM_IDLE_SECONDS)) => {}
+        }
+    }
+}
+
+// sleeps 1 sec without a change stream, a broken one falls back to that
+async fn wait_for_change<T>(stream: &mut Option<ChangeStream<ChangeStreamEvent<T>>>)
+where
+    T: serde::de::DeserializeOwned + Unpin + Send + Sync,
+{
+    let Some(s) = stream else {
         sleep(Duration::from_millis(1_000)).await;
+        return;
+    };
+
+    match s.next().await {
+        Some(Ok(_)) => {}
+        Some(Err(e)) => {
+            error!(target: "telegram_feed_bot", "Change stream error: {e}; Polling every 1 sec.");
+            *stream = None;
+        }
+        None => {
+            error!(target: "telegram_feed_bot", "Change stream closed; Polling every 1 sec.");
+            *stream = None;
+        }
+    }
+}
+
+// unacknowledged alerts are repeated once to the escalation channel, or the main one if not set
+async fn escalate_alerts(bot_father_key: &str, channel_id: &str) {
+    let Some(escalation_minutes) = env::var("ALERT_ESCALATION_MINUTES")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+    else {
+        return;
+    };
+    let escalation_channel_id =
+        env::var("TELEGRAM_ESCALATION_CHANNEL_ID").unwrap_or(channel_id.to_string());
+
+    let fired_before =
+        DateTime::from_millis(DateTime::now().timestamp_millis() - escalation_minutes * 60 * 1000);
+    let mut mongodb_client_alerts = MongoDbClientAlerts::new().await;
+    let alerts = mongodb_client_alerts
+        .get_alerts_to_escalate(fired_before)
+        .await;
+
+    let mut telegram_posting = TelegramPosting::new(bot_father_key, &escalation_channel_id).await;
+    for alert in alerts {
+        let message = format!(
+            "⏫ Not acknowledged for {escalation_minutes} min\n\n{}{}",
+            alert.message,
+            alert.get_ack_links()
+        );
+        telegram_posting.post_message(&message).await;
+        mongodb_client_alerts.set_escalated(&alert).await;
+
+        sleep(Duration::from_millis(250)).await;
     }
 }
 
@@ -396,3 +622,16 @@ fn get_circles(circle: &str, operation_usd: f64) -> String {
 
     circles
 }
+
+fn get_era(era: Option<u32>) -> String {
+    era.map(|e| e.to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+// plain text on networks without a web explorer
+fn get_link(url: Option<&str>, text: &str) -> String {
+    match url {
+        Some(url) => format!(r#"<a href="{url}">{text}</a>"#),
+        None => text.to_string(),
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{Alert, AlertRule, AlertStatus};
+use bson::{doc, Bson, DateTime, Document};
+use mongodb::{options::IndexOptions, IndexModel};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientAlerts {
+    pub client_alerts: MongoDbClient<Alert>,
+}
+
+impl MongoDbClientAlerts {
+    pub async fn new() -> MongoDbClientAlerts {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_ALERTS").unwrap();
+        let client_name = "mongodb_alerts";
+        let client_alerts = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self { client_alerts }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"id": 1u32})
+            .options(options)
+            .build();
+        self.client_alerts.create_index(model, None).await;
+
+        let model = IndexModel::builder()
+            .keys(doc! {"rule": 1u32, "wallet": 1u32, "fired_at": -1i32})
+            .options(None)
+            .build();
+        self.client_alerts.create_index(model, None).await;
+
+        let model = IndexModel::builder()
+            .keys(doc! {"status": 1u32, "escalated_at": 1u32})
+            .options(None)
+            .build();
+        self.client_alerts.create_index(model, None).await;
+    }
+
+    pub async fn import_alert(&mut self, alert: Alert) {
+        self.client_alerts.insert_one(alert, None).await;
+    }
+
+    // an alert fired exactly at since is already outside of the window
+    pub fn get_duplicate_query(rule: AlertRule, wallet: &str, since: DateTime) -> Document {
+        doc! {
+            "rule": rule.to_string(),
+            "wallet": wallet,
+            "fired_at": {
+                "$gt": since,
+            }
+        }
+    }
+
+    pub async fn is_duplicate(&mut self, rule: AlertRule, wallet: &str, since: DateTime) -> bool {
+        let query = Self::get_duplicate_query(rule, wallet, since);
+        self.client_alerts.find_one(query, None).await.is_some()
+    }
+
+    // open alerts fired before it and not escalated yet, acked and resolved ones need no escalation
+    pub fn get_escalation_query(fired_before: DateTime) -> Document {
+        doc! {
+            "status": AlertStatus::Open.to_string(),
+            "escalated_at": null,
+            "fired_at": {
+                "$lt": fired_before,
+            }
+        }
+    }
+
+    pub async fn get_alerts_to_escalate(&mut self, fired_before: DateTime) -> Vec<Alert> {
+        let query = Self::get_escalation_query(fired_before);
+        self.client_alerts.find(query, None).await
+    }
+
+    pub async fn set_escalated(&mut self, alert: &Alert) {
+        self.client_alerts
+            .update_one(
+                doc! { "id": &alert.id },
+                doc! { "$set": { "escalated_at": DateTime::now() } },
+                None,
+            )
+            .await;
+    }
+
+    pub async fn get_alert(&mut self, id: &str) -> Option<Alert> {
+        self.client_alerts.find_one(doc! {"id": id}, None).await
+    }
+
+    // newest first
+    pub async fn get_alerts(
+        &mut self,
+        status: Option<AlertStatus>,
+        after: Option<Vec<Bson>>,
+        limit: i64,
+    ) -> Vec<Alert> {
+        let mut query = doc! {};
+        if let Some(status) = status {
+            query.insert("status", status.to_string());
+        }
+
+        let sort = doc! {"fired_at": -1i32, "id": -1i32};
+        self.client_alerts
+            .find_page(query, sort, after, limit, None)
+            .await
+    }
+
+    pub fn get_page_key(alert: &Alert) -> Vec<Bson> {
+        vec![
+            Bson::DateTime(alert.fired_at),
+            Bson::String(alert.id.clone()),
+        ]
+    }
+
+    // times are set by Alert::set_status, a resolved alert isn't changed by a concurrent ack
+    pub async fn update_status(&mut self, alert: &Alert) {
+        self.client_alerts
+            .update_one(
+                doc! {
+                    "id": &alert.id,
+                    "status": {"$ne": AlertStatus::Resolved.to_string()},
+                },
+                doc! { "$set": {
+                    "status": alert.status.to_string(),
+                    "acked_at": alert.acked_at,
+                    "resolved_at": alert.resolved_at,
+                }},
+                None,
+            )
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mongodb_client_alerts::MongoDbClientAlerts, Alert, AlertRule};
+    use bson::{doc, DateTime};
+
+    #[test]
+    fn duplicates_are_alerts_fired_within_the_window() {
+        let mut alert = Alert::new_for_wallet(
+            AlertRule::Transfer,
+            "treasury".to_string(),
+            "hash".to_string(),
+            String::new(),
+        );
+        alert.fired_at = DateTime::from_millis(1_700_000_600_000);
+
+        // rules without a window never dedup
+        assert_eq!(alert.get_dedup_since(0), None);
+        assert_eq!(alert.get_dedup_since(-60), None);
+
+        let since = alert.get_dedup_since(600).unwrap();
+        assert_eq!(since, DateTime::from_millis(1_700_000_000_000));
+
+        // the earlier alert has to be fired after the start of the window, not at it
+        assert_eq!(
+            MongoDbClientAlerts::get_duplicate_query(alert.rule, &alert.wallet, since),
+            doc! {
+                "rule": "TRANSFER",
+                "wallet": "treasury",
+                "fired_at": {"$gt": DateTime::from_millis(1_700_000_000_000)},
+            }
+        );
+    }
+
+    #[test]
+    fn only_open_alerts_are_escalated_once() {
+        let fired_before = DateTime::from_millis(1_700_000_000_000);
+
+        assert_eq!(
+            MongoDbClientAlerts::get_escalation_query(fired_before),
+            doc! {
+                "status": "OPEN",
+                "escalated_at": null,
+                "fired_at": {"$lt": DateTime::from_millis(1_700_000_000_000)},
+            }
+        );
+    }
+}
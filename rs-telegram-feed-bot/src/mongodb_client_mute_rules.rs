@@ -0,0 +1,64 @@
+use crate::MuteRule;
+use bson::{doc, Bson, DateTime};
+use mongodb::{options::IndexOptions, IndexModel};
+use rs_utils::clients::mongodb_client::MongoDbClient;
+use std::env;
+
+pub struct MongoDbClientMuteRules {
+    pub client_mute_rules: MongoDbClient<MuteRule>,
+}
+
+impl MongoDbClientMuteRules {
+    pub async fn new() -> MongoDbClientMuteRules {
+        let uri = &env::var("MONGODB_URI").unwrap();
+        let db = &env::var("MONGODB_DATABASE").unwrap();
+        let col = &env::var("MONGODB_COLLECTION_MUTE_RULES").unwrap();
+        let client_name = "mongodb_mute_rules";
+        let client_mute_rules = MongoDbClient::new(uri, client_name, db, col).await;
+
+        Self { client_mute_rules }
+    }
+
+    pub async fn create_index(&mut self) {
+        let options = IndexOptions::builder().unique(true).build();
+        let model = IndexModel::builder()
+            .keys(doc! {"id": 1u32})
+            .options(options)
+            .build();
+        self.client_mute_rules.create_index(model, None).await;
+    }
+
+    pub async fn import_mute_rule(&mut self, mute_rule: MuteRule) {
+        self.client_mute_rules.insert_one(mute_rule, None).await;
+    }
+
+    pub async fn delete_mute_rule(&mut self, id: &str) -> bool {
+        let res = self
+            .client_mute_rules
+            .delete_one(doc! {"id": id}, None)
+            .await;
+        res.deleted_count > 0
+    }
+
+    pub async fn get_mute_rules(&mut self, after: Option<Vec<Bson>>, limit: i64) -> Vec<MuteRule> {
+        self.client_mute_rules
+            .find_page(doc! {}, doc! {"id": 1i32}, after, limit, None)
+            .await
+    }
+
+    pub fn get_page_key(mute_rule: &MuteRule) -> Vec<Bson> {
+        vec![Bson::String(mute_rule.id.clone())]
+    }
+
+    // rules which already ended are skipped, rules which have not started yet are kept
+    pub async fn get_not_expired_mute_rules(&mut self) -> Vec<MuteRule> {
+        let query = doc! {
+            "$or": [
+                { "ends_at": null },
+                { "ends_at": { "$gt": DateTime::now() } },
+            ]
+        };
+
+        self.client_mute_rules.find(query, None).await
+    }
+}
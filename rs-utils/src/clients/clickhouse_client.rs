@@ -0,0 +1,66 @@
+use crate::clients::http_client::HttpClient;
+use reqwest::{
+    header::{HeaderMap, HeaderValue},
+    Url,
+};
+use serde::Serialize;
+
+// talks to the clickhouse http interface, queries are retried like the other clients
+pub struct ClickHouseClient {
+    pub url: String,
+    pub headers: HeaderMap,
+    pub http_client: HttpClient,
+}
+
+impl ClickHouseClient {
+    pub async fn new(url: &str, user: &str, password: &str, client_name: &str) -> ClickHouseClient {
+        let mut headers = HeaderMap::new();
+        if let Ok(user) = HeaderValue::from_str(user) {
+            headers.insert("X-ClickHouse-User", user);
+        }
+        if let Ok(password) = HeaderValue::from_str(password) {
+            headers.insert("X-ClickHouse-Key", password);
+        }
+        let http_client = HttpClient::new(client_name).await;
+
+        Self {
+            url: url.to_string(),
+            headers,
+            http_client,
+        }
+    }
+
+    pub async fn execute(&mut self, query: &str) -> String {
+        self.http_client
+            .post_text_request(&self.url, self.headers.clone(), query.to_string())
+            .await
+    }
+
+    // async inserts let the server batch small inserts into bigger parts
+    pub async fn insert_json_each_row<T: Serialize>(&mut self, table: &str, rows: &[T]) {
+        if rows.is_empty() {
+            return;
+        }
+
+        let query = format!("INSERT INTO {table} FORMAT JSONEachRow");
+        let url = Url::parse_with_params(
+            &self.url,
+            [
+                ("query", query.as_str()),
+                ("async_insert", "1"),
+                ("wait_for_async_insert", "1"),
+            ],
+        )
+        .map(|u| u.to_string())
+        .unwrap_or(self.url.clone());
+        let body = rows
+            .iter()
+            .filter_map(|r| serde_json::to_string(r).ok())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        self.http_client
+            .post_text_request(&url, self.headers.clone(), body)
+            .await;
+    }
+}
@@ -7,6 +7,23 @@ use tokio::time::sleep;
 static DELAY_MS: u64 = 100;
 static TIMEOUT_MS: u64 = 10_000;
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RequestError {
+    Send(String),
+    Response(String),
+    Parse(String),
+}
+
+impl std::fmt::Display for RequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestError::Send(e) => write!(f, "send error: {e}"),
+            RequestError::Response(e) => write!(f, "response error: {e}"),
+            RequestError::Parse(e) => write!(f, "parse response error: {e}"),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct HttpClient {
     pub client_name: String,
@@ -126,4 +143,75 @@ impl HttpClient {
             return resp;
         }
     }
+
+    // single attempt, retries are up to the caller
+    pub async fn try_post_request<T, Y>(
+        &mut self,
+        url: &str,
+        headers: HeaderMap,
+        data: &Y,
+    ) -> Result<T, RequestError>
+    where
+        Y: Serialize,
+        T: DeserializeOwned,
+    {
+        let resp = self
+            .client
+            .post(url)
+            .headers(headers)
+            .json(data)
+            .send()
+            .await
+            .map_err(|e| RequestError::Send(e.to_string()))?;
+        let resp = resp
+            .text()
+            .await
+            .map_err(|e| RequestError::Response(e.to_string()))?;
+
+        serde_json::from_str(&resp).map_err(|e| RequestError::Parse(e.to_string()))
+    }
+
+    // posts a raw body, retried until the server answers with a success status
+    pub async fn post_text_request(
+        &mut self,
+        url: &str,
+        headers: HeaderMap,
+        body: String,
+    ) -> String {
+        loop {
+            let resp = self
+                .client
+                .post(url)
+                .headers(headers.clone())
+                .body(body.clone())
+                .send()
+                .await;
+            if let Err(e) = resp {
+                error!(target: &format!("http_client_{}", self.client_name), "post_text_request send error: {e}; Sleeping {DELAY_MS} ms.");
+
+                sleep(Duration::from_millis(DELAY_MS)).await;
+                continue;
+            }
+
+            let resp = resp.unwrap();
+            let status = resp.status();
+            let resp = resp.text().await;
+            if let Err(e) = resp {
+                error!(target: &format!("http_client_{}", self.client_name), "post_text_request response error: {e}; Sleeping {DELAY_MS} ms.");
+
+                sleep(Duration::from_millis(DELAY_MS)).await;
+                continue;
+            }
+
+            let resp = resp.unwrap();
+            if !status.is_success() {
+                error!(target: &format!("http_client_{}", self.client_name), "post_text_request status {status}: {resp}; Sleeping {DELAY_MS} ms.");
+
+                sleep(Duration::from_millis(DELAY_MS)).await;
+                continue;
+            }
+
+            return resp;
+        }
+    }
 }
@@ -1,2 +1,4 @@
+pub mod clickhouse_client;
 pub mod http_client;
 pub mod mongodb_client;
+pub mod schema_registry_client;
@@ -2,9 +2,12 @@ use bson::{doc, Bson, Document};
 use futures::StreamExt;
 use log::error;
 use mongodb::{
+    change_stream::{event::ChangeStreamEvent, ChangeStream},
+    error::ErrorKind,
     options::{
-        ClientOptions, CountOptions, CreateIndexOptions, DeleteOptions, FindOneOptions,
-        FindOptions, InsertOneOptions, UpdateOptions,
+        ChangeStreamOptions, ClientOptions, Collation, CountOptions, CreateIndexOptions,
+        DeleteOptions, FindOneOptions, FindOptions, InsertManyOptions, InsertOneOptions,
+        UpdateOptions,
     },
     results::{CreateIndexResult, DeleteResult, UpdateResult},
     Client, Collection, Database, IndexModel,
@@ -14,6 +17,8 @@ use std::{borrow::Borrow, time::Duration};
 use tokio::time::sleep;
 
 static DELAY_MS: u64 = 100;
+static DUPLICATE_KEY_CODE: i32 = 11000;
+static CHANGE_STREAM_NOT_SUPPORTED_CODE: i32 = 40573;
 
 pub struct MongoDbClient<T> {
     pub client_name: String,
@@ -158,6 +163,41 @@ where
         }
     }
 
+    pub async fn list_index_names(&mut self) -> Vec<String> {
+        loop {
+            let res = self.col.list_index_names().await;
+            if let Err(e) = res {
+                if e.to_string().contains("ns not found") {
+                    return Vec::new();
+                }
+                error!(target: &format!("mongodb_client_{}", self.client_name), "list_index_names error: {e}; Sleeping {DELAY_MS} ms.");
+
+                sleep(Duration::from_millis(DELAY_MS)).await;
+                continue;
+            }
+
+            return res.unwrap();
+        }
+    }
+
+    pub async fn drop_index(&mut self, name: &str) {
+        loop {
+            let res = self.col.drop_index(name, None).await;
+            if let Err(e) = res {
+                let e_str = e.to_string();
+                if e_str.contains("index not found") || e_str.contains("ns not found") {
+                    return;
+                }
+                error!(target: &format!("mongodb_client_{}", self.client_name), "drop_index error: {e}; Sleeping {DELAY_MS} ms.");
+
+                sleep(Duration::from_millis(DELAY_MS)).await;
+                continue;
+            }
+
+            return;
+        }
+    }
+
     pub async fn insert_one(
         &mut self,
         doc: impl Borrow<T> + Clone,
@@ -181,6 +221,72 @@ where
         }
     }
 
+    // duplicates are skipped the same way insert_one does, pass ordered(false) to insert the rest
+    // returns indexes of the docs that were actually inserted, duplicates are skipped
+    pub async fn insert_many(
+        &mut self,
+        docs: &[T],
+        options: Option<InsertManyOptions>,
+    ) -> Vec<usize> {
+        if docs.is_empty() {
+            return Vec::new();
+        }
+
+        let ordered = options.as_ref().and_then(|o| o.ordered).unwrap_or(true);
+        loop {
+            let res = self.col.insert_many(docs, options.clone()).await;
+            if let Err(e) = res {
+                if let ErrorKind::BulkWrite(failure) = e.kind.as_ref() {
+                    let write_errors = failure.write_errors.as_deref().unwrap_or_default();
+                    let only_duplicates = failure.write_concern_error.is_none()
+                        && !write_errors.is_empty()
+                        && write_errors.iter().all(|w| w.code == DUPLICATE_KEY_CODE);
+                    if only_duplicates {
+                        // ordered inserts stop at the first error
+                        let failed = write_errors.iter().map(|w| w.index).collect::<Vec<_>>();
+                        let last = if ordered {
+                            failed.iter().min().copied().unwrap_or(docs.len())
+                        } else {
+                            docs.len()
+                        };
+                        return (0..last).filter(|i| !failed.contains(i)).collect();
+                    }
+                }
+                error!(target: &format!("mongodb_client_{}", self.client_name), "insert_many error: {e}; Sleeping {DELAY_MS} ms.");
+
+                sleep(Duration::from_millis(DELAY_MS)).await;
+                continue;
+            }
+
+            return (0..docs.len()).collect();
+        }
+    }
+
+    // none if the server has no change streams, i.e. it is not a replica set
+    pub async fn watch(
+        &mut self,
+        pipeline: Vec<Document>,
+        options: Option<ChangeStreamOptions>,
+    ) -> Option<ChangeStream<ChangeStreamEvent<T>>> {
+        loop {
+            let res = self.col.watch(pipeline.clone(), options.clone()).await;
+            if let Err(e) = res {
+                if let ErrorKind::Command(c) = e.kind.as_ref() {
+                    if c.code == CHANGE_STREAM_NOT_SUPPORTED_CODE {
+                        error!(target: &format!("mongodb_client_{}", self.client_name), "watch error: {e}; Change streams are not supported.");
+                        return None;
+                    }
+                }
+                error!(target: &format!("mongodb_client_{}", self.client_name), "watch error: {e}; Sleeping {DELAY_MS} ms.");
+
+                sleep(Duration::from_millis(DELAY_MS)).await;
+                continue;
+            }
+
+            return res.ok();
+        }
+    }
+
     pub async fn delete_one(
         &mut self,
         query: Document,
@@ -235,6 +341,31 @@ where
         }
     }
 
+    // keyset pagination, sort must cover a unique combination of fields and after holds
+    // their values in the last doc of the previous page
+    pub async fn find_page(
+        &mut self,
+        query: Document,
+        sort: Document,
+        after: Option<Vec<Bson>>,
+        limit: i64,
+        collation: Option<Collation>,
+    ) -> Vec<T> {
+        let query = match after {
+            Some(after) => doc! { "$and": [query, get_after_query(&sort, &after)] },
+            None => query,
+        };
+        let options = Some(
+            FindOptions::builder()
+                .sort(sort)
+                .limit(limit)
+                .collation(collation)
+                .build(),
+        );
+
+        self.find(query, options).await
+    }
+
     pub async fn find(&mut self, query: Document, options: Option<FindOptions>) -> Vec<T> {
         let mut cur;
         loop {
@@ -329,4 +460,78 @@ where
             .map(|a| a.get_str("_id").unwrap().to_string())
             .collect::<Vec<_>>()
     }
+
+    pub async fn aggregate(&mut self, pipeline: Vec<Document>) -> Vec<Document> {
+        let mut cur;
+        loop {
+            let res = self.col.aggregate(pipeline.clone(), None).await;
+            if let Err(e) = res {
+                error!(target: &format!("mongodb_client_{}", self.client_name), "aggregate error: {e}; Sleeping {DELAY_MS} ms.");
+
+                sleep(Duration::from_millis(DELAY_MS)).await;
+                continue;
+            }
+
+            cur = res.unwrap();
+            break;
+        }
+
+        let mut output = Vec::new();
+        while let Some(res) = cur.next().await {
+            if let Err(e) = res {
+                if e.to_string().contains("Cannot run getMore") {
+                    break;
+                }
+                error!(target: &format!("mongodb_client_{}", self.client_name), "aggregate, cur.next error: {e}; Sleeping {DELAY_MS} ms.");
+
+                sleep(Duration::from_millis(DELAY_MS)).await;
+                continue;
+            }
+
+            output.push(res.unwrap());
+        }
+
+        output
+    }
+}
+
+// docs sorting after the given values, e.g. a > 1 or (a == 1 and b > "x") for sort {a: 1, b: 1}
+pub fn get_after_query(sort: &Document, after: &[Bson]) -> Document {
+    let keys = sort.iter().zip(after).collect::<Vec<_>>();
+    let mut branches = Vec::new();
+    for (i, ((key, direction), value)) in keys.iter().enumerate() {
+        let mut branch = Document::new();
+        for ((k, _), v) in &keys[..i] {
+            branch.insert(k.as_str(), (*v).clone());
+        }
+        let operator = if direction.as_i32() == Some(-1) {
+            "$lt"
+        } else {
+            "$gt"
+        };
+        branch.insert(key.as_str(), doc! { operator: (*value).clone() });
+        branches.push(branch);
+    }
+
+    doc! { "$or": branches }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clients::mongodb_client::get_after_query;
+    use bson::{doc, Bson};
+
+    #[test]
+    fn after_query_follows_sort_directions() {
+        let sort = doc! {"fired_at": -1i32, "id": -1i32};
+        let after = vec![Bson::Int64(10), Bson::String("b".to_string())];
+
+        assert_eq!(
+            get_after_query(&sort, &after),
+            doc! {"$or": [
+                {"fired_at": {"$lt": 10i64}},
+                {"fired_at": 10i64, "id": {"$lt": "b"}},
+            ]}
+        );
+    }
 }
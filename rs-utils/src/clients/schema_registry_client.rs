@@ -0,0 +1,48 @@
+use crate::clients::http_client::HttpClient;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+struct RegisteredSchema {
+    id: u32,
+}
+
+// confluent compatible schema registry, credentials may be given in the url
+pub struct SchemaRegistryClient {
+    pub url: String,
+    pub headers: HeaderMap,
+    pub http_client: HttpClient,
+}
+
+impl SchemaRegistryClient {
+    pub async fn new(url: &str, client_name: &str) -> SchemaRegistryClient {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/vnd.schemaregistry.v1+json"),
+        );
+        let http_client = HttpClient::new(client_name).await;
+
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            headers,
+            http_client,
+        }
+    }
+
+    // an already registered schema returns its id, a schema breaking the compatibility rules
+    // of the subject is refused and retried until the registry or the schema is fixed
+    pub async fn register_avro_schema(&mut self, subject: &str, schema: &str) -> Option<u32> {
+        let url = format!("{}/subjects/{subject}/versions", self.url);
+        let body = json!({"schemaType": "AVRO", "schema": schema}).to_string();
+        let resp = self
+            .http_client
+            .post_text_request(&url, self.headers.clone(), body)
+            .await;
+
+        serde_json::from_str::<RegisteredSchema>(&resp)
+            .ok()
+            .map(|r| r.id)
+    }
+}
@@ -1,11 +1,20 @@
 use dotenvy::dotenv_override;
 use std::error::Error;
+use tracing_subscriber::{filter::LevelFilter, EnvFilter};
 
 extern crate dotenvy;
 extern crate log;
 
 pub fn initialize_logger() -> Result<(), Box<dyn Error>> {
-    env_logger::init();
+    // RUST_LOG filters as before and errors only without it, log records of crates without
+    // spans are picked up too, stdout is left to output piped into other tools
+    let filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::ERROR.into())
+        .from_env_lossy();
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
     log_panics::init();
     dotenv_override()?;
 